@@ -0,0 +1,213 @@
+//! Corpora for the benchmarks: captures named by environment variables,
+//! or synthetic data when those are not set.
+//!
+//! - `BGPARSE_BENCH_MRT`: an uncompressed MRT file, such as a RouteViews
+//!   or RIS full-table dump (TABLE_DUMP_V2) or updates file (BGP4MP).
+//! - `BGPARSE_BENCH_BMP`: back to back BMP messages, such as the TCP
+//!   payload of a BMP session.
+
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+
+const MRT_TABLE_DUMP_V2: u16 = 13;
+const MRT_BGP4MP: u16 = 16;
+const MRT_BGP4MP_ET: u16 = 17;
+
+/// Reads the file named by the environment variable `var`, if set.
+pub fn load(var: &str) -> Option<Vec<u8>> {
+    let path = env::var_os(var)?;
+    match fs::read(&path) {
+        Ok(bytes) => Some(bytes),
+        Err(err) => panic!("{}: {:?}: {}", var, path, err),
+    }
+}
+
+/// The MRT corpus: `BGPARSE_BENCH_MRT`, or a synthetic table dump.
+pub fn mrt_corpus() -> Vec<u8> {
+    load("BGPARSE_BENCH_MRT").unwrap_or_else(|| synthetic_table_dump(20_000))
+}
+
+/// The BMP corpus: `BGPARSE_BENCH_BMP`, or synthetic Route Monitoring
+/// messages.
+pub fn bmp_corpus() -> Vec<u8> {
+    load("BGPARSE_BENCH_BMP").unwrap_or_else(|| synthetic_route_monitoring(20_000))
+}
+
+/// What an MRT record holds for the parser to work on.
+pub enum MrtItem<'a> {
+    /// The attributes of a TABLE_DUMP_V2 RIB entry.
+    RibEntry(&'a [u8]),
+    /// A BGP message of a BGP4MP record, and whether its AS numbers are
+    /// four octets.
+    Message(&'a [u8], bool),
+}
+
+fn u16_at(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some((*bytes.get(offset)? as u16) << 8 | *bytes.get(offset + 1)? as u16)
+}
+
+fn u32_at(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some((u16_at(bytes, offset)? as u32) << 16 | u16_at(bytes, offset + 2)? as u32)
+}
+
+/// Splits an MRT file into RIB entries and BGP messages. Only the
+/// unicast and multicast RIB subtypes of TABLE_DUMP_V2 and the message
+/// subtypes of BGP4MP are looked at; other records are skipped.
+pub fn mrt_items(mut bytes: &[u8]) -> Vec<MrtItem<'_>> {
+    let mut items = Vec::new();
+    while let (Some(mrt_type), Some(subtype), Some(len)) = (u16_at(bytes, 4), u16_at(bytes, 6), u32_at(bytes, 8)) {
+        let end = 12 + len as usize;
+        if bytes.len() < end {
+            break;
+        }
+        let mut body = &bytes[12..end];
+        bytes = &bytes[end..];
+        match (mrt_type, subtype) {
+            (MRT_TABLE_DUMP_V2, 2..=5) => {
+                let _ = rib_entries(body, &mut items);
+            }
+            (MRT_BGP4MP, _) | (MRT_BGP4MP_ET, _) => {
+                if mrt_type == MRT_BGP4MP_ET {
+                    body = body.get(4..).unwrap_or(&[]);
+                }
+                let _ = bgp4mp_message(body, subtype, &mut items);
+            }
+            _ => (),
+        }
+    }
+    items
+}
+
+fn rib_entries<'a>(body: &'a [u8], items: &mut Vec<MrtItem<'a>>) -> Option<()> {
+    let prefix_len = *body.get(4)? as usize;
+    let mut offset = 5 + (prefix_len + 7) / 8;
+    let count = u16_at(body, offset)?;
+    offset += 2;
+    for _ in 0..count {
+        let attr_len = u16_at(body, offset + 6)? as usize;
+        items.push(MrtItem::RibEntry(body.get(offset + 8..offset + 8 + attr_len)?));
+        offset += 8 + attr_len;
+    }
+    Some(())
+}
+
+fn bgp4mp_message<'a>(body: &'a [u8], subtype: u16, items: &mut Vec<MrtItem<'a>>) -> Option<()> {
+    let four_byte_asn = match subtype {
+        1 | 6 => false,
+        4 | 7 => true,
+        _ => return None,
+    };
+    let as_len = if four_byte_asn { 8 } else { 4 };
+    let addr_len = match u16_at(body, as_len + 2)? {
+        1 => 4,
+        2 => 16,
+        _ => return None,
+    };
+    items.push(MrtItem::Message(body.get(as_len + 4 + 2 * addr_len..)?, four_byte_asn));
+    Some(())
+}
+
+/// The path attributes of a typical route: ORIGIN, a 4 AS long AS_PATH,
+/// NEXT_HOP, and 4 communities.
+fn route_attrs(i: u32) -> Vec<u8> {
+    let mut attrs = vec![0x40, 0x01, 0x01, 0x00,
+                         0x40, 0x02, 0x12, 0x02, 0x04];
+    for asn in &[3356, 174, 64496 + (i % 16), 65000 + (i % 256)] {
+        attrs.extend_from_slice(&u32::to_be_bytes(*asn));
+    }
+    attrs.extend_from_slice(&[0x40, 0x03, 0x04, 192, 0, 2, (i % 250) as u8 + 1]);
+    attrs.extend_from_slice(&[0xc0, 0x08, 0x10]);
+    for community in &[0x0d1c_0001u32, 0x0d1c_0002, 0x00ae_0000 + i % 100, 0xffff_ff01] {
+        attrs.extend_from_slice(&community.to_be_bytes());
+    }
+    attrs
+}
+
+/// A TABLE_DUMP_V2 dump of `routes` IPv4 /24s with two RIB entries each.
+pub fn synthetic_table_dump(routes: u32) -> Vec<u8> {
+    let mut dump = Vec::new();
+    for i in 0..routes {
+        let mut body = i.to_be_bytes().to_vec();
+        body.extend_from_slice(&[24, 10, (i >> 8) as u8, i as u8]);
+        body.extend_from_slice(&[0, 2]);
+        for peer in 0..2u16 {
+            let attrs = route_attrs(i + peer as u32);
+            body.extend_from_slice(&peer.to_be_bytes());
+            body.extend_from_slice(&[0, 0, 0, 0]);
+            body.extend_from_slice(&(attrs.len() as u16).to_be_bytes());
+            body.extend_from_slice(&attrs);
+        }
+        dump.extend_from_slice(&[0, 0, 0, 0]);
+        dump.extend_from_slice(&MRT_TABLE_DUMP_V2.to_be_bytes());
+        dump.extend_from_slice(&[0, 2]);
+        dump.extend_from_slice(&(body.len() as u32).to_be_bytes());
+        dump.extend_from_slice(&body);
+    }
+    dump
+}
+
+/// An UPDATE announcing one IPv4 /24 with `attrs`.
+pub fn update(i: u32, attrs: &[u8]) -> Vec<u8> {
+    let len = 19 + 4 + attrs.len() + 4;
+    let mut update = vec![0xff; 16];
+    update.extend_from_slice(&(len as u16).to_be_bytes());
+    update.extend_from_slice(&[2, 0, 0]);
+    update.extend_from_slice(&(attrs.len() as u16).to_be_bytes());
+    update.extend_from_slice(attrs);
+    update.extend_from_slice(&[24, 10, (i >> 8) as u8, i as u8]);
+    update
+}
+
+/// `count` Route Monitoring messages, each carrying one UPDATE.
+pub fn synthetic_route_monitoring(count: u32) -> Vec<u8> {
+    let mut stream = Vec::new();
+    for i in 0..count {
+        let update = update(i, &route_attrs(i));
+        let len = 6 + 42 + update.len();
+        stream.push(3);
+        stream.extend_from_slice(&(len as u32).to_be_bytes());
+        stream.push(0);
+        let mut peer = [0; 42];
+        peer[22..26].copy_from_slice(&[192, 0, 2, (i % 4) as u8 + 1]);
+        peer[26..30].copy_from_slice(&(64496 + i % 4).to_be_bytes());
+        stream.extend_from_slice(&peer);
+        stream.extend_from_slice(&update);
+    }
+    stream
+}
+
+/// Splits a stream of BMP messages on their Message Length fields.
+pub fn bmp_messages(mut bytes: &[u8]) -> Vec<&[u8]> {
+    let mut messages = Vec::new();
+    while let Some(len) = u32_at(bytes, 1) {
+        let len = len as usize;
+        if len < 6 || bytes.len() < len {
+            break;
+        }
+        messages.push(&bytes[..len]);
+        bytes = &bytes[len..];
+    }
+    messages
+}
+
+/// An extended-length UPDATE with a 255 AS long AS_PATH, 4000 communities
+/// and 200 unknown optional transitive attributes.
+pub fn attribute_heavy_update() -> Vec<u8> {
+    let mut attrs = vec![0x40, 0x01, 0x01, 0x00,
+                         0x50, 0x02, 0x03, 0xfe, 0x02, 0xff];
+    for asn in 0..255u32 {
+        attrs.extend_from_slice(&(4_200_000_000 + asn).to_be_bytes());
+    }
+    attrs.extend_from_slice(&[0x40, 0x03, 0x04, 192, 0, 2, 1]);
+    attrs.extend_from_slice(&[0xd0, 0x08, 0x3e, 0x80]);
+    for community in 0..4000u32 {
+        attrs.extend_from_slice(&(0xfde8_0000 + community).to_be_bytes());
+    }
+    for i in 0..200u32 {
+        attrs.extend_from_slice(&[0xc0, 0xf0 + (i % 8) as u8, 0x08]);
+        attrs.extend_from_slice(&u64::to_be_bytes(i as u64));
+    }
+    update(0, &attrs)
+}
@@ -0,0 +1,81 @@
+extern crate bgparse;
+extern crate criterion;
+
+mod common;
+
+use bgparse::bgp::{self, Message, SessionConfig};
+use bgparse::bgp::update::path_attr::PathAttrIter;
+use bgparse::bmp::PeerTable;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use common::MrtItem;
+
+/// Decodes every attribute of the RIB entries and every route of the
+/// UPDATEs in an MRT file.
+fn bench_mrt(c: &mut Criterion) {
+    let corpus = common::mrt_corpus();
+    let items = common::mrt_items(&corpus);
+    let mut group = c.benchmark_group("mrt");
+    group.throughput(Throughput::Elements(items.len() as u64));
+    group.bench_function("decode", |b| b.iter(|| {
+        let mut decoded = 0;
+        for item in &items {
+            match *item {
+                MrtItem::RibEntry(attrs) =>
+                    decoded += PathAttrIter::from_rib_entry(black_box(attrs), true).filter(Result::is_ok).count(),
+                MrtItem::Message(raw, four_byte_asn) => {
+                    let config = SessionConfig { four_byte_asn, ..SessionConfig::default() };
+                    if let Ok(Message::Update(update)) = bgp::decode(black_box(raw), &config) {
+                        decoded += update.routes().filter(Result::is_ok).count();
+                    }
+                }
+            }
+        }
+        decoded
+    }));
+    group.finish();
+}
+
+/// Decodes a BMP stream with a `PeerTable`, down to the routes of every
+/// Route Monitoring message.
+fn bench_bmp(c: &mut Criterion) {
+    let corpus = common::bmp_corpus();
+    let messages = common::bmp_messages(&corpus);
+    let mut group = c.benchmark_group("bmp");
+    group.throughput(Throughput::Bytes(corpus.len() as u64));
+    group.bench_function("route monitoring", |b| b.iter(|| {
+        let mut peers: PeerTable = PeerTable::new();
+        let mut routes = 0;
+        for &bytes in &messages {
+            let decoded = match peers.decode(black_box(bytes)) {
+                Ok(decoded) => decoded,
+                Err(_) => continue,
+            };
+            for message in decoded.messages() {
+                if let Ok(Message::Update(update)) = message {
+                    routes += update.routes().filter(Result::is_ok).count();
+                }
+            }
+        }
+        routes
+    }));
+    group.finish();
+}
+
+/// Parses and fully decodes an UPDATE with thousands of attribute values.
+fn bench_attribute_heavy(c: &mut Criterion) {
+    let raw = common::attribute_heavy_update();
+    let config = SessionConfig { extended_message: true, ..SessionConfig::default() };
+    let mut group = c.benchmark_group("attribute heavy update");
+    group.throughput(Throughput::Bytes(raw.len() as u64));
+    group.bench_function("path_attrs", |b| b.iter(|| match bgp::decode(black_box(&raw), &config) {
+        Ok(Message::Update(update)) => update.path_attrs().count(),
+        _ => 0,
+    }));
+    group.bench_function("deep_validate", |b| b.iter(|| {
+        bgp::decode(black_box(&raw), &config).and_then(|message| message.deep_validate()).is_ok()
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, bench_mrt, bench_bmp, bench_attribute_heavy);
+criterion_main!(benches);
@@ -0,0 +1,54 @@
+extern crate bgparse;
+extern crate criterion;
+
+use bgparse::bgp::{self, Message, SessionConfig};
+use bgparse::types::*;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+
+const UPDATE: [u8; 54] = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                          0xff, 0xff, 0xff, 0xff, 0x00, 0x36, 0x02, 0x00, 0x04, 0x18, 0x0a, 0x00,
+                          0x01, 0x00, 0x14, 0x40, 0x01, 0x01, 0x00, 0x40, 0x02, 0x06, 0x02, 0x01,
+                          0x00, 0x00, 0xfd, 0xe8, 0x40, 0x03, 0x04, 0x0a, 0x00, 0x00, 0x01, 0x18,
+                          0xc6, 0x33, 0x64, 0x10, 0x0a, 0x01];
+
+/// Frames and parses one message at a time, the way callers do without
+/// `parse_batch`.
+fn parse_loop(mut bytes: &[u8], config: &SessionConfig) -> usize {
+    let mut parsed = 0;
+    while bytes.len() >= 19 {
+        let len = (bytes[16] as usize) << 8 | bytes[17] as usize;
+        if len < 19 || len > bytes.len() {
+            break;
+        }
+        if Message::from_bytes_with_config(&bytes[..len], config).is_ok() {
+            parsed += 1;
+        }
+        bytes = &bytes[len..];
+    }
+    parsed
+}
+
+fn bench_parse_batch(c: &mut Criterion) {
+    let mut slab = [0; UPDATE.len() * 1000];
+    for chunk in slab.chunks_mut(UPDATE.len()) {
+        chunk.copy_from_slice(&UPDATE);
+    }
+    let config = SessionConfig::default();
+
+    let mut group = c.benchmark_group("slab of 1000 updates");
+    group.throughput(Throughput::Bytes(slab.len() as u64));
+    group.bench_function("from_bytes loop", |b| b.iter(|| parse_loop(black_box(&slab), &config)));
+    group.bench_function("parse_batch", |b| b.iter(|| {
+        let mut parsed = 0;
+        bgp::parse_batch(black_box(&slab), &config, &mut |message: Result<Message>| {
+            if message.is_ok() {
+                parsed += 1;
+            }
+        });
+        parsed
+    }));
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse_batch);
+criterion_main!(benches);
@@ -1,6 +1,6 @@
 use core::fmt;
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 pub struct Afi(u16);
 
 /// IP version 4
@@ -22,6 +22,69 @@ impl From<u16> for Afi {
     }
 }
 
+impl From<Afi> for u16 {
+    fn from(afi: Afi) -> u16 {
+        afi.0
+    }
+}
+
+impl Afi {
+    /// The description IANA lists for the address family number.
+    #[cfg(feature = "names")]
+    pub fn name(&self) -> &'static str {
+        match self.0 {
+            0 | 65535 => "Reserved",
+            1 => "IP (IP version 4)",
+            2 => "IP6 (IP version 6)",
+            3 => "NSAP",
+            4 => "HDLC (8-bit multidrop)",
+            5 => "BBN 1822",
+            6 => "802 (includes all 802 media plus Ethernet \"canonical format\")",
+            7 => "E.163",
+            8 => "E.164 (SMDS, Frame Relay, ATM)",
+            9 => "F.69 (Telex)",
+            10 => "X.121 (X.25, Frame Relay)",
+            11 => "IPX",
+            12 => "Appletalk",
+            13 => "Decnet IV",
+            14 => "Banyan Vines",
+            15 => "E.164 with NSAP format subaddress",
+            16 => "DNS (Domain Name System)",
+            17 => "Distinguished Name",
+            18 => "AS Number",
+            19 => "XTP over IP version 4",
+            20 => "XTP over IP version 6",
+            21 => "XTP native mode XTP",
+            22 => "Fibre Channel World-Wide Port Name",
+            23 => "Fibre Channel World-Wide Node Name",
+            24 => "GWID",
+            25 => "AFI for L2VPN information",
+            26 => "MPLS-TP Section Endpoint Identifier",
+            27 => "MPLS-TP LSP Endpoint Identifier",
+            28 => "MPLS-TP Pseudowire Endpoint Identifier",
+            29 => "MT IP: Multi-Topology IP version 4",
+            30 => "MT IPv6: Multi-Topology IP version 6",
+            31 => "BGP SFC",
+            16384 => "EIGRP Common Service Family",
+            16385 => "EIGRP IPv4 Service Family",
+            16386 => "EIGRP IPv6 Service Family",
+            16387 => "LISP Canonical Address Format (LCAF)",
+            16388 => "BGP-LS",
+            16389 => "48-bit MAC",
+            16390 => "64-bit MAC",
+            16391 => "OUI",
+            16392 => "MAC/24",
+            16393 => "MAC/40",
+            16394 => "IPv6/64",
+            16395 => "RBridge Port ID",
+            16396 => "TRILL Nickname",
+            16397 => "Universally Unique Identifier (UUID)",
+            16398 => "Routing Policy AFI",
+            _ => "Unassigned",
+        }
+    }
+}
+
 impl fmt::Debug for Afi {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.0 {
@@ -31,3 +94,14 @@ impl fmt::Debug for Afi {
         }
     }
 }
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Afi {
+    fn format(&self, f: defmt::Formatter) {
+        match self.0 {
+            1 => defmt::write!(f, "ipv4"),
+            2 => defmt::write!(f, "ipv6"),
+            n => defmt::write!(f, "unknown({=u16})", n),
+        }
+    }
+}
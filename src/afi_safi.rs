@@ -0,0 +1,93 @@
+use core::fmt;
+use afi::*;
+use safi::*;
+
+/// An address family: an AFI and a SAFI.
+///
+/// Ordered by AFI, then SAFI, and displayed as `ipv4/unicast`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub struct AfiSafi {
+    pub afi: Afi,
+    pub safi: Safi,
+}
+
+/// IPv4 unicast.
+pub const IPV4_UNICAST: AfiSafi = AfiSafi { afi: AFI_IPV4, safi: SAFI_UNICAST };
+/// IPv4 multicast.
+pub const IPV4_MULTICAST: AfiSafi = AfiSafi { afi: AFI_IPV4, safi: SAFI_MULTICAST };
+/// IPv6 unicast.
+pub const IPV6_UNICAST: AfiSafi = AfiSafi { afi: AFI_IPV6, safi: SAFI_UNICAST };
+/// IPv6 multicast.
+pub const IPV6_MULTICAST: AfiSafi = AfiSafi { afi: AFI_IPV6, safi: SAFI_MULTICAST };
+
+impl AfiSafi {
+    pub fn new(afi: Afi, safi: Safi) -> AfiSafi {
+        AfiSafi { afi, safi }
+    }
+
+    /// Reads the 2 octet AFI and 1 octet SAFI at the start of `bytes`, as
+    /// laid out in MP_REACH_NLRI and most capabilities.
+    pub(crate) fn from_bytes(bytes: &[u8]) -> AfiSafi {
+        AfiSafi::new(Afi::from((bytes[0] as u16) << 8 | bytes[1] as u16), Safi::from(bytes[2]))
+    }
+
+    /// Whether this crate decodes the NLRI of the address family, rather
+    /// than leaving them as undecoded bytes.
+    pub fn is_supported_by_crate(&self) -> bool {
+        matches!(*self, IPV4_UNICAST | IPV4_MULTICAST | IPV6_UNICAST | IPV6_MULTICAST)
+            || *self == AfiSafi::new(AFI_IPV4, SAFI_4OVER6)
+            || *self == AfiSafi::new(AFI_IPV6, SAFI_6OVER4)
+            || self.afi == AFI_MT_IPV4 || self.afi == AFI_MT_IPV6
+    }
+}
+
+impl From<(Afi, Safi)> for AfiSafi {
+    fn from((afi, safi): (Afi, Safi)) -> AfiSafi {
+        AfiSafi::new(afi, safi)
+    }
+}
+
+impl fmt::Display for AfiSafi {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:?}/{:?}", self.afi, self.safi)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for AfiSafi {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}/{}", self.afi, self.safi)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+    use types::FmtBuf;
+
+    #[test]
+    fn afi_safi() {
+        let mut buf = FmtBuf::new();
+        write!(buf, "{} {}", IPV4_UNICAST, AfiSafi::new(AFI_L2VPN, SAFI_EVPN)).unwrap();
+        assert_eq!(buf.as_bytes(), b"ipv4/unicast unknown(25)/unknown(70)");
+
+        assert!(IPV6_MULTICAST.is_supported_by_crate());
+        assert!(!AfiSafi::new(AFI_IPV4, SAFI_MPLS_LABEL).is_supported_by_crate());
+        assert!(IPV4_UNICAST < IPV4_MULTICAST);
+        assert!(IPV4_MULTICAST < IPV6_UNICAST);
+        assert_eq!(AfiSafi::from((AFI_IPV6, SAFI_UNICAST)), IPV6_UNICAST);
+        assert_eq!(AfiSafi::from_bytes(&[0x00, 0x02, 0x02]), IPV6_MULTICAST);
+    }
+
+    #[cfg(feature = "names")]
+    #[test]
+    fn names() {
+        assert_eq!(AFI_IPV6.name(), "IP6 (IP version 6)");
+        assert_eq!(AFI_BGP_LS.name(), "BGP-LS");
+        assert_eq!(Afi::from(100).name(), "Unassigned");
+        assert_eq!(SAFI_EVPN.name(), "BGP EVPNs");
+        assert_eq!(Safi::from(3).name(), "Reserved");
+        assert_eq!(Safi::from(250).name(), "Reserved for Private Use");
+    }
+}
@@ -0,0 +1,100 @@
+use core::fmt;
+
+/// The 2 octet stand-in for a 4 octet AS number (RFC 6793).
+pub const AS_TRANS: u32 = 23456;
+
+/// An Autonomous System number.
+///
+/// Displayed in asplain notation, `4200000000`. Use `asdot` for asdot
+/// notation, `64086.59904`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AsNumber(pub u32);
+
+impl AsNumber {
+    /// Formats the AS number in asdot notation (RFC 5396): AS numbers that
+    /// fit in 2 octets as is, others as the high and low 2 octets
+    /// separated by a period.
+    pub fn asdot(self) -> AsDot {
+        AsDot(self)
+    }
+
+    /// Whether the AS number is set aside for private use (RFC 6996).
+    pub fn is_private(self) -> bool {
+        matches!(self.0, 64512..=65534 | 4200000000..=4294967294)
+    }
+
+    /// Whether the AS number is reserved: 0 (RFC 7607), AS_TRANS, the
+    /// last AS numbers of the 2 and 4 octet ranges (RFC 7300), the
+    /// documentation ranges (RFC 5398) and 65552 to 131071.
+    pub fn is_reserved(self) -> bool {
+        matches!(self.0, 0 | AS_TRANS | 64496..=64511 | 65535 | 65536..=131071 | 4294967295)
+    }
+
+    pub fn is_as_trans(self) -> bool {
+        self.0 == AS_TRANS
+    }
+}
+
+impl From<u32> for AsNumber {
+    fn from(asn: u32) -> AsNumber {
+        AsNumber(asn)
+    }
+}
+
+impl From<AsNumber> for u32 {
+    fn from(asn: AsNumber) -> u32 {
+        asn.0
+    }
+}
+
+impl fmt::Display for AsNumber {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write!(fmt, "{}", self.0)
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for AsNumber {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}", self.0)
+    }
+}
+
+/// Displays an AS number in asdot notation. See `AsNumber::asdot`.
+#[derive(Debug, Clone, Copy)]
+pub struct AsDot(AsNumber);
+
+impl fmt::Display for AsDot {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let asn = (self.0).0;
+        if asn > 0xffff {
+            write!(fmt, "{}.{}", asn >> 16, asn & 0xffff)
+        } else {
+            write!(fmt, "{}", asn)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+    use types::FmtBuf;
+
+    #[test]
+    fn as_number() {
+        let mut buf = FmtBuf::new();
+        write!(buf, "{} {} {}", AsNumber(4200000000), AsNumber(4200000000).asdot(),
+               AsNumber(65000).asdot()).unwrap();
+        assert_eq!(buf.as_bytes(), b"4200000000 64086.59904 65000");
+
+        assert!(AsNumber(64512).is_private());
+        assert!(AsNumber(4294967294).is_private());
+        assert!(!AsNumber(65535).is_private());
+        assert!(AsNumber(65535).is_reserved());
+        assert!(AsNumber(0).is_reserved());
+        assert!(!AsNumber(3356).is_reserved());
+        assert!(AsNumber::from(AS_TRANS).is_as_trans());
+        assert_eq!(u32::from(AsNumber(3356)), 3356);
+    }
+}
@@ -0,0 +1,32 @@
+//! The flags octet that leads every path attribute (RFC 4271, section
+//! 4.3), and the `Attr` trait giving access to the header fields of any
+//! decoded attribute. Re-exported from `bgp::update::path_attr`.
+
+/// Defines whether the attribute is optional (if set to 1) or well-known (if set to 0)
+pub const FLAG_OPTIONAL:   u8 = 0b10000000;
+/// Defines whether an optional attribute is transitive (if set to 1) or non-transitive (if set to 0).
+/// For well-known attributes, the Transitive bit MUST be set to 1.
+pub const FLAG_TRANSITIVE: u8 = 0b01000000;
+/// Defines whether the information contained in the optional transitive attribute is partial (if
+/// set to 1) or complete (if set to 0).  For well-known attributes
+/// and for optional non-transitive attributes, the Partial bit
+/// MUST be set to 0.
+pub const FLAG_PARTIAL:    u8 = 0b00100000;
+/// Defines whether the Attribute Length is one octet (if set to 0) or two octets (if set to 1).
+pub const FLAG_EXT_LEN:    u8 = 0b00010000;
+
+pub trait Attr<'a> {
+
+    fn flags(&self) -> u8;
+    fn code(&self) -> u8;
+    fn value(&self) -> &'a [u8];
+
+    /// The Attribute Length: the length of the value.
+    fn len(&self) -> usize { self.value().len() }
+    fn is_empty(&self) -> bool { self.value().is_empty() }
+
+    fn is_optional(&self) ->   bool { self.flags() & FLAG_OPTIONAL > 0 }
+    fn is_partial(&self) ->    bool { self.flags() & FLAG_PARTIAL > 0 }
+    fn is_transitive(&self) -> bool { self.flags() & FLAG_TRANSITIVE > 0 }
+    fn is_ext_len(&self) ->    bool { self.flags() & FLAG_EXT_LEN > 0 }
+}
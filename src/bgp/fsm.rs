@@ -0,0 +1,263 @@
+//! The events of the BGP finite state machine (RFC 4271 section 8.1.4)
+//! that received messages give rise to, so that session implementations
+//! built on the parser share one mapping.
+
+use types::*;
+use bgp::{check_header_with_config, Message, SessionConfig};
+use bgp::notification::ErrorNotification;
+use bgp::open::{Open, OpenError};
+use bgp::update::Update;
+
+/// A message event. The error events carry what to send in the
+/// NOTIFICATION that closes the session.
+#[derive(Debug)]
+pub enum Event<'a> {
+    /// Event 19: a valid OPEN.
+    BgpOpen(Open<'a>),
+    /// Event 21: the message header is not valid.
+    BgpHeaderErr(HeaderError<'a>),
+    /// Event 22: the OPEN is not valid.
+    BgpOpenMsgErr(OpenError),
+    /// Event 24: a NOTIFICATION with the Unsupported Version Number
+    /// subcode.
+    NotifMsgVerErr(&'a [u8]),
+    /// Event 25: any other NOTIFICATION, with its error code, subcode and
+    /// data.
+    NotifMsg(u8, u8, &'a [u8]),
+    /// Event 26.
+    KeepAliveMsg,
+    /// Event 27: an UPDATE that parses.
+    UpdateMsg(Update<'a>),
+    /// Event 28: an UPDATE that does not parse.
+    UpdateMsgErr(UpdateError),
+}
+
+impl<'a> Event<'a> {
+    /// The event number of RFC 4271.
+    pub fn number(&self) -> u8 {
+        match *self {
+            Event::BgpOpen(_) => 19,
+            Event::BgpHeaderErr(_) => 21,
+            Event::BgpOpenMsgErr(_) => 22,
+            Event::NotifMsgVerErr(_) => 24,
+            Event::NotifMsg(..) => 25,
+            Event::KeepAliveMsg => 26,
+            Event::UpdateMsg(_) => 27,
+            Event::UpdateMsgErr(_) => 28,
+        }
+    }
+
+    /// The NOTIFICATION to send for an error event, or `None` for the
+    /// others.
+    pub fn notification(&self) -> Option<ErrorNotification<'a>> {
+        match *self {
+            Event::BgpHeaderErr(err) => Some(ErrorNotification::from(err)),
+            Event::BgpOpenMsgErr(err) => Some(ErrorNotification::from(err)),
+            Event::UpdateMsgErr(err) => Some(ErrorNotification::from_update_error(err, &[])),
+            _ => None,
+        }
+    }
+
+    /// The event for a message that parsed. An OPEN is checked with
+    /// `Open::validate`. ROUTE-REFRESH messages are not FSM events and
+    /// give `None`.
+    pub fn from_message(message: Message<'a>) -> Option<Event<'a>> {
+        let event = match message {
+            Message::Open(open) => match open.validate() {
+                Ok(()) => Event::BgpOpen(open),
+                Err(err) => Event::BgpOpenMsgErr(err),
+            },
+            Message::Update(update) => Event::UpdateMsg(update),
+            Message::Notification(_, raw) => notification(raw),
+            Message::KeepAlive(raw) if raw.len() != 19 =>
+                Event::BgpHeaderErr(HeaderError::BadMessageLength(&raw[16..18])),
+            Message::KeepAlive(_) => Event::KeepAliveMsg,
+            Message::Refresh(_) => return None,
+        };
+        Some(event)
+    }
+}
+
+fn notification(raw: &[u8]) -> Event<'_> {
+    match (raw[19], raw[20]) {
+        (2, 1) => Event::NotifMsgVerErr(&raw[21..]),
+        (code, subcode) => Event::NotifMsg(code, subcode, &raw[21..]),
+    }
+}
+
+/// Parses a complete message received on a session with the parameters
+/// in `config` and returns the event it gives rise to, or `None` for a
+/// ROUTE-REFRESH.
+///
+/// Messages too short for their type are header errors, as RFC 4271
+/// section 6.1 requires, and an UPDATE whose length fields do not fit
+/// is a Malformed Attribute List.
+pub fn classify<'a>(raw: &'a [u8], config: &SessionConfig) -> Option<Event<'a>> {
+    let message_type = match check_header_with_config(raw, config) {
+        Ok(message_type) => message_type,
+        Err(BgpError::Invalid) => return Some(Event::BgpHeaderErr(HeaderError::ConnectionNotSynchronized)),
+        Err(BgpError::BadLength) =>
+            return Some(Event::BgpHeaderErr(HeaderError::BadMessageLength(raw.get(16..18).unwrap_or(&[])))),
+    };
+    let min_len = match message_type {
+        1 => 29,
+        2 => 23,
+        3 => 21,
+        4 => 19,
+        5 => 23,
+        _ => return Some(Event::BgpHeaderErr(HeaderError::BadMessageType(&raw[18..19]))),
+    };
+    if raw.len() < min_len {
+        return Some(Event::BgpHeaderErr(HeaderError::BadMessageLength(&raw[16..18])));
+    }
+    match Message::from_bytes_with_config(raw, config) {
+        Ok(message) => Event::from_message(message),
+        Err(_) => match message_type {
+            1 => Some(Event::BgpOpenMsgErr(OpenError::Unspecific)),
+            2 => Some(Event::UpdateMsgErr(UpdateError::MalformedAttributeList)),
+            3 => Some(notification(raw)),
+            _ => None,
+        },
+    }
+}
+
+/// A Message Header Error.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeaderError<'a> {
+    ConnectionNotSynchronized,
+    /// With the erroneous Length field.
+    BadMessageLength(&'a [u8]),
+    /// With the erroneous Type field.
+    BadMessageType(&'a [u8]),
+}
+
+impl<'a> HeaderError<'a> {
+    /// The Error Code of Message Header Errors.
+    pub const CODE: u8 = 1;
+
+    /// The Error Subcode to send.
+    pub fn subcode(&self) -> u8 {
+        match *self {
+            HeaderError::ConnectionNotSynchronized => 1,
+            HeaderError::BadMessageLength(_) => 2,
+            HeaderError::BadMessageType(_) => 3,
+        }
+    }
+
+    /// The Data to send.
+    pub fn data(&self) -> &'a [u8] {
+        match *self {
+            HeaderError::ConnectionNotSynchronized => &[],
+            HeaderError::BadMessageLength(data) | HeaderError::BadMessageType(data) => data,
+        }
+    }
+}
+
+/// An UPDATE Message Error. `classify` only finds Malformed Attribute
+/// List; the others are for callers checking the attributes themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateError {
+    MalformedAttributeList,
+    UnrecognizedWellKnownAttribute,
+    MissingWellKnownAttribute,
+    AttributeFlagsError,
+    AttributeLengthError,
+    InvalidOriginAttribute,
+    InvalidNextHopAttribute,
+    OptionalAttributeError,
+    InvalidNetworkField,
+    MalformedAsPath,
+}
+
+impl UpdateError {
+    /// The Error Code of UPDATE Message Errors.
+    pub const CODE: u8 = 3;
+
+    /// The Error Subcode to send.
+    pub fn subcode(&self) -> u8 {
+        match *self {
+            UpdateError::MalformedAttributeList => 1,
+            UpdateError::UnrecognizedWellKnownAttribute => 2,
+            UpdateError::MissingWellKnownAttribute => 3,
+            UpdateError::AttributeFlagsError => 4,
+            UpdateError::AttributeLengthError => 5,
+            UpdateError::InvalidOriginAttribute => 6,
+            UpdateError::InvalidNextHopAttribute => 8,
+            UpdateError::OptionalAttributeError => 9,
+            UpdateError::InvalidNetworkField => 10,
+            UpdateError::MalformedAsPath => 11,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message(len: u16, msg_type: u8, body: &[u8], buf: &mut [u8; 64]) -> usize {
+        buf[..16].copy_from_slice(&[0xff; 16]);
+        buf[16..19].copy_from_slice(&[(len >> 8) as u8, len as u8, msg_type]);
+        buf[19..19 + body.len()].copy_from_slice(body);
+        19 + body.len()
+    }
+
+    #[test]
+    fn classify_messages() {
+        let config = SessionConfig::default();
+        let mut buf = [0; 64];
+
+        let len = message(19, 4, &[], &mut buf);
+        assert!(matches!(classify(&buf[..len], &config), Some(Event::KeepAliveMsg)));
+        assert_eq!(classify(&buf[..len], &config).unwrap().notification(), None);
+
+        let len = message(20, 4, &[0], &mut buf);
+        match classify(&buf[..len], &config) {
+            Some(Event::BgpHeaderErr(err)) => {
+                assert_eq!(err, HeaderError::BadMessageLength(&[0, 20]));
+                assert_eq!((HeaderError::CODE, err.subcode(), err.data()), (1, 2, &[0, 20][..]));
+            }
+            x => panic!("expected BgpHeaderErr, got {:?}", x)
+        }
+
+        let len = message(19, 9, &[], &mut buf);
+        match classify(&buf[..len], &config) {
+            Some(Event::BgpHeaderErr(err)) => assert_eq!(err, HeaderError::BadMessageType(&[9])),
+            x => panic!("expected BgpHeaderErr, got {:?}", x)
+        }
+
+        buf[0] = 0;
+        match classify(&buf[..len], &config) {
+            Some(event @ Event::BgpHeaderErr(HeaderError::ConnectionNotSynchronized)) => assert_eq!(event.number(), 21),
+            x => panic!("expected BgpHeaderErr, got {:?}", x)
+        }
+
+        let len = message(29, 1, &[4, 0xfd, 0xe8, 0, 90, 192, 0, 2, 1, 0], &mut buf);
+        assert!(matches!(classify(&buf[..len], &config), Some(Event::BgpOpen(_))));
+        buf[19] = 3;
+        assert!(matches!(classify(&buf[..len], &config),
+                         Some(Event::BgpOpenMsgErr(OpenError::UnsupportedVersionNumber))));
+        buf[28] = 1;
+        assert!(matches!(classify(&buf[..len], &config), Some(Event::BgpOpenMsgErr(OpenError::Unspecific))));
+
+        let len = message(23, 2, &[0, 0, 0, 0], &mut buf);
+        assert!(matches!(classify(&buf[..len], &config), Some(Event::UpdateMsg(_))));
+        buf[20] = 1;
+        match classify(&buf[..len], &config) {
+            Some(event @ Event::UpdateMsgErr(UpdateError::MalformedAttributeList)) => {
+                assert_eq!(event.number(), 28);
+                assert_eq!(event.notification(), Some(ErrorNotification::new(3, 1, &[])));
+            }
+            x => panic!("expected UpdateMsgErr, got {:?}", x)
+        }
+
+        let len = message(23, 3, &[2, 1, 0, 4], &mut buf);
+        assert!(matches!(classify(&buf[..len], &config), Some(Event::NotifMsgVerErr(&[0, 4]))));
+        let len = message(21, 3, &[6, 2], &mut buf);
+        assert!(matches!(classify(&buf[..len], &config), Some(Event::NotifMsg(6, 2, &[]))));
+        let len = message(21, 3, &[9, 9], &mut buf);
+        assert!(matches!(classify(&buf[..len], &config), Some(Event::NotifMsg(9, 9, &[]))));
+
+        let len = message(23, 5, &[0, 1, 0, 1], &mut buf);
+        assert!(classify(&buf[..len], &config).is_none());
+    }
+}
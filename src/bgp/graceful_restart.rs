@@ -0,0 +1,162 @@
+//! End-of-RIB tracking for Graceful Restart (RFC 4724): which of the
+//! address families both speakers negotiated have had their initial
+//! routing update completed, marking the points at which a collector's
+//! copy of the peer's table is consistent.
+
+use types::*;
+use bgp::open::Open;
+use bgp::open::OptionalParam;
+use bgp::open::capability::Capability;
+use bgp::update::Update;
+
+#[derive(Debug, Clone, Copy)]
+struct Family {
+    family: AfiSafi,
+    received: bool,
+}
+
+/// The End-of-RIB markers received for up to `N` address families.
+#[derive(Debug, Clone)]
+pub struct EndOfRib<const N: usize = 8> {
+    families: [Option<Family>; N],
+}
+
+impl<const N: usize> EndOfRib<N> {
+    pub fn new() -> EndOfRib<N> {
+        EndOfRib {
+            families: [None; N],
+        }
+    }
+
+    /// Tracks the families listed in the Graceful Restart capability of
+    /// both OPENs, e.g. those of `PeerUpNotification::opens`. Fails with
+    /// `BgpError::BadLength` if more than `N` are negotiated.
+    pub fn from_opens(sent: &Open, received: &Open) -> Result<EndOfRib<N>> {
+        let mut end_of_rib = EndOfRib::new();
+        for param in sent.params() {
+            let restart = match param? {
+                OptionalParam::Capability(Capability::GracefulRestart(restart)) => restart,
+                _ => continue,
+            };
+            for family in restart.families() {
+                let (family, _) = family?;
+                if graceful_restart_families(received).any(|f| f == family) {
+                    end_of_rib.add(family)?;
+                }
+            }
+        }
+        Ok(end_of_rib)
+    }
+
+    /// Tracks `family`. Fails with `BgpError::BadLength` if `N` families
+    /// are already tracked.
+    pub fn add(&mut self, family: AfiSafi) -> Result<()> {
+        if self.received(family).is_some() {
+            return Ok(());
+        }
+        let slot = self.families.iter_mut().find(|f| f.is_none()).ok_or(BgpError::BadLength)?;
+        *slot = Some(Family { family, received: false });
+        Ok(())
+    }
+
+    /// Returns the tracked family whose first End-of-RIB marker `update`
+    /// is, if any.
+    pub fn record(&mut self, update: &Update) -> Result<Option<AfiSafi>> {
+        let family = match update.end_of_rib()? {
+            Some(family) => family,
+            None => return Ok(None),
+        };
+        match self.families.iter_mut().flatten().find(|f| f.family == family) {
+            Some(f) if !f.received => {
+                f.received = true;
+                Ok(Some(family))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Whether the End-of-RIB marker of `family` has been received, or
+    /// `None` if it is not tracked.
+    pub fn received(&self, family: AfiSafi) -> Option<bool> {
+        self.families.iter().flatten().find(|f| f.family == family).map(|f| f.received)
+    }
+
+    /// Whether every tracked family has received its End-of-RIB marker.
+    pub fn is_complete(&self) -> bool {
+        self.families.iter().flatten().all(|f| f.received)
+    }
+}
+
+impl<const N: usize> Default for EndOfRib<N> {
+    fn default() -> EndOfRib<N> {
+        EndOfRib::new()
+    }
+}
+
+/// The families in the Graceful Restart capabilities of `open`, skipping
+/// any that do not decode.
+fn graceful_restart_families<'a>(open: &Open<'a>) -> impl Iterator<Item = AfiSafi> + 'a {
+    open.params()
+        .filter_map(|param| match param {
+            Ok(OptionalParam::Capability(Capability::GracefulRestart(restart))) => Some(restart.families()),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|family| family.ok().map(|(family, _)| family))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bgp::Message;
+    use core::convert::TryFrom;
+
+    fn open(bytes: &[u8]) -> Open<'_> {
+        match Message::try_from(bytes) {
+            Ok(Message::Open(open)) => open,
+            x => panic!("expected Open, got {:?}", x),
+        }
+    }
+
+    fn update(bytes: &[u8]) -> Update<'_> {
+        match Message::try_from(bytes) {
+            Ok(Message::Update(update)) => update,
+            x => panic!("expected Update, got {:?}", x),
+        }
+    }
+
+    #[test]
+    fn end_of_rib_markers() {
+        // Graceful Restart for IPv4 unicast, IPv6 unicast and IPv4 multicast
+        let sent = open(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                          0xff, 0xff, 0xff, 0xff, 0x00, 0x2f, 0x01, 0x04, 0xfd, 0xe8, 0x00, 0xb4,
+                          0xc0, 0x00, 0x02, 0x01, 0x12, 0x02, 0x10, 0x40, 0x0e, 0x00, 0x78,
+                          0x00, 0x01, 0x01, 0x00, 0x00, 0x02, 0x01, 0x00, 0x00, 0x01, 0x02, 0x00]);
+        // Graceful Restart for IPv4 unicast and IPv6 unicast
+        let received = open(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                              0xff, 0xff, 0xff, 0xff, 0x00, 0x2b, 0x01, 0x04, 0xfd, 0xe9, 0x00, 0xb4,
+                              0xc0, 0x00, 0x02, 0x02, 0x0e, 0x02, 0x0c, 0x40, 0x0a, 0x80, 0x78,
+                              0x00, 0x01, 0x01, 0x80, 0x00, 0x02, 0x01, 0x80]);
+        let ipv4 = update(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                            0xff, 0xff, 0xff, 0xff, 0x00, 0x17, 0x02, 0x00, 0x00, 0x00, 0x00]);
+        let ipv6 = update(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                            0xff, 0xff, 0xff, 0xff, 0x00, 0x1d, 0x02, 0x00, 0x00, 0x00, 0x06,
+                            0x80, 0x0f, 0x03, 0x00, 0x02, 0x01]);
+        let multicast = update(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                                 0xff, 0xff, 0xff, 0xff, 0x00, 0x1d, 0x02, 0x00, 0x00, 0x00, 0x06,
+                                 0x80, 0x0f, 0x03, 0x00, 0x01, 0x02]);
+
+        assert!(matches!(EndOfRib::<1>::from_opens(&sent, &received), Err(BgpError::BadLength)));
+        let mut end_of_rib: EndOfRib = EndOfRib::from_opens(&sent, &received).unwrap();
+        assert_eq!(end_of_rib.received(IPV4_UNICAST), Some(false));
+        assert_eq!(end_of_rib.received(IPV6_UNICAST), Some(false));
+        assert_eq!(end_of_rib.received(IPV4_MULTICAST), None);
+
+        assert_eq!(end_of_rib.record(&multicast).unwrap(), None);
+        assert_eq!(end_of_rib.record(&ipv6).unwrap(), Some(IPV6_UNICAST));
+        assert_eq!(end_of_rib.record(&ipv6).unwrap(), None);
+        assert!(!end_of_rib.is_complete());
+        assert_eq!(end_of_rib.record(&ipv4).unwrap(), Some(IPV4_UNICAST));
+        assert!(end_of_rib.is_complete());
+    }
+}
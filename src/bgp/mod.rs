@@ -1,19 +1,35 @@
 pub mod open;
 pub mod update;
 pub mod notification;
+pub mod route_refresh;
+pub mod fsm;
+pub mod prefix_limit;
+pub mod graceful_restart;
+pub mod attr_flags;
+#[cfg(feature = "alloc")]
+mod reassembler;
+#[cfg(feature = "alloc")]
+pub use self::reassembler::*;
 
 use types::*;
+use core::convert::TryFrom;
 use self::open::*;
+use self::open::capability::{AddPathDirection, Capability};
 use self::update::*;
 use self::notification::*;
+use self::route_refresh::*;
+#[cfg(feature = "stats")]
+use stats::ParserStats;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Message<'a> {
     Open(Open<'a>),
     Update(Update<'a>),
-    Notification(Notification<'a>),
-    KeepAlive,
-    Refresh,
+    /// A NOTIFICATION and the raw message it was parsed from.
+    Notification(Notification<'a>, &'a [u8]),
+    KeepAlive(&'a [u8]),
+    Refresh(RouteRefresh<'a>),
 }
 
 
@@ -71,30 +87,424 @@ impl<'a> Message<'a> {
     //     }
     // }
 
-    pub fn from_bytes(raw: &'a [u8], four_byte_asn: bool, add_paths: bool) -> Result<Message> {
-        if raw.len() < 19 || raw.len() > 4096 {
-            return Err(BgpError::BadLength);
+    pub fn from_bytes(raw: &'a [u8], four_byte_asn: bool, add_paths: bool) -> Result<Message<'a>> {
+        let config = SessionConfig {
+            four_byte_asn,
+            add_paths,
+            ..SessionConfig::default()
+        };
+        Message::from_bytes_with_config(raw, &config)
+    }
+
+    /// Parses a message according to `config`.
+    pub fn from_bytes_with_config(raw: &'a [u8], config: &SessionConfig) -> Result<Message<'a>> {
+        let message_type = check_header_with_config(raw, config)?;
+        Message::from_checked(raw, message_type, config)
+    }
+
+    /// Parses the body of a message whose header has already been checked.
+    fn from_checked(raw: &'a [u8], message_type: u8, config: &SessionConfig) -> Result<Message<'a>> {
+        match message_type {
+            1 => Ok(Message::Open(Open::from_message(raw)?)),
+            2 => Ok(Message::Update(Update::from_message(raw, config.four_byte_asn, config.add_paths)?
+                                        .with_max_labels(config.max_labels))),
+            3 => Ok(Message::Notification(Notification::from_message(raw)?, raw)),
+            4 => Ok(Message::KeepAlive(raw)),
+            5 => Ok(Message::Refresh(RouteRefresh::from_message(raw)?)),
+            _ => Err(BgpError::Invalid),
         }
-        let (marker, message) = raw.split_at(16);
+    }
 
-        if marker != VALID_BGP_MARKER {
-            return Err(BgpError::Invalid);
+    /// The complete message, header included.
+    #[inline]
+    pub fn raw(&self) -> &'a [u8] {
+        match *self {
+            Message::Open(ref open) => open.inner,
+            Message::Update(ref update) => update.inner,
+            Message::Refresh(ref refresh) => refresh.inner,
+            Message::Notification(_, raw) |
+            Message::KeepAlive(raw) => raw,
         }
+    }
 
-        let message_len  = (message[0] as usize) << 8 | (message[1] as usize);
-        let message_type = message[2];
+    /// The Type field of the message header.
+    #[inline]
+    pub fn msg_type(&self) -> u8 {
+        self.raw()[18]
+    }
+
+    /// The Length field of the message header, i.e. the length of `raw()`.
+    #[allow(clippy::len_without_is_empty)]
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.raw().len()
+    }
 
-        if message_len != raw.len() {
-            return Err(BgpError::BadLength);
+    /// The message following the 19-octet header.
+    #[inline]
+    pub fn body(&self) -> &'a [u8] {
+        &self.raw()[19..]
+    }
+
+    /// Decodes every part of the message, returning the first error.
+    ///
+    /// Parsing is lazy: `from_bytes` only checks what it needs to find the
+    /// parts of a message, and nested structures are decoded as they are
+    /// accessed. Fuzzers and strict ingestion pipelines can call this to
+    /// find out up front whether all of the message decodes.
+    pub fn deep_validate(&self) -> Result<()> {
+        match *self {
+            Message::Open(ref open) => open.deep_validate(),
+            Message::Update(ref update) => update.deep_validate(),
+            Message::Notification(ref notification, _) => notification.deep_validate(),
+            Message::Refresh(ref refresh) => refresh.deep_validate(),
+            _ => Ok(()),
         }
-        match message_type {
-            1 => Ok(Message::Open(try!(Open::from_bytes(raw)))),
-            2 => Ok(Message::Update(try!(Update::from_bytes(raw, four_byte_asn, add_paths)))),
-            3 => Ok(Message::Notification(try!(Notification::from_bytes(raw)))),
-            4 => Ok(Message::KeepAlive),
-            5 => Ok(Message::Refresh),
-            _ => Err(BgpError::Invalid),
+    }
+
+}
+
+/// Parses a BGP message received on a session with the parameters in
+/// `config`. This is the same as `Message::from_bytes_with_config`.
+pub fn decode<'a>(raw: &'a [u8], config: &SessionConfig) -> Result<Message<'a>> {
+    Message::from_bytes_with_config(raw, config)
+}
+
+/// Parses back to back messages, such as the BGP messages of an MRT
+/// replay, passing each to `f` in order. Returns the number of octets
+/// taken up by the messages passed, which is less than `bytes.len()` when
+/// `bytes` ends in a partial message.
+///
+/// A message that fails to parse is passed as an error and skipped. A
+/// Length field shorter than a message header makes the rest impossible
+/// to frame, so it is passed as `BgpError::BadLength` and ends the batch.
+pub fn parse_batch<'a, F>(bytes: &'a [u8], config: &SessionConfig, f: &mut F) -> usize
+    where F: FnMut(Result<Message<'a>>)
+{
+    let max_message_len = config.max_message_len();
+    let mut rest = bytes;
+    while let Some(Ok(header)) = rest.get(..19).map(<&[u8; 19]>::try_from) {
+        let [ref marker @ .., len_high, len_low, message_type] = *header;
+        let message_len = (len_high as usize) << 8 | len_low as usize;
+        if message_len < 19 {
+            f(Err(BgpError::BadLength));
+            break;
+        }
+        let raw = match rest.get(..message_len) {
+            Some(raw) => raw,
+            None => break,
+        };
+        rest = &rest[message_len..];
+        // the header checks of `check_header_with_config`, without
+        // reading the header again
+        f(if message_len > max_message_len {
+            Err(BgpError::BadLength)
+        } else if config.validate_marker && u128::from_ne_bytes(*marker) != u128::MAX {
+            Err(BgpError::Invalid)
+        } else {
+            Message::from_checked(raw, message_type, config)
+        });
+    }
+    bytes.len() - rest.len()
+}
+
+/// Like `decode`, recording the message or the error in `stats`.
+#[cfg(feature = "stats")]
+pub fn decode_with_stats<'a, const N: usize>(raw: &'a [u8], config: &SessionConfig,
+                                             stats: &mut ParserStats<N>) -> Result<Message<'a>> {
+    let message = decode(raw, config);
+    stats.record(&message);
+    message
+}
+
+/// Parses a message from a session using four-octet AS numbers and no
+/// ADD-PATH, which is what current BGP speakers negotiate. Use
+/// `Message::from_bytes` for other sessions.
+impl<'a> TryFrom<&'a [u8]> for Message<'a> {
+    type Error = BgpError;
+
+    fn try_from(raw: &'a [u8]) -> Result<Message<'a>> {
+        Message::from_bytes(raw, true, false)
+    }
+}
+
+/// What is known about the session a message was received on, as far as
+/// it affects parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionConfig {
+    /// Both speakers advertised the four-octet AS number capability.
+    pub four_byte_asn: bool,
+    /// ADD-PATH is in use, so NLRI carry a path identifier.
+    pub add_paths: bool,
+    /// Reject messages whose marker is not all ones. Turn this off for
+    /// feeds that zero out the marker, such as some BMP route mirroring
+    /// implementations and sanitized captures.
+    pub validate_marker: bool,
+    /// Both speakers advertised the Extended Message capability (RFC
+    /// 8654), raising the maximum message length from 4096 to 65535.
+    pub extended_message: bool,
+    /// The most labels a labeled or VPN NLRI carries: 1, unless raised
+    /// with the Multiple Labels capability (RFC 8277).
+    pub max_labels: u8,
+}
+
+impl SessionConfig {
+    /// The largest message this session may carry.
+    pub fn max_message_len(&self) -> usize {
+        if self.extended_message { 65535 } else { 4096 }
+    }
+
+    /// Sets `add_paths` from the ADD-PATH direction negotiated for the
+    /// address family, as seen by the receiver of the messages being
+    /// parsed: path identifiers are present when it receives.
+    pub fn add_path(mut self, negotiated: AddPathDirection) -> SessionConfig {
+        self.add_paths = negotiated.receives();
+        self
+    }
+
+    /// Sets `max_labels` for `family` from the Multiple Labels capability
+    /// of the OPENs: the count advertised in `sent`, the OPEN of the
+    /// receiver of the messages being parsed, if `received` advertises the
+    /// capability for the family too. 1 otherwise.
+    pub fn multiple_labels(mut self, sent: &Open, received: &Open, family: AfiSafi) -> SessionConfig {
+        self.max_labels = match (label_count(sent, family), label_count(received, family)) {
+            (Some(count), Some(_)) => count.max(1),
+            _ => 1,
+        };
+        self
+    }
+}
+
+fn label_count(open: &Open, family: AfiSafi) -> Option<u8> {
+    open.params().find_map(|param| match param {
+        Ok(OptionalParam::Capability(Capability::MultipleLabels(ref labels))) => labels.count(family),
+        _ => None,
+    })
+}
+
+/// Four-octet AS numbers, no ADD-PATH, marker validated.
+impl Default for SessionConfig {
+    fn default() -> SessionConfig {
+        SessionConfig {
+            four_byte_asn: true,
+            add_paths: false,
+            validate_marker: true,
+            extended_message: false,
+            max_labels: 1,
         }
     }
+}
+
+/// Validates the marker and length field of a message header and returns
+/// the message type.
+pub fn check_header(raw: &[u8]) -> Result<u8> {
+    check_header_with_config(raw, &SessionConfig::default())
+}
+
+/// Like `check_header`, but accepts any marker.
+pub fn check_header_length(raw: &[u8]) -> Result<u8> {
+    let config = SessionConfig { validate_marker: false, ..SessionConfig::default() };
+    check_header_with_config(raw, &config)
+}
+
+/// Validates a message header against the marker and maximum length
+/// settings of `config` and returns the message type.
+pub fn check_header_with_config(raw: &[u8], config: &SessionConfig) -> Result<u8> {
+    // a single bounds check covers the marker, length and type, and the
+    // marker is compared as one word rather than octet by octet
+    let header = match raw.get(..19).map(<&[u8; 19]>::try_from) {
+        Some(Ok(header)) if raw.len() <= config.max_message_len() => header,
+        _ => return Err(BgpError::BadLength),
+    };
+    let [ref marker @ .., len_high, len_low, message_type] = *header;
+    if config.validate_marker && u128::from_ne_bytes(*marker) != u128::MAX {
+        return Err(BgpError::Invalid);
+    }
+    let message_len = (len_high as usize) << 8 | len_low as usize;
+    if message_len != raw.len() {
+        return Err(BgpError::BadLength);
+    }
+    Ok(message_type)
+}
+
+/// Writes a message header to the start of `buf`: the marker, the Length
+/// field set to `len`, the length of the whole message header included,
+/// and the Type field set to `msg_type`.
+pub fn write_header(buf: &mut [u8], len: usize, msg_type: u8) -> Result<()> {
+    if buf.len() < 19 || !(19..=0xffff).contains(&len) {
+        return Err(BgpError::BadLength);
+    }
+    buf[..16].copy_from_slice(&VALID_BGP_MARKER);
+    buf[16..19].copy_from_slice(&[(len >> 8) as u8, len as u8, msg_type]);
+    Ok(())
+}
+
+/// Writes a KEEPALIVE message to the start of `buf` and returns it.
+pub fn write_keepalive(buf: &mut [u8]) -> Result<&[u8]> {
+    write_header(buf, 19, 4)?;
+    Ok(&buf[..19])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn raw_accessors() {
+        let bytes = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x13, 0x04];
+        let message = Message::try_from(&bytes[..]).unwrap();
+        assert_eq!(message.msg_type(), 4);
+        assert_eq!(message.len(), 19);
+        assert_eq!(message.raw(), &bytes[..]);
+        assert!(message.body().is_empty());
+
+        let bytes = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x15, 0x03, 0x06, 0x02];
+        let message = Message::try_from(&bytes[..]).unwrap();
+        assert_eq!(message.msg_type(), 3);
+        assert_eq!(message.len(), 21);
+        assert_eq!(message.body(), &[0x06, 0x02]);
+    }
+
+    #[test]
+    fn write_messages() {
+        let mut buf = [0; 32];
+        let keepalive = write_keepalive(&mut buf).unwrap();
+        assert!(matches!(Message::try_from(keepalive), Ok(Message::KeepAlive(_))));
+        assert!(write_keepalive(&mut buf[..18]).is_err());
+
+        write_header(&mut buf, 21, 3).unwrap();
+        buf[19..21].copy_from_slice(&[6, 2]);
+        assert!(matches!(Message::try_from(&buf[..21]), Ok(Message::Notification(Notification::Cease(2, _), _))));
+        assert!(write_header(&mut buf, 18, 3).is_err());
+        assert!(write_header(&mut buf, 0x10000, 3).is_err());
+    }
 
+    #[test]
+    fn skip_marker_validation() {
+        let bytes = &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+                      0x00, 0x00, 0x00, 0x00, 0x00, 0x1f, 0x02, 0x00, 0x00, 0x00, 0x04, 0x40,
+                      0x01, 0x01, 0x00, 0x18, 0xc6, 0x33, 0x64];
+        assert!(Message::try_from(&bytes[..]).is_err());
+
+        let config = SessionConfig { validate_marker: false, ..SessionConfig::default() };
+        match Message::from_bytes_with_config(bytes, &config) {
+            Ok(Message::Update(update)) => assert_eq!(update.nlris().count(), 1),
+            x => panic!("expected update, got {:?}", x)
+        }
+        // lengths are still checked
+        assert!(Message::from_bytes_with_config(&bytes[..30], &config).is_err());
+
+        // a single octet off in the marker
+        let mut keepalive = [0xff; 19];
+        keepalive[16..].copy_from_slice(&[0x00, 0x13, 0x04]);
+        assert_eq!(check_header(&keepalive).unwrap(), 4);
+        keepalive[9] = 0xfe;
+        assert!(matches!(check_header(&keepalive), Err(BgpError::Invalid)));
+        assert_eq!(check_header_length(&keepalive).unwrap(), 4);
+        assert!(matches!(check_header(&keepalive[..18]), Err(BgpError::BadLength)));
+    }
+
+    #[test]
+    fn extended_message_length() {
+        let mut bytes = [0; 5000];
+        bytes[..16].copy_from_slice(&VALID_BGP_MARKER);
+        bytes[16] = (5000 >> 8) as u8;
+        bytes[17] = (5000 & 0xff) as u8;
+        bytes[18] = 5;
+        assert!(Message::try_from(&bytes[..]).is_err());
+
+        let config = SessionConfig { extended_message: true, ..SessionConfig::default() };
+        let message = Message::from_bytes_with_config(&bytes, &config).unwrap();
+        assert_eq!(message.len(), 5000);
+    }
+
+    #[test]
+    fn deep_validate() {
+        let mut bytes = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                         0xff, 0xff, 0xff, 0xff, 0x00, 0x21, 0x02, 0x00, 0x00, 0x00, 0x07, 0xc0,
+                         0x08, 0x04, 0xfd, 0xe8, 0x00, 0x01, 0x10, 0xc6, 0x33];
+        let message = Message::try_from(&bytes[..]).unwrap();
+        assert!(message.deep_validate().is_ok());
+
+        // a /32 that overruns the message
+        bytes[30] = 0x20;
+        let message = Message::try_from(&bytes[..]).unwrap();
+        assert!(matches!(message.deep_validate(), Err(BgpError::BadLength)));
+    }
+
+    #[test]
+    fn parse_messages_in_batch() {
+        // three KEEPALIVEs and the start of a fourth
+        let mut bytes = [0xff; 19 * 3 + 10];
+        for i in 0..3 {
+            bytes[i * 19 + 16..i * 19 + 19].copy_from_slice(&[0x00, 0x13, 0x04]);
+        }
+        // an unknown message type
+        bytes[19 + 18] = 9;
+
+        let mut results = [None; 4];
+        let mut count = 0;
+        let consumed = parse_batch(&bytes, &SessionConfig::default(), &mut |message: Result<Message>| {
+            results[count] = Some(message.map(|message| message.msg_type()));
+            count += 1;
+        });
+        assert_eq!(consumed, 19 * 3);
+        assert_eq!(count, 3);
+        assert!(matches!(results[0], Some(Ok(4))));
+        assert!(matches!(results[1], Some(Err(BgpError::Invalid))));
+        assert!(matches!(results[2], Some(Ok(4))));
+
+        bytes[19 + 17] = 0x12;
+        let mut count = 0;
+        let consumed = parse_batch(&bytes, &SessionConfig::default(), &mut |message: Result<Message>| {
+            assert!(count > 0 || message.is_ok());
+            count += 1;
+        });
+        assert_eq!((consumed, count), (19, 2));
+    }
+
+    #[test]
+    fn session_config_add_path() {
+        assert!(SessionConfig::default().add_path(AddPathDirection::Both).add_paths);
+        assert!(SessionConfig::default().add_path(AddPathDirection::Receive).add_paths);
+        assert!(!SessionConfig::default().add_path(AddPathDirection::Send).add_paths);
+        assert!(!SessionConfig::default().add_path(AddPathDirection::Unknown(0)).add_paths);
+    }
+
+    #[test]
+    fn session_config_multiple_labels() {
+        // AS 65000, Multiple Labels: 3 labels for IPv4 labeled unicast
+        let sent = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                    0xff, 0xff, 0xff, 0xff, 0x00, 0x25, 0x01, 0x04, 0xfd, 0xe8, 0x00, 0xb4,
+                    0x0a, 0x00, 0x00, 0x01, 0x08, 0x02, 0x06, 0x08, 0x04, 0x00, 0x01, 0x04,
+                    0x03];
+        let mut received = sent;
+        received[36] = 2;
+        // the four-octet AS capability instead
+        let mut plain = sent;
+        plain[31..].copy_from_slice(&[0x41, 0x04, 0x00, 0x00, 0xfd, 0xe8]);
+        let (sent, received, plain) = (Open::from_bytes(&sent).unwrap(), Open::from_bytes(&received).unwrap(),
+                                       Open::from_bytes(&plain).unwrap());
+        let family = AfiSafi::new(AFI_IPV4, SAFI_MPLS_LABEL);
+        assert_eq!(SessionConfig::default().max_labels, 1);
+        assert_eq!(SessionConfig::default().multiple_labels(&sent, &received, family).max_labels, 3);
+        assert_eq!(SessionConfig::default().multiple_labels(&received, &sent, family).max_labels, 2);
+        assert_eq!(SessionConfig::default().multiple_labels(&sent, &plain, family).max_labels, 1);
+        assert_eq!(SessionConfig::default().multiple_labels(&plain, &received, family).max_labels, 1);
+        let config = SessionConfig::default().multiple_labels(&sent, &received, IPV4_UNICAST);
+        assert_eq!(config.max_labels, 1);
+
+        let config = SessionConfig::default().multiple_labels(&sent, &received, family);
+        let mut eor = [0xff; 23];
+        eor[16..].copy_from_slice(&[0x00, 0x17, 0x02, 0x00, 0x00, 0x00, 0x00]);
+        match Message::from_bytes_with_config(&eor, &config) {
+            Ok(Message::Update(update)) => {
+                assert_eq!(update.max_labels(), 3);
+                assert_eq!(update.path_attrs().max_labels(), 3);
+            }
+            x => panic!("expected Update, got {:?}", x)
+        }
+    }
 }
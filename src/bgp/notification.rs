@@ -2,8 +2,13 @@
 //! The BGP connection is closed immediately after it is sent.
 
 use types::*;
+use bgp::{check_header, write_header};
+use bgp::fsm::{HeaderError, UpdateError};
+use bgp::open::OpenError;
+use core::convert::TryFrom;
 
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Notification<'a> {
     // message header errors
     /// The expected value of the Marker field of the message header is all
@@ -14,18 +19,18 @@ pub enum Notification<'a> {
     /// If at least one of the following is true:
     ///
     /// - if the Length field of the message header is less than 19 or
-    /// greater than 4096, or
+    ///   greater than 4096, or
     ///
     /// - if the Length field of an OPEN message is less than the minimum
-    /// length of the OPEN message, or
+    ///   length of the OPEN message, or
     ///
     /// - if the Length field of an UPDATE message is less than the
-    /// minimum length of the UPDATE message, or
+    ///   minimum length of the UPDATE message, or
     ///
     /// - if the Length field of a KEEPALIVE message is not equal to 19, or
     ///
     /// - if the Length field of a NOTIFICATION message is less than the
-    /// minimum length of the NOTIFICATION message,
+    ///   minimum length of the NOTIFICATION message,
     ///
     /// then the Error Subcode MUST be set to Bad Message Length.  The Data
     /// field MUST contain the erroneous Length field.
@@ -185,16 +190,38 @@ pub enum Notification<'a> {
     /// received from the neighbor exceeds the locally-configured, upper
     /// bound, then the speaker MUST send the neighbor a NOTIFICATION message
     /// with the Error Code Cease.  The speaker MAY also log this locally.
-    Cease(&'a [u8]),
+    ///
+    /// With the Error Subcode (RFC 4486) and the Data.
+    Cease(u8, &'a [u8]),
 }
 
+/// The Cease subcode Administrative Shutdown.
+pub const CEASE_ADMINISTRATIVE_SHUTDOWN: u8 = 2;
+/// The Cease subcode Administrative Reset.
+pub const CEASE_ADMINISTRATIVE_RESET: u8 = 4;
+/// The Cease subcode Hard Reset.
+pub const CEASE_HARD_RESET: u8 = 9;
+
 impl<'a> Notification<'a> {
 
-    pub fn from_bytes(bytes: &'a [u8]) -> Result<Notification<'a>> {
-        if bytes.len() < 2 {
+    pub fn from_bytes(raw: &'a [u8]) -> Result<Notification<'a>> {
+        Notification::try_from(raw)
+    }
+
+    /// Decodes a message whose header has already been checked.
+    pub(crate) fn from_message(raw: &'a [u8]) -> Result<Notification<'a>> {
+        if raw.len() < 19 + 2 {
             return Err(BgpError::BadLength);
         }
+        Notification::from_value(&raw[19..])
+    }
 
+    /// Decodes the Error Code, Error Subcode and Data following the
+    /// header, as also encapsulated by a Hard Reset.
+    fn from_value(bytes: &'a [u8]) -> Result<Notification<'a>> {
+        if bytes.len() < 2 {
+            return Err(BgpError::BadLength);
+        }
         let error_code = bytes[0];
         let error_subcode = bytes[1];
         let data = &bytes[2..];
@@ -225,9 +252,286 @@ impl<'a> Notification<'a> {
 
             (4,_) => Notification::HoldTimerExpired(data),
             (5,_) => Notification::FiniteStateMachineError(data),
-            (6,_) => Notification::Cease(data),
+            (6,subcode) => Notification::Cease(subcode, data),
             _ => return Err(BgpError::Invalid),
         };
         Ok(notification)
     }
+
+    /// The name IANA lists for the error subcode, or for the error code
+    /// when it has no subcodes.
+    #[cfg(feature = "names")]
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Notification::ConnectionNotSynchronised(_) => "Connection Not Synchronized",
+            Notification::BadMessageLength(_) => "Bad Message Length",
+            Notification::BadMessageType(_) => "Bad Message Type",
+            Notification::UnsupportedVersionNumber(_) => "Unsupported Version Number",
+            Notification::BadPeerAs(_) => "Bad Peer AS",
+            Notification::BadBgpIdentifier(_) => "Bad BGP Identifier",
+            Notification::UnsupportedOptionalParameter(_) => "Unsupported Optional Parameter",
+            Notification::AuthenticationFailure(_) => "Deprecated",
+            Notification::UnacceptableHoldTime(_) => "Unacceptable Hold Time",
+            Notification::MalformedAttributeList(_) => "Malformed Attribute List",
+            Notification::UnrecognizedWellKnownAttribute(_) => "Unrecognized Well-known Attribute",
+            Notification::MissingWellKnownAttribute(_) => "Missing Well-known Attribute",
+            Notification::AttributeFlagsError(_) => "Attribute Flags Error",
+            Notification::AttributeLengthError(_) => "Attribute Length Error",
+            Notification::InvalidOriginAttribute(_) => "Invalid ORIGIN Attribute",
+            Notification::AsRoutingLoop(_) => "Deprecated",
+            Notification::InvalidNextHopAttribute(_) => "Invalid NEXT_HOP Attribute",
+            Notification::OptionalAttributeError(_) => "Optional Attribute Error",
+            Notification::InvalidNetworkField(_) => "Invalid Network Field",
+            Notification::MalformedAsPath(_) => "Malformed AS_PATH",
+            Notification::HoldTimerExpired(_) => "Hold Timer Expired",
+            Notification::FiniteStateMachineError(_) => "Finite State Machine Error",
+            Notification::Cease(subcode, _) => match subcode {
+                1 => "Maximum Number of Prefixes Reached",
+                2 => "Administrative Shutdown",
+                3 => "Peer De-configured",
+                4 => "Administrative Reset",
+                5 => "Connection Rejected",
+                6 => "Other Configuration Change",
+                7 => "Connection Collision Resolution",
+                8 => "Out of Resources",
+                9 => "Hard Reset",
+                10 => "BFD Down",
+                _ => "Cease",
+            },
+        }
+    }
+}
+
+impl<'a> Notification<'a> {
+    /// The Shutdown Communication of an Administrative Shutdown or
+    /// Administrative Reset (RFC 9003): a length octet followed by that
+    /// many octets of UTF-8. `None` for other notifications and when the
+    /// Data is empty or the length is 0.
+    ///
+    /// Fails with `BgpError::BadLength` if the length octet does not match
+    /// the rest of the Data, and with `BgpError::Invalid` if the message is
+    /// not UTF-8.
+    pub fn shutdown_message(&self) -> Result<Option<&'a str>> {
+        let data = match *self {
+            Notification::Cease(CEASE_ADMINISTRATIVE_SHUTDOWN, data) |
+            Notification::Cease(CEASE_ADMINISTRATIVE_RESET, data) => data,
+            _ => return Ok(None),
+        };
+        let (&len, message) = match data.split_first() {
+            Some(split) => split,
+            None => return Ok(None),
+        };
+        if message.len() != len as usize {
+            return Err(BgpError::BadLength);
+        }
+        match core::str::from_utf8(message) {
+            Ok("") => Ok(None),
+            Ok(message) => Ok(Some(message)),
+            Err(_) => Err(BgpError::Invalid),
+        }
+    }
+
+    /// The notification a Hard Reset (RFC 8538) encapsulates: the error
+    /// that made the speaker reset the session without retaining routes
+    /// for Graceful Restart. `None` for other notifications.
+    ///
+    /// Fails like `Notification::from_bytes` if the encapsulated
+    /// notification does not parse.
+    pub fn hard_reset(&self) -> Result<Option<Notification<'a>>> {
+        match *self {
+            Notification::Cease(CEASE_HARD_RESET, data) => Notification::from_value(data).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    /// Decodes the Shutdown Communication and the notification a Hard
+    /// Reset encapsulates, returning the first error. See
+    /// `Message::deep_validate`. A Hard Reset inside a Hard Reset fails
+    /// with `BgpError::Invalid`, so nesting is decoded one level deep.
+    pub fn deep_validate(&self) -> Result<()> {
+        self.shutdown_message()?;
+        match self.hard_reset()? {
+            Some(Notification::Cease(CEASE_HARD_RESET, _)) => Err(BgpError::Invalid),
+            Some(inner) => inner.shutdown_message().map(|_| ()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Notification<'a> {
+    type Error = BgpError;
+
+    fn try_from(raw: &'a [u8]) -> Result<Notification<'a>> {
+        if check_header(raw)? != 3 {
+            return Err(BgpError::Invalid);
+        }
+        Notification::from_message(raw)
+    }
+}
+
+/// A NOTIFICATION to send: the Error Code, Error Subcode and Data, chosen
+/// from the error found so that callers need not encode them by hand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorNotification<'a> {
+    pub code: u8,
+    pub subcode: u8,
+    pub data: &'a [u8],
+}
+
+impl<'a> ErrorNotification<'a> {
+    pub fn new(code: u8, subcode: u8, data: &'a [u8]) -> ErrorNotification<'a> {
+        ErrorNotification { code, subcode, data }
+    }
+
+    /// An UPDATE Message Error, with the Data RFC 4271 section 6.3 calls
+    /// for taken from `attr`: the erroneous attribute (type, length and
+    /// value) for errors about an attribute, and the type code of the
+    /// attribute for Missing Well-known Attribute. Malformed Attribute
+    /// List, Invalid Network Field and Malformed AS_PATH carry no Data and
+    /// ignore `attr`.
+    pub fn from_update_error(err: UpdateError, attr: &'a [u8]) -> ErrorNotification<'a> {
+        let data = match err {
+            UpdateError::MalformedAttributeList |
+            UpdateError::InvalidNetworkField |
+            UpdateError::MalformedAsPath => &[],
+            _ => attr,
+        };
+        ErrorNotification::new(UpdateError::CODE, err.subcode(), data)
+    }
+
+    /// Writes the NOTIFICATION to the start of `buf` and returns it.
+    pub fn write<'b>(&self, buf: &'b mut [u8]) -> Result<&'b [u8]> {
+        let len = 21 + self.data.len();
+        if buf.len() < len {
+            return Err(BgpError::BadLength);
+        }
+        write_header(buf, len, 3)?;
+        buf[19] = self.code;
+        buf[20] = self.subcode;
+        buf[21..len].copy_from_slice(self.data);
+        Ok(&buf[..len])
+    }
+
+    /// Writes a Hard Reset (RFC 8538) encapsulating the NOTIFICATION to
+    /// the start of `buf` and returns it. On sessions that negotiated the
+    /// N bit of Graceful Restart, this is what to send instead, so that
+    /// the peer does not retain the routes.
+    pub fn write_hard_reset<'b>(&self, buf: &'b mut [u8]) -> Result<&'b [u8]> {
+        let len = 23 + self.data.len();
+        if buf.len() < len {
+            return Err(BgpError::BadLength);
+        }
+        write_header(buf, len, 3)?;
+        buf[19..23].copy_from_slice(&[6, CEASE_HARD_RESET, self.code, self.subcode]);
+        buf[23..len].copy_from_slice(self.data);
+        Ok(&buf[..len])
+    }
+}
+
+impl From<OpenError> for ErrorNotification<'static> {
+    fn from(err: OpenError) -> ErrorNotification<'static> {
+        ErrorNotification::new(OpenError::CODE, err.subcode(), err.data())
+    }
+}
+
+impl<'a> From<HeaderError<'a>> for ErrorNotification<'a> {
+    fn from(err: HeaderError<'a>) -> ErrorNotification<'a> {
+        ErrorNotification::new(HeaderError::CODE, err.subcode(), err.data())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bgp::Message;
+
+    #[test]
+    fn parse_notification() {
+        let bytes = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x17, 0x03, 0x02, 0x02, 0xfd, 0xe8];
+        match Message::try_from(&bytes[..]) {
+            Ok(Message::Notification(Notification::BadPeerAs(data), _)) => {
+                assert_eq!(data, &[0xfd, 0xe8]);
+            }
+            x => panic!("expected BadPeerAs, got {:?}", x)
+        }
+        assert!(Notification::try_from(&bytes[..22]).is_err());
+        #[cfg(feature = "names")]
+        assert_eq!(Notification::try_from(&bytes[..]).unwrap().name(), "Bad Peer AS");
+    }
+
+    #[test]
+    fn write_error_notifications() {
+        let mut buf = [0; 32];
+        let raw = ErrorNotification::from(OpenError::UnsupportedVersionNumber).write(&mut buf).unwrap();
+        assert!(matches!(Notification::try_from(raw), Ok(Notification::UnsupportedVersionNumber(&[0, 4]))));
+
+        let raw = ErrorNotification::from(HeaderError::BadMessageType(&[9])).write(&mut buf).unwrap();
+        assert_eq!(&raw[16..], &[0x00, 0x16, 0x03, 0x01, 0x03, 0x09]);
+
+        // ORIGIN with an undefined value
+        let origin = &[0x40, 0x01, 0x01, 0x03];
+        let err = ErrorNotification::from_update_error(UpdateError::InvalidOriginAttribute, origin);
+        assert_eq!(err, ErrorNotification::new(3, 6, origin));
+        let raw = err.write(&mut buf).unwrap();
+        assert!(matches!(Notification::try_from(raw), Ok(Notification::InvalidOriginAttribute(&[0x40, 0x01, 0x01, 0x03]))));
+        assert!(matches!(err.write(&mut buf[..24]), Err(BgpError::BadLength)));
+
+        let err = ErrorNotification::from_update_error(UpdateError::MalformedAsPath, origin);
+        assert_eq!((err.code, err.subcode, err.data), (3, 11, &[][..]));
+    }
+
+    #[test]
+    fn shutdown_communication() {
+        let mut buf = [0; 64];
+        let data = b"\x0fmaintenance \xe2\x9c\x93";
+        let raw = ErrorNotification::new(6, CEASE_ADMINISTRATIVE_SHUTDOWN, data).write(&mut buf).unwrap();
+        let notification = Notification::try_from(raw).unwrap();
+        assert_eq!(notification.shutdown_message().unwrap(), Some("maintenance \u{2713}"));
+        #[cfg(feature = "names")]
+        assert_eq!(notification.name(), "Administrative Shutdown");
+
+        assert_eq!(Notification::Cease(CEASE_ADMINISTRATIVE_RESET, &[]).shutdown_message().unwrap(), None);
+        assert_eq!(Notification::Cease(CEASE_ADMINISTRATIVE_RESET, &[0]).shutdown_message().unwrap(), None);
+        assert_eq!(Notification::Cease(3, &data[..]).shutdown_message().unwrap(), None);
+        assert!(matches!(Notification::Cease(CEASE_ADMINISTRATIVE_RESET, &data[..15]).shutdown_message(),
+                         Err(BgpError::BadLength)));
+        assert!(matches!(Notification::Cease(CEASE_ADMINISTRATIVE_RESET, &[2, 0xc3, 0x28]).shutdown_message(),
+                         Err(BgpError::Invalid)));
+    }
+
+    #[test]
+    fn hard_reset() {
+        let mut buf = [0; 64];
+        let shutdown = ErrorNotification::new(6, CEASE_ADMINISTRATIVE_SHUTDOWN, b"\x04down");
+        let raw = shutdown.write_hard_reset(&mut buf).unwrap();
+        assert_eq!(&raw[16..], b"\x00\x1c\x03\x06\x09\x06\x02\x04down");
+        let notification = Notification::try_from(raw).unwrap();
+        #[cfg(feature = "names")]
+        assert_eq!(notification.name(), "Hard Reset");
+        let inner = notification.hard_reset().unwrap().unwrap();
+        assert!(matches!(inner, Notification::Cease(CEASE_ADMINISTRATIVE_SHUTDOWN, _)));
+        assert_eq!(inner.shutdown_message().unwrap(), Some("down"));
+        assert!(inner.hard_reset().unwrap().is_none());
+
+        let raw = ErrorNotification::new(4, 0, &[]).write_hard_reset(&mut buf).unwrap();
+        assert!(matches!(Notification::try_from(raw).unwrap().hard_reset(), Ok(Some(Notification::HoldTimerExpired(&[])))));
+        assert!(matches!(Notification::Cease(CEASE_HARD_RESET, &[4]).hard_reset(), Err(BgpError::BadLength)));
+        assert!(matches!(Notification::Cease(CEASE_HARD_RESET, &[9, 1]).hard_reset(), Err(BgpError::Invalid)));
+        assert!(matches!(Notification::Cease(CEASE_HARD_RESET, &[6, 2, 5]).deep_validate(), Err(BgpError::BadLength)));
+        assert!(matches!(shutdown.write_hard_reset(&mut buf[..27]), Err(BgpError::BadLength)));
+    }
+
+    #[test]
+    fn nested_hard_reset() {
+        assert!(Notification::Cease(CEASE_HARD_RESET, &[6, 2, 0]).deep_validate().is_ok());
+        assert!(matches!(Notification::Cease(CEASE_HARD_RESET, &[6, 9, 4, 0]).deep_validate(),
+                         Err(BgpError::Invalid)));
+        // a deeply nested Hard Reset is rejected without recursing
+        let mut data = [CEASE_HARD_RESET; 4096];
+        for pair in data.chunks_mut(2) {
+            pair[0] = 6;
+        }
+        assert!(matches!(Notification::Cease(CEASE_HARD_RESET, &data).deep_validate(), Err(BgpError::Invalid)));
+    }
 }
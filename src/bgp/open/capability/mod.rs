@@ -13,14 +13,25 @@ pub enum Capability<'a> {
     RouteRefresh(RouteRefresh<'a>),
     /// Outbound Route Filtering Capability. RFC 5291.
     Orf(Orf<'a>),
-    /// Carrying Label Information. RFC 3107.
+    /// Multiple routes to a destination. RFC 3107, deprecated by RFC 8277.
+    /// Always has an empty value.
     MultipleRoutes(MultipleRoutes<'a>),
     /// Advertising IPv4 Network Layer Reachability Information with an IPv6 Next Hop. RFC 5549.
     ExtendedNextHopEncoding(ExtendedNextHopEncoding<'a>),
+    /// Extended Message Support for BGP. RFC 8654.
+    ExtendedMessage(ExtendedMessage<'a>),
+    /// BGPsec. RFC 8205.
+    BgpSec(BgpSec<'a>),
+    /// Multiple Labels. RFC 8277.
+    MultipleLabels(MultipleLabels<'a>),
+    /// BGP Role. RFC 9234.
+    Role(Role<'a>),
     /// Graceful Restart Mechanism. RFC 4724.
     GracefulRestart(GracefulRestart<'a>),
     /// BGP Support for Four-Octet Autonomous System (AS) Number Space. RFC 6793.
     FourByteASN(FourByteASN<'a>),
+    /// Deprecated (2003-03-06). Formerly used for dynamic capability.
+    DeprecatedDynamicCapability(DeprecatedDynamicCapability<'a>),
     /// Dynamic Capability. draft-ietf-idr-dynamic-cap.
     DynamicCapability(DynamicCapability<'a>),
     /// Multisession BGP. draft-ietf-idr-bgp-multisession.
@@ -29,6 +40,10 @@ pub enum Capability<'a> {
     AddPath(AddPath<'a>),
     /// Enhanced Route Refresh Capability. RFC 7313.
     EnhancedRouteRefresh(EnhancedRouteRefresh<'a>),
+    /// Long-Lived Graceful Restart. RFC 9494.
+    LongLivedGracefulRestart(LongLivedGracefulRestart<'a>),
+    /// Hostname Capability. draft-walton-bgp-hostname-capability.
+    Fqdn(Fqdn<'a>),
     /// Private use capability codes.
     Private(Private<'a>),
     /// Unassigned capability codes.
@@ -39,32 +54,98 @@ impl<'a> Capability<'a> {
 
     #[cfg_attr(feature="clippy", allow(match_same_arms))]
     pub fn from_bytes(bytes: &'a [u8]) -> Result<Capability<'a>> {
-        if bytes.len() < 2 { return Err(BgpError::BadLength) }
-        let capability_type = bytes[0];
-        let capability_len  = bytes[1] as usize;
+        let capability_type = bytes.at(0)?;
+        let capability_len  = bytes.at(1)? as usize;
         if bytes.len() != capability_len + 2 {
             return Err(BgpError::BadLength);
         }
-        let subslice = &bytes[..];
+        let subslice = bytes;
         match (capability_type, capability_len) {
             ( 0, _) => Err(BgpError::Invalid),
             ( 1, 4) => Ok(Capability::MultiProtocol(MultiProtocol{inner: subslice})),
             ( 1, _) => Err(BgpError::Invalid),
             ( 2, _) => Ok(Capability::RouteRefresh(RouteRefresh{inner: subslice})),
             ( 3, _) => Ok(Capability::Orf(Orf{inner: subslice})),
-            ( 4, _) => Ok(Capability::MultipleRoutes(MultipleRoutes{inner: subslice})),
+            ( 4, 0) => Ok(Capability::MultipleRoutes(MultipleRoutes{inner: subslice})),
+            ( 4, _) => Err(BgpError::Invalid),
             ( 5, _) => Ok(Capability::ExtendedNextHopEncoding(ExtendedNextHopEncoding{inner: subslice})),
+            ( 6, 0) => Ok(Capability::ExtendedMessage(ExtendedMessage{inner: subslice})),
+            ( 6, _) => Err(BgpError::Invalid),
+            ( 7, 3) => Ok(Capability::BgpSec(BgpSec{inner: subslice})),
+            ( 7, _) => Err(BgpError::Invalid),
+            ( 8, _) => Ok(Capability::MultipleLabels(MultipleLabels{inner: subslice})),
+            ( 9, 1) => Ok(Capability::Role(Role{inner: subslice})),
+            ( 9, _) => Err(BgpError::Invalid),
             (64, _) => Ok(Capability::GracefulRestart(GracefulRestart{inner: subslice})),
             (65, 4) => Ok(Capability::FourByteASN(FourByteASN{inner: subslice})),
             (65, _) => Err(BgpError::Invalid),
+            (66, _) => Ok(Capability::DeprecatedDynamicCapability(DeprecatedDynamicCapability{inner: subslice})),
             (67, _) => Ok(Capability::DynamicCapability(DynamicCapability{inner: subslice})),
             (68, _) => Ok(Capability::MultiSession(MultiSession{inner: subslice})),
             (69, 4) => Ok(Capability::AddPath(AddPath{inner: subslice})),
             (69, _) => Err(BgpError::Invalid),
             (70, _) => Ok(Capability::EnhancedRouteRefresh(EnhancedRouteRefresh{inner: subslice})),
-            (128...255, _) =>
+            (71, _) => Ok(Capability::LongLivedGracefulRestart(LongLivedGracefulRestart{inner: subslice})),
+            (73, _) => Ok(Capability::Fqdn(Fqdn{inner: subslice})),
+            (128..=255, _) =>
                   Ok(Capability::Private(Private{inner: subslice})),
-            __ => Ok(Capability::Other(Other{inner: subslice})),
+            _ => Ok(Capability::Other(Other{inner: subslice})),
+        }
+    }
+
+    /// Whether the capability code is deprecated: Multiple Routes to a
+    /// Destination (RFC 8277) and the pre-2003 Dynamic Capability code.
+    pub fn is_deprecated(&self) -> bool {
+        matches!(*self, Capability::MultipleRoutes(_) | Capability::DeprecatedDynamicCapability(_))
+    }
+
+    /// The name IANA lists for the capability code.
+    #[cfg(feature = "names")]
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Capability::MultiProtocol(_) => "Multiprotocol Extensions for BGP-4",
+            Capability::RouteRefresh(_) => "Route Refresh Capability for BGP-4",
+            Capability::Orf(_) => "Outbound Route Filtering Capability",
+            Capability::MultipleRoutes(_) => "Multiple routes to a destination capability (deprecated)",
+            Capability::ExtendedNextHopEncoding(_) => "Extended Next Hop Encoding",
+            Capability::ExtendedMessage(_) => "BGP Extended Message",
+            Capability::BgpSec(_) => "BGPsec Capability",
+            Capability::MultipleLabels(_) => "Multiple Labels Capability",
+            Capability::Role(_) => "BGP Role",
+            Capability::GracefulRestart(_) => "Graceful Restart Capability",
+            Capability::FourByteASN(_) => "Support for 4-octet AS number capability",
+            Capability::DeprecatedDynamicCapability(_) => "Deprecated (2003-03-06)",
+            Capability::DynamicCapability(_) => "Support for Dynamic Capability (capability specific)",
+            Capability::MultiSession(_) => "Multisession BGP Capability",
+            Capability::AddPath(_) => "ADD-PATH Capability",
+            Capability::EnhancedRouteRefresh(_) => "Enhanced Route Refresh Capability",
+            Capability::LongLivedGracefulRestart(_) => "Long-Lived Graceful Restart (LLGR) Capability",
+            Capability::Fqdn(_) => "FQDN Capability",
+            Capability::Private(_) => "Reserved for Private Use",
+            Capability::Other(ref other) => match other.code() {
+                72 => "Routing Policy Distribution",
+                74 => "BFD Capability",
+                75 => "Software Version Capability",
+                _ => "Unassigned",
+            },
+        }
+    }
+
+    /// Decodes the parts of the capability that are otherwise decoded on
+    /// access, returning the first error.
+    pub fn deep_validate(&self) -> Result<()> {
+        match *self {
+            Capability::MultipleLabels(ref labels) => validate_all(labels.entries()),
+            Capability::GracefulRestart(ref restart) => {
+                restart.restart_time().ok_or(BgpError::BadLength)?;
+                validate_all(restart.families())
+            }
+            Capability::Fqdn(ref fqdn) => {
+                fqdn.hostname().ok_or(BgpError::BadLength)?;
+                fqdn.domain_name().ok_or(BgpError::BadLength)?;
+                Ok(())
+            }
+            _ => Ok(()),
         }
     }
 }
@@ -81,6 +162,7 @@ macro_rules! define_capability {
         }
 
         impl<'a> CapabilityCode for $name<'a> {
+            #[inline]
             fn code(&self) -> u8 {
                 self.inner[0]
             }
@@ -93,12 +175,19 @@ define_capability!(RouteRefresh);
 define_capability!(Orf);
 define_capability!(MultipleRoutes);
 define_capability!(ExtendedNextHopEncoding);
+define_capability!(ExtendedMessage);
+define_capability!(BgpSec);
+define_capability!(MultipleLabels);
+define_capability!(Role);
 define_capability!(GracefulRestart);
 define_capability!(FourByteASN);
+define_capability!(DeprecatedDynamicCapability);
 define_capability!(DynamicCapability);
 define_capability!(MultiSession);
 define_capability!(AddPath);
 define_capability!(EnhancedRouteRefresh);
+define_capability!(LongLivedGracefulRestart);
+define_capability!(Fqdn);
 define_capability!(Private);
 define_capability!(Other);
 
@@ -110,14 +199,53 @@ impl<'a> MultiProtocol<'a> {
     pub fn safi(&self) -> Safi {
         Safi::from(self.inner[5])
     }
+
+    pub fn afi_safi(&self) -> AfiSafi {
+        AfiSafi::new(self.afi(), self.safi())
+    }
 }
 
-#[derive(Debug,PartialEq)]
-pub struct AddPathDirection(u8);
+/// Whether a speaker can receive, send, or both receive and send
+/// multiple paths for an address family.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddPathDirection {
+    Receive,
+    Send,
+    Both,
+    Unknown(u8),
+}
 
-pub const ADDPATH_DIRECTION_RECEIVE: AddPathDirection = AddPathDirection(1);
-pub const ADDPATH_DIRECTION_SEND: AddPathDirection = AddPathDirection(2);
-pub const ADDPATH_DIRECTION_BOTH: AddPathDirection = AddPathDirection(3);
+impl AddPathDirection {
+    pub fn receives(self) -> bool {
+        self == AddPathDirection::Receive || self == AddPathDirection::Both
+    }
+
+    pub fn sends(self) -> bool {
+        self == AddPathDirection::Send || self == AddPathDirection::Both
+    }
+}
+
+impl From<u8> for AddPathDirection {
+    fn from(other: u8) -> AddPathDirection {
+        match other {
+            1 => AddPathDirection::Receive,
+            2 => AddPathDirection::Send,
+            3 => AddPathDirection::Both,
+            n => AddPathDirection::Unknown(n),
+        }
+    }
+}
+
+impl From<AddPathDirection> for u8 {
+    fn from(other: AddPathDirection) -> u8 {
+        match other {
+            AddPathDirection::Receive => 1,
+            AddPathDirection::Send => 2,
+            AddPathDirection::Both => 3,
+            AddPathDirection::Unknown(n) => n,
+        }
+    }
+}
 
 impl<'a> AddPath<'a> {
     pub fn afi(&self) -> Afi {
@@ -128,24 +256,384 @@ impl<'a> AddPath<'a> {
         Safi::from(self.inner[4])
     }
 
+    pub fn afi_safi(&self) -> AfiSafi {
+        AfiSafi::from_bytes(&self.inner[2..])
+    }
+
     pub fn direction(&self) -> AddPathDirection {
-        AddPathDirection(self.inner[5])
+        AddPathDirection::from(self.inner[5])
     }
 }
 
 impl<'a> FourByteASN<'a> {
-    pub fn aut_num(&self) -> u32 {
-        (self.inner[2] as u32) << 24
+    pub fn aut_num(&self) -> AsNumber {
+        AsNumber((self.inner[2] as u32) << 24
             | (self.inner[3] as u32) << 16
             | (self.inner[4] as u32) << 8
-            | (self.inner[5] as u32)
+            | (self.inner[5] as u32))
+    }
+}
+
+impl<'a> BgpSec<'a> {
+    pub fn version(&self) -> u8 {
+        self.inner[2] >> 4
+    }
+
+    /// Whether the speaker can send (`true`) or receive (`false`) BGPsec
+    /// UPDATE messages.
+    pub fn send(&self) -> bool {
+        self.inner[2] & 0b0000_1000 > 0
+    }
+
+    pub fn afi(&self) -> Afi {
+        Afi::from((self.inner[3] as u16) << 8 | self.inner[4] as u16)
+    }
+}
+
+impl<'a> MultipleLabels<'a> {
+    /// The address families and the number of labels the speaker can
+    /// process per NLRI of each.
+    pub fn entries(&self) -> MultipleLabelsIter<'a> {
+        MultipleLabelsIter {
+            inner: &self.inner[2..],
+            error: false,
+        }
     }
+
+    /// The label count advertised for `family`, if any. Labeled NLRI of
+    /// that family carry up to this many labels instead of one.
+    pub fn count(&self, family: AfiSafi) -> Option<u8> {
+        self.entries()
+            .filter_map(|entry| entry.ok())
+            .find(|&(f, _)| f == family)
+            .map(|(_, count)| count)
+    }
+}
+
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct MultipleLabelsIter<'a> {
+    inner: &'a [u8],
+    error: bool,
 }
-// #[cfg(test)]
-// mod tests {
-//     use super::*;
 
-//     #[test]
+impl<'a> Iterator for MultipleLabelsIter<'a> {
+    type Item = Result<(AfiSafi, u8)>;
 
+    fn next(&mut self) -> Option<Result<(AfiSafi, u8)>> {
+        if self.error || self.inner.is_empty() {
+            return None;
+        }
 
-// }
+        let entry = match self.inner.take(4) {
+            Ok(entry) => entry,
+            Err(err) => {
+                self.error = true;
+                return Some(Err(err));
+            }
+        };
+        self.inner = &self.inner[4..];
+
+        Some(Ok((AfiSafi::from_bytes(entry), entry[3])))
+    }
+}
+
+impl<'a> GracefulRestart<'a> {
+    /// The Restart Flags (4 bits) and Restart Time (12 bits), or `None` if
+    /// the capability is shorter than the 2 octets RFC 4724 requires.
+    fn flags_and_time(&self) -> Option<u16> {
+        self.inner.u16_at(2).ok()
+    }
+
+    /// Whether the R bit is set: the speaker has restarted.
+    pub fn restart_state(&self) -> bool {
+        self.flags_and_time().map_or(false, |value| value & 0x8000 > 0)
+    }
+
+    /// Whether the N bit is set: the speaker supports Graceful Restart for
+    /// NOTIFICATION messages and Hard Reset (RFC 8538).
+    pub fn notification(&self) -> bool {
+        self.flags_and_time().map_or(false, |value| value & 0x4000 > 0)
+    }
+
+    /// The Restart Time in seconds, or `None` if the capability is
+    /// truncated.
+    pub fn restart_time(&self) -> Option<u16> {
+        self.flags_and_time().map(|value| value & 0x0fff)
+    }
+
+    /// The address families the speaker preserves routes for, each with
+    /// the F bit: whether forwarding state was preserved across the
+    /// restart.
+    pub fn families(&self) -> GracefulRestartIter<'a> {
+        GracefulRestartIter {
+            inner: self.inner.get(4..).unwrap_or(&[]),
+            error: false,
+        }
+    }
+
+    /// The F bit advertised for `family`, or `None` if the family is not
+    /// listed.
+    pub fn forwarding_preserved(&self, family: AfiSafi) -> Option<bool> {
+        self.families()
+            .filter_map(|entry| entry.ok())
+            .find(|&(f, _)| f == family)
+            .map(|(_, preserved)| preserved)
+    }
+}
+
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct GracefulRestartIter<'a> {
+    inner: &'a [u8],
+    error: bool,
+}
+
+impl<'a> Iterator for GracefulRestartIter<'a> {
+    type Item = Result<(AfiSafi, bool)>;
+
+    fn next(&mut self) -> Option<Result<(AfiSafi, bool)>> {
+        if self.error || self.inner.is_empty() {
+            return None;
+        }
+
+        let entry = match self.inner.take(4) {
+            Ok(entry) => entry,
+            Err(err) => {
+                self.error = true;
+                return Some(Err(err));
+            }
+        };
+        self.inner = &self.inner[4..];
+
+        Some(Ok((AfiSafi::from_bytes(entry), entry[3] & 0x80 > 0)))
+    }
+}
+
+/// The role a speaker takes in a peering relationship (RFC 9234).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BgpRole {
+    Provider,
+    RouteServer,
+    RouteServerClient,
+    Customer,
+    Peer,
+    Unknown(u8),
+}
+
+impl From<u8> for BgpRole {
+    fn from(other: u8) -> BgpRole {
+        match other {
+            0 => BgpRole::Provider,
+            1 => BgpRole::RouteServer,
+            2 => BgpRole::RouteServerClient,
+            3 => BgpRole::Customer,
+            4 => BgpRole::Peer,
+            n => BgpRole::Unknown(n),
+        }
+    }
+}
+
+impl<'a> Role<'a> {
+    pub fn role(&self) -> BgpRole {
+        BgpRole::from(self.inner[2])
+    }
+}
+
+impl<'a> DynamicCapability<'a> {
+    /// The codes of the capabilities the speaker can advertise or
+    /// withdraw with CAPABILITY messages, one octet each.
+    pub fn codes(&self) -> &'a [u8] {
+        &self.inner[2..]
+    }
+
+    pub fn supports(&self, code: u8) -> bool {
+        self.codes().contains(&code)
+    }
+}
+
+impl<'a> MultiSession<'a> {
+    /// The flags octet, or `None` if the capability is empty.
+    pub fn flags(&self) -> Option<u8> {
+        self.inner.get(2).cloned()
+    }
+
+    /// Whether the G bit is set: the speaker wants the address families
+    /// of this session grouped into their own session.
+    pub fn grouping(&self) -> bool {
+        self.flags().map_or(false, |flags| flags & 0b1000_0000 > 0)
+    }
+}
+
+impl<'a> Fqdn<'a> {
+    /// The advertised hostname, or `None` if the capability is truncated.
+    pub fn hostname(&self) -> Option<&'a [u8]> {
+        let len = *self.inner.get(2)? as usize;
+        self.inner.get(3..3 + len)
+    }
+
+    /// The advertised domain name, or `None` if the capability is truncated.
+    pub fn domain_name(&self) -> Option<&'a [u8]> {
+        let offset = 3 + *self.inner.get(2)? as usize;
+        let len = *self.inner.get(offset)? as usize;
+        self.inner.get(offset + 1..offset + 1 + len)
+    }
+}
+
+macro_rules! impl_capability_value {
+    ($name:ident) => {
+        impl<'a> $name<'a> {
+            /// The capability value, without the code and length octets.
+            pub fn value(&self) -> &'a [u8] {
+                &self.inner[2..]
+            }
+
+            /// The length of the capability value.
+            #[allow(clippy::len_without_is_empty)]
+            pub fn len(&self) -> usize {
+                self.inner[1] as usize
+            }
+        }
+    }
+}
+
+impl_capability_value!(MultipleRoutes);
+impl_capability_value!(Private);
+impl_capability_value!(Other);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_iana_capabilities() {
+        match Capability::from_bytes(&[6, 0]) {
+            Ok(Capability::ExtendedMessage(_)) => {}
+            x => panic!("expected ExtendedMessage, got {:?}", x)
+        }
+        assert!(Capability::from_bytes(&[6, 1, 0]).is_err());
+
+        match Capability::from_bytes(&[7, 3, 0x08, 0x00, 0x01]) {
+            Ok(Capability::BgpSec(bgpsec)) => {
+                assert_eq!(bgpsec.version(), 0);
+                assert!(bgpsec.send());
+                assert_eq!(bgpsec.afi(), AFI_IPV4);
+            }
+            x => panic!("expected BgpSec, got {:?}", x)
+        }
+
+        match Capability::from_bytes(&[9, 1, 3]) {
+            Ok(Capability::Role(role)) => assert_eq!(role.role(), BgpRole::Customer),
+            x => panic!("expected Role, got {:?}", x)
+        }
+
+        match Capability::from_bytes(&[73, 9, 4, b'r', b't', b'r', b'1', 3, b'n', b'e', b't']) {
+            Ok(Capability::Fqdn(fqdn)) => {
+                assert_eq!(fqdn.hostname(), Some(&b"rtr1"[..]));
+                assert_eq!(fqdn.domain_name(), Some(&b"net"[..]));
+            }
+            x => panic!("expected Fqdn, got {:?}", x)
+        }
+
+        match Capability::from_bytes(&[8, 8, 0x00, 0x01, 0x04, 0x02, 0x00, 0x02, 0x04, 0x03]) {
+            Ok(Capability::MultipleLabels(labels)) => {
+                let mut entries = labels.entries();
+                let ipv6_labeled = AfiSafi::new(AFI_IPV6, SAFI_MPLS_LABEL);
+                assert_eq!(entries.next().unwrap().unwrap(), (AfiSafi::new(AFI_IPV4, SAFI_MPLS_LABEL), 2));
+                assert_eq!(entries.next().unwrap().unwrap(), (ipv6_labeled, 3));
+                assert!(entries.next().is_none());
+                assert_eq!(labels.count(ipv6_labeled), Some(3));
+                assert_eq!(labels.count(IPV4_UNICAST), None);
+            }
+            x => panic!("expected MultipleLabels, got {:?}", x)
+        }
+        match Capability::from_bytes(&[8, 3, 0x00, 0x01, 0x04]) {
+            Ok(Capability::MultipleLabels(labels)) => {
+                assert!(labels.entries().next().unwrap().is_err());
+            }
+            x => panic!("expected MultipleLabels, got {:?}", x)
+        }
+
+        match Capability::from_bytes(&[64, 10, 0xc0, 0x78, 0x00, 0x01, 0x01, 0x80, 0x00, 0x02, 0x01, 0x00]) {
+            Ok(Capability::GracefulRestart(restart)) => {
+                assert!(Capability::GracefulRestart(GracefulRestart { inner: restart.inner }).deep_validate().is_ok());
+                assert!(restart.restart_state());
+                assert!(restart.notification());
+                assert_eq!(restart.restart_time(), Some(120));
+                let mut families = restart.families();
+                assert_eq!(families.next().unwrap().unwrap(), (IPV4_UNICAST, true));
+                assert_eq!(families.next().unwrap().unwrap(), (IPV6_UNICAST, false));
+                assert!(families.next().is_none());
+                assert_eq!(restart.forwarding_preserved(IPV6_UNICAST), Some(false));
+                assert_eq!(restart.forwarding_preserved(IPV4_MULTICAST), None);
+            }
+            x => panic!("expected GracefulRestart, got {:?}", x)
+        }
+        match Capability::from_bytes(&[64, 1, 0x80]) {
+            Ok(cap @ Capability::GracefulRestart(_)) => assert!(matches!(cap.deep_validate(), Err(BgpError::BadLength))),
+            x => panic!("expected GracefulRestart, got {:?}", x)
+        }
+
+        match Capability::from_bytes(&[66, 0]) {
+            Ok(Capability::DeprecatedDynamicCapability(cap)) => assert_eq!(cap.code(), 66),
+            x => panic!("expected DeprecatedDynamicCapability, got {:?}", x)
+        }
+
+        match Capability::from_bytes(&[4, 0]) {
+            Ok(cap @ Capability::MultipleRoutes(_)) => assert!(cap.is_deprecated()),
+            x => panic!("expected MultipleRoutes, got {:?}", x)
+        }
+        assert!(matches!(Capability::from_bytes(&[4, 1, 0]), Err(BgpError::Invalid)));
+
+        match Capability::from_bytes(&[67, 3, 2, 5, 70]) {
+            Ok(Capability::DynamicCapability(cap)) => {
+                assert_eq!(cap.codes(), &[2, 5, 70]);
+                assert!(cap.supports(70));
+                assert!(!cap.supports(1));
+            }
+            x => panic!("expected DynamicCapability, got {:?}", x)
+        }
+
+        match Capability::from_bytes(&[68, 1, 0x80]) {
+            Ok(Capability::MultiSession(cap)) => {
+                assert_eq!(cap.flags(), Some(0x80));
+                assert!(cap.grouping());
+            }
+            x => panic!("expected MultiSession, got {:?}", x)
+        }
+        match Capability::from_bytes(&[68, 0]) {
+            Ok(Capability::MultiSession(cap)) => {
+                assert_eq!(cap.flags(), None);
+                assert!(!cap.grouping());
+            }
+            x => panic!("expected MultiSession, got {:?}", x)
+        }
+    }
+
+    #[test]
+    fn unknown_capability_value() {
+        match Capability::from_bytes(&[131, 3, 0xaa, 0xbb, 0xcc]) {
+            Ok(Capability::Private(cap)) => {
+                assert_eq!(cap.code(), 131);
+                assert_eq!(cap.len(), 3);
+                assert_eq!(cap.value(), &[0xaa, 0xbb, 0xcc]);
+            }
+            x => panic!("expected Private, got {:?}", x)
+        }
+        match Capability::from_bytes(&[100, 0]) {
+            Ok(Capability::Other(cap)) => {
+                assert_eq!(cap.len(), 0);
+                assert!(cap.value().is_empty());
+            }
+            x => panic!("expected Other, got {:?}", x)
+        }
+    }
+
+    #[cfg(feature = "names")]
+    #[test]
+    fn capability_names() {
+        assert_eq!(Capability::from_bytes(&[65, 4, 0, 0, 0xfd, 0xe8]).unwrap().name(),
+                   "Support for 4-octet AS number capability");
+        assert_eq!(Capability::from_bytes(&[75, 0]).unwrap().name(), "Software Version Capability");
+        assert_eq!(Capability::from_bytes(&[100, 0]).unwrap().name(), "Unassigned");
+        assert_eq!(Capability::from_bytes(&[200, 0]).unwrap().name(), "Reserved for Private Use");
+    }
+}
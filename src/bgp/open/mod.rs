@@ -3,26 +3,24 @@
 //! KEEPALIVE message confirming the OPEN is sent back.
 
 use types::*;
+use bgp::check_header;
+use core::convert::TryFrom;
+use core::fmt;
 
 pub mod capability;
 use self::capability::*;
 
 
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub struct Open<'a> {
     pub inner: &'a [u8],
 }
 
 impl<'a> Open<'a> {
 
-    pub fn from_bytes(raw: &'a [u8]) -> Result<Open> {
-        if raw.len() < 29 {
-            Err(BgpError::BadLength)
-        } else {
-            Ok(Open {
-                inner: raw,
-            })
-        }
+    pub fn from_bytes(raw: &'a [u8]) -> Result<Open<'a>> {
+        Open::try_from(raw)
     }
 
     pub fn value(&self) -> &'a [u8] {
@@ -33,8 +31,10 @@ impl<'a> Open<'a> {
         self.value()[0]
     }
 
-    pub fn aut_num(&self) -> u32 {
-        (self.value()[1] as u32) << 8 | self.value()[2] as u32
+    /// My Autonomous System: the speaker's AS number, or `AS_TRANS` if it
+    /// does not fit in 2 octets.
+    pub fn aut_num(&self) -> AsNumber {
+        AsNumber((self.value()[1] as u32) << 8 | self.value()[2] as u32)
     }
 
     pub fn hold_time(&self) -> u16 {
@@ -46,8 +46,187 @@ impl<'a> Open<'a> {
         (self.value()[7] as u32) <<  8 | (self.value()[8] as u32)
     }
 
-    pub fn params(&self) -> OptionalParams {
-        OptionalParams::new(&self.value()[10..])
+    pub fn router_id(&self) -> RouterId {
+        RouterId(self.ident())
+    }
+
+    /// The speaker's AS number: the one in the four-octet AS number
+    /// capability if present, otherwise My Autonomous System.
+    pub fn as_number(&self) -> AsNumber {
+        self.params()
+            .filter_map(|param| match param {
+                Ok(OptionalParam::Capability(Capability::FourByteASN(asn))) => Some(asn.aut_num()),
+                _ => None,
+            })
+            .next()
+            .unwrap_or_else(|| self.aut_num())
+    }
+
+    /// Whether the optional parameters use the extended encoding of
+    /// RFC 9072: Non-Ext OP Len and Non-Ext OP Type of 255, followed by
+    /// a two-octet length, with two-octet parameter lengths.
+    pub fn extended_params(&self) -> bool {
+        self.value()[9] == 255 && self.value().get(10) == Some(&255)
+    }
+
+    pub fn params(&self) -> OptionalParams<'a> {
+        if self.extended_params() {
+            OptionalParams::new(&self.value()[13..]).with_extended_length(true)
+        } else {
+            OptionalParams::new(&self.value()[10..])
+        }
+    }
+
+    /// Decodes every optional parameter and capability, returning the
+    /// first error. See `Message::deep_validate`.
+    pub fn deep_validate(&self) -> Result<()> {
+        for param in self.params() {
+            if let OptionalParam::Capability(capability) = param? {
+                capability.deep_validate()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> Open<'a> {
+
+    /// Checks the fixed fields against RFC 4271 section 6.2, as a BGP
+    /// speaker must before accepting the OPEN:
+    ///
+    /// - the version must be 4,
+    /// - the AS number must not be 0, neither in the My Autonomous System
+    ///   field nor in a four-octet AS number capability (RFC 7607),
+    /// - the hold time must not be 1 or 2 seconds,
+    /// - the BGP Identifier must not be 0 (RFC 6286).
+    ///
+    /// The error tells which NOTIFICATION to send in response. Whether the
+    /// AS number is the expected one and whether the hold time is
+    /// acceptable locally is left to the caller.
+    pub fn validate(&self) -> result::Result<(), OpenError> {
+        if self.version() != 4 {
+            return Err(OpenError::UnsupportedVersionNumber);
+        }
+        let four_byte_asn_zero = self.params().any(|param| match param {
+            Ok(OptionalParam::Capability(Capability::FourByteASN(ref asn))) => asn.aut_num() == AsNumber(0),
+            _ => false,
+        });
+        if self.aut_num() == AsNumber(0) || four_byte_asn_zero {
+            return Err(OpenError::BadPeerAs);
+        }
+        if self.hold_time() == 1 || self.hold_time() == 2 {
+            return Err(OpenError::UnacceptableHoldTime);
+        }
+        if self.ident() == 0 {
+            return Err(OpenError::BadBgpIdentifier);
+        }
+        Ok(())
+    }
+}
+
+/// A BGP Identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RouterId(pub u32);
+
+impl fmt::Display for RouterId {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        IpAddrBytes::V4(&self.0.to_be_bytes()).fmt(fmt)
+    }
+}
+
+/// Which of two colliding connections between the same pair of speakers
+/// to keep.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Collision {
+    /// Keep the connection the local speaker opened, close the other.
+    KeepLocallyInitiated,
+    /// Keep the connection the peer opened, close the other.
+    KeepRemotelyInitiated,
+}
+
+/// Resolves a connection collision (RFC 4271 section 6.8), given the OPEN
+/// the local speaker sent and the OPEN received from the peer.
+///
+/// The connection opened by the speaker with the higher BGP Identifier is
+/// kept. Speakers in different ASes may share an identifier (RFC 6286),
+/// in which case the one with the higher AS number wins. Fails with
+/// `BgpError::Invalid` if both the identifiers and AS numbers match.
+pub fn resolve_collision(local: &Open, remote: &Open) -> Result<Collision> {
+    let local_key = (local.router_id(), local.as_number());
+    let remote_key = (remote.router_id(), remote.as_number());
+    if local_key > remote_key {
+        Ok(Collision::KeepLocallyInitiated)
+    } else if local_key < remote_key {
+        Ok(Collision::KeepRemotelyInitiated)
+    } else {
+        Err(BgpError::Invalid)
+    }
+}
+
+/// An OPEN Message Error found by `Open::validate`, or by
+/// `bgp::fsm::classify` for an OPEN that does not parse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenError {
+    /// A malformed optional parameter.
+    Unspecific,
+    UnsupportedVersionNumber,
+    BadPeerAs,
+    BadBgpIdentifier,
+    UnacceptableHoldTime,
+}
+
+impl OpenError {
+    /// The Error Code of OPEN Message Errors.
+    pub const CODE: u8 = 2;
+
+    /// The Error Subcode to send.
+    pub fn subcode(&self) -> u8 {
+        match *self {
+            OpenError::Unspecific => 0,
+            OpenError::UnsupportedVersionNumber => 1,
+            OpenError::BadPeerAs => 2,
+            OpenError::BadBgpIdentifier => 3,
+            OpenError::UnacceptableHoldTime => 6,
+        }
+    }
+
+    /// The Data to send: the supported version, 4, for Unsupported
+    /// Version Number, and nothing otherwise.
+    pub fn data(&self) -> &'static [u8] {
+        match *self {
+            OpenError::UnsupportedVersionNumber => &[0, 4],
+            _ => &[],
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for Open<'a> {
+    type Error = BgpError;
+
+    fn try_from(raw: &'a [u8]) -> Result<Open<'a>> {
+        if check_header(raw)? != 1 {
+            return Err(BgpError::Invalid);
+        }
+        Open::from_message(raw)
+    }
+}
+
+impl<'a> Open<'a> {
+
+    /// Validates the OPEN-specific lengths of a message whose header has
+    /// already been checked.
+    pub(crate) fn from_message(raw: &'a [u8]) -> Result<Open<'a>> {
+        let params_len = raw.at(28)?;
+        if params_len == 255 && raw.at(29)? == 255 {
+            if raw.u16_at(30)? as usize != raw.len() - 32 {
+                return Err(BgpError::BadLength);
+            }
+        } else if params_len as usize != raw.len() - 29 {
+            return Err(BgpError::BadLength);
+        }
+        Ok(Open {
+            inner: raw,
+        })
     }
 }
 
@@ -58,18 +237,48 @@ pub enum OptionalParam<'a> {
 }
 
 #[derive(Debug)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
 pub struct OptionalParams<'a> {
     pub inner: &'a [u8],
+    extended_length: bool,
     error: Option<BgpError>,
 }
 
 impl<'a> OptionalParams<'a> {
     pub fn new(inner: &'a [u8]) -> OptionalParams<'a> {
         OptionalParams {
-            inner: inner,
+            inner,
+            extended_length: false,
             error: None,
         }
     }
+
+    /// Read two-octet parameter lengths, as in an OPEN with extended
+    /// optional parameters (RFC 9072).
+    pub fn with_extended_length(mut self, extended_length: bool) -> OptionalParams<'a> {
+        self.extended_length = extended_length;
+        self
+    }
+
+    /// Whether parameter lengths are read as two octets.
+    pub fn extended_length(&self) -> bool {
+        self.extended_length
+    }
+}
+
+impl<'a> OptionalParams<'a> {
+    /// Splits off the next parameter: its type and value.
+    fn split_param(&mut self) -> Result<(u8, &'a [u8])> {
+        let param_type = self.inner.at(0)?;
+        let (header_len, param_len) = if self.extended_length {
+            (3, self.inner.u16_at(1)? as usize)
+        } else {
+            (2, self.inner.at(1)? as usize)
+        };
+        let param_value = self.inner.slice(header_len, param_len)?;
+        self.inner = self.inner.skip(header_len + param_len)?;
+        Ok((param_type, param_value))
+    }
 }
 
 impl<'a> Iterator for OptionalParams<'a> {
@@ -79,23 +288,16 @@ impl<'a> Iterator for OptionalParams<'a> {
         if self.error.is_some() {
             return None;
         }
-        if self.inner.len() == 0 {
+        if self.inner.is_empty() {
             return None;
         }
-        if self.inner.len() < 2 {
-            let err = BgpError::BadLength;
-            self.error = Some(err);
-            return Some(Err(err));
-        }
-        let param_type = self.inner[0];
-        let param_len = self.inner[1] as usize;
-        if self.inner.len() < param_len + 2 {
-            let err = BgpError::BadLength;
-            self.error = Some(err);
-            return Some(Err(err));
-        }
-        let param_value = &self.inner[2..param_len + 2];
-        self.inner = &self.inner[param_len + 2..];
+        let (param_type, param_value) = match self.split_param() {
+            Ok(param) => param,
+            Err(err) => {
+                self.error = Some(err);
+                return Some(Err(err));
+            }
+        };
         match param_type {
             2 => {
                 match Capability::from_bytes(param_value) {
@@ -113,8 +315,7 @@ impl<'a> Iterator for OptionalParams<'a> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use super::capability::*;
-    use types::*;
+    use core::fmt::Write;
 
     macro_rules! expect_capability {
         ($a:expr, $p:pat, $blk:block) => {
@@ -138,7 +339,7 @@ mod tests {
         let open = Open::from_bytes(bytes).unwrap();
 
         assert_eq!(open.version(), 4);
-        assert_eq!(open.aut_num(), 64512);
+        assert_eq!(open.aut_num(), AsNumber(64512));
         assert_eq!(open.hold_time(), 180);
         assert_eq!(open.ident(), 167772166);
 
@@ -147,6 +348,7 @@ mod tests {
         expect_capability!(params.next(), Capability::MultiProtocol(mp), {
             assert_eq!(mp.afi(),AFI_IPV4);
             assert_eq!(mp.safi(),SAFI_UNICAST);
+            assert_eq!(mp.afi_safi(), IPV4_UNICAST);
         });
 
         expect_capability!(params.next(), Capability::Private(p), {
@@ -160,13 +362,104 @@ mod tests {
         expect_capability!(params.next(), Capability::AddPath(ap), {
             assert_eq!(ap.afi(), AFI_IPV4);
             assert_eq!(ap.safi(), SAFI_UNICAST);
-            assert_eq!(ap.direction(), ADDPATH_DIRECTION_BOTH);
+            assert_eq!(ap.afi_safi(), IPV4_UNICAST);
+            assert_eq!(ap.direction(), AddPathDirection::Both);
         });
 
         expect_capability!(params.next(), Capability::FourByteASN(fba), {
-            assert_eq!(fba.aut_num(), 64512);
+            assert_eq!(fba.aut_num(), AsNumber(64512));
         });
 
         assert!(params.next().is_none());
+        assert_eq!(open.validate(), Ok(()));
+    }
+
+    #[test]
+    fn extended_params() {
+        // RFC 9072: capabilities for IPv4 unicast and four-octet AS 65000
+        let bytes = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+            0xff, 0xff, 0xff, 0xff, 0x00, 0x32, 0x01,
+            0x04, 0xfd, 0xe8, 0x00, 0xb4, 0xc0, 0x00, 0x02, 0x01,
+            0xff, 0xff, 0x00, 0x12,
+            0x02, 0x00, 0x06, 0x01, 0x04, 0x00, 0x01, 0x00, 0x01,
+            0x02, 0x00, 0x06, 0x41, 0x04, 0x00, 0x00, 0xfd, 0xe8];
+        let open = Open::from_bytes(bytes).unwrap();
+        assert!(open.extended_params() && open.params().extended_length());
+        let mut params = open.params();
+        expect_capability!(params.next(), Capability::MultiProtocol(mp), {
+            assert_eq!(mp.afi_safi(), IPV4_UNICAST);
+        });
+        expect_capability!(params.next(), Capability::FourByteASN(asn), {
+            assert_eq!(asn.aut_num(), AsNumber(65000));
+        });
+        assert!(params.next().is_none());
+        assert_eq!(open.as_number(), AsNumber(65000));
+        assert!(open.deep_validate().is_ok());
+
+        let mut bad_len = [0; 50];
+        bad_len.copy_from_slice(bytes);
+        bad_len[31] = 0x13;
+        assert!(matches!(Open::from_bytes(&bad_len), Err(BgpError::BadLength)));
+        let mut plain = [0; 29];
+        plain.copy_from_slice(&bytes[..29]);
+        plain[17] = 29;
+        plain[28] = 0;
+        let open = Open::from_bytes(&plain).unwrap();
+        assert!(!open.extended_params() && open.params().next().is_none());
+    }
+
+    #[test]
+    fn validate_open() {
+        let mut bytes = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                         0xff, 0xff, 0xff, 0xff, 0x00, 0x25, 0x01, 0x04, 0xfd, 0xe8, 0x00, 0xb4,
+                         0x0a, 0x00, 0x00, 0x01, 0x08, 0x02, 0x06, 0x41, 0x04, 0x00, 0x00, 0xfd,
+                         0xe8];
+        assert_eq!(Open::from_bytes(&bytes).unwrap().validate(), Ok(()));
+
+        let cases: [(usize, &[u8], OpenError); 4] = [
+            (19, &[3], OpenError::UnsupportedVersionNumber),
+            (22, &[0, 2], OpenError::UnacceptableHoldTime),
+            (24, &[0, 0, 0, 0], OpenError::BadBgpIdentifier),
+            (33, &[0, 0, 0, 0], OpenError::BadPeerAs),
+        ];
+        for &(offset, value, error) in &cases {
+            let mut bytes = bytes;
+            bytes[offset..offset + value.len()].copy_from_slice(value);
+            assert_eq!(Open::from_bytes(&bytes).unwrap().validate(), Err(error));
+        }
+        bytes[20..22].copy_from_slice(&[0, 0]);
+        let error = Open::from_bytes(&bytes).unwrap().validate().unwrap_err();
+        assert_eq!((error.subcode(), error.data()), (2, &[][..]));
+        assert_eq!(OpenError::UnsupportedVersionNumber.data(), &[0, 4]);
+    }
+
+    #[test]
+    fn connection_collision() {
+        // AS 65000, identifier 10.0.0.1, four-octet AS 65000
+        let local = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                     0xff, 0xff, 0xff, 0xff, 0x00, 0x25, 0x01, 0x04, 0xfd, 0xe8, 0x00, 0xb4,
+                     0x0a, 0x00, 0x00, 0x01, 0x08, 0x02, 0x06, 0x41, 0x04, 0x00, 0x00, 0xfd,
+                     0xe8];
+        let mut remote = local;
+        let open = Open::from_bytes(&local).unwrap();
+        assert_eq!(open.router_id(), RouterId(0x0a000001));
+        assert_eq!(open.as_number(), AsNumber(65000));
+        assert!(resolve_collision(&open, &open).is_err());
+
+        // 10.0.0.2
+        remote[27] = 2;
+        assert_eq!(resolve_collision(&open, &Open::from_bytes(&remote).unwrap()).unwrap(),
+                   Collision::KeepRemotelyInitiated);
+
+        // 10.0.0.1 in AS 4200000000
+        remote[27] = 1;
+        remote[33..37].copy_from_slice(&[0xfa, 0x56, 0xea, 0x00]);
+        let remote = Open::from_bytes(&remote).unwrap();
+        assert_eq!(remote.as_number(), AsNumber(4200000000));
+        assert_eq!(resolve_collision(&remote, &open).unwrap(), Collision::KeepLocallyInitiated);
+
+        let mut buf = FmtBuf::new();
+        write!(buf, "{}", open.router_id()).unwrap();
+        assert_eq!(buf.as_bytes(), b"10.0.0.1");
     }
 }
@@ -0,0 +1,176 @@
+//! Maximum prefix limits, as BGP speakers configure per neighbor: a
+//! warning when the number of prefixes received passes a threshold, and
+//! a Cease NOTIFICATION when it passes the maximum (RFC 4486).
+
+use types::*;
+use bgp::update::Update;
+use bgp::update::routes::RouteEvent;
+
+/// The Cease subcode Maximum Number of Prefixes Reached.
+pub const CEASE_MAX_PREFIXES: u8 = 1;
+
+/// What crossing a limit calls for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitEvent {
+    /// The count of `family` passed its warning threshold.
+    Threshold { family: AfiSafi, count: u32 },
+    /// The count of `family` passed `max`: the session is to be closed.
+    Exceeded { family: AfiSafi, max: u32 },
+}
+
+impl LimitEvent {
+    /// The Data of the Cease NOTIFICATION to send for `Exceeded`: the
+    /// AFI, the SAFI and the upper bound (RFC 4486 section 4).
+    pub fn cease_data(&self) -> Option<[u8; 7]> {
+        match *self {
+            LimitEvent::Exceeded { family, max } => {
+                let afi = u16::from(family.afi);
+                Some([(afi >> 8) as u8, afi as u8, u8::from(family.safi),
+                      (max >> 24) as u8, (max >> 16) as u8, (max >> 8) as u8, max as u8])
+            }
+            LimitEvent::Threshold { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Limit {
+    family: AfiSafi,
+    max: u32,
+    threshold: u32,
+    count: u32,
+}
+
+/// Prefix counts and limits for up to `N` address families.
+///
+/// Counts go up for each prefix announced and down for each withdrawn.
+/// Without a RIB, a prefix announced again is counted twice, so the
+/// counts are an upper bound for peers that re-announce without first
+/// withdrawing. Prefixes of families without a limit are not counted.
+#[derive(Debug, Clone)]
+pub struct PrefixLimits<const N: usize = 4> {
+    limits: [Option<Limit>; N],
+}
+
+impl<const N: usize> PrefixLimits<N> {
+    pub fn new() -> PrefixLimits<N> {
+        PrefixLimits {
+            limits: [None; N],
+        }
+    }
+
+    /// Limits `family` to `max` prefixes, warning when more than
+    /// `threshold_percent` percent of `max` are received. Fails with
+    /// `BgpError::BadLength` if `N` families already have a limit.
+    pub fn set_limit(&mut self, family: AfiSafi, max: u32, threshold_percent: u8) -> Result<()> {
+        let threshold = (max as u64 * threshold_percent.min(100) as u64 / 100) as u32;
+        if let Some(limit) = self.limit_mut(family) {
+            limit.max = max;
+            limit.threshold = threshold;
+            return Ok(());
+        }
+        let slot = self.limits.iter_mut().find(|limit| limit.is_none()).ok_or(BgpError::BadLength)?;
+        *slot = Some(Limit { family, max, threshold, count: 0 });
+        Ok(())
+    }
+
+    fn limit_mut(&mut self, family: AfiSafi) -> Option<&mut Limit> {
+        self.limits.iter_mut().filter_map(Option::as_mut).find(|limit| limit.family == family)
+    }
+
+    /// The number of prefixes of `family` counted, or `None` if it has no
+    /// limit.
+    pub fn count(&self, family: AfiSafi) -> Option<u32> {
+        self.limits.iter().filter_map(|limit| *limit).find(|limit| limit.family == family)
+            .map(|limit| limit.count)
+    }
+
+    /// Counts the prefixes of `update` and returns the limit crossed, if
+    /// any. A threshold or maximum is only reported when the count passes
+    /// it, and again after it has dropped back. When several are crossed,
+    /// an exceeded maximum is reported ahead of a threshold.
+    ///
+    /// Fails with the first error decoding the prefixes, after counting
+    /// those before it.
+    pub fn record(&mut self, update: &Update) -> Result<Option<LimitEvent>> {
+        let mut crossed = None;
+        for event in update.routes() {
+            let (nlri, announced) = match event? {
+                RouteEvent::Announce { nlri, .. } => (nlri, true),
+                RouteEvent::Withdraw { nlri } => (nlri, false),
+                RouteEvent::OtherAnnounce { .. } | RouteEvent::OtherWithdraw { .. } => continue,
+            };
+            let family = AfiSafi::new(nlri.afi, nlri.safi);
+            let limit = match self.limit_mut(family) {
+                Some(limit) => limit,
+                None => continue,
+            };
+            if !announced {
+                limit.count = limit.count.saturating_sub(1);
+                continue;
+            }
+            limit.count = limit.count.saturating_add(1);
+            if limit.count == limit.max.saturating_add(1) {
+                crossed = Some(LimitEvent::Exceeded { family, max: limit.max });
+            } else if limit.count == limit.threshold.saturating_add(1) && crossed.is_none() {
+                crossed = Some(LimitEvent::Threshold { family, count: limit.count });
+            }
+        }
+        Ok(crossed)
+    }
+
+    /// Sets every count to 0, e.g. when the session restarts.
+    pub fn reset(&mut self) {
+        for limit in self.limits.iter_mut().flatten() {
+            limit.count = 0;
+        }
+    }
+}
+
+impl<const N: usize> Default for PrefixLimits<N> {
+    fn default() -> PrefixLimits<N> {
+        PrefixLimits::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bgp::Message;
+    use core::convert::TryFrom;
+
+    #[test]
+    fn max_prefixes() {
+        // withdraws 10.0.1.0/24, announces 198.51.100.0/24 and 10.1.0.0/16
+        let bytes = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x36, 0x02, 0x00, 0x04, 0x18, 0x0a, 0x00,
+                      0x01, 0x00, 0x14, 0x40, 0x01, 0x01, 0x00, 0x40, 0x02, 0x06, 0x02, 0x01,
+                      0x00, 0x00, 0xfd, 0xe8, 0x40, 0x03, 0x04, 0x0a, 0x00, 0x00, 0x01, 0x18,
+                      0xc6, 0x33, 0x64, 0x10, 0x0a, 0x01];
+        let update = match Message::try_from(&bytes[..]) {
+            Ok(Message::Update(update)) => update,
+            x => panic!("expected Update, got {:?}", x),
+        };
+
+        let mut limits: PrefixLimits<1> = PrefixLimits::new();
+        limits.set_limit(IPV4_UNICAST, 5, 50).unwrap();
+        assert!(matches!(limits.set_limit(IPV6_UNICAST, 5, 50), Err(BgpError::BadLength)));
+
+        assert_eq!(limits.record(&update).unwrap(), None);
+        assert_eq!(limits.count(IPV4_UNICAST), Some(2));
+        assert_eq!(limits.record(&update).unwrap(),
+                   Some(LimitEvent::Threshold { family: IPV4_UNICAST, count: 3 }));
+        // the withdrawal takes the count back to the threshold
+        assert_eq!(limits.record(&update).unwrap(),
+                   Some(LimitEvent::Threshold { family: IPV4_UNICAST, count: 3 }));
+        assert_eq!(limits.record(&update).unwrap(), None);
+        assert_eq!(limits.count(IPV4_UNICAST), Some(5));
+        let exceeded = limits.record(&update).unwrap().unwrap();
+        assert_eq!(exceeded, LimitEvent::Exceeded { family: IPV4_UNICAST, max: 5 });
+        assert_eq!(exceeded.cease_data(), Some([0x00, 0x01, 0x01, 0x00, 0x00, 0x00, 0x05]));
+        assert_eq!(limits.count(IPV6_UNICAST), None);
+
+        limits.reset();
+        assert_eq!(limits.count(IPV4_UNICAST), Some(0));
+    }
+}
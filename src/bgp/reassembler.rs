@@ -0,0 +1,161 @@
+use alloc::vec::Vec;
+use types::*;
+use super::{Message, SessionConfig};
+
+/// The most octets a `Reassembler` holds ahead of a gap in the stream.
+pub const MAX_PENDING_LEN: usize = 1 << 20;
+
+/// Puts BGP messages back together from the TCP payloads of a session,
+/// such as those extracted from a packet capture.
+///
+/// Payloads are pushed with their offset in the stream and may arrive out
+/// of order, overlap or repeat. Complete messages are returned in stream
+/// order by `next_message`.
+///
+/// Offsets are 64-bit stream offsets that do not wrap. TCP sequence
+/// numbers wrap after 4 GiB, so callers reading them from packets must
+/// unwrap them first, e.g. by counting from the initial sequence number
+/// and adding 2^32 on each wrap.
+#[derive(Debug, Clone, Default)]
+pub struct Reassembler {
+    /// Stream offset of the first octet of `buf`.
+    offset: u64,
+    /// Contiguous stream data not yet consumed.
+    buf: Vec<u8>,
+    /// Octets at the start of `buf` returned by the last `next_message`.
+    consumed: usize,
+    /// Payloads received ahead of a gap in the stream.
+    pending: Vec<(u64, Vec<u8>)>,
+    /// Total length of the `pending` payloads.
+    pending_len: usize,
+    error: bool,
+}
+
+impl Reassembler {
+    /// Reassembles a stream starting at offset 0.
+    pub fn new() -> Reassembler {
+        Reassembler::default()
+    }
+
+    /// Reassembles a stream starting at `offset`.
+    pub fn with_offset(offset: u64) -> Reassembler {
+        Reassembler {
+            offset,
+            ..Reassembler::default()
+        }
+    }
+
+    /// The stream offset of the end of the contiguous data received.
+    fn end(&self) -> u64 {
+        self.offset + self.buf.len() as u64
+    }
+
+    /// Adds `payload`, found at `offset` in the stream. Data already
+    /// received is ignored.
+    ///
+    /// Fails with `BgpError::BadLength`, dropping `payload`, if it would
+    /// take the octets held ahead of a gap past `MAX_PENDING_LEN`.
+    pub fn push(&mut self, offset: u64, payload: &[u8]) -> Result<()> {
+        if offset > self.end() {
+            if self.pending_len + payload.len() > MAX_PENDING_LEN {
+                return Err(BgpError::BadLength);
+            }
+            self.pending_len += payload.len();
+            self.pending.push((offset, payload.to_vec()));
+            return Ok(());
+        }
+        self.append(offset, payload);
+        while let Some(index) = self.pending.iter().position(|&(offset, _)| offset <= self.end()) {
+            let (offset, payload) = self.pending.swap_remove(index);
+            self.pending_len -= payload.len();
+            self.append(offset, &payload);
+        }
+        Ok(())
+    }
+
+    fn append(&mut self, offset: u64, payload: &[u8]) {
+        let seen = self.end() - offset;
+        if seen < payload.len() as u64 {
+            self.buf.extend_from_slice(&payload[seen as usize..]);
+        }
+    }
+
+    /// Parses the next complete message with `config`, or returns `None`
+    /// until more of the stream has been pushed.
+    ///
+    /// A message that fails to parse is returned as an error and skipped.
+    /// A Length field shorter than a message header makes the rest of the
+    /// stream impossible to frame, so it is returned as
+    /// `BgpError::BadLength` once and no further messages are returned.
+    pub fn next_message(&mut self, config: &SessionConfig) -> Option<Result<Message<'_>>> {
+        if self.consumed > 0 {
+            self.buf.drain(..self.consumed);
+            self.offset += self.consumed as u64;
+            self.consumed = 0;
+        }
+        if self.error || self.buf.len() < 19 {
+            return None;
+        }
+        let message_len = (self.buf[16] as usize) << 8 | (self.buf[17] as usize);
+        if message_len < 19 {
+            self.error = true;
+            return Some(Err(BgpError::BadLength));
+        }
+        if self.buf.len() < message_len {
+            return None;
+        }
+        self.consumed = message_len;
+        Some(Message::from_bytes_with_config(&self.buf[..message_len], config))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reassemble_out_of_order() {
+        let mut keepalive = [0xff; 19];
+        keepalive[16..].copy_from_slice(&[0x00, 0x13, 0x04]);
+        let mut stream = [0; 19 * 3];
+        for message in stream.chunks_mut(19) {
+            message.copy_from_slice(&keepalive);
+        }
+        let config = SessionConfig::default();
+
+        let mut reassembler = Reassembler::with_offset(1000);
+        reassembler.push(1030, &stream[30..45]).unwrap();
+        reassembler.push(1045, &stream[45..]).unwrap();
+        assert!(reassembler.next_message(&config).is_none());
+        reassembler.push(1000, &stream[..10]).unwrap();
+        // a retransmission overlapping what was received
+        reassembler.push(1005, &stream[5..20]).unwrap();
+        assert!(matches!(reassembler.next_message(&config), Some(Ok(Message::KeepAlive(_)))));
+        assert!(reassembler.next_message(&config).is_none());
+        reassembler.push(1020, &stream[20..30]).unwrap();
+        assert!(matches!(reassembler.next_message(&config), Some(Ok(Message::KeepAlive(_)))));
+        assert!(matches!(reassembler.next_message(&config), Some(Ok(Message::KeepAlive(_)))));
+        assert!(reassembler.next_message(&config).is_none());
+
+        let mut bad = keepalive;
+        bad[17] = 0x12;
+        reassembler.push(1057, &bad).unwrap();
+        reassembler.push(1076, &keepalive).unwrap();
+        assert!(matches!(reassembler.next_message(&config), Some(Err(BgpError::BadLength))));
+        assert!(reassembler.next_message(&config).is_none());
+    }
+
+    #[test]
+    fn pending_limit() {
+        let payload = [0; 1024];
+        let mut reassembler = Reassembler::new();
+        for i in 0..MAX_PENDING_LEN / payload.len() {
+            reassembler.push(1 + (i * payload.len()) as u64, &payload).unwrap();
+        }
+        assert!(matches!(reassembler.push(1 << 30, &[0]), Err(BgpError::BadLength)));
+        // filling the gap releases the pending payloads
+        reassembler.push(0, &[0]).unwrap();
+        assert_eq!(reassembler.pending_len, 0);
+        reassembler.push(1 << 30, &[0]).unwrap();
+    }
+}
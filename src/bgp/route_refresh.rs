@@ -0,0 +1,425 @@
+//! A ROUTE-REFRESH message asks the peer to re-advertise its Adj-RIB-Out
+//! for an address family (RFC 2918). It may carry Outbound Route
+//! Filtering entries for the peer to apply first (RFC 5291).
+
+use types::*;
+use bgp::{check_header, write_header};
+use core::convert::TryFrom;
+
+/// The Address Prefix ORF type (RFC 5292).
+pub const ORF_TYPE_ADDRESS_PREFIX: u8 = 64;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RouteRefresh<'a> {
+    pub inner: &'a [u8],
+}
+
+impl<'a> RouteRefresh<'a> {
+
+    pub fn from_bytes(raw: &'a [u8]) -> Result<RouteRefresh<'a>> {
+        RouteRefresh::try_from(raw)
+    }
+
+    /// Decodes a message whose header has already been checked.
+    pub(crate) fn from_message(raw: &'a [u8]) -> Result<RouteRefresh<'a>> {
+        if raw.len() < 19 + 4 {
+            return Err(BgpError::BadLength);
+        }
+        Ok(RouteRefresh { inner: raw })
+    }
+
+    pub fn value(&self) -> &'a [u8] {
+        &self.inner[19..]
+    }
+
+    pub fn afi(&self) -> Afi {
+        Afi::from((self.value()[0] as u16) << 8 | self.value()[1] as u16)
+    }
+
+    /// The Message Subtype of Enhanced Route Refresh (RFC 7313): 0 for a
+    /// plain route refresh, 1 and 2 for the beginning and end of one.
+    pub fn subtype(&self) -> u8 {
+        self.value()[2]
+    }
+
+    pub fn safi(&self) -> Safi {
+        Safi::from(self.value()[3])
+    }
+
+    pub fn afi_safi(&self) -> AfiSafi {
+        AfiSafi::new(self.afi(), self.safi())
+    }
+
+    /// When the peer should re-advertise, if the message carries ORFs.
+    pub fn when_to_refresh(&self) -> Result<Option<WhenToRefresh>> {
+        match self.value().get(4) {
+            None => Ok(None),
+            Some(&1) => Ok(Some(WhenToRefresh::Immediate)),
+            Some(&2) => Ok(Some(WhenToRefresh::Defer)),
+            Some(_) => Err(BgpError::Invalid),
+        }
+    }
+
+    /// The ORFs carried, one per ORF type.
+    pub fn orfs(&self) -> Orfs<'a> {
+        Orfs {
+            inner: self.value().get(5..).unwrap_or(&[]),
+            afi: self.afi(),
+            error: false,
+        }
+    }
+
+    /// Decodes every ORF entry of a known type, returning the first error.
+    /// See `Message::deep_validate`.
+    pub fn deep_validate(&self) -> Result<()> {
+        self.when_to_refresh()?;
+        for orf in self.orfs() {
+            let orf = orf?;
+            if orf.orf_type == ORF_TYPE_ADDRESS_PREFIX {
+                validate_all(orf.prefix_entries()?)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for RouteRefresh<'a> {
+    type Error = BgpError;
+
+    fn try_from(raw: &'a [u8]) -> Result<RouteRefresh<'a>> {
+        if check_header(raw)? != 5 {
+            return Err(BgpError::Invalid);
+        }
+        RouteRefresh::from_message(raw)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WhenToRefresh {
+    /// Re-advertise as soon as the ORFs are applied.
+    Immediate,
+    /// Wait for a further ROUTE-REFRESH.
+    Defer,
+}
+
+impl WhenToRefresh {
+    fn code(self) -> u8 {
+        match self {
+            WhenToRefresh::Immediate => 1,
+            WhenToRefresh::Defer => 2,
+        }
+    }
+}
+
+/// The entries of one ORF type.
+#[derive(Debug, Clone, Copy)]
+pub struct Orf<'a> {
+    pub orf_type: u8,
+    pub entries: &'a [u8],
+    afi: Afi,
+}
+
+impl<'a> Orf<'a> {
+    /// The entries of an Address Prefix ORF of IPv4 or IPv6 prefixes.
+    pub fn prefix_entries(&self) -> Result<PrefixOrfEntries<'a>> {
+        if self.orf_type != ORF_TYPE_ADDRESS_PREFIX || !(self.afi == AFI_IPV4 || self.afi == AFI_IPV6) {
+            return Err(BgpError::Invalid);
+        }
+        Ok(PrefixOrfEntries {
+            inner: self.entries,
+            ipv6: self.afi == AFI_IPV6,
+            error: false,
+        })
+    }
+}
+
+/// Iterator over the ORFs of a ROUTE-REFRESH message.
+#[derive(Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct Orfs<'a> {
+    inner: &'a [u8],
+    afi: Afi,
+    error: bool,
+}
+
+impl<'a> Iterator for Orfs<'a> {
+    type Item = Result<Orf<'a>>;
+
+    fn next(&mut self) -> Option<Result<Orf<'a>>> {
+        if self.error || self.inner.is_empty() {
+            return None;
+        }
+        let len = match self.inner.u16_at(1) {
+            Ok(len) => len as usize,
+            Err(err) => {
+                self.error = true;
+                return Some(Err(err));
+            }
+        };
+        let entries = match self.inner.slice(3, len) {
+            Ok(entries) => entries,
+            Err(err) => {
+                self.error = true;
+                return Some(Err(err));
+            }
+        };
+        let orf = Orf {
+            orf_type: self.inner[0],
+            entries,
+            afi: self.afi,
+        };
+        self.inner = &self.inner[3 + len..];
+        Some(Ok(orf))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OrfMatch {
+    Permit,
+    Deny,
+}
+
+/// An Address Prefix ORF entry (RFC 5292). Prefixes covered by `prefix`
+/// with a length from `min_len` to `max_len` match; a length of 0 leaves
+/// that end of the range at the prefix length or the address length.
+#[derive(Debug, PartialEq)]
+pub struct AddressPrefixOrf<'a> {
+    pub orf_match: OrfMatch,
+    /// Entries are applied in ascending sequence order.
+    pub sequence: u32,
+    pub min_len: u8,
+    pub max_len: u8,
+    pub prefix: Prefix<'a>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PrefixOrfEntry<'a> {
+    Add(AddressPrefixOrf<'a>),
+    Remove(AddressPrefixOrf<'a>),
+    /// Removes every entry previously sent.
+    RemoveAll,
+}
+
+/// Iterator over the entries of an Address Prefix ORF.
+#[derive(Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct PrefixOrfEntries<'a> {
+    inner: &'a [u8],
+    ipv6: bool,
+    error: bool,
+}
+
+impl<'a> PrefixOrfEntries<'a> {
+    fn entry(&mut self) -> Result<PrefixOrfEntry<'a>> {
+        let common = self.inner.at(0)?;
+        let orf_match = if common & 0b0010_0000 > 0 { OrfMatch::Deny } else { OrfMatch::Permit };
+        if common >> 6 == 2 {
+            self.inner = &self.inner[1..];
+            return Ok(PrefixOrfEntry::RemoveAll);
+        }
+        let (prefix, rest) = Prefix::split(self.inner.skip(7)?, self.ipv6)?;
+        let entry = AddressPrefixOrf {
+            orf_match,
+            sequence: self.inner.u32_at(1)?,
+            min_len: self.inner[5],
+            max_len: self.inner[6],
+            prefix,
+        };
+        self.inner = rest;
+        match common >> 6 {
+            0 => Ok(PrefixOrfEntry::Add(entry)),
+            1 => Ok(PrefixOrfEntry::Remove(entry)),
+            _ => Err(BgpError::Invalid),
+        }
+    }
+}
+
+impl<'a> Iterator for PrefixOrfEntries<'a> {
+    type Item = Result<PrefixOrfEntry<'a>>;
+
+    fn next(&mut self) -> Option<Result<PrefixOrfEntry<'a>>> {
+        if self.error || self.inner.is_empty() {
+            return None;
+        }
+        let entry = self.entry();
+        self.error = entry.is_err();
+        Some(entry)
+    }
+}
+
+/// Encodes a ROUTE-REFRESH message into a caller supplied buffer,
+/// optionally with an Address Prefix ORF.
+pub struct RouteRefreshBuilder<'b> {
+    buf: &'b mut [u8],
+    pos: usize,
+    afi: Afi,
+    /// Offset of the ORF length field, if ORF entries are written.
+    orf_len: Option<usize>,
+}
+
+impl<'b> RouteRefreshBuilder<'b> {
+    /// Writes a plain route refresh for `afi`/`safi`.
+    pub fn new(buf: &'b mut [u8], afi: Afi, safi: Safi) -> Result<RouteRefreshBuilder<'b>> {
+        if buf.len() < 19 + 4 {
+            return Err(BgpError::BadLength);
+        }
+        let afi_bytes = u16::from(afi);
+        buf[19..23].copy_from_slice(&[(afi_bytes >> 8) as u8, afi_bytes as u8, 0, u8::from(safi)]);
+        Ok(RouteRefreshBuilder {
+            buf,
+            pos: 19 + 4,
+            afi,
+            orf_len: None,
+        })
+    }
+
+    /// Writes a route refresh carrying Address Prefix ORF entries, added
+    /// with `push`. Only IPv4 and IPv6 prefixes are supported.
+    pub fn prefix_orf(buf: &'b mut [u8], afi: Afi, safi: Safi,
+                      when: WhenToRefresh) -> Result<RouteRefreshBuilder<'b>> {
+        if !(afi == AFI_IPV4 || afi == AFI_IPV6) {
+            return Err(BgpError::Invalid);
+        }
+        let mut builder = RouteRefreshBuilder::new(buf, afi, safi)?;
+        builder.put(&[when.code(), ORF_TYPE_ADDRESS_PREFIX, 0, 0])?;
+        builder.orf_len = Some(builder.pos - 2);
+        Ok(builder)
+    }
+
+    fn put(&mut self, bytes: &[u8]) -> Result<()> {
+        let end = self.pos + bytes.len();
+        if end > self.buf.len() {
+            return Err(BgpError::BadLength);
+        }
+        self.buf[self.pos..end].copy_from_slice(bytes);
+        self.pos = end;
+        Ok(())
+    }
+
+    /// Appends an ORF entry. Its prefix must be of the builder's address
+    /// family. Nothing is written on error.
+    pub fn push(&mut self, entry: &PrefixOrfEntry) -> Result<()> {
+        if self.orf_len.is_none() {
+            return Err(BgpError::Invalid);
+        }
+        let (action, orf) = match *entry {
+            PrefixOrfEntry::Add(ref orf) => (0, orf),
+            PrefixOrfEntry::Remove(ref orf) => (1, orf),
+            PrefixOrfEntry::RemoveAll => return self.put(&[2 << 6]),
+        };
+        match (self.afi, &orf.prefix) {
+            (AFI_IPV4, Prefix::Ipv4(_)) | (AFI_IPV6, Prefix::Ipv6(_)) => (),
+            _ => return Err(BgpError::Invalid),
+        }
+        let deny = if orf.orf_match == OrfMatch::Deny { 1 } else { 0 };
+        let seq = orf.sequence;
+
+        let start = self.pos;
+        let res = self.put(&[action << 6 | deny << 5,
+                             (seq >> 24) as u8, (seq >> 16) as u8, (seq >> 8) as u8, seq as u8,
+                             orf.min_len, orf.max_len])
+            .and_then(|_| self.put(&[orf.prefix.mask_len()]))
+            .and_then(|_| self.put(orf.prefix.octets()));
+        if res.is_err() {
+            self.pos = start;
+        }
+        res
+    }
+
+    /// Completes the message, header included, and returns it.
+    pub fn finish(self) -> Result<&'b [u8]> {
+        if let Some(offset) = self.orf_len {
+            let len = self.pos - offset - 2;
+            if len > 0xffff {
+                return Err(BgpError::BadLength);
+            }
+            self.buf[offset] = (len >> 8) as u8;
+            self.buf[offset + 1] = len as u8;
+        }
+        write_header(self.buf, self.pos, 5)?;
+        Ok(&self.buf[..self.pos])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bgp::Message;
+
+    #[test]
+    fn prefix_orf_round_trip() {
+        let mut buf = [0; 64];
+        let mut builder = RouteRefreshBuilder::prefix_orf(&mut buf, AFI_IPV4, SAFI_UNICAST,
+                                                          WhenToRefresh::Immediate).unwrap();
+        builder.push(&PrefixOrfEntry::RemoveAll).unwrap();
+        builder.push(&PrefixOrfEntry::Add(AddressPrefixOrf {
+            orf_match: OrfMatch::Permit,
+            sequence: 10,
+            min_len: 0,
+            max_len: 24,
+            prefix: Prefix::Ipv4(Ipv4Prefix::new(0x10, &[0xc0, 0xa8])),
+        })).unwrap();
+        let ipv6 = AddressPrefixOrf {
+            orf_match: OrfMatch::Deny,
+            sequence: 20,
+            min_len: 0,
+            max_len: 0,
+            prefix: Prefix::Ipv6(Ipv6Prefix::new(0x20, &[0x20, 0x01, 0x0d, 0xb8])),
+        };
+        assert!(matches!(builder.push(&PrefixOrfEntry::Remove(ipv6)), Err(BgpError::Invalid)));
+        builder.push(&PrefixOrfEntry::Remove(AddressPrefixOrf {
+            orf_match: OrfMatch::Deny,
+            sequence: 20,
+            min_len: 25,
+            max_len: 32,
+            prefix: Prefix::Ipv4(Ipv4Prefix::new(0x18, &[0x0a, 0x00, 0x01])),
+        })).unwrap();
+        let bytes = builder.finish().unwrap();
+        assert_eq!(&bytes[16..], &[0x00, 0x31, 0x05, 0x00, 0x01, 0x00, 0x01, 0x01, 0x40, 0x00, 0x16,
+                                    0x80,
+                                    0x00, 0x00, 0x00, 0x00, 0x0a, 0x00, 0x18, 0x10, 0xc0, 0xa8,
+                                    0x60, 0x00, 0x00, 0x00, 0x14, 0x19, 0x20, 0x18, 0x0a, 0x00, 0x01][..]);
+
+        let refresh = match Message::try_from(bytes) {
+            Ok(Message::Refresh(refresh)) => refresh,
+            x => panic!("expected Refresh, got {:?}", x),
+        };
+        assert_eq!(refresh.afi_safi(), IPV4_UNICAST);
+        assert_eq!(refresh.subtype(), 0);
+        assert_eq!(refresh.when_to_refresh().unwrap(), Some(WhenToRefresh::Immediate));
+        refresh.deep_validate().unwrap();
+        let mut orfs = refresh.orfs();
+        let orf = orfs.next().unwrap().unwrap();
+        assert!(orfs.next().is_none());
+        assert_eq!(orf.orf_type, ORF_TYPE_ADDRESS_PREFIX);
+        let mut entries = orf.prefix_entries().unwrap();
+        assert_eq!(entries.next().unwrap().unwrap(), PrefixOrfEntry::RemoveAll);
+        match entries.next() {
+            Some(Ok(PrefixOrfEntry::Add(orf))) => {
+                assert_eq!(orf.orf_match, OrfMatch::Permit);
+                assert_eq!((orf.sequence, orf.min_len, orf.max_len), (10, 0, 24));
+                assert_eq!(orf.prefix, Prefix::Ipv4(Ipv4Prefix::new(0x10, &[0xc0, 0xa8])));
+            }
+            x => panic!("expected Add, got {:?}", x)
+        }
+        match entries.next() {
+            Some(Ok(PrefixOrfEntry::Remove(orf))) => {
+                assert_eq!(orf.orf_match, OrfMatch::Deny);
+                assert_eq!((orf.sequence, orf.min_len, orf.max_len), (20, 25, 32));
+            }
+            x => panic!("expected Remove, got {:?}", x)
+        }
+        assert!(entries.next().is_none());
+
+        let mut buf = [0; 23];
+        let plain = RouteRefreshBuilder::new(&mut buf, AFI_IPV6, SAFI_UNICAST).unwrap().finish().unwrap();
+        let refresh = RouteRefresh::from_bytes(plain).unwrap();
+        assert_eq!(refresh.afi_safi(), IPV6_UNICAST);
+        assert_eq!(refresh.when_to_refresh().unwrap(), None);
+        assert!(refresh.orfs().next().is_none());
+        assert!(matches!(RouteRefresh::from_bytes(&plain[..22]), Err(BgpError::BadLength)));
+    }
+}
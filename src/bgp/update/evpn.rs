@@ -0,0 +1,224 @@
+//! EVPN NLRI (RFC 7432), carried in MP_REACH_NLRI and MP_UNREACH_NLRI
+//! with AFI L2VPN and SAFI EVPN, see `OtherReachNlri::nlri_bytes`.
+//!
+//! The multicast membership route types of RFC 9251 are decoded; other
+//! route types are passed through undecoded.
+
+use types::*;
+
+pub const EVPN_SELECTIVE_MULTICAST: u8 = 6;
+pub const EVPN_MEMBERSHIP_REPORT_SYNCH: u8 = 7;
+pub const EVPN_LEAVE_SYNCH: u8 = 8;
+
+/// The membership request is from an IGMPv1 host.
+pub const EVPN_FLAG_IGMPV1: u8 = 0x01;
+/// The membership request is from an IGMPv2 host.
+pub const EVPN_FLAG_IGMPV2: u8 = 0x02;
+/// The membership request is from an IGMPv3 host.
+pub const EVPN_FLAG_IGMPV3: u8 = 0x04;
+/// The IGMPv3 source filter mode is Exclude rather than Include.
+pub const EVPN_FLAG_EXCLUDE: u8 = 0x08;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EvpnRoute<'a> {
+    /// Selective Multicast Ethernet Tag route (type 6).
+    SelectiveMulticast(MulticastRoute<'a>),
+    /// Multicast Membership Report Synch route (type 7).
+    MembershipReportSynch(MulticastRoute<'a>),
+    /// Multicast Leave Synch route (type 8).
+    LeaveSynch(MulticastRoute<'a>),
+    /// A route of another type, with its value.
+    Other(u8, &'a [u8]),
+}
+
+impl<'a> EvpnRoute<'a> {
+    /// Decodes the value of a route of type `route_type`.
+    pub fn from_value(route_type: u8, value: &'a [u8]) -> Result<EvpnRoute<'a>> {
+        match route_type {
+            EVPN_SELECTIVE_MULTICAST =>
+                MulticastRoute::from_value(route_type, value).map(EvpnRoute::SelectiveMulticast),
+            EVPN_MEMBERSHIP_REPORT_SYNCH =>
+                MulticastRoute::from_value(route_type, value).map(EvpnRoute::MembershipReportSynch),
+            EVPN_LEAVE_SYNCH =>
+                MulticastRoute::from_value(route_type, value).map(EvpnRoute::LeaveSynch),
+            _ => Ok(EvpnRoute::Other(route_type, value)),
+        }
+    }
+
+    pub fn route_type(&self) -> u8 {
+        match *self {
+            EvpnRoute::SelectiveMulticast(_) => EVPN_SELECTIVE_MULTICAST,
+            EvpnRoute::MembershipReportSynch(_) => EVPN_MEMBERSHIP_REPORT_SYNCH,
+            EvpnRoute::LeaveSynch(_) => EVPN_LEAVE_SYNCH,
+            EvpnRoute::Other(route_type, _) => route_type,
+        }
+    }
+}
+
+/// The fields of the multicast membership route types, which share their
+/// layout. The source, group and originator are `None` when their length
+/// is 0, as for a (*,G) or (*,*) membership.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MulticastRoute<'a> {
+    pub route_distinguisher: RouteDistinguisher<'a>,
+    /// The Ethernet Segment Identifier, absent from type 6 routes.
+    pub esi: Option<Esi<'a>>,
+    pub ethernet_tag: u32,
+    pub source: Option<IpAddrBytes<'a>>,
+    pub group: Option<IpAddrBytes<'a>>,
+    pub originator: Option<IpAddrBytes<'a>>,
+    /// The Maximum Response Time of type 8 routes.
+    pub max_response_time: Option<u8>,
+    /// The `EVPN_FLAG_*` bits.
+    pub flags: u8,
+}
+
+impl<'a> MulticastRoute<'a> {
+    fn from_value(route_type: u8, value: &'a [u8]) -> Result<MulticastRoute<'a>> {
+        let route_distinguisher = RouteDistinguisher::from_bytes(value.take(8)?)?;
+        let mut rest = value.skip(8)?;
+        let esi = match route_type {
+            EVPN_SELECTIVE_MULTICAST => None,
+            _ => {
+                let esi = Esi::from_bytes(rest.take(10)?)?;
+                rest = rest.skip(10)?;
+                Some(esi)
+            }
+        };
+        let ethernet_tag = rest.u32_at(0)?;
+        rest = rest.skip(4)?;
+        let source = address(&mut rest)?;
+        let group = address(&mut rest)?;
+        let originator = address(&mut rest)?;
+        let max_response_time = match route_type {
+            EVPN_LEAVE_SYNCH => {
+                let time = rest.at(4)?;
+                rest = rest.skip(5)?;
+                Some(time)
+            }
+            _ => None,
+        };
+        if rest.len() != 1 {
+            return Err(BgpError::BadLength);
+        }
+        Ok(MulticastRoute {
+            route_distinguisher,
+            esi,
+            ethernet_tag,
+            source,
+            group,
+            originator,
+            max_response_time,
+            flags: rest[0],
+        })
+    }
+}
+
+/// An address preceded by its length in bits, which is 0, 32 or 128.
+fn address<'a>(rest: &mut &'a [u8]) -> Result<Option<IpAddrBytes<'a>>> {
+    let len = match rest.at(0)? {
+        0 => 0,
+        32 => 4,
+        128 => 16,
+        _ => return Err(BgpError::Invalid),
+    };
+    let bytes = rest.slice(1, len)?;
+    *rest = rest.skip(1 + len)?;
+    match len {
+        0 => Ok(None),
+        _ => IpAddrBytes::from_bytes(bytes).map(Some),
+    }
+}
+
+/// Iterates over EVPN NLRI. An NLRI that does not fit yields
+/// `BgpError::BadLength` and ends the iteration; one that fits but does
+/// not decode yields its error and the iteration continues.
+#[derive(Debug, Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct EvpnNlriIter<'a> {
+    tlvs: TlvIter<'a>,
+}
+
+impl<'a> EvpnNlriIter<'a> {
+    pub fn new(inner: &'a [u8]) -> EvpnNlriIter<'a> {
+        EvpnNlriIter {
+            tlvs: TlvIter::with_widths(inner, 1, 1),
+        }
+    }
+}
+
+impl<'a> Iterator for EvpnNlriIter<'a> {
+    type Item = Result<EvpnRoute<'a>>;
+
+    fn next(&mut self) -> Option<Result<EvpnRoute<'a>>> {
+        Some(self.tlvs.next()?.and_then(|tlv| EvpnRoute::from_value(tlv.tlv_type as u8, tlv.value)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bgp::update::path_attr::*;
+
+    #[test]
+    fn multicast_routes() {
+        let bytes = [
+            // type 6: (*, 239.1.1.1) from 192.0.2.1, IGMPv2
+            0x06, 0x18, 0x00, 0x01, 0xc0, 0x00, 0x02, 0x01, 0x00, 0x64, 0x00, 0x00, 0x00, 0x0a,
+            0x00, 0x20, 0xef, 0x01, 0x01, 0x01, 0x20, 0xc0, 0x00, 0x02, 0x01, 0x02,
+            // type 8: (198.51.100.1, 239.1.1.1), IGMPv3 exclude, max response time 10
+            0x08, 0x2b, 0x00, 0x01, 0xc0, 0x00, 0x02, 0x01, 0x00, 0x64,
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0x00, 0x00, 0x00, 0x0a,
+            0x20, 0xc6, 0x33, 0x64, 0x01, 0x20, 0xef, 0x01, 0x01, 0x01, 0x20, 0xc0, 0x00, 0x02, 0x01,
+            0x00, 0x00, 0x00, 0x00, 0x0a, 0x0c,
+            // type 2, not decoded
+            0x02, 0x02, 0xaa, 0xbb,
+            // type 7 with a group address of 3 octets
+            0x07, 0x1b, 0x00, 0x01, 0xc0, 0x00, 0x02, 0x01, 0x00, 0x64,
+            0x00, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x99, 0x00, 0x00, 0x00, 0x0a,
+            0x00, 0x18, 0xef, 0x01, 0x01,
+        ];
+        let mut routes = EvpnNlriIter::new(&bytes);
+        match routes.next() {
+            Some(Ok(EvpnRoute::SelectiveMulticast(route))) => {
+                assert_eq!(route.route_distinguisher, RouteDistinguisher(&[0, 1, 192, 0, 2, 1, 0, 100]));
+                assert_eq!(route.esi, None);
+                assert_eq!(route.ethernet_tag, 10);
+                assert_eq!(route.source, None);
+                assert_eq!(route.group, Some(IpAddrBytes::V4(&[239, 1, 1, 1])));
+                assert_eq!(route.originator, Some(IpAddrBytes::V4(&[192, 0, 2, 1])));
+                assert_eq!(route.flags, EVPN_FLAG_IGMPV2);
+            }
+            x => panic!("expected SelectiveMulticast, got {:?}", x)
+        }
+        match routes.next() {
+            Some(Ok(EvpnRoute::LeaveSynch(route))) => {
+                assert_eq!(route.esi.unwrap().esi_type(), 0);
+                assert_eq!(route.source, Some(IpAddrBytes::V4(&[198, 51, 100, 1])));
+                assert_eq!(route.max_response_time, Some(10));
+                assert_eq!(route.flags, EVPN_FLAG_IGMPV3 | EVPN_FLAG_EXCLUDE);
+            }
+            x => panic!("expected LeaveSynch, got {:?}", x)
+        }
+        assert_eq!(routes.next().unwrap().unwrap(), EvpnRoute::Other(2, &[0xaa, 0xbb]));
+        assert!(matches!(routes.next(), Some(Err(BgpError::Invalid))));
+        assert!(routes.next().is_none());
+
+        // EVPN Multicast Flags with the IGMP Proxy flag, Router's MAC
+        let bytes = &[0x06, 0x09, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00,
+                      0x06, 0x03, 0x00, 0x11, 0x22, 0x33, 0x44, 0x55];
+        let mut communities = ExtendedCommunityIter::new(bytes).unwrap();
+        match communities.next() {
+            Some(ExtendedCommunity::Evpn(evpn)) => {
+                assert_eq!(evpn.multicast_flags(), Some(EVPN_MULTICAST_IGMP_PROXY));
+                assert_eq!(evpn.mac_address(), None);
+            }
+            x => panic!("expected Evpn, got {:?}", x)
+        }
+        match communities.next() {
+            Some(ExtendedCommunity::Evpn(evpn)) =>
+                assert_eq!(evpn.mac_address(), Some(MacAddr(&[0x00, 0x11, 0x22, 0x33, 0x44, 0x55]))),
+            x => panic!("expected Evpn, got {:?}", x)
+        }
+    }
+}
@@ -0,0 +1,260 @@
+//! BGP-LS NLRI (RFC 9552): the nodes, links and prefixes of an IGP
+//! topology, each identified by descriptor TLVs. The NLRI are carried in
+//! MP_REACH_NLRI and MP_UNREACH_NLRI with the BGP-LS AFI, see
+//! `OtherReachNlri::nlri_bytes`.
+//!
+//! The descriptors are decoded into plain values that compare and hash
+//! as a whole, to serve as the keys of a topology graph.
+
+use types::*;
+
+pub const LS_NLRI_NODE: u16 = 1;
+pub const LS_NLRI_LINK: u16 = 2;
+pub const LS_NLRI_IPV4_PREFIX: u16 = 3;
+pub const LS_NLRI_IPV6_PREFIX: u16 = 4;
+
+pub const LS_LOCAL_NODE_DESCRIPTORS: u16 = 256;
+pub const LS_REMOTE_NODE_DESCRIPTORS: u16 = 257;
+pub const LS_LINK_IDENTIFIERS: u16 = 258;
+pub const LS_IPV4_INTERFACE_ADDRESS: u16 = 259;
+pub const LS_IPV4_NEIGHBOR_ADDRESS: u16 = 260;
+pub const LS_IPV6_INTERFACE_ADDRESS: u16 = 261;
+pub const LS_IPV6_NEIGHBOR_ADDRESS: u16 = 262;
+pub const LS_MULTI_TOPOLOGY_ID: u16 = 263;
+
+pub const LS_AUTONOMOUS_SYSTEM: u16 = 512;
+pub const LS_BGP_LS_IDENTIFIER: u16 = 513;
+pub const LS_OSPF_AREA_ID: u16 = 514;
+pub const LS_IGP_ROUTER_ID: u16 = 515;
+
+/// A node, link or prefix NLRI.
+#[derive(Debug, Clone, Copy)]
+pub struct LinkStateNlri<'a> {
+    pub inner: &'a [u8],
+}
+
+impl<'a> LinkStateNlri<'a> {
+    /// One of the `LS_NLRI_*` constants.
+    pub fn nlri_type(&self) -> u16 {
+        self.inner.u16_at(0).unwrap_or(0)
+    }
+
+    fn body(&self) -> &'a [u8] {
+        &self.inner[4..]
+    }
+
+    /// The IGP the topology was learned from, e.g. 1 for IS-IS Level 1,
+    /// 3 for OSPFv2 or 7 for BGP.
+    pub fn protocol_id(&self) -> u8 {
+        self.body()[0]
+    }
+
+    /// The identifier of the routing universe the topology belongs to.
+    pub fn identifier(&self) -> Result<u64> {
+        self.body().u64_at(1)
+    }
+
+    /// The descriptor TLVs following the identifier.
+    pub fn tlvs(&self) -> TlvIter<'a> {
+        TlvIter::new(&self.body()[9..])
+    }
+
+    fn tlv(&self, tlv_type: u16) -> Result<Option<&'a [u8]>> {
+        for tlv in self.tlvs() {
+            let tlv = tlv?;
+            if tlv.tlv_type == tlv_type {
+                return Ok(Some(tlv.value));
+            }
+        }
+        Ok(None)
+    }
+
+    /// The Local Node Descriptors, which every NLRI type carries. Fails
+    /// with `BgpError::Invalid` if they are missing.
+    pub fn local_node(&self) -> Result<NodeDescriptor<'a>> {
+        let value = self.tlv(LS_LOCAL_NODE_DESCRIPTORS)?.ok_or(BgpError::Invalid)?;
+        NodeDescriptor::from_tlvs(value)
+    }
+
+    /// The Remote Node Descriptors of a link NLRI, or `None` for other
+    /// NLRI types.
+    pub fn remote_node(&self) -> Result<Option<NodeDescriptor<'a>>> {
+        match self.tlv(LS_REMOTE_NODE_DESCRIPTORS)? {
+            Some(value) => NodeDescriptor::from_tlvs(value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// The Link Descriptors of a link NLRI, which tell apart parallel
+    /// links between the same nodes. Empty for other NLRI types.
+    pub fn link(&self) -> Result<LinkDescriptor<'a>> {
+        let mut link = LinkDescriptor::default();
+        for tlv in self.tlvs() {
+            let tlv = tlv?;
+            match tlv.tlv_type {
+                LS_LINK_IDENTIFIERS => {
+                    if tlv.value.len() != 8 {
+                        return Err(BgpError::BadLength);
+                    }
+                    link.link_ids = Some((tlv.value.u32_at(0)?, tlv.value.u32_at(4)?));
+                }
+                LS_IPV4_INTERFACE_ADDRESS => link.interface_address = Some(address(tlv.value, 4)?),
+                LS_IPV4_NEIGHBOR_ADDRESS => link.neighbor_address = Some(address(tlv.value, 4)?),
+                LS_IPV6_INTERFACE_ADDRESS => link.interface_address = Some(address(tlv.value, 16)?),
+                LS_IPV6_NEIGHBOR_ADDRESS => link.neighbor_address = Some(address(tlv.value, 16)?),
+                LS_MULTI_TOPOLOGY_ID => link.multi_topology_id = Some(tlv.value.u16_at(0)? & 0x0fff),
+                _ => (),
+            }
+        }
+        Ok(link)
+    }
+}
+
+fn address(value: &[u8], len: usize) -> Result<IpAddrBytes<'_>> {
+    if value.len() != len {
+        return Err(BgpError::BadLength);
+    }
+    IpAddrBytes::from_bytes(value)
+}
+
+/// The descriptors identifying a node. Fields are `None` when the
+/// corresponding sub-TLV is absent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct NodeDescriptor<'a> {
+    pub asn: Option<AsNumber>,
+    pub bgp_ls_identifier: Option<u32>,
+    pub ospf_area_id: Option<u32>,
+    /// 4 octets for an OSPF router ID, 6 for an IS-IS system ID, 7 for an
+    /// IS-IS pseudonode and 8 for an OSPF pseudonode.
+    pub igp_router_id: Option<&'a [u8]>,
+}
+
+impl<'a> NodeDescriptor<'a> {
+    /// Decodes the sub-TLVs of a node descriptors TLV. Unknown sub-TLVs
+    /// are skipped.
+    pub fn from_tlvs(value: &'a [u8]) -> Result<NodeDescriptor<'a>> {
+        let mut node = NodeDescriptor::default();
+        for tlv in TlvIter::new(value) {
+            let tlv = tlv?;
+            match tlv.tlv_type {
+                LS_AUTONOMOUS_SYSTEM => node.asn = Some(AsNumber(u32_value(tlv.value)?)),
+                LS_BGP_LS_IDENTIFIER => node.bgp_ls_identifier = Some(u32_value(tlv.value)?),
+                LS_OSPF_AREA_ID => node.ospf_area_id = Some(u32_value(tlv.value)?),
+                LS_IGP_ROUTER_ID => node.igp_router_id = Some(tlv.value),
+                _ => (),
+            }
+        }
+        Ok(node)
+    }
+}
+
+fn u32_value(value: &[u8]) -> Result<u32> {
+    if value.len() != 4 {
+        return Err(BgpError::BadLength);
+    }
+    value.u32_at(0)
+}
+
+/// The descriptors identifying a link between two nodes. Fields are
+/// `None` when the corresponding TLV is absent.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct LinkDescriptor<'a> {
+    /// The Link Local and Link Remote Identifiers.
+    pub link_ids: Option<(u32, u32)>,
+    pub interface_address: Option<IpAddrBytes<'a>>,
+    pub neighbor_address: Option<IpAddrBytes<'a>>,
+    pub multi_topology_id: Option<u16>,
+}
+
+/// Iterates over the NLRI of an MP_REACH_NLRI or MP_UNREACH_NLRI with the
+/// BGP-LS AFI. An NLRI that does not fit, or whose body is shorter than
+/// the protocol ID and identifier, yields `BgpError::BadLength` and ends
+/// the iteration.
+#[derive(Debug, Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct LinkStateNlriIter<'a> {
+    inner: &'a [u8],
+    error: bool,
+}
+
+impl<'a> LinkStateNlriIter<'a> {
+    pub fn new(inner: &'a [u8]) -> LinkStateNlriIter<'a> {
+        LinkStateNlriIter {
+            inner,
+            error: false,
+        }
+    }
+}
+
+impl<'a> Iterator for LinkStateNlriIter<'a> {
+    type Item = Result<LinkStateNlri<'a>>;
+
+    fn next(&mut self) -> Option<Result<LinkStateNlri<'a>>> {
+        if self.error || self.inner.is_empty() {
+            return None;
+        }
+        let len = match self.inner.u16_at(2) {
+            Ok(len) if len >= 9 => 4 + len as usize,
+            _ => 0,
+        };
+        match self.inner.take(len) {
+            Ok(nlri) if len > 0 => {
+                self.inner = &self.inner[len..];
+                Some(Ok(LinkStateNlri { inner: nlri }))
+            }
+            _ => {
+                self.error = true;
+                Some(Err(BgpError::BadLength))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn link_descriptors() {
+        // an OSPFv2 link between 192.0.2.1 and 192.0.2.2 in area 0 of AS
+        // 65000, followed by a truncated NLRI
+        let bytes = [0x00, 0x02, 0x00, 0x55, 0x03, 0, 0, 0, 0, 0, 0, 0, 0x07,
+                     0x01, 0x00, 0x00, 0x18,
+                     0x02, 0x00, 0x00, 0x04, 0x00, 0x00, 0xfd, 0xe8,
+                     0x02, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00,
+                     0x02, 0x03, 0x00, 0x04, 192, 0, 2, 1,
+                     0x01, 0x01, 0x00, 0x18,
+                     0x02, 0x00, 0x00, 0x04, 0x00, 0x00, 0xfd, 0xe8,
+                     0x02, 0x02, 0x00, 0x04, 0x00, 0x00, 0x00, 0x00,
+                     0x02, 0x03, 0x00, 0x04, 192, 0, 2, 2,
+                     0x01, 0x02, 0x00, 0x08, 0x00, 0x00, 0x00, 0x05, 0x00, 0x00, 0x00, 0x06,
+                     0x01, 0x03, 0x00, 0x04, 198, 51, 100, 1,
+                     0x00, 0x01, 0x00, 0x20];
+        let mut nlris = LinkStateNlriIter::new(&bytes);
+        let nlri = nlris.next().unwrap().unwrap();
+        assert_eq!(nlri.nlri_type(), LS_NLRI_LINK);
+        assert_eq!(nlri.protocol_id(), 3);
+        assert_eq!(nlri.identifier().unwrap(), 7);
+        let short = LinkStateNlri { inner: &bytes[..8] };
+        assert!(matches!(short.identifier(), Err(BgpError::BadLength)));
+
+        let local = nlri.local_node().unwrap();
+        assert_eq!(local, NodeDescriptor {
+            asn: Some(AsNumber(65000)),
+            bgp_ls_identifier: None,
+            ospf_area_id: Some(0),
+            igp_router_id: Some(&[192, 0, 2, 1]),
+        });
+        let remote = nlri.remote_node().unwrap().unwrap();
+        assert_eq!(remote.igp_router_id, Some(&[192, 0, 2, 2][..]));
+        assert_ne!(local, remote);
+
+        let link = nlri.link().unwrap();
+        assert_eq!(link.link_ids, Some((5, 6)));
+        assert_eq!(link.interface_address, Some(IpAddrBytes::V4(&[198, 51, 100, 1])));
+        assert_eq!(link.neighbor_address, None);
+
+        assert!(matches!(nlris.next(), Some(Err(BgpError::BadLength))));
+        assert!(nlris.next().is_none());
+    }
+}
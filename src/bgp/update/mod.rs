@@ -5,34 +5,80 @@
 //! from service.  The UPDATE message always includes the fixed-size BGP
 
 use types::*;
+use bgp::check_header;
+use core::convert::TryFrom;
 use core::fmt;
 
 pub mod path_attr;
 pub mod withdrawn_routes;
 pub mod nlri;
+pub mod routes;
+pub mod link_state;
+pub mod evpn;
+pub(crate) mod normalize;
 
 use self::path_attr::*;
 use self::withdrawn_routes::*;
 use self::nlri::*;
+use self::routes::*;
 
 pub struct Update<'a> {
     pub inner: &'a [u8],
     four_byte_asn: bool,
     add_paths: bool,
+    max_labels: u8,
 }
 
 impl<'a> Update<'a> {
 
-    pub fn from_bytes(raw: &'a [u8], four_byte_asn: bool, add_paths: bool) -> Result<Update> {
-        if raw.len() < 19+4 {
-            Err(BgpError::BadLength)
-        } else {
-            Ok(Update {
-                inner: raw,
-                four_byte_asn: four_byte_asn,
-                add_paths: add_paths,
-            })
+    pub fn from_bytes(raw: &'a [u8], four_byte_asn: bool, add_paths: bool) -> Result<Update<'a>> {
+        if check_header(raw)? != 2 {
+            return Err(BgpError::Invalid);
         }
+        Update::from_message(raw, four_byte_asn, add_paths)
+    }
+
+    /// Validates the UPDATE section lengths of a message whose header has
+    /// already been checked.
+    pub(crate) fn from_message(raw: &'a [u8], four_byte_asn: bool, add_paths: bool) -> Result<Update<'a>> {
+        let update = Update {
+            inner: raw,
+            four_byte_asn,
+            add_paths,
+            max_labels: 1,
+        };
+        update.sections()?;
+        Ok(update)
+    }
+
+    /// Whether AS numbers in AS_PATH and AGGREGATOR are read as four
+    /// octets, as given to `from_bytes`.
+    pub fn four_byte_asn(&self) -> bool {
+        self.four_byte_asn
+    }
+
+    /// Whether the NLRI field is read with path identifiers,
+    /// as given to `from_bytes`.
+    pub fn add_paths(&self) -> bool {
+        self.add_paths
+    }
+
+    /// Read labeled and VPN NLRI in MP_REACH_NLRI with up to `max_labels`
+    /// labels, as negotiated with the Multiple Labels capability.
+    pub fn with_max_labels(mut self, max_labels: u8) -> Update<'a> {
+        self.max_labels = max_labels;
+        self
+    }
+
+    /// The most labels a labeled or VPN NLRI is read with.
+    pub fn max_labels(&self) -> u8 {
+        self.max_labels
+    }
+
+    fn attr_iter(&self, attrs: &'a [u8]) -> PathAttrIter<'a> {
+        PathAttrIter::new(attrs, self.four_byte_asn)
+            .with_add_paths(self.add_paths)
+            .with_max_labels(self.max_labels)
     }
 
     fn value(&self) -> &'a [u8] {
@@ -48,29 +94,379 @@ impl<'a> Update<'a> {
         (self.value()[offset] as usize) << 8 | self.value()[offset+1] as usize
     }
 
-    pub fn withdrawn_routes(&self) -> WithdrawnRoutes {
+    /// Splits the message into its withdrawn routes, path attribute and
+    /// NLRI sections, checking that the two length fields fit.
+    fn sections(&self) -> Result<(&'a [u8], &'a [u8], &'a [u8])> {
+        let value = self.inner.get(19..).ok_or(BgpError::BadLength)?;
+        if value.len() < 2 {
+            return Err(BgpError::BadLength);
+        }
+        let (withdrawn_len, rest) = value.split_at(2);
+        let withdrawn_len = (withdrawn_len[0] as usize) << 8 | withdrawn_len[1] as usize;
+        if rest.len() < withdrawn_len + 2 {
+            return Err(BgpError::BadLength);
+        }
+        let (withdrawn, rest) = rest.split_at(withdrawn_len);
+        let (attrs_len, rest) = rest.split_at(2);
+        let attrs_len = (attrs_len[0] as usize) << 8 | attrs_len[1] as usize;
+        if rest.len() < attrs_len {
+            return Err(BgpError::BadLength);
+        }
+        let (attrs, nlris) = rest.split_at(attrs_len);
+        Ok((withdrawn, attrs, nlris))
+    }
+
+    pub fn withdrawn_routes(&self) -> WithdrawnRoutes<'a> {
         let slice = &self.value()[2..self.withdrawn_routes_len()+2];
-        WithdrawnRoutes::new(slice)
+        WithdrawnRoutes::new(slice).with_add_paths(self.add_paths)
     }
 
-    pub fn path_attrs(&self) -> PathAttrIter {
+    /// Like `withdrawn_routes`, but checks the section lengths first
+    /// instead of relying on `from_bytes` having done so.
+    pub fn try_withdrawn_routes(&self) -> Result<WithdrawnRoutes<'a>> {
+        let (withdrawn, _, _) = self.sections()?;
+        Ok(WithdrawnRoutes::new(withdrawn).with_add_paths(self.add_paths))
+    }
+
+    pub fn path_attrs(&self) -> PathAttrIter<'a> {
         let offset = 4 + self.withdrawn_routes_len();
         let slice = &self.value()[offset..self.total_path_attr_len() + offset];
-        PathAttrIter::new(slice, self.four_byte_asn)
+        self.attr_iter(slice)
+    }
+
+    /// Like `path_attrs`, but checks the section lengths first instead of
+    /// relying on `from_bytes` having done so.
+    pub fn try_path_attrs(&self) -> Result<PathAttrIter<'a>> {
+        let (_, attrs, _) = self.sections()?;
+        Ok(self.attr_iter(attrs))
+    }
+
+    /// The type codes of the path attributes present, found by reading
+    /// only the attribute headers. Fails with `BgpError::BadLength` if an
+    /// attribute does not fit the path attribute section.
+    pub fn attr_bitmap(&self) -> Result<AttrBitmap> {
+        let mut bitmap = AttrBitmap::new();
+        let mut attrs = self.path_attrs();
+        while let Some(attr) = attrs.next_raw() {
+            bitmap.insert(attr?.code);
+        }
+        Ok(bitmap)
+    }
+
+    /// A 64-bit digest of the routes and path attributes, for spotting
+    /// duplicate UPDATEs and flaps without keeping the messages around.
+    ///
+    /// UPDATEs that differ only in the order of their path attributes or
+    /// prefixes, in the Partial and Extended Length flags, or in the
+    /// unused bits of a prefix have the same digest. MP_REACH_NLRI and
+    /// MP_UNREACH_NLRI count with their address family and next hop, and
+    /// their prefixes like the others. The NLRI of address families whose
+    /// prefixes are not decoded count as one opaque value per attribute.
+    /// The digest does not depend on the platform.
+    pub fn content_digest(&self) -> Result<u64> {
+        let mut digest = 0u64;
+        let mut attrs = self.path_attrs();
+        while let Some(raw) = attrs.next_raw() {
+            let raw = raw?;
+            let value = match raw.code {
+                14 => raw.value.take(4 + raw.value.at(3)? as usize)?,
+                15 => raw.value.take(3)?,
+                _ => raw.value,
+            };
+            let mut hash = Fnv::new();
+            hash.write(&[raw.flags & (FLAG_OPTIONAL | FLAG_TRANSITIVE), raw.code]);
+            hash.write_len(value.len());
+            hash.write(value);
+            digest = digest.wrapping_add(hash.finish());
+        }
+        for event in self.routes() {
+            let (nlri, announced) = match event? {
+                RouteEvent::Announce { nlri, .. } => (nlri, true),
+                RouteEvent::Withdraw { nlri } => (nlri, false),
+                RouteEvent::OtherAnnounce { reach, .. } => {
+                    let mut hash = Fnv::route(true, reach.afi(), reach.safi());
+                    hash.write_len(reach.nlri_bytes().len());
+                    hash.write(reach.nlri_bytes());
+                    digest = digest.wrapping_add(hash.finish());
+                    continue;
+                }
+                RouteEvent::OtherWithdraw { unreach } => {
+                    let mut hash = Fnv::route(false, unreach.afi(), unreach.safi());
+                    hash.write_len(unreach.nlri_bytes().len());
+                    hash.write(unreach.nlri_bytes());
+                    digest = digest.wrapping_add(hash.finish());
+                    continue;
+                }
+            };
+            let mut hash = Fnv::route(announced, nlri.afi, nlri.safi);
+            if let Some(path_id) = nlri.path_id {
+                hash.write(&path_id.to_be_bytes());
+            }
+            if let Some(mt_id) = nlri.mt_id {
+                hash.write(&mt_id.to_be_bytes());
+            }
+            for part in [nlri.labels, nlri.rd.map(|rd| rd.octets())].iter().flatten() {
+                hash.write_len(part.len());
+                hash.write(part);
+            }
+            let (mask_len, bits) = (nlri.prefix.mask_len(), nlri.prefix.octets());
+            hash.write(&[mask_len]);
+            if let Some((&last, rest)) = bits.split_last() {
+                hash.write(rest);
+                hash.write(&[last & 0xff << ((8 - mask_len % 8) % 8)]);
+            }
+            digest = digest.wrapping_add(hash.finish());
+        }
+        Ok(digest)
+    }
+
+    /// Decodes the path attributes into `attrs`, in order, so they can be
+    /// looked at more than once without iterating again or allocating.
+    /// Slots after the last attribute are set to `None`. Returns the number
+    /// of attributes, or `BgpError::BadLength` if there are more than `N`.
+    pub fn collect_attrs_into<const N: usize>(&self, attrs: &mut [Option<PathAttr<'a>>; N]) -> Result<usize> {
+        let mut count = 0;
+        for attr in self.path_attrs() {
+            let slot = attrs.get_mut(count).ok_or(BgpError::BadLength)?;
+            *slot = Some(attr?);
+            count += 1;
+        }
+        for slot in &mut attrs[count..] {
+            *slot = None;
+        }
+        Ok(count)
     }
 
-    pub fn nlris(&self) -> NlriIter {
+    /// Collects the attributes used in best path selection in one pass
+    /// over the path attributes. Where an attribute occurs more than once,
+    /// the first occurrence is used.
+    pub fn attributes(&self) -> Result<UpdateAttributes<'a>> {
+        let mut summary = UpdateAttributes::default();
+        for attr in self.path_attrs() {
+            match attr? {
+                PathAttr::Origin(origin) =>
+                    summary.origin = summary.origin.or(Some(origin.origin())),
+                PathAttr::AsPath(path) =>
+                    summary.as_path = summary.as_path.or(Some(path.segments())),
+                PathAttr::As4Path(ref path) if path.code() == 2 =>
+                    summary.as_path = summary.as_path.or(Some(path.segments())),
+                PathAttr::NextHop(next_hop) =>
+                    summary.next_hop = summary.next_hop.or(Some(next_hop.ip())),
+                PathAttr::MultiExitDisc(med) =>
+                    summary.med = summary.med.or(Some(med.med())),
+                PathAttr::LocalPreference(pref) =>
+                    summary.local_pref = summary.local_pref.or(Some(pref.preference())),
+                PathAttr::AtomicAggregate(_) =>
+                    summary.atomic_aggregate = true,
+                PathAttr::OriginatorId(id) =>
+                    summary.originator_id = summary.originator_id.or(Some(id.ident())),
+                PathAttr::MpReachNlri(reach) if summary.mp_next_hop.is_none() =>
+                    summary.mp_next_hop = Some(reach.nexthop()),
+                _ => (),
+            }
+        }
+        Ok(summary)
+    }
+
+    /// All COMMUNITIES, EXTENDED_COMMUNITIES, IPv6 Address Specific
+    /// Extended Community and LARGE_COMMUNITY values, in attribute order.
+    pub fn all_communities(&self) -> AllCommunities<'a> {
+        AllCommunities::new(self.path_attrs())
+    }
+
+    /// Whether any COMMUNITIES attribute carries `community`.
+    pub fn has_community(&self, community: Community) -> bool {
+        self.path_attrs().any(|attr| match attr {
+            Ok(PathAttr::Communities(communities)) => communities.contains(community),
+            _ => false,
+        })
+    }
+
+    /// Whether the route is tagged BLACKHOLE (RFC 7999).
+    pub fn is_blackhole(&self) -> bool {
+        self.has_community(COMMUNITY_BLACKHOLE)
+    }
+
+    /// Whether the route is tagged GRACEFUL_SHUTDOWN (RFC 8326).
+    pub fn graceful_shutdown(&self) -> bool {
+        self.has_community(COMMUNITY_GRACEFUL_SHUTDOWN)
+    }
+
+    /// Whether the route is tagged NO_EXPORT (RFC 1997).
+    pub fn is_no_export(&self) -> bool {
+        self.has_community(COMMUNITY_NO_EXPORT)
+    }
+
+    pub fn nlris(&self) -> NlriIter<'a> {
         let offset = 4 + self.withdrawn_routes_len() + self.total_path_attr_len();
         let slice = &self.value()[offset..];
         NlriIter::new(slice, self.add_paths)
     }
+
+    /// Every withdrawn and announced prefix, including those in
+    /// MP_UNREACH_NLRI and MP_REACH_NLRI, one event per prefix.
+    pub fn routes(&self) -> Routes<'a> {
+        Routes::new(self.withdrawn_routes(), self.path_attrs(), self.nlris())
+    }
+
+    /// The address family whose End-of-RIB marker this is (RFC 4724
+    /// section 2): an UPDATE without routes or attributes for IPv4
+    /// unicast, or one with only an empty MP_UNREACH_NLRI for other
+    /// families. `None` for any other UPDATE.
+    pub fn end_of_rib(&self) -> Result<Option<AfiSafi>> {
+        if self.inner.len() == 23 {
+            return Ok(Some(IPV4_UNICAST));
+        }
+        if !self.try_withdrawn_routes()?.inner.is_empty() || self.try_nlris()?.next().is_some() {
+            return Ok(None);
+        }
+        let mut attrs = self.try_path_attrs()?;
+        match (attrs.next(), attrs.next()) {
+            (Some(Ok(PathAttr::MpUnreachNlri(unreach))), None) if unreach.value().len() == 3 =>
+                Ok(Some(unreach.afi_safi())),
+            (Some(Err(err)), _) => Err(err),
+            _ => Ok(None),
+        }
+    }
+
+    /// Like `nlris`, but checks the section lengths first instead of
+    /// relying on `from_bytes` having done so.
+    pub fn try_nlris(&self) -> Result<NlriIter<'a>> {
+        let (_, _, nlris) = self.sections()?;
+        Ok(NlriIter::new(nlris, self.add_paths))
+    }
+
+    /// Decodes the withdrawn routes, every path attribute and the NLRI,
+    /// returning the first error. See `Message::deep_validate`.
+    pub fn deep_validate(&self) -> Result<()> {
+        let (withdrawn, attrs, nlris) = self.sections()?;
+        validate_all(WithdrawnRoutes::new(withdrawn).with_add_paths(self.add_paths))?;
+        for attr in self.attr_iter(attrs) {
+            attr?.deep_validate()?;
+        }
+        validate_all(NlriIter::new(nlris, self.add_paths))
+    }
+
+    /// Like `deep_validate`, with the offset of the failing attribute or
+    /// prefix and the octets from there.
+    pub fn validate_with_context(&self) -> result::Result<(), ParseError<'a>> {
+        let fail = |error, offset| ParseError::new(error, self.inner, offset);
+        let (withdrawn, attrs, nlris) = self.sections().map_err(|err| fail(err, 19))?;
+        let mut next = self.offset_of(withdrawn);
+        for nlri in WithdrawnRoutes::new(withdrawn).with_add_paths(self.add_paths) {
+            let octets = nlri.map_err(|err| fail(err, next))?.prefix.octets();
+            next = self.offset_of(octets) + octets.len();
+        }
+        let mut iter = self.attr_iter(attrs);
+        next = self.offset_of(attrs);
+        while let Some(raw) = iter.next_raw() {
+            let raw = raw.map_err(|err| fail(err, next))?;
+            next = self.offset_of(raw.bytes);
+            PathAttr::from_raw(raw, self.four_byte_asn, self.add_paths, self.max_labels)
+                .and_then(|attr| attr.deep_validate())
+                .map_err(|err| fail(err, next))?;
+            next += raw.bytes.len();
+        }
+        next = self.offset_of(nlris);
+        for nlri in NlriIter::new(nlris, self.add_paths) {
+            let octets = nlri.map_err(|err| fail(err, next))?.prefix.octets();
+            next = self.offset_of(octets) + octets.len();
+        }
+        Ok(())
+    }
+
+    /// The offset of `part`, a slice of the message, from its start.
+    fn offset_of(&self, part: &[u8]) -> usize {
+        part.as_ptr() as usize - self.inner.as_ptr() as usize
+    }
 }
 
+/// 64-bit FNV-1a, for `Update::content_digest`.
+struct Fnv(u64);
+
+impl Fnv {
+    fn new() -> Fnv {
+        Fnv(0xcbf2_9ce4_8422_2325)
+    }
+
+    /// A hash that starts with whether a route is announced and its
+    /// address family.
+    fn route(announced: bool, afi: Afi, safi: Safi) -> Fnv {
+        let mut hash = Fnv::new();
+        let afi = u16::from(afi);
+        hash.write(&[announced as u8, (afi >> 8) as u8, afi as u8, u8::from(safi)]);
+        hash
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_len(&mut self, len: usize) {
+        self.write(&(len as u32).to_be_bytes());
+    }
+
+    /// The hash, with its bits mixed so that sums of hashes collide no
+    /// more than the hashes do.
+    fn finish(self) -> u64 {
+        let mut hash = self.0;
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        hash ^ (hash >> 31)
+    }
+}
+
+/// The path attributes of an UPDATE that best path selection looks at,
+/// with `None` for those that are absent. See `Update::attributes`.
+#[derive(Debug, Default)]
+pub struct UpdateAttributes<'a> {
+    pub origin: Option<OriginType>,
+    pub as_path: Option<AsPathIter<'a>>,
+    pub next_hop: Option<u32>,
+    pub med: Option<u32>,
+    pub local_pref: Option<u32>,
+    pub atomic_aggregate: bool,
+    pub originator_id: Option<u32>,
+    /// The next hop of the MP_REACH_NLRI attribute.
+    pub mp_next_hop: Option<Nexthop<'a>>,
+}
+
+/// Parses an UPDATE from a session using four-octet AS numbers and no
+/// ADD-PATH. Use `Update::from_bytes` for other sessions.
+impl<'a> TryFrom<&'a [u8]> for Update<'a> {
+    type Error = BgpError;
+
+    fn try_from(raw: &'a [u8]) -> Result<Update<'a>> {
+        Update::from_bytes(raw, true, false)
+    }
+}
+
+/// Summarizes the section lengths. Use `verbose()` to decode the contents.
 impl<'a> fmt::Debug for Update<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("Update")
+            .field("len", &self.inner.len())
+            .field("withdrawn_routes_len", &self.withdrawn_routes_len())
+            .field("total_path_attr_len", &self.total_path_attr_len())
+            .finish()
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl<'a> defmt::Format for Update<'a> {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "Update {{ len: {=usize}, withdrawn_routes_len: {=usize}, total_path_attr_len: {=usize} }}",
+                      self.inner.len(), self.withdrawn_routes_len(), self.total_path_attr_len())
+    }
+}
+
+impl<'a> FmtVerbose for Update<'a> {
+    fn fmt_verbose(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.debug_struct("Update")
             .field("withdrawn_routes", &self.withdrawn_routes())
-            .field("path_attrs", &self.path_attrs())
+            .field("path_attrs", &self.path_attrs().verbose())
             .field("nlris", &self.nlris())
             .finish()
     }
@@ -78,10 +474,7 @@ impl<'a> fmt::Debug for Update<'a> {
 
 #[cfg(test)]
 mod tests {
-    use types::*;
     use super::*;
-    use super::path_attr::*;
-    use super::nlri::*;
 
     macro_rules! expect_attr {
         ($a:expr, $p:pat, $b:block) => {
@@ -111,6 +504,22 @@ mod tests {
                       0x00, 0x00, 0x01, 0x20, 0x05, 0x05, 0x05, 0x05, 0x00, 0x00, 0x00, 0x01,
                       0x20, 0xc0, 0xa8, 0x01, 0x05];
         let update = Update::from_bytes(bytes, four_byte_asn, add_paths).unwrap();
+        assert!(update.four_byte_asn() && update.add_paths());
+        assert!(update.path_attrs().four_byte_asn());
+        assert!(update.nlris().add_paths() && update.withdrawn_routes().add_paths());
+        assert!(update.path_attrs().add_paths());
+        let routes = update.routes();
+        assert!(routes.four_byte_asn() && routes.add_paths());
+        assert!(!Update::from_bytes(bytes, false, true).unwrap().four_byte_asn());
+
+        let bitmap = update.attr_bitmap().unwrap();
+        assert_eq!(bitmap.len(), 7);
+        assert!(bitmap.contains(10) && bitmap.contains(9) && !bitmap.contains(8));
+        let mut communities = AttrBitmap::new();
+        communities.insert(8);
+        assert!(!bitmap.intersects(&communities));
+        communities.insert(5);
+        assert!(bitmap.intersects(&communities));
 
         // withdrawn
         let mut withdrawn = update.withdrawn_routes();
@@ -128,7 +537,7 @@ mod tests {
             match segments.next() {
                 Some(Ok(AsPathSegment::AsSequence(seq))) => {
                     let mut asns = seq.aut_nums().unwrap();
-                    assert_eq!(asns.next().unwrap(), 64511);
+                    assert_eq!(asns.next().unwrap(), AsNumber(64511));
                     assert!(asns.next().is_none());
                 }
                 _ => panic!("expected AS_SEQUENCE")
@@ -166,11 +575,270 @@ mod tests {
 
         let mut nlri = update.nlris();
         assert_eq!(nlri.next().unwrap().unwrap(),
-                   Nlri{path_id: Some(1),
-                        prefix: Ipv4Prefix{inner: &[0x20, 0x05, 0x05, 0x05, 0x05]}});
+                   Nlri{afi: AFI_IPV4, safi: SAFI_UNICAST,
+                        path_id: Some(1), labels: None, rd: None, mt_id: None,
+                        prefix: Prefix::Ipv4(Ipv4Prefix::new(0x20, &[0x05, 0x05, 0x05, 0x05]))});
         assert_eq!(nlri.next().unwrap().unwrap(),
-                   Nlri{path_id: Some(1),
-                        prefix: Ipv4Prefix{inner: &[0x20, 0xc0, 0xa8, 0x01, 0x05]}});
+                   Nlri{afi: AFI_IPV4, safi: SAFI_UNICAST,
+                        path_id: Some(1), labels: None, rd: None, mt_id: None,
+                        prefix: Prefix::Ipv4(Ipv4Prefix::new(0x20, &[0xc0, 0xa8, 0x01, 0x05]))});
         assert!(nlri.next().is_none());
+
+        let summary = update.attributes().unwrap();
+        assert_eq!(summary.origin, Some(OriginType::Igp));
+        assert_eq!(summary.next_hop, Some(0x0a000e01));
+        assert_eq!(summary.med, Some(0));
+        assert_eq!(summary.local_pref, Some(100));
+        assert_eq!(summary.originator_id, Some(0x0a000f01));
+        assert!(summary.as_path.is_some());
+        assert!(!summary.atomic_aggregate);
+        assert!(summary.mp_next_hop.is_none());
+
+        let mut attrs: [Option<PathAttr>; 8] = Default::default();
+        assert_eq!(update.collect_attrs_into(&mut attrs).unwrap(), 7);
+        assert!(matches!(attrs[0], Some(PathAttr::Origin(_))));
+        assert!(matches!(attrs[6], Some(PathAttr::OriginatorId(_))));
+        assert!(attrs[7].is_none());
+        let mut attrs: [Option<PathAttr>; 6] = Default::default();
+        assert!(matches!(update.collect_attrs_into(&mut attrs), Err(BgpError::BadLength)));
+    }
+
+    #[test]
+    fn all_communities() {
+        let bytes = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x54, 0x02, 0x00, 0x00, 0x00, 0x39, 0x40,
+                      0x01, 0x01, 0x00, 0x40, 0x02, 0x06, 0x02, 0x01, 0x00, 0x00, 0xfd, 0xe8,
+                      0x40, 0x03, 0x04, 0xc0, 0x00, 0x02, 0x01, 0xc0, 0x08, 0x08, 0xfd, 0xe8,
+                      0x00, 0x64, 0xff, 0xff, 0x00, 0x00, 0xc0, 0x10, 0x08, 0x00, 0x02, 0xfd,
+                      0xe8, 0x00, 0x00, 0x00, 0x64, 0xc0, 0x20, 0x0c, 0x00, 0x00, 0xfd, 0xe8,
+                      0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02, 0x18, 0xc6, 0x33, 0x64];
+        let update = Update::from_bytes(bytes, true, false).unwrap();
+
+        let mut communities = update.all_communities();
+        match communities.next() {
+            Some(Ok(AnyCommunity::Standard(c))) => assert_eq!(c, Community::new(65000, 100)),
+            x => panic!("expected standard community, got {:?}", x)
+        }
+        match communities.next() {
+            Some(Ok(AnyCommunity::Standard(c))) => assert_eq!(c, COMMUNITY_GRACEFUL_SHUTDOWN),
+            x => panic!("expected standard community, got {:?}", x)
+        }
+        match communities.next() {
+            Some(Ok(AnyCommunity::Extended(ExtendedCommunity::RouteTarget(rt)))) => {
+                assert_eq!(rt.admin(), ExtCommAdmin::As2(65000));
+                assert_eq!(rt.assigned(), 100);
+            }
+            x => panic!("expected route target, got {:?}", x)
+        }
+        match communities.next() {
+            Some(Ok(AnyCommunity::Large(large))) => {
+                assert_eq!(large.global_admin(), 65000);
+                assert_eq!(large.local_data_1(), 1);
+                assert_eq!(large.local_data_2(), 2);
+            }
+            x => panic!("expected large community, got {:?}", x)
+        }
+        assert!(communities.next().is_none());
+
+        // no MULTI_EXIT_DISC or LOCAL_PREF: absent rather than zero
+        let summary = update.attributes().unwrap();
+        assert_eq!(summary.med, None);
+        assert_eq!(summary.local_pref, None);
+        assert_eq!(summary.next_hop, Some(0xc0000201));
+
+        assert!(update.has_community(Community::new(65000, 100)));
+        assert!(!update.has_community(Community::new(65000, 200)));
+        assert!(update.graceful_shutdown());
+        assert!(!update.is_blackhole());
+        assert!(!update.is_no_export());
+    }
+
+    #[test]
+    fn try_from_rejects_bad_section_lengths() {
+        // total path attribute length runs past the end of the message
+        let bytes = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x1a, 0x02, 0x00, 0x00, 0x00, 0x04, 0x40,
+                      0x01, 0x01];
+        assert!(Update::try_from(&bytes[..]).is_err());
+        // not an UPDATE
+        let bytes = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x13, 0x04];
+        assert!(Update::try_from(&bytes[..]).is_err());
+    }
+
+    #[test]
+    fn try_accessors_check_sections() {
+        let bytes = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x1f, 0x02, 0x00, 0x00, 0x00, 0x04, 0x40,
+                      0x01, 0x01, 0x00, 0x18, 0xc6, 0x33, 0x64];
+        let mut update = Update::try_from(&bytes[..]).unwrap();
+        assert_eq!(update.try_withdrawn_routes().unwrap().count(), 0);
+        assert_eq!(update.try_path_attrs().unwrap().count(), 1);
+        assert_eq!(update.try_nlris().unwrap().count(), 1);
+
+        // inner replaced after parsing: path attribute length overruns
+        update.inner = &bytes[..25];
+        assert!(update.try_withdrawn_routes().is_err());
+        assert!(update.try_path_attrs().is_err());
+        assert!(update.try_nlris().is_err());
+    }
+
+    fn build_update<'b>(attrs: &[&[u8]], nlri: &[u8], buf: &'b mut [u8; 64]) -> Update<'b> {
+        let attrs_len: usize = attrs.iter().map(|attr| attr.len()).sum();
+        let len = 23 + attrs_len + nlri.len();
+        buf[..16].copy_from_slice(&[0xff; 16]);
+        buf[16..23].copy_from_slice(&[0, len as u8, 2, 0, 0, 0, attrs_len as u8]);
+        let mut pos = 23;
+        for part in attrs.iter().chain(Some(&nlri)) {
+            buf[pos..pos + part.len()].copy_from_slice(part);
+            pos += part.len();
+        }
+        Update::try_from(&buf[..len]).unwrap()
+    }
+
+    #[test]
+    fn content_digest() {
+        let origin: &[u8] = &[0x40, 0x01, 0x01, 0x00];
+        let as_path: &[u8] = &[0x40, 0x02, 0x06, 0x02, 0x01, 0x00, 0x00, 0xfd, 0xe8];
+        let next_hop: &[u8] = &[0x40, 0x03, 0x04, 0x0a, 0x00, 0x00, 0x01];
+        let (mut a, mut b) = ([0; 64], [0; 64]);
+
+        let digest = build_update(&[origin, as_path, next_hop], &[0x17, 0xc6, 0x33, 0x64, 0x10, 0x0a, 0x01], &mut a)
+            .content_digest().unwrap();
+        // attributes and prefixes reordered, an extended length ORIGIN and
+        // a host bit set in the /23
+        let reordered = build_update(&[next_hop, &[0x50, 0x01, 0x00, 0x01, 0x00], as_path],
+                                     &[0x10, 0x0a, 0x01, 0x17, 0xc6, 0x33, 0x65], &mut b);
+        assert_eq!(reordered.content_digest().unwrap(), digest);
+        let longer = build_update(&[next_hop, origin, as_path],
+                                  &[0x10, 0x0a, 0x01, 0x18, 0xc6, 0x33, 0x64], &mut b);
+        assert_ne!(longer.content_digest().unwrap(), digest);
+
+        let other_prefix = build_update(&[origin, as_path, next_hop], &[0x17, 0xc6, 0x33, 0x66, 0x10, 0x0a, 0x01], &mut b);
+        assert_ne!(other_prefix.content_digest().unwrap(), digest);
+        let other_next_hop = build_update(&[origin, as_path, &[0x40, 0x03, 0x04, 0x0a, 0x00, 0x00, 0x02]],
+                                          &[0x17, 0xc6, 0x33, 0x64, 0x10, 0x0a, 0x01], &mut b);
+        assert_ne!(other_next_hop.content_digest().unwrap(), digest);
+
+        // VPNv4, label 20, RD 65000:1, 198.51.100.0/24
+        let mut vpn = [0x80, 0x0e, 0x20, 0x00, 0x01, 0x80, 0x0c, 0, 0, 0, 0, 0, 0, 0, 0, 0x0a, 0x00, 0x00, 0x01,
+                       0x00, 0x70, 0x00, 0x01, 0x41, 0x00, 0x00, 0xfd, 0xe8, 0x00, 0x00, 0x00, 0x01,
+                       0xc6, 0x33, 0x64];
+        let digest = build_update(&[origin, &vpn], &[], &mut a).content_digest().unwrap();
+        vpn[34] = 0x65;
+        assert_ne!(build_update(&[origin, &vpn], &[], &mut b).content_digest().unwrap(), digest);
+        vpn[34] = 0x64;
+        vpn[31] = 0x02;
+        assert_ne!(build_update(&[origin, &vpn], &[], &mut b).content_digest().unwrap(), digest);
+
+        // EVPN, whose routes are not decoded
+        let mut evpn = [0x80, 0x0e, 0x0e, 0x00, 0x19, 0x46, 0x04, 0x0a, 0x00, 0x00, 0x01, 0x00,
+                        0x02, 0x03, 0x01, 0x02, 0x03];
+        let digest = build_update(&[origin, &evpn], &[], &mut a).content_digest().unwrap();
+        evpn[16] = 0x04;
+        assert_ne!(build_update(&[origin, &evpn], &[], &mut b).content_digest().unwrap(), digest);
+    }
+
+    #[test]
+    fn validate_with_context() {
+        let origin: &[u8] = &[0x40, 0x01, 0x01, 0x00];
+        let mut buf = [0; 64];
+        let update = build_update(&[origin], &[0x18, 0xc6, 0x33, 0x64, 0x21, 0x0a], &mut buf);
+        let err = update.validate_with_context().unwrap_err();
+        assert!(matches!(err.error, BgpError::Invalid));
+        assert_eq!((err.offset, err.window()), (31, &[0x21, 0x0a][..]));
+
+        // COMMUNITIES of 3 octets
+        let update = build_update(&[origin, &[0xc0, 0x08, 0x03, 0xfd, 0xe8, 0x00]], &[0x08, 0x0a], &mut buf);
+        let err = update.validate_with_context().unwrap_err();
+        assert!(matches!(err.error, BgpError::BadLength));
+        assert_eq!((err.offset, err.window()), (27, &[0xc0, 0x08, 0x03, 0xfd, 0xe8, 0x00, 0x08, 0x0a][..]));
+
+        let update = build_update(&[origin], &[0x08, 0x0a], &mut buf);
+        assert!(update.validate_with_context().is_ok());
+    }
+
+    #[test]
+    fn mp_routes() {
+        let mp_unreach: &[u8] = &[0x80, 0x0f, 0x05, 0x00, 0x19, 0x46, 0x01, 0xff];
+        // IPv6 unicast 2001:db8::/32 with path identifier 7, then EVPN
+        let mp_reach: &[u8] = &[0x80, 0x0e, 0x1e, 0x00, 0x02, 0x01, 0x10,
+                                0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01, 0x00,
+                                0x00, 0x00, 0x00, 0x07, 0x20, 0x20, 0x01, 0x0d, 0xb8];
+        let evpn: &[u8] = &[0x80, 0x0e, 0x0e, 0x00, 0x19, 0x46, 0x04, 0x0a, 0x00, 0x00, 0x01, 0x00,
+                            0x02, 0x03, 0x01, 0x02, 0x03];
+        let mut buf = [0; 96];
+        let len = 23 + mp_unreach.len() + mp_reach.len() + evpn.len();
+        buf[..16].copy_from_slice(&[0xff; 16]);
+        buf[16..23].copy_from_slice(&[0, len as u8, 2, 0, 0, 0, (len - 23) as u8]);
+        let mut pos = 23;
+        for part in &[mp_unreach, mp_reach, evpn] {
+            buf[pos..pos + part.len()].copy_from_slice(part);
+            pos += part.len();
+        }
+        let update = Update::from_bytes(&buf[..len], true, true).unwrap();
+
+        let mut routes = update.routes();
+        match routes.next() {
+            Some(Ok(RouteEvent::OtherWithdraw { unreach })) => {
+                assert_eq!(unreach.afi_safi(), AfiSafi::new(AFI_L2VPN, SAFI_EVPN));
+                assert_eq!(unreach.nlri_bytes(), &[0x01, 0xff]);
+            }
+            x => panic!("expected OtherWithdraw, got {:?}", x)
+        }
+        match routes.next() {
+            Some(Ok(RouteEvent::Announce { nlri, .. })) => {
+                assert_eq!((nlri.afi, nlri.safi, nlri.path_id), (AFI_IPV6, SAFI_UNICAST, Some(7)));
+                assert_eq!((nlri.prefix.mask_len(), nlri.prefix.octets()), (32, &[0x20, 0x01, 0x0d, 0xb8][..]));
+            }
+            x => panic!("expected Announce, got {:?}", x)
+        }
+        match routes.next() {
+            Some(Ok(RouteEvent::OtherAnnounce { reach, .. })) => {
+                assert_eq!(reach.afi_safi(), AfiSafi::new(AFI_L2VPN, SAFI_EVPN));
+                assert_eq!(reach.nlri_bytes(), &[0x02, 0x03, 0x01, 0x02, 0x03]);
+            }
+            x => panic!("expected OtherAnnounce, got {:?}", x)
+        }
+        assert!(routes.next().is_none());
+
+        // without ADD-PATH the path identifier is read as a prefix length
+        let update = Update::from_bytes(&buf[..len], true, false).unwrap();
+        assert!(update.routes().any(|event| event.is_err()));
+    }
+
+    #[test]
+    fn debug_summaries() {
+        use core::fmt::Write;
+        use types::FmtBuf;
+
+        let origin: &[u8] = &[0x40, 0x01, 0x01, 0x00];
+        // AS_SEQUENCE 65000 2914, AS_SET {1}
+        let as_path: &[u8] = &[0x40, 0x02, 0x10, 0x02, 0x02, 0x00, 0x00, 0xfd, 0xe8, 0x00, 0x00, 0x0b, 0x62,
+                               0x01, 0x01, 0x00, 0x00, 0x00, 0x01];
+        let mut buf = [0; 64];
+        let update = build_update(&[origin, as_path], &[0x08, 0x0a], &mut buf);
+
+        let mut out = FmtBuf::new();
+        write!(out, "{:?}", update).unwrap();
+        assert_eq!(out.as_bytes(), &b"Update { len: 48, withdrawn_routes_len: 0, total_path_attr_len: 23 }"[..]);
+        let mut out = FmtBuf::new();
+        write!(out, "{:?}", update.path_attrs()).unwrap();
+        assert_eq!(out.as_bytes(), &b"PathAttrIter { len: 23, four_byte_asn: true }"[..]);
+        let as_path = match update.path_attrs().nth(1) {
+            Some(Ok(PathAttr::As4Path(path))) => path,
+            x => panic!("expected As4Path, got {:?}", x)
+        };
+        let mut out = FmtBuf::new();
+        write!(out, "{:?}", as_path.segments()).unwrap();
+        assert_eq!(out.as_bytes(), &b"AsPathIter { len: 16, four_byte: true }"[..]);
+
+        let mut out = FmtBuf::new();
+        write!(out, "{:?}", as_path.segments().verbose()).unwrap();
+        assert_eq!(out.as_bytes(), &b"[[65000, 2914], {1}]"[..]);
+        let mut out = FmtBuf::new();
+        write!(out, "{:?}", update.verbose()).unwrap();
+        assert_eq!(out.as_bytes(), &b"Update { withdrawn_routes: [], \
+path_attrs: [Ok(Origin(Igp)), Ok(As4Path([[65000, 2914], {1}]))], nlris: [Ok(10.0.0.0/8)] }"[..]);
     }
 }
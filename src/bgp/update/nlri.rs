@@ -1,36 +1,100 @@
 use types::*;
 use core::fmt;
 
+/// A reachability entry: a prefix with the address family it belongs to
+/// and whatever the encoding of that family adds to it. Only the labeled
+/// (SAFI 4) and VPN (SAFI 128) families carry labels, only the VPN
+/// families carry a route distinguisher, and only the multi-topology
+/// families carry a topology identifier.
 #[derive(PartialEq)]
 pub struct Nlri<'a> {
+    pub afi: Afi,
+    pub safi: Safi,
+    /// The path identifier, on ADD-PATH sessions.
     pub path_id: Option<u32>,
-    pub prefix: Ipv4Prefix<'a>,
+    /// The MPLS label stack, 3 octets per label, for labeled families.
+    pub labels: Option<&'a [u8]>,
+    /// The route distinguisher, for VPN families.
+    pub rd: Option<RouteDistinguisher<'a>>,
+    /// The topology identifier, for the MT-IPv4 and MT-IPv6 families.
+    pub mt_id: Option<u16>,
+    pub prefix: Prefix<'a>,
+}
+
+impl<'a> Nlri<'a> {
+    /// The MPLS label stack, for labeled families.
+    pub fn label_stack(&self) -> Option<LabelStack<'a>> {
+        self.labels.map(LabelStack::new)
+    }
 }
 
 impl<'a> fmt::Debug for Nlri<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.prefix {
+            Prefix::Ipv4(ref prefix) => prefix.fmt(fmt)?,
+            Prefix::Ipv6(ref prefix) => prefix.fmt(fmt)?,
+        }
+        if let Some(rd) = self.rd {
+            fmt.write_fmt(format_args!("(rd {})", rd))?;
+        }
+        if let Some(mt_id) = self.mt_id {
+            fmt.write_fmt(format_args!("(topology {})", mt_id))?;
+        }
         match self.path_id {
-            None => self.prefix.fmt(fmt),
-            Some(id) => fmt.write_fmt(format_args!("{:?}(path id {})", self.prefix, id))
+            None => Ok(()),
+            Some(id) => fmt.write_fmt(format_args!("(path id {})", id))
         }
     }
 }
 
+/// Iterator over a sequence of encoded prefixes of one address family.
 #[derive(Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
 pub struct NlriIter<'a> {
     inner: &'a [u8],
+    afi: Afi,
+    safi: Safi,
     add_paths: bool,
+    max_labels: u8,
     error: Option<BgpError>,
 }
 
 impl<'a> NlriIter<'a> {
-    pub fn new(inner: &'a[u8], add_paths: bool) -> NlriIter {
+    /// Iterate over the IPv4 unicast NLRI field of an UPDATE.
+    pub fn new(inner: &'a[u8], add_paths: bool) -> NlriIter<'a> {
+        NlriIter::with_family(inner, AFI_IPV4, SAFI_UNICAST, add_paths)
+    }
+
+    /// Iterate over prefixes of `afi`/`safi`, e.g. from MP_REACH_NLRI or
+    /// MP_UNREACH_NLRI.
+    pub fn with_family(inner: &'a[u8], afi: Afi, safi: Safi, add_paths: bool) -> NlriIter<'a> {
         NlriIter {
-            inner: inner,
-            add_paths: add_paths,
+            inner,
+            afi,
+            safi,
+            add_paths,
+            max_labels: 1,
             error: None,
         }
     }
+
+    /// Read labeled and VPN NLRI with up to `max_labels` labels, as
+    /// negotiated with the Multiple Labels capability. Reading stops
+    /// early at the label with the bottom of stack bit set.
+    pub fn with_max_labels(mut self, max_labels: u8) -> NlriIter<'a> {
+        self.max_labels = max_labels;
+        self
+    }
+
+    /// Whether each NLRI is read with a path identifier.
+    pub fn add_paths(&self) -> bool {
+        self.add_paths
+    }
+
+    fn fail(&mut self, err: BgpError) -> Option<Result<Nlri<'a>>> {
+        self.error = Some(err);
+        Some(Err(err))
+    }
 }
 
 impl<'a> Iterator for NlriIter<'a> {
@@ -38,13 +102,11 @@ impl<'a> Iterator for NlriIter<'a> {
 
     fn next(&mut self) -> Option<Result<Nlri<'a>>> {
         if self.error.is_some() {return None;}
-        if self.inner.len() == 0 { return None;}
+        if self.inner.is_empty() { return None;}
 
         let path = if self.add_paths {
             if self.inner.len() < 5 {
-                let err = BgpError::BadLength;
-                self.error = Some(err);
-                return Some(Err(err));
+                return self.fail(BgpError::BadLength);
             }
             let (path_bytes,rest) = self.inner.split_at(4);
             self.inner = rest;
@@ -56,20 +118,72 @@ impl<'a> Iterator for NlriIter<'a> {
             None
         };
 
-        let mask_len = self.inner[0] as usize;
-        let byte_len = (mask_len+15) / 8;
-        if self.inner.len() < byte_len {
-            let err = BgpError::BadLength;
-            self.error = Some(err);
-            return Some(Err(err));
-        }
-        let slice = &self.inner[..byte_len];
-        let nlri = Nlri{path_id: path, prefix: Ipv4Prefix{inner: slice}};
-        self.inner = &self.inner[byte_len..];
+        let mt_id = if self.afi == AFI_MT_IPV4 || self.afi == AFI_MT_IPV6 {
+            let mt_id = match self.inner.u16_at(0) {
+                Ok(mt_id) => mt_id,
+                Err(err) => return self.fail(err),
+            };
+            self.inner = &self.inner[2..];
+            Some(mt_id)
+        } else {
+            None
+        };
+
+        let ipv6 = self.afi == AFI_IPV6 || self.afi == AFI_MT_IPV6;
+        let vpn = self.safi == SAFI_MPLS_LABELED_VPN_ADDR;
+        let split = if vpn || self.safi == SAFI_MPLS_LABEL {
+            split_labeled(self.inner, self.max_labels, vpn, ipv6)
+        } else {
+            Prefix::split(self.inner, ipv6).map(|(prefix, rest)| (None, None, prefix, rest))
+        };
+        let (labels, rd, prefix, rest) = match split {
+            Ok(split) => split,
+            Err(err) => return self.fail(err),
+        };
+        let nlri = Nlri {
+            afi: self.afi,
+            safi: self.safi,
+            path_id: path,
+            labels,
+            rd,
+            mt_id,
+            prefix,
+        };
+        self.inner = rest;
         Some(Ok(nlri))
     }
 }
 
+type Labeled<'a> = (Option<&'a [u8]>, Option<RouteDistinguisher<'a>>, Prefix<'a>, &'a [u8]);
+
+/// Splits the labeled (RFC 8277) or VPN (RFC 4364) NLRI at the start of
+/// `bytes` into its labels, route distinguisher if `vpn`, prefix and the
+/// rest. The length octet counts the bits of all three. Labels are read up
+/// to the one with the bottom of stack bit set, but no more than
+/// `max_labels`, as a withdrawal may carry a label without it.
+fn split_labeled<'a>(bytes: &'a [u8], max_labels: u8, vpn: bool, ipv6: bool) -> Result<Labeled<'a>> {
+    let bits = bytes.at(0)? as usize;
+    let mut offset = 1;
+    for label in LabelStack::new(bytes.skip(1)?).take(max_labels.max(1) as usize) {
+        label?;
+        offset += 3;
+    }
+    if offset == 1 {
+        return Err(BgpError::BadLength);
+    }
+    let labels = &bytes[1..offset];
+    let rd = if vpn {
+        let rd = RouteDistinguisher::from_bytes(bytes.slice(offset, 8)?)?;
+        offset += 8;
+        Some(rd)
+    } else {
+        None
+    };
+    let mask_len = bits.checked_sub((offset - 1) * 8).ok_or(BgpError::Invalid)?;
+    let (prefix, len) = Prefix::from_bits(mask_len as u8, &bytes[offset..], ipv6)?;
+    Ok((Some(labels), rd, prefix, &bytes[offset + len..]))
+}
+
 impl<'a> fmt::Debug for NlriIter<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         let mut debug_list = fmt.debug_list();
@@ -79,3 +193,312 @@ impl<'a> fmt::Debug for NlriIter<'a> {
         debug_list.finish()
     }
 }
+
+/// Encodes NLRI into a caller supplied buffer, either as the bare NLRI
+/// field of an UPDATE or wrapped in an MP_REACH_NLRI or MP_UNREACH_NLRI
+/// attribute.
+///
+/// Labels and route distinguishers are written between the path
+/// identifier and the prefix, and counted in the prefix length, as in
+/// RFC 8277 and RFC 4364.
+pub struct NlriWriter<'b> {
+    buf: &'b mut [u8],
+    pos: usize,
+    /// Offset of the attribute's extended length field, if any.
+    attr_len: Option<usize>,
+    afi: Afi,
+    safi: Safi,
+    add_paths: bool,
+}
+
+impl<'b> NlriWriter<'b> {
+    /// Writes the NLRI field of an UPDATE, which is always IPv4 unicast.
+    pub fn new(buf: &'b mut [u8], add_paths: bool) -> NlriWriter<'b> {
+        NlriWriter {
+            buf,
+            pos: 0,
+            attr_len: None,
+            afi: AFI_IPV4,
+            safi: SAFI_UNICAST,
+            add_paths,
+        }
+    }
+
+    /// Writes an MP_REACH_NLRI attribute (flags, type code, length and
+    /// value) announcing prefixes of `afi`/`safi` via `nexthop`.
+    pub fn mp_reach(buf: &'b mut [u8], afi: Afi, safi: Safi, nexthop: &[u8],
+                    add_paths: bool) -> Result<NlriWriter<'b>> {
+        if nexthop.len() > 255 {
+            return Err(BgpError::Invalid);
+        }
+        let mut writer = NlriWriter::attr(buf, 14, afi, safi, add_paths)?;
+        writer.put(&[nexthop.len() as u8])?;
+        writer.put(nexthop)?;
+        writer.put(&[0])?; // reserved
+        Ok(writer)
+    }
+
+    /// Writes an MP_UNREACH_NLRI attribute (flags, type code, length and
+    /// value) withdrawing prefixes of `afi`/`safi`.
+    pub fn mp_unreach(buf: &'b mut [u8], afi: Afi, safi: Safi,
+                      add_paths: bool) -> Result<NlriWriter<'b>> {
+        NlriWriter::attr(buf, 15, afi, safi, add_paths)
+    }
+
+    fn attr(buf: &'b mut [u8], code: u8, afi: Afi, safi: Safi,
+            add_paths: bool) -> Result<NlriWriter<'b>> {
+        let afi_bytes = u16::from(afi);
+        let mut writer = NlriWriter {
+            buf,
+            pos: 0,
+            attr_len: Some(2),
+            afi,
+            safi,
+            add_paths,
+        };
+        // optional, extended length
+        writer.put(&[0x90, code, 0, 0])?;
+        writer.put(&[(afi_bytes >> 8) as u8, afi_bytes as u8, u8::from(safi)])?;
+        Ok(writer)
+    }
+
+    fn put(&mut self, bytes: &[u8]) -> Result<()> {
+        let end = self.pos + bytes.len();
+        if end > self.buf.len() {
+            return Err(BgpError::BadLength);
+        }
+        self.buf[self.pos..end].copy_from_slice(bytes);
+        self.pos = end;
+        Ok(())
+    }
+
+    /// Appends `nlri`. Its address family must be the writer's, and it
+    /// must carry a path identifier exactly when the writer was created
+    /// for an ADD-PATH session. Nothing is written on error.
+    pub fn push(&mut self, nlri: &Nlri) -> Result<()> {
+        match (self.afi, &nlri.prefix) {
+            (AFI_IPV4, Prefix::Ipv4(_)) | (AFI_IPV6, Prefix::Ipv6(_)) => (),
+            _ => return Err(BgpError::Invalid),
+        }
+        if nlri.afi != self.afi || nlri.safi != self.safi || nlri.mt_id.is_some()
+            || nlri.path_id.is_some() != self.add_paths {
+            return Err(BgpError::Invalid);
+        }
+        let labels = nlri.labels.unwrap_or(&[]);
+        let rd = nlri.rd.map(|rd| rd.octets()).unwrap_or(&[]);
+        if labels.len() % 3 != 0 {
+            return Err(BgpError::Invalid);
+        }
+        let mask_len = (labels.len() + rd.len()) * 8 + nlri.prefix.mask_len() as usize;
+        if mask_len > 255 {
+            return Err(BgpError::Invalid);
+        }
+
+        let start = self.pos;
+        let res = self.put_nlri(nlri.path_id, labels, rd, mask_len as u8, nlri.prefix.octets());
+        if res.is_err() {
+            self.pos = start;
+        }
+        res
+    }
+
+    fn put_nlri(&mut self, path_id: Option<u32>, labels: &[u8], rd: &[u8],
+                mask_len: u8, prefix: &[u8]) -> Result<()> {
+        if let Some(id) = path_id {
+            self.put(&[(id >> 24) as u8, (id >> 16) as u8, (id >> 8) as u8, id as u8])?;
+        }
+        self.put(&[mask_len])?;
+        self.put(labels)?;
+        self.put(rd)?;
+        self.put(prefix)
+    }
+
+    /// Completes the encoding and returns the bytes written.
+    pub fn finish(self) -> Result<&'b [u8]> {
+        if let Some(offset) = self.attr_len {
+            let len = self.pos - offset - 2;
+            if len > 0xffff {
+                return Err(BgpError::BadLength);
+            }
+            self.buf[offset] = (len >> 8) as u8;
+            self.buf[offset + 1] = len as u8;
+        }
+        Ok(&self.buf[..self.pos])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bgp::update::path_attr::*;
+
+    #[test]
+    fn write_nlri() {
+        let mut buf = [0; 32];
+        let mut writer = NlriWriter::new(&mut buf, true);
+        let nlri = Nlri {
+            afi: AFI_IPV4,
+            safi: SAFI_UNICAST,
+            path_id: Some(7),
+            labels: None,
+            rd: None,
+            mt_id: None,
+            prefix: Prefix::Ipv4(Ipv4Prefix::new(24, &[192, 0, 2])),
+        };
+        writer.push(&nlri).unwrap();
+        let ipv6 = Nlri {
+            afi: AFI_IPV6,
+            prefix: Prefix::Ipv6(Ipv6Prefix::new(32, &[0x20, 0x01, 0x0d, 0xb8])),
+            ..nlri
+        };
+        assert!(matches!(writer.push(&ipv6), Err(BgpError::Invalid)));
+        let bytes = writer.finish().unwrap();
+        assert_eq!(bytes, &[0, 0, 0, 7, 24, 192, 0, 2]);
+
+        let mut iter = NlriIter::new(bytes, true);
+        assert_eq!(iter.next().unwrap().unwrap(), nlri);
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn write_mp_nlri() {
+        let mut buf = [0; 64];
+        let nexthop = [0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1];
+        let mut writer = NlriWriter::mp_reach(&mut buf, AFI_IPV6, SAFI_UNICAST,
+                                              &nexthop, false).unwrap();
+        let nlri = Nlri {
+            afi: AFI_IPV6,
+            safi: SAFI_UNICAST,
+            path_id: None,
+            labels: None,
+            rd: None,
+            mt_id: None,
+            prefix: Prefix::Ipv6(Ipv6Prefix::new(32, &[0x20, 0x01, 0x0d, 0xb8])),
+        };
+        writer.push(&nlri).unwrap();
+        let bytes = writer.finish().unwrap();
+        match MpReachNlri::from_bytes(bytes) {
+            Ok(MpReachNlri::Ipv6Unicast(reach)) => {
+                assert_eq!(reach.nexthop().bytes(), &nexthop);
+                let mut nlris = reach.nlris();
+                assert_eq!(nlris.next().unwrap().unwrap(), nlri);
+                assert!(nlris.next().is_none());
+            }
+            x => panic!("{:?}", x),
+        }
+
+        // labeled VPN: one label, route distinguisher 65000:1, 10.0.0.0/8
+        let mut buf = [0; 32];
+        let mut writer = NlriWriter::mp_unreach(&mut buf, AFI_IPV4,
+                                                SAFI_MPLS_LABELED_VPN_ADDR, false).unwrap();
+        writer.push(&Nlri {
+            afi: AFI_IPV4,
+            safi: SAFI_MPLS_LABELED_VPN_ADDR,
+            path_id: None,
+            labels: Some(&[0x00, 0x01, 0x01]),
+            rd: Some(RouteDistinguisher(&[0, 0, 0xfd, 0xe8, 0, 0, 0, 1])),
+            mt_id: None,
+            prefix: Prefix::Ipv4(Ipv4Prefix::new(8, &[10])),
+        }).unwrap();
+        assert_eq!(writer.finish().unwrap(),
+                   &[0x90, 15, 0, 16, 0, 1, 128,
+                     96, 0x00, 0x01, 0x01, 0, 0, 0xfd, 0xe8, 0, 0, 0, 1, 10]);
+
+        let mut buf = [0; 8];
+        assert!(matches!(NlriWriter::mp_reach(&mut buf, AFI_IPV6, SAFI_UNICAST, &nexthop, false),
+                         Err(BgpError::BadLength)));
+    }
+
+    fn family_nlri(safi: Safi, ipv6: bool, path_id: Option<u32>) -> Nlri<'static> {
+        Nlri {
+            afi: if ipv6 { AFI_IPV6 } else { AFI_IPV4 },
+            safi,
+            path_id,
+            labels: if safi == SAFI_UNICAST { None } else { Some(&[0x00, 0x01, 0x01]) },
+            rd: if safi == SAFI_MPLS_LABELED_VPN_ADDR {
+                Some(RouteDistinguisher(&[0, 0, 0xfd, 0xe8, 0, 0, 0, 1]))
+            } else {
+                None
+            },
+            mt_id: None,
+            prefix: if ipv6 {
+                Prefix::Ipv6(Ipv6Prefix::new(33, &[0x20, 0x01, 0x0d, 0xb8, 0x80]))
+            } else {
+                Prefix::Ipv4(Ipv4Prefix::new(22, &[198, 51, 100]))
+            },
+        }
+    }
+
+    #[test]
+    fn mp_nlri_round_trip() {
+        for &safi in &[SAFI_UNICAST, SAFI_MPLS_LABEL, SAFI_MPLS_LABELED_VPN_ADDR] {
+            for &(ipv6, add_paths) in &[(false, false), (false, true), (true, false), (true, true)] {
+                let nlri = family_nlri(safi, ipv6, if add_paths { Some(7) } else { None });
+
+                let mut buf = [0; 96];
+                let mut writer = NlriWriter::mp_reach(&mut buf, nlri.afi, safi,
+                                                      &[192, 0, 2, 1], add_paths).unwrap();
+                writer.push(&nlri).unwrap();
+                writer.push(&nlri).unwrap();
+                let bytes = writer.finish().unwrap();
+                let attr = PathAttrIter::new(bytes, true).with_add_paths(add_paths).next().unwrap().unwrap();
+                let mut nlris = match attr {
+                    PathAttr::MpReachNlri(ref reach) => reach.nlris().unwrap(),
+                    ref x => panic!("{:?}", x),
+                };
+                assert_eq!(nlris.next().unwrap().unwrap(), nlri);
+                assert_eq!(nlris.next().unwrap().unwrap(), nlri);
+                assert!(nlris.next().is_none());
+
+                let mut buf = [0; 64];
+                let mut writer = NlriWriter::mp_unreach(&mut buf, nlri.afi, safi, add_paths).unwrap();
+                writer.push(&nlri).unwrap();
+                let bytes = writer.finish().unwrap();
+                let attr = PathAttrIter::new(bytes, true).with_add_paths(add_paths).next().unwrap().unwrap();
+                let mut nlris = match attr {
+                    PathAttr::MpUnreachNlri(ref unreach) => unreach.nlris().unwrap(),
+                    ref x => panic!("{:?}", x),
+                };
+                assert_eq!(nlris.next().unwrap().unwrap(), nlri);
+                assert!(nlris.next().is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn multiple_labels() {
+        // labels 16 and 17, then 10.0.0.0/8
+        let bytes = &[56, 0x00, 0x01, 0x00, 0x00, 0x01, 0x11, 10];
+        let mut nlris = NlriIter::with_family(bytes, AFI_IPV4, SAFI_MPLS_LABEL, false).with_max_labels(3);
+        let nlri = nlris.next().unwrap().unwrap();
+        assert_eq!(nlri.labels, Some(&bytes[1..7]));
+        let values = nlri.label_stack().unwrap().map(|label| label.unwrap().value);
+        assert!(values.eq([16, 17].iter().cloned()));
+        assert_eq!(nlri.prefix, Prefix::Ipv4(Ipv4Prefix::new(8, &[10])));
+        assert!(nlris.next().is_none());
+
+        // read with a single label, the second is taken for part of a /32
+        let mut nlris = NlriIter::with_family(bytes, AFI_IPV4, SAFI_MPLS_LABEL, false);
+        let nlri = nlris.next().unwrap().unwrap();
+        assert_eq!(nlri.prefix, Prefix::Ipv4(Ipv4Prefix::new(32, &[0x00, 0x01, 0x11, 10])));
+    }
+
+    #[test]
+    fn labeled_nlri_lengths() {
+        // the length covers the label but not the route distinguisher
+        let mut nlris = NlriIter::with_family(&[80, 0x00, 0x01, 0x01, 0, 0, 0xfd, 0xe8, 0, 0, 0, 1, 10],
+                                              AFI_IPV4, SAFI_MPLS_LABELED_VPN_ADDR, false);
+        assert!(matches!(nlris.next(), Some(Err(BgpError::Invalid))));
+        assert!(nlris.next().is_none());
+
+        // a prefix longer than 32 bits once the label is taken off
+        let mut nlris = NlriIter::with_family(&[57, 0x00, 0x01, 0x01, 192, 0, 2, 1, 0],
+                                              AFI_IPV4, SAFI_MPLS_LABEL, false);
+        assert!(matches!(nlris.next(), Some(Err(BgpError::Invalid))));
+
+        // truncated route distinguisher
+        let mut nlris = NlriIter::with_family(&[96, 0x00, 0x01, 0x01, 0, 0, 0xfd],
+                                              AFI_IPV4, SAFI_MPLS_LABELED_VPN_ADDR, false);
+        assert!(matches!(nlris.next(), Some(Err(BgpError::BadLength))));
+    }
+}
@@ -0,0 +1,248 @@
+use types::*;
+use bgp::{write_header, SessionConfig};
+use super::Update;
+use super::path_attr::*;
+
+/// Writes into a caller supplied buffer, failing with
+/// `BgpError::BadLength` when it is full.
+struct Writer<'b> {
+    buf: &'b mut [u8],
+    pos: usize,
+}
+
+impl<'b> Writer<'b> {
+    fn put(&mut self, bytes: &[u8]) -> Result<()> {
+        let end = self.pos + bytes.len();
+        if end > self.buf.len() {
+            return Err(BgpError::BadLength);
+        }
+        self.buf[self.pos..end].copy_from_slice(bytes);
+        self.pos = end;
+        Ok(())
+    }
+
+    /// Writes a path attribute, using the extended length only when the
+    /// value needs it.
+    fn attr(&mut self, flags: u8, code: u8, value: &[u8]) -> Result<()> {
+        let len = value.len();
+        if len > 0xff {
+            self.put(&[flags | FLAG_EXT_LEN, code, (len >> 8) as u8, len as u8])?;
+        } else {
+            self.put(&[flags & !FLAG_EXT_LEN, code, len as u8])?;
+        }
+        self.put(value)
+    }
+}
+
+/// The number of AS numbers a path counts as for merging with AS4_PATH:
+/// an AS_SET counts as one (RFC 6793 section 4.2.3).
+fn path_len(segments: AsPathIter) -> Result<usize> {
+    let mut len = 0;
+    for segment in segments {
+        len += match segment? {
+            AsPathSegment::AsSequence(seq) => seq.aut_nums()?.count(),
+            AsPathSegment::AsSet(_) => 1,
+        };
+    }
+    Ok(len)
+}
+
+/// Appends the first `count` AS numbers of `segments` to `builder`, an
+/// AS_SET counting as one, or all of them if `count` is `None`.
+fn push_segments(builder: &mut AsPathBuilder, segments: AsPathIter, mut count: Option<usize>) -> Result<()> {
+    for segment in segments {
+        if count == Some(0) {
+            break;
+        }
+        match segment? {
+            AsPathSegment::AsSequence(seq) => {
+                for asn in seq.aut_nums()? {
+                    if count == Some(0) {
+                        break;
+                    }
+                    builder.push_asn(asn.0)?;
+                    count = count.map(|count| count - 1);
+                }
+            }
+            AsPathSegment::AsSet(set) => {
+                let mut asns = [0; 255];
+                let mut len = 0;
+                for (slot, asn) in asns.iter_mut().zip(set.aut_nums()?) {
+                    *slot = asn.0;
+                    len += 1;
+                }
+                builder.push_set(&asns[..len])?;
+                count = count.map(|count| count - 1);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Appends to `builder`, which must use four-octet AS numbers, the path
+/// that `path` from a two-octet AS session stands for once merged with
+/// `as4_path` (RFC 6793 section 4.2.3). `as4_path` is ignored when it is
+/// longer than `path`.
+///
+/// The merged value is at most twice as long as the value of `path` plus
+/// the value of `as4_path`: the AS numbers taken from `path` are widened
+/// to four octets, those from `as4_path` are copied, and the builder adds
+/// no segment headers that neither had.
+pub(crate) fn merge_as4_path(builder: &mut AsPathBuilder, path: AsPathIter, as4_path: Option<AsPathIter>) -> Result<()> {
+    match as4_path {
+        Some(as4_path) if path_len(as4_path.clone())? <= path_len(path.clone())? => {
+            let keep = path_len(path.clone())? - path_len(as4_path.clone())?;
+            push_segments(builder, path, Some(keep))?;
+            push_segments(builder, as4_path, None)
+        }
+        _ => push_segments(builder, path, None),
+    }
+}
+
+/// The AGGREGATOR value, with a four-octet AS number, that `aggregator`
+/// from a two-octet AS session stands for: that of `as4_aggregator` if
+/// `aggregator` holds AS_TRANS (RFC 6793 section 4.2.3).
+pub(crate) fn merge_as4_aggregator(aggregator: &Aggregator, as4_aggregator: Option<&As4Aggregator>) -> [u8; 8] {
+    let mut value = [0; 8];
+    match as4_aggregator {
+        Some(as4) if aggregator.aut_num().is_as_trans() => value.copy_from_slice(as4.value()),
+        _ => value[2..].copy_from_slice(aggregator.value()),
+    }
+    value
+}
+
+impl<'a> Update<'a> {
+    /// The first AS4_PATH and AS4_AGGREGATOR attributes, for merging
+    /// with AS_PATH and AGGREGATOR.
+    pub(crate) fn as4_attrs(&self) -> Result<(Option<As4Path<'a>>, Option<As4Aggregator<'a>>)> {
+        let mut as4_path = None;
+        let mut as4_aggregator = None;
+        for attr in self.path_attrs() {
+            match attr? {
+                PathAttr::As4Path(path) if as4_path.is_none() && path.code() == 17 => as4_path = Some(path),
+                PathAttr::As4Aggregator(aggregator) if as4_aggregator.is_none() && aggregator.code() == 18 =>
+                    as4_aggregator = Some(aggregator),
+                _ => (),
+            }
+        }
+        Ok((as4_path, as4_aggregator))
+    }
+
+    /// Writes the UPDATE into `buf` in a canonical form for a session
+    /// with the parameters in `config`, so that UPDATEs from speakers
+    /// that encode the same routes differently compare equal byte for
+    /// byte:
+    ///
+    /// - path attributes are sorted by type code, keeping the order of
+    ///   attributes with the same code,
+    /// - the Extended Length flag is only set for values longer than 255
+    ///   octets,
+    /// - for four-octet AS sessions, an AS_PATH received with two-octet
+    ///   AS numbers is merged with AS4_PATH (RFC 6793 section 4.2.3) and
+    ///   AGGREGATOR with AS4_AGGREGATOR, and AS4_PATH and AS4_AGGREGATOR
+    ///   are left out.
+    ///
+    /// Withdrawn routes, NLRI and other attribute values are copied as
+    /// they are. Narrowing four-octet AS numbers for a two-octet session
+    /// and changing ADD-PATH fail with `BgpError::Invalid`.
+    pub fn normalize_into<'b>(&self, buf: &'b mut [u8], config: &SessionConfig) -> Result<&'b [u8]> {
+        if config.add_paths != self.add_paths || (self.four_byte_asn && !config.four_byte_asn) {
+            return Err(BgpError::Invalid);
+        }
+        let (withdrawn, attrs, nlris) = self.sections()?;
+        let widen = config.four_byte_asn && !self.four_byte_asn;
+        let (as4_path, as4_aggregator) = if widen { self.as4_attrs()? } else { (None, None) };
+
+        let mut writer = Writer { buf, pos: 19 };
+        writer.put(&(withdrawn.len() as u16).to_be_bytes())?;
+        writer.put(withdrawn)?;
+        let attrs_start = writer.pos + 2;
+        writer.put(&[0, 0])?;
+        let present = self.attr_bitmap()?;
+        for code in (0..=255).filter(|&code| present.contains(code)) {
+            if config.four_byte_asn && (code == 17 || code == 18) {
+                continue;
+            }
+            let mut iter = PathAttrIter::new(attrs, self.four_byte_asn);
+            while let Some(raw) = iter.next_raw() {
+                let raw = raw?;
+                if raw.code != code {
+                    continue;
+                }
+                match (code, PathAttr::from_raw(raw, self.four_byte_asn, self.add_paths, self.max_labels)?) {
+                    (2, PathAttr::AsPath(path)) if widen => {
+                        let mut builder = AsPathBuilder::new(&mut writer.buf[writer.pos..], true);
+                        merge_as4_path(&mut builder, path.segments(), as4_path.as_ref().map(|path| path.segments()))?;
+                        writer.pos += builder.finish()?.len();
+                    }
+                    (7, PathAttr::Aggregator(aggregator)) if widen => {
+                        let value = merge_as4_aggregator(&aggregator, as4_aggregator.as_ref());
+                        writer.attr(raw.flags, code, &value)?;
+                    }
+                    _ => writer.attr(raw.flags, code, raw.value)?,
+                }
+            }
+        }
+        let attrs_len = writer.pos - attrs_start;
+        if attrs_len > 0xffff {
+            return Err(BgpError::BadLength);
+        }
+        writer.buf[attrs_start - 2..attrs_start].copy_from_slice(&(attrs_len as u16).to_be_bytes());
+        writer.put(nlris)?;
+
+        let len = writer.pos;
+        if len > config.max_message_len() {
+            return Err(BgpError::BadLength);
+        }
+        write_header(writer.buf, len, 2)?;
+        Ok(&writer.buf[..len])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_two_octet_update() {
+        let bytes = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x4d, 0x02, 0x00, 0x00, 0x00, 0x32,
+                      // AS4_AGGREGATOR 4200000000, 192.0.2.1
+                      0xc0, 0x12, 0x08, 0xfa, 0x56, 0xea, 0x00, 0xc0, 0x00, 0x02, 0x01,
+                      // NEXT_HOP 10.0.0.1
+                      0x40, 0x03, 0x04, 0x0a, 0x00, 0x00, 0x01,
+                      // AS4_PATH [4200000000]
+                      0xc0, 0x11, 0x06, 0x02, 0x01, 0xfa, 0x56, 0xea, 0x00,
+                      // ORIGIN IGP, with the extended length flag
+                      0x50, 0x01, 0x00, 0x01, 0x00,
+                      // AS_PATH [65001, AS_TRANS]
+                      0x40, 0x02, 0x06, 0x02, 0x02, 0xfd, 0xe9, 0x5b, 0xa0,
+                      // AGGREGATOR AS_TRANS, 192.0.2.1
+                      0xc0, 0x07, 0x06, 0x5b, 0xa0, 0xc0, 0x00, 0x02, 0x01,
+                      0x18, 0xc6, 0x33, 0x64];
+        let update = Update::from_bytes(bytes, false, false).unwrap();
+
+        let mut buf = [0; 128];
+        let normalized = update.normalize_into(&mut buf, &SessionConfig::default()).unwrap();
+        assert_eq!(&normalized[16..], &[0x00, 0x3e, 0x02, 0x00, 0x00, 0x00, 0x23,
+                                        0x40, 0x01, 0x01, 0x00,
+                                        0x40, 0x02, 0x0a, 0x02, 0x02, 0x00, 0x00, 0xfd, 0xe9,
+                                        0xfa, 0x56, 0xea, 0x00,
+                                        0x40, 0x03, 0x04, 0x0a, 0x00, 0x00, 0x01,
+                                        0xc0, 0x07, 0x08, 0xfa, 0x56, 0xea, 0x00, 0xc0, 0x00, 0x02, 0x01,
+                                        0x18, 0xc6, 0x33, 0x64][..]);
+
+        // normalizing again changes nothing
+        let again = Update::from_bytes(normalized, true, false).unwrap();
+        let mut buf2 = [0; 128];
+        assert_eq!(again.normalize_into(&mut buf2, &SessionConfig::default()).unwrap(), normalized);
+
+        let two_octet = SessionConfig { four_byte_asn: false, ..SessionConfig::default() };
+        let sorted = update.normalize_into(&mut buf2, &two_octet).unwrap();
+        assert_eq!(sorted.len(), bytes.len() - 1);
+        assert_eq!(&sorted[23..27], &[0x40, 0x01, 0x01, 0x00]);
+        assert!(matches!(again.normalize_into(&mut buf2, &two_octet), Err(BgpError::Invalid)));
+        assert!(matches!(update.normalize_into(&mut buf2[..40], &SessionConfig::default()),
+                         Err(BgpError::BadLength)));
+    }
+}
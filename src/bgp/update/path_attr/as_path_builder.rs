@@ -0,0 +1,193 @@
+use super::*;
+use bgp::SessionConfig;
+
+const AS_SET: u8 = 1;
+const AS_SEQUENCE: u8 = 2;
+const MAX_SEGMENT_LEN: u8 = 255;
+
+/// Encodes an AS_PATH attribute into a caller supplied buffer.
+///
+/// Consecutive AS numbers are collected into AS_SEQUENCE segments, which
+/// are split every 255 AS numbers. When encoding for a session without
+/// four-octet AS numbers, AS numbers that do not fit in two octets are
+/// replaced by `AS_TRANS`.
+pub struct AsPathBuilder<'b> {
+    buf: &'b mut [u8],
+    pos: usize,
+    four_byte_asn: bool,
+    /// Offset of the header of the AS_SEQUENCE being extended, if any.
+    sequence: Option<usize>,
+}
+
+impl<'b> AsPathBuilder<'b> {
+    pub fn new(buf: &'b mut [u8], four_byte_asn: bool) -> AsPathBuilder<'b> {
+        AsPathBuilder {
+            buf,
+            // room for the flags, type code and extended length
+            pos: 4,
+            four_byte_asn,
+            sequence: None,
+        }
+    }
+
+    /// Chooses the AS number width negotiated for the session.
+    pub fn with_config(buf: &'b mut [u8], config: &SessionConfig) -> AsPathBuilder<'b> {
+        AsPathBuilder::new(buf, config.four_byte_asn)
+    }
+
+    fn as_size(&self) -> usize {
+        if self.four_byte_asn { 4 } else { 2 }
+    }
+
+    fn put_asn(&mut self, asn: u32) {
+        let pos = self.pos;
+        if self.four_byte_asn {
+            self.buf[pos..pos + 4].copy_from_slice(&[(asn >> 24) as u8, (asn >> 16) as u8,
+                                                    (asn >> 8) as u8, asn as u8]);
+            self.pos += 4;
+        } else {
+            let asn = if asn > 0xffff { AS_TRANS } else { asn };
+            self.buf[pos..pos + 2].copy_from_slice(&[(asn >> 8) as u8, asn as u8]);
+            self.pos += 2;
+        }
+    }
+
+    /// Appends `asn` to the path, extending the current AS_SEQUENCE or
+    /// starting a new one.
+    pub fn push_asn(&mut self, asn: u32) -> Result<()> {
+        let segment = match self.sequence {
+            Some(offset) if self.buf[offset + 1] < MAX_SEGMENT_LEN => Some(offset),
+            _ => None,
+        };
+        let needed = self.as_size() + if segment.is_none() { 2 } else { 0 };
+        if self.pos + needed > self.buf.len() {
+            return Err(BgpError::BadLength);
+        }
+        let offset = match segment {
+            Some(offset) => offset,
+            None => {
+                let offset = self.pos;
+                self.buf[offset] = AS_SEQUENCE;
+                self.buf[offset + 1] = 0;
+                self.pos += 2;
+                self.sequence = Some(offset);
+                offset
+            }
+        };
+        self.buf[offset + 1] += 1;
+        self.put_asn(asn);
+        Ok(())
+    }
+
+    /// Appends each of `asns` in order.
+    pub fn push_sequence(&mut self, asns: &[u32]) -> Result<()> {
+        for &asn in asns {
+            self.push_asn(asn)?;
+        }
+        Ok(())
+    }
+
+    /// Appends an AS_SET segment. A set can not be split without changing
+    /// its meaning, so it must hold between 1 and 255 AS numbers.
+    pub fn push_set(&mut self, asns: &[u32]) -> Result<()> {
+        if asns.is_empty() || asns.len() > MAX_SEGMENT_LEN as usize {
+            return Err(BgpError::Invalid);
+        }
+        if self.pos + 2 + asns.len() * self.as_size() > self.buf.len() {
+            return Err(BgpError::BadLength);
+        }
+        let pos = self.pos;
+        self.buf[pos] = AS_SET;
+        self.buf[pos + 1] = asns.len() as u8;
+        self.pos += 2;
+        for &asn in asns {
+            self.put_asn(asn);
+        }
+        self.sequence = None;
+        Ok(())
+    }
+
+    /// The segments appended so far, for reading the path back before
+    /// `finish`.
+    pub fn segments(&self) -> AsPathIter<'_> {
+        AsPathIter {
+            inner: &self.buf[4..self.pos],
+            error: false,
+            four_byte: self.four_byte_asn,
+        }
+    }
+
+    /// Completes the encoding and returns the AS_PATH attribute (flags,
+    /// type code, length and value). The extended length flag is only set
+    /// when the value is longer than 255 octets.
+    pub fn finish(self) -> Result<&'b [u8]> {
+        if self.buf.len() < 4 {
+            return Err(BgpError::BadLength);
+        }
+        let len = self.pos - 4;
+        if len > 0xffff {
+            return Err(BgpError::BadLength);
+        }
+        if len > 0xff {
+            self.buf[..4].copy_from_slice(&[FLAG_TRANSITIVE | FLAG_EXT_LEN, 2,
+                                            (len >> 8) as u8, len as u8]);
+            Ok(&self.buf[..self.pos])
+        } else {
+            self.buf.copy_within(4..self.pos, 3);
+            self.buf[..3].copy_from_slice(&[FLAG_TRANSITIVE, 2, len as u8]);
+            Ok(&self.buf[..self.pos - 1])
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_as_path() {
+        let mut buf = [0; 64];
+        let mut builder = AsPathBuilder::new(&mut buf, false);
+        builder.push_sequence(&[65000, 4200000000]).unwrap();
+        builder.push_set(&[10, 20]).unwrap();
+        builder.push_asn(30).unwrap();
+        assert_eq!(builder.finish().unwrap(),
+                   &[0x40, 0x02, 0x10,
+                     0x02, 0x02, 0xfd, 0xe8, 0x5b, 0xa0,
+                     0x01, 0x02, 0x00, 0x0a, 0x00, 0x14,
+                     0x02, 0x01, 0x00, 0x1e]);
+
+        let mut buf = [0; 4];
+        let mut builder = AsPathBuilder::new(&mut buf, true);
+        assert!(matches!(builder.push_asn(1), Err(BgpError::BadLength)));
+        assert!(matches!(builder.push_set(&[]), Err(BgpError::Invalid)));
+        assert_eq!(builder.finish().unwrap(), &[0x40, 0x02, 0x00]);
+    }
+
+    #[test]
+    fn split_long_sequence() {
+        let mut buf = [0; 1300];
+        let mut builder = AsPathBuilder::with_config(&mut buf, &SessionConfig::default());
+        for asn in 0..300 {
+            builder.push_asn(asn).unwrap();
+        }
+        let bytes = builder.finish().unwrap();
+        assert_eq!(&bytes[..4], &[0x50, 0x02, 0x04, 0xb4]);
+        let as_path = As4Path::from_attr_bytes(bytes).unwrap();
+        let mut segments = as_path.segments();
+        let mut next = 0;
+        for &end in &[255, 300] {
+            match segments.next() {
+                Some(Ok(AsPathSegment::AsSequence(seq))) => {
+                    for asn in seq.aut_nums().unwrap() {
+                        assert_eq!(asn, AsNumber(next));
+                        next += 1;
+                    }
+                    assert_eq!(next, end);
+                }
+                x => panic!("expected AS_SEQUENCE, got {:?}", x)
+            }
+        }
+        assert!(segments.next().is_none());
+    }
+}
@@ -1,18 +1,8 @@
 use types::*;
+use core::convert::TryFrom;
 use core::fmt;
 
-/// Defines whether the attribute is optional (if set to 1) or well-known (if set to 0)
-pub const FLAG_OPTIONAL:   u8 = 0b10000000;
-/// Defines whether an optional attribute is transitive (if set to 1) or non-transitive (if set to 0).
-/// For well-known attributes, the Transitive bit MUST be set to 1.
-pub const FLAG_TRANSITIVE: u8 = 0b01000000;
-/// Defines whether the information contained in the optional transitive attribute is partial (if
-/// set to 1) or complete (if set to 0).  For well-known attributes
-/// and for optional non-transitive attributes, the Partial bit
-/// MUST be set to 0.
-pub const FLAG_PARTIAL:    u8 = 0b00100000;
-/// Defines whether the Attribute Length is one octet (if set to 0) or two octets (if set to 1).
-pub const FLAG_EXT_LEN:    u8 = 0b00010000;
+pub use bgp::attr_flags::*;
 
 #[derive(Debug)]
 pub enum PathAttr<'a> {
@@ -38,77 +28,305 @@ pub enum PathAttr<'a> {
     Aigp(Aigp<'a>),
     PeDistinguisherLabels(PeDistinguisherLabels<'a>),
     BgpLs(BgpLs<'a>),
+    LargeCommunities(LargeCommunities<'a>),
     AttrSet(AttrSet<'a>),
     Other(Other<'a>),
 }
 
 impl<'a> PathAttr<'a> {
 
-    #[cfg_attr(feature="clippy", allow(match_same_arms))]
+    /// Decodes a complete attribute. The NLRI of MP_REACH_NLRI and
+    /// MP_UNREACH_NLRI are read without path identifiers and with one
+    /// label, see `PathAttrIter::with_add_paths` and `with_max_labels`.
     pub fn from_bytes(bytes: &'a [u8], four_byte_asn: bool) -> Result<PathAttr<'a>> {
-        if bytes.len() < 3 { return Err(BgpError::BadLength);}
+        PathAttr::from_raw(RawAttr::split(bytes)?, four_byte_asn, false, 1)
+    }
 
-        let attr_flags = bytes[0];
-        let attr_type  = bytes[1];
-        let is_extended = attr_flags & FLAG_EXT_LEN > 0;
+    /// Decodes an attribute whose header has already been read.
+    pub(crate) fn from_raw(raw: RawAttr<'a>, four_byte_asn: bool, add_paths: bool,
+                           max_labels: u8) -> Result<PathAttr<'a>> {
+        match raw.code {
+             0 => Err(BgpError::Invalid),
+             1 => Origin::from_raw(raw).map(PathAttr::Origin),
+             2 if four_byte_asn => As4Path::from_raw(raw).map(PathAttr::As4Path),
+             2 => AsPath::from_raw(raw).map(PathAttr::AsPath),
+             3 => NextHop::from_raw(raw).map(PathAttr::NextHop),
+             4 => MultiExitDisc::from_raw(raw).map(PathAttr::MultiExitDisc),
+             5 => LocalPreference::from_raw(raw).map(PathAttr::LocalPreference),
+             6 => AtomicAggregate::from_raw(raw).map(PathAttr::AtomicAggregate),
+             7 if four_byte_asn => As4Aggregator::from_raw(raw).map(PathAttr::As4Aggregator),
+             7 => Aggregator::from_raw(raw).map(PathAttr::Aggregator),
+             8 => Communities::from_raw(raw).map(PathAttr::Communities),
+             9 => OriginatorId::from_raw(raw).map(PathAttr::OriginatorId),
+            10 => ClusterList::from_raw(raw).map(PathAttr::ClusterList),
+            14 => MpReachNlri::from_raw(raw, add_paths, max_labels).map(PathAttr::MpReachNlri),
+            15 => MpUnreachNlri::from_raw(raw, add_paths).map(PathAttr::MpUnreachNlri),
+            16 => ExtendedCommunities::from_raw(raw).map(PathAttr::ExtendedCommunities),
+            17 => As4Path::from_raw(raw).map(PathAttr::As4Path),
+            18 => As4Aggregator::from_raw(raw).map(PathAttr::As4Aggregator),
+            22 => PmsiTunnel::from_raw(raw).map(PathAttr::PmsiTunnel),
+            23 => TunnelEncapAttr::from_raw(raw).map(PathAttr::TunnelEncapAttr),
+            24 => TrafficEngineering::from_raw(raw).map(PathAttr::TrafficEngineering),
+            25 => Ipv6AddrSpecificExtCommunity::from_raw(raw).map(PathAttr::Ipv6AddrSpecificExtCommunity),
+            26 => Aigp::from_raw(raw).map(PathAttr::Aigp),
+            27 => PeDistinguisherLabels::from_raw(raw).map(PathAttr::PeDistinguisherLabels),
+            29 => BgpLs::from_raw(raw).map(PathAttr::BgpLs),
+            32 => LargeCommunities::from_raw(raw).map(PathAttr::LargeCommunities),
+            128 => AttrSet::from_raw(raw).map(PathAttr::AttrSet),
+            _ => Other::from_raw(raw).map(PathAttr::Other),
+        }
+    }
 
-        if is_extended && bytes.len() < 4 { return Err(BgpError::BadLength);}
+    /// Like `from_bytes`, but for an attribute stored in an MRT
+    /// TABLE_DUMP_V2 RIB entry, where MP_REACH_NLRI is abbreviated to
+    /// just the next hop (RFC 6396, section 4.3.4). See
+    /// `MpReachNlri::from_rib_value`.
+    pub fn from_rib_bytes(bytes: &'a [u8], four_byte_asn: bool) -> Result<PathAttr<'a>> {
+        PathAttr::from_rib_raw(RawAttr::split(bytes)?, four_byte_asn, false, 1)
+    }
 
-        let attr_len = if is_extended {
-            (bytes[2] as u16) << 8
-                | bytes[3] as u16
-        } else {
-            bytes[2] as u16
-        };
+    fn from_rib_raw(raw: RawAttr<'a>, four_byte_asn: bool, add_paths: bool,
+                    max_labels: u8) -> Result<PathAttr<'a>> {
+        match raw.code {
+            14 => MpReachNlri::from_rib_raw(raw).map(PathAttr::MpReachNlri),
+            _ => PathAttr::from_raw(raw, four_byte_asn, add_paths, max_labels),
+        }
+    }
+}
+
+/// How this crate classifies a path attribute type code.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum AttrKind {
+    /// Decoded into its own `PathAttr` variant.
+    Known(u8),
+    /// Listed as deprecated by IANA, decoded as `PathAttr::Other`.
+    Deprecated(u8),
+    /// Neither known nor deprecated, decoded as `PathAttr::Other`.
+    Unknown(u8),
+}
+
+impl From<u8> for AttrKind {
+    fn from(code: u8) -> AttrKind {
+        match code {
+            1..=10 | 14..=18 | 22..=27 | 29 | 32 | 128 => AttrKind::Known(code),
+            11..=13 | 19..=21 | 28 | 30 | 31 => AttrKind::Deprecated(code),
+            _ => AttrKind::Unknown(code),
+        }
+    }
+}
+
+impl AttrKind {
+    pub fn code(&self) -> u8 {
+        match *self {
+            AttrKind::Known(code) | AttrKind::Deprecated(code) | AttrKind::Unknown(code) => code,
+        }
+    }
+
+    /// The name IANA lists for a deprecated attribute code.
+    pub fn deprecated_name(&self) -> Option<&'static str> {
+        match *self {
+            AttrKind::Deprecated(11) => Some("DPA"),
+            AttrKind::Deprecated(12) => Some("ADVERTISER"),
+            AttrKind::Deprecated(13) => Some("RCID_PATH / CLUSTER_ID"),
+            AttrKind::Deprecated(19) => Some("SAFI Specific Attribute (SSA)"),
+            AttrKind::Deprecated(20) => Some("Connector Attribute"),
+            AttrKind::Deprecated(21) => Some("AS_PATHLIMIT"),
+            AttrKind::Deprecated(28) => Some("BGP Entropy Label Capability Attribute"),
+            AttrKind::Deprecated(_) => Some("deprecated"),
+            _ => None,
+        }
+    }
+
+    /// The name IANA lists for the attribute code.
+    #[cfg(feature = "names")]
+    pub fn name(&self) -> &'static str {
+        if let Some(name) = self.deprecated_name() {
+            return name;
+        }
+        match self.code() {
+            1 => "ORIGIN",
+            2 => "AS_PATH",
+            3 => "NEXT_HOP",
+            4 => "MULTI_EXIT_DISC",
+            5 => "LOCAL_PREF",
+            6 => "ATOMIC_AGGREGATE",
+            7 => "AGGREGATOR",
+            8 => "COMMUNITY",
+            9 => "ORIGINATOR_ID",
+            10 => "CLUSTER_LIST",
+            14 => "MP_REACH_NLRI",
+            15 => "MP_UNREACH_NLRI",
+            16 => "EXTENDED COMMUNITIES",
+            17 => "AS4_PATH",
+            18 => "AS4_AGGREGATOR",
+            22 => "PMSI_TUNNEL",
+            23 => "Tunnel Encapsulation",
+            24 => "Traffic Engineering",
+            25 => "IPv6 Address Specific Extended Community",
+            26 => "AIGP",
+            27 => "PE Distinguisher Labels",
+            29 => "BGP-LS Attribute",
+            32 => "LARGE_COMMUNITY",
+            33 => "BGPsec_Path",
+            34 => "BGP Community Container Attribute",
+            35 => "Only to Customer (OTC)",
+            36 => "BGP Domain Path (D-PATH)",
+            37 => "SFP attribute",
+            38 => "BFD Discriminator",
+            39 => "BGP Next Hop Dependent Characteristics (NHC)",
+            40 => "BGP Prefix-SID",
+            128 => "ATTR_SET",
+            0 | 255 => "Reserved",
+            _ => "Unassigned",
+        }
+    }
+}
+
+/// A set of path attribute type codes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct AttrBitmap([u64; 4]);
+
+impl AttrBitmap {
+    pub fn new() -> AttrBitmap {
+        AttrBitmap::default()
+    }
+
+    pub fn insert(&mut self, code: u8) {
+        self.0[code as usize / 64] |= 1 << (code % 64);
+    }
+
+    pub fn contains(&self, code: u8) -> bool {
+        self.0[code as usize / 64] & 1 << (code % 64) > 0
+    }
+
+    /// Whether any code is in both sets.
+    pub fn intersects(&self, other: &AttrBitmap) -> bool {
+        self.0.iter().zip(other.0.iter()).any(|(a, b)| a & b > 0)
+    }
+
+    /// The number of codes in the set.
+    pub fn len(&self) -> usize {
+        self.0.iter().map(|word| word.count_ones() as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == [0; 4]
+    }
+}
+
+/// Forwards to the decoded attribute.
+impl<'a> Attr<'a> for PathAttr<'a> {
+    fn flags(&self) -> u8 {
+        match *self {
+            PathAttr::MpReachNlri(ref attr) => attr.flags(),
+            PathAttr::MpUnreachNlri(ref attr) => attr.flags(),
+            _ => self.as_attr().map_or(0, |attr| attr.flags()),
+        }
+    }
+
+    fn code(&self) -> u8 {
+        match *self {
+            PathAttr::MpReachNlri(_) => 14,
+            PathAttr::MpUnreachNlri(_) => 15,
+            _ => self.as_attr().map_or(0, |attr| attr.code()),
+        }
+    }
+
+    fn value(&self) -> &'a [u8] {
+        match *self {
+            PathAttr::MpReachNlri(ref attr) => attr.value(),
+            PathAttr::MpUnreachNlri(ref attr) => attr.value(),
+            _ => self.as_attr().map_or(&[], |attr| attr.value()),
+        }
+    }
+}
+
+impl<'a> PathAttr<'a> {
+    fn as_attr(&self) -> Option<&dyn Attr<'a>> {
+        Some(match *self {
+            PathAttr::Origin(ref attr) => attr,
+            PathAttr::AsPath(ref attr) => attr,
+            PathAttr::NextHop(ref attr) => attr,
+            PathAttr::MultiExitDisc(ref attr) => attr,
+            PathAttr::LocalPreference(ref attr) => attr,
+            PathAttr::AtomicAggregate(ref attr) => attr,
+            PathAttr::Aggregator(ref attr) => attr,
+            PathAttr::Communities(ref attr) => attr,
+            PathAttr::OriginatorId(ref attr) => attr,
+            PathAttr::ClusterList(ref attr) => attr,
+            PathAttr::ExtendedCommunities(ref attr) => attr,
+            PathAttr::As4Path(ref attr) => attr,
+            PathAttr::As4Aggregator(ref attr) => attr,
+            PathAttr::PmsiTunnel(ref attr) => attr,
+            PathAttr::TunnelEncapAttr(ref attr) => attr,
+            PathAttr::TrafficEngineering(ref attr) => attr,
+            PathAttr::Ipv6AddrSpecificExtCommunity(ref attr) => attr,
+            PathAttr::Aigp(ref attr) => attr,
+            PathAttr::PeDistinguisherLabels(ref attr) => attr,
+            PathAttr::BgpLs(ref attr) => attr,
+            PathAttr::LargeCommunities(ref attr) => attr,
+            PathAttr::AttrSet(ref attr) => attr,
+            PathAttr::Other(ref attr) => attr,
+            PathAttr::MpReachNlri(_) | PathAttr::MpUnreachNlri(_) => return None,
+        })
+    }
+}
 
-        match (attr_type, attr_len) {
-            ( 0, _) => Err(BgpError::Invalid),
-            ( 1, 1) => Ok(PathAttr::Origin(Origin{inner: bytes})),
-            ( 1, _) => Err(BgpError::Invalid),
-            ( 2, _) if four_byte_asn => Ok(PathAttr::As4Path(As4Path{inner: bytes})),
-            ( 2, _) => Ok(PathAttr::AsPath(AsPath{inner: bytes})),
-            ( 3, _) => Ok(PathAttr::NextHop(NextHop{inner: bytes})),
-            ( 4, 4) => Ok(PathAttr::MultiExitDisc(MultiExitDisc{inner: bytes})),
-            ( 4, _) => Err(BgpError::Invalid),
-            ( 5, 4) => Ok(PathAttr::LocalPreference(LocalPreference{inner: bytes})),
-            ( 5, _) => Err(BgpError::Invalid),
-            ( 6, 0) => Ok(PathAttr::AtomicAggregate(AtomicAggregate{inner: bytes})),
-            ( 6, _) => Err(BgpError::Invalid),
-            ( 7, 8) if four_byte_asn => Ok(PathAttr::As4Aggregator(As4Aggregator{inner: bytes})),
-            ( 7, 6) if !four_byte_asn => Ok(PathAttr::Aggregator(Aggregator{inner: bytes})),
-            ( 7, _) => Err(BgpError::Invalid),
-            ( 8, _) => Ok(PathAttr::Communities(Communities{inner: bytes})),
-            ( 9, 4) => Ok(PathAttr::OriginatorId(OriginatorId{inner: bytes})),
-            ( 9, _) => Err(BgpError::Invalid),
-            (10, _) => Ok(PathAttr::ClusterList(ClusterList{inner: bytes})),
-            (14, _) => Ok(PathAttr::MpReachNlri(try!(MpReachNlri::from_bytes(bytes)))),
-            (15, _) => Ok(PathAttr::MpUnreachNlri(try!(MpUnreachNlri::from_bytes(bytes)))),
-            (16, _) => Ok(PathAttr::ExtendedCommunities(ExtendedCommunities{inner: bytes})),
-            (17, _) => Ok(PathAttr::As4Path(As4Path{inner: bytes})),
-            (18, _) => Ok(PathAttr::As4Aggregator(As4Aggregator{inner: bytes})),
-            (22, _) => Ok(PathAttr::PmsiTunnel(PmsiTunnel{inner: bytes})),
-            (23, _) => Ok(PathAttr::TunnelEncapAttr(TunnelEncapAttr{inner: bytes})),
-            (24, _) => Ok(PathAttr::TrafficEngineering(TrafficEngineering{inner: bytes})),
-            (25, _) => Ok(PathAttr::Ipv6AddrSpecificExtCommunity(Ipv6AddrSpecificExtCommunity{inner: bytes})),
-            (26, _) => Ok(PathAttr::Aigp(Aigp{inner: bytes})),
-            (27, _) => Ok(PathAttr::PeDistinguisherLabels(PeDistinguisherLabels{inner: bytes})),
-            (29, _) => Ok(PathAttr::BgpLs(BgpLs{inner: bytes})),
-            (128,_) => Ok(PathAttr::AttrSet(AttrSet{inner: bytes})),
-            _ => Ok(PathAttr::Other(Other{inner: bytes})),
+impl<'a> PathAttr<'a> {
+    /// Decodes everything in the attribute that is otherwise decoded on
+    /// access, returning the first error. See `Message::deep_validate`.
+    pub fn deep_validate(&self) -> Result<()> {
+        match *self {
+            PathAttr::Origin(ref origin) => origin.try_origin().map(|_| ()),
+            PathAttr::AsPath(ref path) => validate_segments(path.segments()),
+            PathAttr::As4Path(ref path) => validate_segments(path.segments()),
+            PathAttr::Communities(ref communities) => communities.communities().map(|_| ()),
+            PathAttr::ClusterList(ref list) => validate_all(list.ids()),
+            PathAttr::ExtendedCommunities(ref communities) => communities.communities().map(|_| ()),
+            PathAttr::Ipv6AddrSpecificExtCommunity(ref communities) => communities.communities().map(|_| ()),
+            PathAttr::LargeCommunities(ref communities) => communities.communities().map(|_| ()),
+            PathAttr::MpReachNlri(ref reach) => reach.nlris().map_or(Ok(()), validate_all),
+            PathAttr::MpUnreachNlri(ref unreach) => unreach.nlris().map_or(Ok(()), validate_all),
+            _ => Ok(()),
         }
     }
+}
 
+fn validate_segments(segments: AsPathIter) -> Result<()> {
+    for segment in segments {
+        match segment? {
+            AsPathSegment::AsSequence(seq) => seq.aut_nums().map(|_| ())?,
+            AsPathSegment::AsSet(set) => set.aut_nums().map(|_| ())?,
+        }
+    }
+    Ok(())
 }
 
 #[derive(Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
 pub struct PathAttrIter<'a> {
     inner: &'a [u8],
     error: bool,
     four_byte_asn: bool,
+    add_paths: bool,
+    max_labels: u8,
+    rib_entry: bool,
+    detect_as_width: bool,
 }
 
+/// Summarizes the remaining attribute bytes. Use `verbose()` to decode
+/// the attributes.
 impl<'a> fmt::Debug for PathAttrIter<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("PathAttrIter")
+            .field("len", &self.inner.len())
+            .field("four_byte_asn", &self.four_byte_asn)
+            .finish()
+    }
+}
+
+impl<'a> FmtVerbose for PathAttrIter<'a> {
+    fn fmt_verbose(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         fmt.debug_list().entries(self.clone()).finish()
     }
 }
@@ -117,61 +335,177 @@ impl<'a> PathAttrIter<'a> {
 
     pub fn new(inner: &'a [u8], four_byte_asn: bool) -> PathAttrIter<'a> {
         PathAttrIter {
-            inner: inner,
+            inner,
+            error: false,
+            four_byte_asn,
+            add_paths: false,
+            max_labels: 1,
+            rib_entry: false,
+            detect_as_width: false,
+        }
+    }
+
+    /// Iterate over the attribute section of an MRT TABLE_DUMP_V2 RIB
+    /// entry (RFC 6396, section 4.3.4). This is a bare attribute list
+    /// without UPDATE framing, in which MP_REACH_NLRI only holds the next
+    /// hop; such attributes are returned as `MpReachNlri::NextHopOnly`,
+    /// see `MpReachNlri::from_rib_value`. Dumps that store the full
+    /// MP_REACH_NLRI instead are read with `PathAttrIter::new`.
+    ///
+    /// TABLE_DUMP_V2 always encodes AS numbers as four octets, so
+    /// `four_byte_asn` should be `true` unless the dump says otherwise.
+    pub fn from_rib_entry(inner: &'a [u8], four_byte_asn: bool) -> PathAttrIter<'a> {
+        PathAttrIter {
+            inner,
             error: false,
-            four_byte_asn: four_byte_asn,
+            four_byte_asn,
+            add_paths: false,
+            max_labels: 1,
+            rib_entry: true,
+            detect_as_width: false,
         }
     }
+
+    /// Whether AS numbers are read as four octets, unless
+    /// `detect_as_width` overrides it for an attribute.
+    pub fn four_byte_asn(&self) -> bool {
+        self.four_byte_asn
+    }
+
+    /// Read the NLRI of MP_REACH_NLRI and MP_UNREACH_NLRI with a path
+    /// identifier, for sessions that negotiated ADD-PATH receive.
+    pub fn with_add_paths(mut self, add_paths: bool) -> PathAttrIter<'a> {
+        self.add_paths = add_paths;
+        self
+    }
+
+    /// Whether MP_REACH_NLRI and MP_UNREACH_NLRI NLRI are read with a
+    /// path identifier.
+    pub fn add_paths(&self) -> bool {
+        self.add_paths
+    }
+
+    /// Read labeled and VPN NLRI in MP_REACH_NLRI with up to `max_labels`
+    /// labels, as negotiated with the Multiple Labels capability.
+    pub fn with_max_labels(mut self, max_labels: u8) -> PathAttrIter<'a> {
+        self.max_labels = max_labels;
+        self
+    }
+
+    /// The most labels a labeled or VPN NLRI in MP_REACH_NLRI is read
+    /// with.
+    pub fn max_labels(&self) -> u8 {
+        self.max_labels
+    }
+
+    /// Iterate over each attribute together with its bytes, header
+    /// included, for tooling that wants to recover what it can from a
+    /// malformed attribute list. See `LossyPathAttrIter`.
+    pub fn lossy(self) -> LossyPathAttrIter<'a> {
+        LossyPathAttrIter { attrs: self }
+    }
+
+    /// Iterate over just the optional transitive attributes this crate
+    /// does not decode, keeping their exact bytes so that they can be
+    /// propagated verbatim, see `UnknownTransitive::write_forward`.
+    pub fn unknown_transitive(self) -> UnknownTransitiveIter<'a> {
+        UnknownTransitiveIter { attrs: self }
+    }
+
+    /// Decode AS_PATH and AGGREGATOR with the AS number width their
+    /// content is consistent with, using the configured width only when
+    /// both or neither fit. This recovers paths from BMP speakers that set
+    /// the A flag inconsistently with the UPDATE they carry.
+    pub fn detect_as_width(mut self) -> PathAttrIter<'a> {
+        self.detect_as_width = true;
+        self
+    }
 }
 
 impl<'a> Iterator for PathAttrIter<'a> {
     type Item = Result<PathAttr<'a>>;
 
     fn next(&mut self) -> Option<Result<PathAttr<'a>>> {
-        if self.error || self.inner.is_empty() {
-            return None;
+        match self.next_raw()? {
+            Ok(raw) => Some(self.decode(raw)),
+            Err(err) => Some(Err(err)),
         }
+    }
+}
 
-        if self.inner.len() < 2 {
-            self.error = true;
-            return Some(Err(BgpError::BadLength));
-        }
-
-        let attr_flags = self.inner[0];
-        let is_extended = attr_flags & FLAG_EXT_LEN > 0;
-
-        let attr_value_offset = if is_extended { 4 } else { 3 };
+impl<'a> PathAttrIter<'a> {
 
-        let attr_len = if is_extended {
-            (self.inner[2] as usize) << 8 | self.inner[3] as usize
+    fn decode(&self, raw: RawAttr<'a>) -> Result<PathAttr<'a>> {
+        let four_byte_asn = if self.detect_as_width {
+            detect_four_byte_asn(raw, self.four_byte_asn)
         } else {
-            self.inner[2] as usize
+            self.four_byte_asn
         };
 
-        if self.inner.len() < attr_value_offset + attr_len{
-            self.error = true;
-            return Some(Err(BgpError::BadLength));
+        if self.rib_entry {
+            PathAttr::from_rib_raw(raw, four_byte_asn, self.add_paths, self.max_labels)
+        } else {
+            PathAttr::from_raw(raw, four_byte_asn, self.add_paths, self.max_labels)
         }
+    }
 
-        let next_offset = attr_value_offset + attr_len;
-        let slice = &self.inner[..next_offset];
-        self.inner = &self.inner[next_offset..];
+    /// Returns the next attribute split into its header fields and value,
+    /// without decoding it.
+    pub(crate) fn next_raw(&mut self) -> Option<Result<RawAttr<'a>>> {
+        if self.error || self.inner.is_empty() {
+            return None;
+        }
 
-        Some(PathAttr::from_bytes(slice, self.four_byte_asn))
+        match RawAttr::read(self.inner) {
+            Ok(raw) => {
+                self.inner = &self.inner[raw.bytes.len()..];
+                Some(Ok(raw))
+            }
+            Err(err) => {
+                self.error = true;
+                Some(Err(err))
+            }
+        }
     }
 }
 
+/// A path attribute split into its header fields and value, read once so
+/// that decoding it does not read the header again.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RawAttr<'a> {
+    /// The whole attribute, header included.
+    pub bytes: &'a [u8],
+    pub flags: u8,
+    pub code: u8,
+    pub value: &'a [u8],
+}
 
-pub trait Attr<'a> {
-
-    fn flags(&self) -> u8;
-    fn code(&self) -> u8;
-    fn value(&self) -> &'a [u8];
+impl<'a> RawAttr<'a> {
+    /// Reads the path attribute at the start of `bytes`.
+    fn read(bytes: &'a [u8]) -> Result<RawAttr<'a>> {
+        let flags = bytes.at(0)?;
+        let (value_offset, attr_len) = if flags & FLAG_EXT_LEN > 0 {
+            (4, bytes.u16_at(2)? as usize)
+        } else {
+            (3, bytes.at(2)? as usize)
+        };
+        Ok(RawAttr {
+            bytes: bytes.take(value_offset + attr_len)?,
+            flags,
+            code: bytes[1],
+            value: bytes.slice(value_offset, attr_len)?,
+        })
+    }
 
-    fn is_optional(&self) ->   bool { self.flags() & FLAG_OPTIONAL > 0 }
-    fn is_partial(&self) ->    bool { self.flags() & FLAG_PARTIAL > 0 }
-    fn is_transitive(&self) -> bool { self.flags() & FLAG_TRANSITIVE > 0 }
-    fn is_ext_len(&self) ->    bool { self.flags() & FLAG_EXT_LEN > 0 }
+    /// Reads a path attribute, checking that the Attribute Length covers
+    /// exactly the rest of `bytes`.
+    pub(crate) fn split(bytes: &'a [u8]) -> Result<RawAttr<'a>> {
+        let raw = RawAttr::read(bytes)?;
+        if raw.bytes.len() != bytes.len() {
+            return Err(BgpError::BadLength);
+        }
+        Ok(raw)
+    }
 }
 
 
@@ -180,34 +514,134 @@ macro_rules! define_path_attr {
     ($name:ident, $( $m:meta ),*) => {
         $( #[$m] )*
         pub struct $name<'a> {
-            inner: &'a [u8],
+            flags: u8,
+            code: u8,
+            value: &'a [u8],
         }
 
         impl<'a> Attr<'a> for $name<'a> {
+            #[inline]
             fn flags(&self) -> u8 {
-                self.inner[0]
+                self.flags
             }
 
+            #[inline]
             fn code(&self) -> u8 {
-                self.inner[0]
+                self.code
             }
 
+            #[inline]
             fn value(&self) -> &'a [u8] {
-                if self.is_ext_len() {
-                    &self.inner[4..]
-                } else {
-                    &self.inner[3..]
+                self.value
+            }
+        }
+    }
+}
+
+/// Implements `TryFrom<&[u8]>` and `from_attr_bytes` for an attribute
+/// wrapper, accepting the given type codes and value lengths.
+macro_rules! impl_try_from_attr {
+    ($name:ident, $( $code:pat )|+, $len:pat) => {
+        impl<'a> TryFrom<&'a [u8]> for $name<'a> {
+            type Error = BgpError;
+
+            fn try_from(bytes: &'a [u8]) -> Result<$name<'a>> {
+                $name::from_raw(RawAttr::split(bytes)?)
+            }
+        }
+
+        impl<'a> $name<'a> {
+            #[allow(unreachable_patterns)]
+            pub(crate) fn from_raw(raw: RawAttr<'a>) -> Result<$name<'a>> {
+                match (raw.code, raw.value.len()) {
+                    ($( $code )|+, $len) => Ok($name{flags: raw.flags, code: raw.code, value: raw.value}),
+                    _ => Err(BgpError::Invalid),
+                }
+            }
+
+            /// Parses a complete attribute (flags, type code, length and
+            /// value), such as one entry of an MRT attribute section.
+            pub fn from_attr_bytes(bytes: &'a [u8]) -> Result<$name<'a>> {
+                $name::try_from(bytes)
+            }
+        }
+    }
+}
+
+/// Implements `from_value` for an attribute wrapper, which takes a bare
+/// attribute value and gives it the type code and flags the attribute is
+/// normally sent with.
+macro_rules! impl_from_value {
+    ($name:ident, $code:expr, $flags:expr, $len:pat) => {
+        impl<'a> $name<'a> {
+            #[allow(unreachable_patterns)]
+            pub fn from_value(value: &'a [u8]) -> Result<$name<'a>> {
+                let ext_len = if value.len() > 0xff { FLAG_EXT_LEN } else { 0 };
+                match value.len() {
+                    0x10000.. => Err(BgpError::BadLength),
+                    $len => Ok($name{flags: $flags | ext_len, code: $code, value}),
+                    _ => Err(BgpError::Invalid),
                 }
             }
         }
     }
 }
 
+impl_try_from_attr!(Origin, 1, 1);
+impl_try_from_attr!(AsPath, 2, _);
+impl_try_from_attr!(NextHop, 3, 4);
+impl_try_from_attr!(MultiExitDisc, 4, 4);
+impl_try_from_attr!(LocalPreference, 5, 4);
+impl_try_from_attr!(AtomicAggregate, 6, 0);
+impl_try_from_attr!(Aggregator, 7, 6);
+impl_try_from_attr!(Communities, 8, _);
+impl_try_from_attr!(OriginatorId, 9, 4);
+impl_try_from_attr!(ClusterList, 10, _);
+impl_try_from_attr!(ExtendedCommunities, 16, _);
+impl_try_from_attr!(As4Path, 2 | 17, _);
+impl_try_from_attr!(As4Aggregator, 7 | 18, 8);
+impl_try_from_attr!(PmsiTunnel, 22, _);
+impl_try_from_attr!(TunnelEncapAttr, 23, _);
+impl_try_from_attr!(TrafficEngineering, 24, 36..);
+impl_try_from_attr!(Ipv6AddrSpecificExtCommunity, 25, _);
+impl_try_from_attr!(Aigp, 26, _);
+impl_try_from_attr!(PeDistinguisherLabels, 27, _);
+impl_try_from_attr!(BgpLs, 29, _);
+impl_try_from_attr!(LargeCommunities, 32, _);
+impl_try_from_attr!(AttrSet, 128, _);
+impl_try_from_attr!(Other, _, _);
+
+const FLAGS_WELL_KNOWN: u8 = FLAG_TRANSITIVE;
+const FLAGS_OPTIONAL_TRANSITIVE: u8 = FLAG_OPTIONAL | FLAG_TRANSITIVE;
+
+impl_from_value!(Origin, 1, FLAGS_WELL_KNOWN, 1);
+impl_from_value!(AsPath, 2, FLAGS_WELL_KNOWN, _);
+impl_from_value!(NextHop, 3, FLAGS_WELL_KNOWN, 4);
+impl_from_value!(MultiExitDisc, 4, FLAG_OPTIONAL, 4);
+impl_from_value!(LocalPreference, 5, FLAGS_WELL_KNOWN, 4);
+impl_from_value!(AtomicAggregate, 6, FLAGS_WELL_KNOWN, 0);
+impl_from_value!(Aggregator, 7, FLAGS_OPTIONAL_TRANSITIVE, 6);
+impl_from_value!(Communities, 8, FLAGS_OPTIONAL_TRANSITIVE, _);
+impl_from_value!(OriginatorId, 9, FLAG_OPTIONAL, 4);
+impl_from_value!(ClusterList, 10, FLAG_OPTIONAL, _);
+impl_from_value!(ExtendedCommunities, 16, FLAGS_OPTIONAL_TRANSITIVE, _);
+impl_from_value!(As4Path, 17, FLAGS_OPTIONAL_TRANSITIVE, _);
+impl_from_value!(As4Aggregator, 18, FLAGS_OPTIONAL_TRANSITIVE, 8);
+impl_from_value!(PmsiTunnel, 22, FLAGS_OPTIONAL_TRANSITIVE, _);
+impl_from_value!(TunnelEncapAttr, 23, FLAGS_OPTIONAL_TRANSITIVE, _);
+impl_from_value!(TrafficEngineering, 24, FLAG_OPTIONAL, 36..);
+impl_from_value!(Ipv6AddrSpecificExtCommunity, 25, FLAGS_OPTIONAL_TRANSITIVE, _);
+impl_from_value!(Aigp, 26, FLAG_OPTIONAL, _);
+impl_from_value!(PeDistinguisherLabels, 27, FLAGS_OPTIONAL_TRANSITIVE, _);
+impl_from_value!(BgpLs, 29, FLAG_OPTIONAL, _);
+impl_from_value!(LargeCommunities, 32, FLAGS_OPTIONAL_TRANSITIVE, _);
+impl_from_value!(AttrSet, 128, FLAGS_OPTIONAL_TRANSITIVE, _);
+
 define_path_attr!(Origin,
                   doc="The ORIGIN attribute is generated by the speaker that originates the associated routing information.
                   ORIGIN is a well-known mandatory attribute.");
 
-#[derive(PartialEq,Debug)]
+#[derive(PartialEq,Debug,Clone,Copy)]
 pub enum OriginType {
     /// Network Layer Reachability Information is interior to the originating AS
     Igp,
@@ -215,17 +649,51 @@ pub enum OriginType {
     Egp,
     /// Network Layer Reachability Information learned by some other means
     Incomplete,
-    Unknown,
+    /// A value not defined by RFC 4271.
+    Unknown(u8),
 }
 
-impl<'a> Origin<'a> {
-
-    pub fn origin(&self) -> OriginType {
-        match self.value()[0] {
+impl From<u8> for OriginType {
+    fn from(value: u8) -> OriginType {
+        match value {
             0 => OriginType::Igp,
             1 => OriginType::Egp,
             2 => OriginType::Incomplete,
-            _ => OriginType::Unknown,
+            n => OriginType::Unknown(n),
+        }
+    }
+}
+
+impl From<OriginType> for u8 {
+    fn from(origin: OriginType) -> u8 {
+        match origin {
+            OriginType::Igp => 0,
+            OriginType::Egp => 1,
+            OriginType::Incomplete => 2,
+            OriginType::Unknown(n) => n,
+        }
+    }
+}
+
+impl<'a> Origin<'a> {
+
+    /// The origin, with undefined values as `Unknown`. An empty value,
+    /// which the constructors reject, reads as `Unknown(255)`.
+    #[inline]
+    pub fn origin(&self) -> OriginType {
+        OriginType::from(self.value().first().cloned().unwrap_or(u8::MAX))
+    }
+
+    /// The origin, failing with `BgpError::Invalid` for values RFC 4271
+    /// does not define, which it treats as an Invalid ORIGIN Attribute
+    /// error.
+    pub fn try_origin(&self) -> Result<OriginType> {
+        match self.value().first() {
+            None => Err(BgpError::BadLength),
+            Some(&value) => match OriginType::from(value) {
+                OriginType::Unknown(_) => Err(BgpError::Invalid),
+                origin => Ok(origin),
+            },
         }
     }
 }
@@ -239,14 +707,14 @@ impl<'a> fmt::Debug for Origin<'a> {
 
 define_path_attr!(AsPath,
                   doc="This attribute identifies the autonomous systems through which routing information
-                   carried in this UPDATE message has passed.
-
+                   carried in this UPDATE message has passed.\n\
+                   \n\
                    The components of this list can be AS_SETs or AS_SEQUENCEs.
                    AS_PATH is a well-known mandatory attribute.");
 
 impl<'a> AsPath<'a> {
 
-    pub fn segments(&self) -> AsPathIter {
+    pub fn segments(&self) -> AsPathIter<'a> {
         AsPathIter{
             inner: self.value(),
             error: false,
@@ -255,9 +723,76 @@ impl<'a> AsPath<'a> {
     }
 }
 
+/// The AS number widths an AS_PATH value can be decoded with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AsPathWidth {
+    TwoByte,
+    FourByte,
+    /// Both widths give well-formed segments, e.g. for an empty path.
+    Either,
+    Neither,
+}
+
+/// Checks that `value` is a sequence of non-empty AS_SET and AS_SEQUENCE
+/// segments, the types `AsPathIter` decodes, whose lengths add up to the
+/// value length.
+fn segments_fit(mut value: &[u8], as_size: usize) -> bool {
+    while !value.is_empty() {
+        let (segment_type, count) = match (value.at(0), value.at(1)) {
+            (Ok(segment_type), Ok(count)) => (segment_type, count),
+            _ => return false,
+        };
+        if !(segment_type == 1 || segment_type == 2) || count == 0 {
+            return false;
+        }
+        value = match value.skip(2 + count as usize * as_size) {
+            Ok(rest) => rest,
+            Err(_) => return false,
+        };
+    }
+    true
+}
+
+fn as_path_width(value: &[u8]) -> AsPathWidth {
+    match (segments_fit(value, 2), segments_fit(value, 4)) {
+        (true, true) => AsPathWidth::Either,
+        (true, false) => AsPathWidth::TwoByte,
+        (false, true) => AsPathWidth::FourByte,
+        (false, false) => AsPathWidth::Neither,
+    }
+}
+
+/// Guesses whether the AS_PATH or AGGREGATOR in `raw` uses four-octet
+/// AS numbers, falling back to `default` when the content does not tell.
+fn detect_four_byte_asn(raw: RawAttr, default: bool) -> bool {
+    match raw.code {
+        2 => match as_path_width(raw.value) {
+            AsPathWidth::TwoByte => false,
+            AsPathWidth::FourByte => true,
+            _ => default,
+        },
+        7 => match raw.value.len() {
+            6 => false,
+            8 => true,
+            _ => default,
+        },
+        _ => default,
+    }
+}
+
+impl<'a> AsPath<'a> {
+
+    /// Checks which AS number widths the segment lengths are consistent
+    /// with. BMP speakers that set the A flag wrongly produce paths that
+    /// only decode with the other width.
+    pub fn validate_width(&self) -> AsPathWidth {
+        as_path_width(self.value())
+    }
+}
+
 impl<'a> fmt::Debug for AsPath<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        self.segments().fmt(fmt)
+        self.segments().fmt_verbose(fmt)
     }
 }
 
@@ -269,22 +804,70 @@ pub enum AsPathSegment<'a> {
 }
 
 #[derive(Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
 pub struct AsPathIter<'a> {
     inner: &'a [u8],
     error: bool,
     four_byte: bool,
 }
 
+/// Summarizes the remaining segment bytes. Use `verbose()` to decode the
+/// segments.
 impl<'a> fmt::Debug for AsPathIter<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        for segment in self.clone() {
+        fmt.debug_struct("AsPathIter")
+            .field("len", &self.inner.len())
+            .field("four_byte", &self.four_byte)
+            .finish()
+    }
+}
+
+fn fmt_segment<I>(fmt: &mut fmt::Formatter, aut_nums: Result<I>, open: &str, close: &str) -> fmt::Result
+    where I: Iterator<Item = AsNumber>
+{
+    let aut_nums = match aut_nums {
+        Ok(aut_nums) => aut_nums,
+        Err(err) => return write!(fmt, "Err({:?})", err),
+    };
+    fmt.write_str(open)?;
+    for (i, asn) in aut_nums.enumerate() {
+        if i > 0 {
+            fmt.write_str(", ")?;
+        }
+        write!(fmt, "{}", asn)?;
+    }
+    fmt.write_str(close)
+}
+
+/// Formats the path as `[[65000, 3356], {10, 20}]`: AS_SEQUENCEs in
+/// brackets, AS_SETs in braces, and errors in place of the segments they
+/// stopped.
+impl<'a> FmtVerbose for AsPathIter<'a> {
+    fn fmt_verbose(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str("[")?;
+        for (i, segment) in self.clone().enumerate() {
+            if i > 0 {
+                fmt.write_str(", ")?;
+            }
             match segment {
-               Ok(AsPathSegment::AsSet(x)) => {&x.aut_nums().fmt(fmt);}
-               Ok(AsPathSegment::AsSequence(x)) => {&x.aut_nums().fmt(fmt);}
-               x => {&x.fmt(fmt);}
-            };
+                Ok(AsPathSegment::AsSequence(seq)) => fmt_segment(fmt, seq.aut_nums(), "[", "]")?,
+                Ok(AsPathSegment::AsSet(set)) => fmt_segment(fmt, set.aut_nums(), "{", "}")?,
+                Err(err) => write!(fmt, "Err({:?})", err)?,
+            }
         }
-        Ok(())
+        fmt.write_str("]")
+    }
+}
+
+impl<'a> AsPathIter<'a> {
+    /// Splits off the next segment: its type and AS numbers.
+    fn split_segment(&mut self) -> Result<(u8, &'a [u8])> {
+        let as_size = if self.four_byte { 4 } else { 2 };
+        let segment_type = self.inner.at(0)?;
+        let segment_len = self.inner.at(1)? as usize * as_size;
+        let slice = self.inner.slice(2, segment_len)?;
+        self.inner = self.inner.skip(2 + segment_len)?;
+        Ok((segment_type, slice))
     }
 }
 
@@ -297,21 +880,16 @@ impl<'a> Iterator for AsPathIter<'a> {
             return None;
         }
 
-        let as_size = if self.four_byte { 4 } else { 2 };
-        let segment_type = self.inner[0];
-        let ret = match segment_type {
-            1 => {
-                let len = self.inner[1] as usize;
-                let slice = &self.inner[2..(len*as_size) + 2];
-                self.inner = &self.inner[(len*as_size) + 2..];
-                Ok(AsPathSegment::AsSet(AsSet{inner: slice, four_byte: self.four_byte}))
-            }
-            2 => {
-                let len = self.inner[1] as usize;
-                let slice = &self.inner[2..(len*as_size) + 2];
-                self.inner = &self.inner[(len*as_size) + 2..];
-                Ok(AsPathSegment::AsSequence(AsSequence{inner: slice, four_byte: self.four_byte}))
+        let (segment_type, slice) = match self.split_segment() {
+            Ok(segment) => segment,
+            Err(err) => {
+                self.error = true;
+                return Some(Err(err));
             }
+        };
+        let ret = match segment_type {
+            1 => Ok(AsPathSegment::AsSet(AsSet{inner: slice, four_byte: self.four_byte})),
+            2 => Ok(AsPathSegment::AsSequence(AsSequence{inner: slice, four_byte: self.four_byte})),
             _ => {
                 self.error = true;
                 Err(BgpError::Invalid)
@@ -334,7 +912,7 @@ macro_rules! impl_as_path_segment {
 
         impl<'a> $coll<'a> {
 
-            pub fn aut_nums(&self) -> Result<$iter> {
+            pub fn aut_nums(&self) -> Result<$iter<'a>> {
                 let as_size = if self.four_byte { 4 } else { 2 };
                 if self.inner.len() % as_size > 0 {
                     return Err(BgpError::BadLength);
@@ -357,27 +935,29 @@ macro_rules! impl_as_path_segment {
         }
 
         impl<'a> Iterator for $iter<'a> {
-            type Item = u32;
+            type Item = AsNumber;
 
-            fn next(&mut self) -> Option<u32> {
+            fn next(&mut self) -> Option<AsNumber> {
                 if self.error || self.inner.is_empty() {
                     return None;
                 }
 
-                let as_size = if self.four_byte { 4 } else { 2 };
-
-                let asn = if self.four_byte {
-                    (self.inner[0] as u32) << 24
-                        | (self.inner[1] as u32) << 16
-                        | (self.inner[2] as u32) << 8
-                        | (self.inner[3] as u32)
+                let (as_size, asn) = if self.four_byte {
+                    (4, self.inner.u32_at(0))
                 } else {
-                    (self.inner[0] as u32) << 8
-                        | (self.inner[1] as u32)
+                    (2, self.inner.u16_at(0).map(u32::from))
                 };
 
-                self.inner = &self.inner[as_size..];
-                Some(asn)
+                match asn {
+                    Ok(asn) => {
+                        self.inner = &self.inner[as_size..];
+                        Some(AsNumber(asn))
+                    }
+                    Err(_) => {
+                        self.error = true;
+                        None
+                    }
+                }
             }
         }
     }
@@ -394,19 +974,23 @@ define_path_attr!(NextHop,
                    destinations listed in the UPDATE message.");
 
 impl<'a> NextHop<'a> {
+    #[inline]
     pub fn ip(&self) -> u32 {
         (self.value()[0] as u32) << 24
             | (self.value()[1] as u32) << 16
             | (self.value()[2] as u32) << 8
             | (self.value()[3] as u32)
     }
+
+    #[inline]
+    pub fn addr(&self) -> IpAddrBytes<'a> {
+        IpAddrBytes::v4(self.value())
+    }
 }
 
 impl<'a> fmt::Debug for NextHop<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        fmt.write_fmt(format_args!("{}.{}.{}.{}",
-                                   self.value()[0], self.value()[1],
-                                   self.value()[2], self.value()[3], ))
+        self.addr().fmt(fmt)
     }
 }
 
@@ -416,6 +1000,7 @@ define_path_attr!(MultiExitDisc,
                    among multiple exit or entry points to the same neighboring AS.");
 
 impl<'a> MultiExitDisc<'a> {
+    #[inline]
     pub fn med(&self) -> u32 {
         (self.value()[0] as u32) << 24
             | (self.value()[1] as u32) << 16
@@ -433,14 +1018,15 @@ impl<'a> fmt::Debug for MultiExitDisc<'a> {
 define_path_attr!(LocalPreference,
                   doc="LOCAL_PREF is a well-known attribute that SHALL be included in all
                    UPDATE messages that a given BGP speaker sends to other internal
-                   peers.
-
+                   peers.\n\
+                   \n\
                    A BGP speaker SHALL calculate the degree of preference for
                    each external route based on the locally-configured policy, and
                    include the degree of preference when advertising a route to its
                    internal peers.  The higher degree of preference MUST be preferred.");
 
 impl<'a> LocalPreference<'a> {
+    #[inline]
     pub fn preference(&self) -> u32 {
         (self.value()[0] as u32) << 24
             | (self.value()[1] as u32) << 16
@@ -457,8 +1043,8 @@ impl<'a> fmt::Debug for LocalPreference<'a> {
 
 define_path_attr!(AtomicAggregate,
                   doc="ATOMIC_AGGREGATE is a well-known discretionary
-                   attribute.
-
+                   attribute.\n\
+                   \n\
                    When a BGP speaker aggregates several routes for the purpose of
                    advertisement to a particular peer, the AS_PATH of the aggregated
                    route normally includes an AS_SET formed from the set of ASes from
@@ -483,34 +1069,40 @@ define_path_attr!(Aggregator,
 impl<'a> Aggregator<'a> {
 
     /// The last AS number that formed the aggregate route
-    pub fn aut_num(&self) -> u32 {
-        (self.value()[0] as u32) << 8
-            | self.value()[1] as u32
+    #[inline]
+    pub fn aut_num(&self) -> AsNumber {
+        AsNumber((self.value()[0] as u32) << 8
+            | self.value()[1] as u32)
     }
 
     /// The IP address of the BGP speaker that formed the aggregate route
     /// (encoded as 4 octets).  This SHOULD be the same address as
     /// the one used for the BGP Identifier of the speaker.
+    #[inline]
     pub fn ident(&self) -> u32 {
         (self.value()[2] as u32) << 24
             | (self.value()[3] as u32) << 16
             | (self.value()[4] as u32) << 8
             |  self.value()[5] as u32
     }
+
+    /// The identifier as an IPv4 address.
+    #[inline]
+    pub fn ident_addr(&self) -> IpAddrBytes<'a> {
+        IpAddrBytes::v4(&self.value()[2..])
+    }
 }
 
 impl<'a> fmt::Debug for Aggregator<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        fmt.write_fmt(format_args!("AS{}, {}.{}.{}.{}", self.aut_num(),
-                                   self.value()[2], self.value()[3],
-                                   self.value()[4], self.value()[5],))
+        write!(fmt, "AS{}, {}", self.aut_num(), self.ident_addr())
     }
 }
 
 define_path_attr!(Communities, doc="BGP Community Attribute.");
 
 impl<'a> Communities<'a> {
-    pub fn communities(&self) -> Result<CommunityIter> {
+    pub fn communities(&self) -> Result<CommunityIter<'a>> {
         let slice = self.value();
         if slice.len() % 4 > 0 {
             Err(BgpError::BadLength)
@@ -522,38 +1114,84 @@ impl<'a> Communities<'a> {
     }
 }
 
+impl<'a> Communities<'a> {
+    pub fn contains(&self, community: Community) -> bool {
+        match self.communities() {
+            Ok(mut iter) => iter.any(|c| c == community),
+            Err(_) => false,
+        }
+    }
+}
+
 impl<'a> fmt::Debug for Communities<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         self.communities().fmt(fmt)
     }
 }
 
-pub struct Community<'a> {
-    inner: &'a [u8],
+/// A COMMUNITIES value, conventionally written as `asn:value`.
+#[derive(PartialEq,Eq,Clone,Copy)]
+pub struct Community(u32);
+
+/// GRACEFUL_SHUTDOWN [RFC8326]
+pub const COMMUNITY_GRACEFUL_SHUTDOWN: Community = Community(0xffff0000);
+/// ACCEPT_OWN [RFC7611]
+pub const COMMUNITY_ACCEPT_OWN: Community = Community(0xffff0001);
+/// BLACKHOLE [RFC7999]
+pub const COMMUNITY_BLACKHOLE: Community = Community(0xffff029a);
+/// NO_EXPORT [RFC1997]
+pub const COMMUNITY_NO_EXPORT: Community = Community(0xffffff01);
+/// NO_ADVERTISE [RFC1997]
+pub const COMMUNITY_NO_ADVERTISE: Community = Community(0xffffff02);
+/// NO_EXPORT_SUBCONFED [RFC1997]
+pub const COMMUNITY_NO_EXPORT_SUBCONFED: Community = Community(0xffffff03);
+/// NOPEER [RFC3765]
+pub const COMMUNITY_NO_PEER: Community = Community(0xffffff04);
+
+impl Community {
+    pub fn new(asn: u16, value: u16) -> Community {
+        Community((asn as u32) << 16 | value as u32)
+    }
+
+    pub fn asn(&self) -> u16 {
+        (self.0 >> 16) as u16
+    }
+
+    pub fn value(&self) -> u16 {
+        self.0 as u16
+    }
+}
+
+impl From<u32> for Community {
+    fn from(other: u32) -> Community {
+        Community(other)
+    }
 }
 
-impl<'a> fmt::Debug for Community<'a> {
+impl From<Community> for u32 {
+    fn from(other: Community) -> u32 {
+        other.0
+    }
+}
+
+impl fmt::Debug for Community {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        let left = (self.inner[0] as u16) << 8
-            | self.inner[1] as u16;
-        let right = (self.inner[2] as u16) << 8
-            | self.inner[3] as u16;
-        fmt.write_fmt(format_args!("{}:{}", left, right))
+        fmt.write_fmt(format_args!("{}:{}", self.asn(), self.value()))
     }
 }
 
 
 #[derive(Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
 pub struct CommunityIter<'a> {
     inner: &'a [u8],
 }
 
 impl<'a> Iterator for CommunityIter<'a> {
-    type Item = Community<'a>;
+    type Item = Community;
 
-    fn next(&mut self) -> Option<Community<'a>> {
-        if self.inner.is_empty() { return None;}
-        let community = Community{inner: &self.inner[..4]};
+    fn next(&mut self) -> Option<Community> {
+        let community = Community(self.inner.u32_at(0).ok()?);
         self.inner = &self.inner[4..];
         Some(community)
     }
@@ -568,6 +1206,7 @@ impl<'a> fmt::Debug for CommunityIter<'a> {
 define_path_attr!(OriginatorId, derive(Debug), doc="BGP Route Reflection");
 
 impl<'a> OriginatorId<'a> {
+    #[inline]
     pub fn ident(&self) -> u32 {
         (self.value()[0] as u32) << 24
             | (self.value()[1] as u32) << 16
@@ -579,14 +1218,15 @@ impl<'a> OriginatorId<'a> {
 define_path_attr!(ClusterList, derive(Debug), doc="BGP Route Reflection");
 
 impl<'a> ClusterList<'a> {
-    pub fn ids(&self) -> ClusterListIter {
+    pub fn ids(&self) -> ClusterListIter<'a> {
         ClusterListIter{
-            inner: &self.value(),
+            inner: self.value(),
             error: false,
         }
     }
 }
 
+#[must_use = "iterators are lazy and do nothing unless consumed"]
 pub struct ClusterListIter<'a> {
     inner: &'a [u8],
     error: bool,
@@ -600,54 +1240,51 @@ impl<'a> Iterator for ClusterListIter<'a> {
             return None;
         }
 
-        if self.inner.len() < 4 {
-            self.error = true;
-            return Some(Err(BgpError::BadLength));
+        match self.inner.u32_at(0) {
+            Ok(id) => {
+                self.inner = &self.inner[4..];
+                Some(Ok(id))
+            }
+            Err(err) => {
+                self.error = true;
+                Some(Err(err))
+            }
         }
-
-        let id
-            = (self.inner[0]  as u32) << 24
-            | (self.inner[1]  as u32) << 16
-            | (self.inner[2]  as u32) << 8
-            | (self.inner[3]  as u32);
-
-        self.inner = &self.inner[4..];
-
-        Some(Ok(id))
     }
 }
 
 mod mp_reach_nlri;
 pub use self::mp_reach_nlri::*;
+mod as_path_builder;
+pub use self::as_path_builder::*;
+mod wide_communities;
+pub use self::wide_communities::*;
 
 
 define_path_attr!(ExtendedCommunities, doc="Extended Communities Attribute");
 
 impl<'a> ExtendedCommunities<'a> {
     pub fn communities(&self) -> Result<ExtendedCommunityIter<'a>> {
-        if self.value().len() % 8 == 0 {
-            Ok(ExtendedCommunityIter {
-                inner: self.value(),
-            })
-        } else {
-            Err(BgpError::BadLength)
-        }
+        ExtendedCommunityIter::new(self.value())
     }
 }
 
 impl<'a> fmt::Debug for ExtendedCommunities<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        match self.clone().communities() {
-            Ok(iter) => fmt.debug_list().entries(iter).finish(),
-            Err(err) => err.fmt(fmt)
-        }
+        self.communities().fmt(fmt)
     }
 }
 
+/// Set in the high-order type octet when the community is non-transitive
+/// across ASes.
+pub const EXT_COMM_FLAG_NON_TRANSITIVE: u8 = 0b01000000;
+
 pub trait ExtendedComm<'a> {
     fn type_high(&self) -> u8;
     fn type_low(&self) -> u8;
     fn value(&self) -> &'a [u8];
+
+    fn is_transitive(&self) -> bool { self.type_high() & EXT_COMM_FLAG_NON_TRANSITIVE == 0 }
 }
 
 macro_rules! define_ext_comm {
@@ -686,6 +1323,11 @@ define_ext_comm!(ExtCommTwoOctetAsSpecific);
 define_ext_comm!(ExtCommIpv4AddrSpecific);
 define_ext_comm!(ExtCommFourOctetAsSpecific);
 define_ext_comm!(ExtCommOpaque);
+define_ext_comm!(ExtCommOriginValidation);
+define_ext_comm!(ExtCommCost);
+define_ext_comm!(ExtCommColor);
+define_ext_comm!(ExtCommEncapsulation);
+define_ext_comm!(ExtCommDefaultGateway);
 define_ext_comm!(ExtCommRouteTarget);
 define_ext_comm!(ExtCommRouteOrigin);
 define_ext_comm!(ExtCommQosMarking);
@@ -695,36 +1337,204 @@ define_ext_comm!(ExtCommFlowSpec);
 define_ext_comm!(ExtCommExperimental);
 define_ext_comm!(ExtCommOther);
 
-#[derive(Debug)]
-pub enum ExtendedCommunity<'a> {
-    TwoOctetAsSpecific(ExtCommTwoOctetAsSpecific<'a>),
-    Ipv4AddrSpecific(ExtCommIpv4AddrSpecific<'a>),
-    FourOctetAsSpecific(ExtCommFourOctetAsSpecific<'a>),
-    Opaque(ExtCommOpaque<'a>),
-    RouteTarget(ExtCommRouteTarget<'a>),
-    RouteOrigin(ExtCommRouteOrigin<'a>),
-    QosMarking(ExtCommQosMarking<'a>),
-    CosCapability(ExtCommCosCapability<'a>),
-    Evpn(ExtCommEvpn<'a>),
-    FlowSpec(ExtCommFlowSpec<'a>),
-    Experimental(ExtCommExperimental<'a>),
-    Other(ExtCommOther<'a>),
-}
+pub use types::ExtCommAdmin;
 
+macro_rules! impl_ext_comm_admin {
+    ($comm_name:ident) => {
+        impl<'a> $comm_name<'a> {
+
+            /// The Global Administrator field, tagged with its encoding.
+            pub fn admin(&self) -> ExtCommAdmin {
+                let value = self.value();
+                match self.type_high() {
+                    0x00 => ExtCommAdmin::As2((value[0] as u16) << 8 | value[1] as u16),
+                    0x01 => ExtCommAdmin::Ipv4((value[0] as u32) << 24
+                                               | (value[1] as u32) << 16
+                                               | (value[2] as u32) << 8
+                                               | (value[3] as u32)),
+                    _ => ExtCommAdmin::As4((value[0] as u32) << 24
+                                           | (value[1] as u32) << 16
+                                           | (value[2] as u32) << 8
+                                           | (value[3] as u32)),
+                }
+            }
 
-pub struct ExtendedCommunityIter<'a> {
-    inner: &'a [u8],
+            /// The Local Administrator field. Four octets wide when the
+            /// Global Administrator is a two-octet AS number, two octets
+            /// otherwise.
+            pub fn assigned(&self) -> u32 {
+                let value = self.value();
+                match self.type_high() {
+                    0x00 => (value[2] as u32) << 24
+                        | (value[3] as u32) << 16
+                        | (value[4] as u32) << 8
+                        | (value[5] as u32),
+                    _ => (value[4] as u32) << 8 | (value[5] as u32),
+                }
+            }
+        }
+    }
 }
 
-impl<'a> Iterator for ExtendedCommunityIter<'a> {
-    type Item = ExtendedCommunity<'a>;
+impl_ext_comm_admin!(ExtCommRouteTarget);
+impl_ext_comm_admin!(ExtCommRouteOrigin);
+
+/// RPKI origin validation state (RFC 8097).
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum ValidationState {
+    Valid,
+    NotFound,
+    Invalid,
+    Unknown(u8),
+}
+
+impl From<u8> for ValidationState {
+    fn from(value: u8) -> ValidationState {
+        match value {
+            0 => ValidationState::Valid,
+            1 => ValidationState::NotFound,
+            2 => ValidationState::Invalid,
+            n => ValidationState::Unknown(n),
+        }
+    }
+}
+
+impl<'a> ExtCommOriginValidation<'a> {
+    /// The validation state, carried in the last octet. The reserved
+    /// octets before it are ignored.
+    pub fn state(&self) -> ValidationState {
+        ValidationState::from(self.value()[5])
+    }
+}
+
+/// Cost community (draft-ietf-idr-custom-decision), non-transitive
+/// opaque subtype 0x01.
+impl<'a> ExtCommCost<'a> {
+    /// The step of best path selection before which the cost is
+    /// considered, e.g. 128 for "absolute value".
+    pub fn point_of_insertion(&self) -> u8 {
+        self.value()[0]
+    }
+
+    pub fn community_id(&self) -> u8 {
+        self.value()[1]
+    }
+
+    pub fn cost(&self) -> u32 {
+        let value = self.value();
+        (value[2] as u32) << 24
+            | (value[3] as u32) << 16
+            | (value[4] as u32) << 8
+            | (value[5] as u32)
+    }
+}
+
+/// Color extended community (RFC 9012), transitive opaque subtype 0x0b.
+impl<'a> ExtCommColor<'a> {
+    pub fn flags(&self) -> u16 {
+        (self.value()[0] as u16) << 8 | self.value()[1] as u16
+    }
+
+    pub fn color(&self) -> u32 {
+        let value = self.value();
+        (value[2] as u32) << 24
+            | (value[3] as u32) << 16
+            | (value[4] as u32) << 8
+            | (value[5] as u32)
+    }
+}
+
+/// Encapsulation extended community (RFC 9012), transitive opaque
+/// subtype 0x0c.
+impl<'a> ExtCommEncapsulation<'a> {
+    /// The tunnel type, from the IANA BGP Tunnel Encapsulation Attribute
+    /// Tunnel Types registry. The four octets before it are reserved.
+    pub fn tunnel_type(&self) -> u16 {
+        (self.value()[4] as u16) << 8 | self.value()[5] as u16
+    }
+}
+
+// Default Gateway (RFC 7432), transitive opaque subtype 0x0d, carries no
+// fields.
+
+/// The I flag of the EVPN Multicast Flags community: IGMP Proxy support.
+pub const EVPN_MULTICAST_IGMP_PROXY: u16 = 0x0001;
+/// The M flag of the EVPN Multicast Flags community: MLD Proxy support.
+pub const EVPN_MULTICAST_MLD_PROXY: u16 = 0x0002;
+
+impl<'a> ExtCommEvpn<'a> {
+    /// The flags of an EVPN Multicast Flags community (subtype 0x09, RFC
+    /// 9251), or `None` for other subtypes.
+    pub fn multicast_flags(&self) -> Option<u16> {
+        match self.type_low() {
+            0x09 => Some((self.value()[0] as u16) << 8 | self.value()[1] as u16),
+            _ => None,
+        }
+    }
+
+    /// The MAC address of an ES-Import Route Target (subtype 0x02, RFC
+    /// 7432) or a Router's MAC (subtype 0x03, RFC 9135) community, or
+    /// `None` for other subtypes.
+    pub fn mac_address(&self) -> Option<MacAddr<'a>> {
+        match self.type_low() {
+            0x02 | 0x03 => MacAddr::from_bytes(self.value()).ok(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ExtendedCommunity<'a> {
+    TwoOctetAsSpecific(ExtCommTwoOctetAsSpecific<'a>),
+    Ipv4AddrSpecific(ExtCommIpv4AddrSpecific<'a>),
+    FourOctetAsSpecific(ExtCommFourOctetAsSpecific<'a>),
+    Opaque(ExtCommOpaque<'a>),
+    OriginValidation(ExtCommOriginValidation<'a>),
+    Cost(ExtCommCost<'a>),
+    Color(ExtCommColor<'a>),
+    Encapsulation(ExtCommEncapsulation<'a>),
+    DefaultGateway(ExtCommDefaultGateway<'a>),
+    RouteTarget(ExtCommRouteTarget<'a>),
+    RouteOrigin(ExtCommRouteOrigin<'a>),
+    QosMarking(ExtCommQosMarking<'a>),
+    CosCapability(ExtCommCosCapability<'a>),
+    Evpn(ExtCommEvpn<'a>),
+    FlowSpec(ExtCommFlowSpec<'a>),
+    Experimental(ExtCommExperimental<'a>),
+    Other(ExtCommOther<'a>),
+}
 
-    fn next(&mut self) -> Option<ExtendedCommunity<'a>> {
-        if self.inner.is_empty() {
-            return None;
-        }
 
-        let slice = &self.inner[..8];
+#[derive(Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct ExtendedCommunityIter<'a> {
+    inner: &'a [u8],
+}
+
+impl<'a> ExtendedCommunityIter<'a> {
+
+    /// Iterate over the value of an EXTENDED_COMMUNITIES attribute, which
+    /// must be a whole number of 8-octet communities.
+    pub fn new(inner: &'a [u8]) -> Result<ExtendedCommunityIter<'a>> {
+        if inner.len() % 8 == 0 {
+            Ok(ExtendedCommunityIter { inner })
+        } else {
+            Err(BgpError::BadLength)
+        }
+    }
+}
+
+impl<'a> fmt::Debug for ExtendedCommunityIter<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a> Iterator for ExtendedCommunityIter<'a> {
+    type Item = ExtendedCommunity<'a>;
+
+    fn next(&mut self) -> Option<ExtendedCommunity<'a>> {
+        let slice = self.inner.take(8).ok()?;
         self.inner = &self.inner[8..];
 
         let extcomm_type = slice[0];
@@ -733,16 +1543,23 @@ impl<'a> Iterator for ExtendedCommunityIter<'a> {
             (0, 2) => ExtendedCommunity::RouteTarget(ExtCommRouteTarget{inner: slice}),
             (0, 3) => ExtendedCommunity::RouteOrigin(ExtCommRouteOrigin{inner: slice}),
             (0, _) => ExtendedCommunity::TwoOctetAsSpecific(ExtCommTwoOctetAsSpecific{inner: slice}),
+            (1, 2) => ExtendedCommunity::RouteTarget(ExtCommRouteTarget{inner: slice}),
+            (1, 3) => ExtendedCommunity::RouteOrigin(ExtCommRouteOrigin{inner: slice}),
             (1, _) => ExtendedCommunity::Ipv4AddrSpecific(ExtCommIpv4AddrSpecific{inner: slice}),
             (2, 2) => ExtendedCommunity::RouteTarget(ExtCommRouteTarget{inner: slice}),
             (2, 3) => ExtendedCommunity::RouteOrigin(ExtCommRouteOrigin{inner: slice}),
             (2, _) => ExtendedCommunity::FourOctetAsSpecific(ExtCommFourOctetAsSpecific{inner: slice}),
+            (3, 0x0b) => ExtendedCommunity::Color(ExtCommColor{inner: slice}),
+            (3, 0x0c) => ExtendedCommunity::Encapsulation(ExtCommEncapsulation{inner: slice}),
+            (3, 0x0d) => ExtendedCommunity::DefaultGateway(ExtCommDefaultGateway{inner: slice}),
             (3, _) => ExtendedCommunity::Opaque(ExtCommOpaque{inner: slice}),
             (4, _) => ExtendedCommunity::QosMarking(ExtCommQosMarking{inner: slice}),
             (5, _) => ExtendedCommunity::CosCapability(ExtCommCosCapability{inner: slice}),
             (6, _) => ExtendedCommunity::Evpn(ExtCommEvpn{inner: slice}),
             (8, _) => ExtendedCommunity::FlowSpec(ExtCommFlowSpec{inner: slice}),
-            (0x80...0x8f, _) => ExtendedCommunity::Experimental(ExtCommExperimental{inner: slice}),
+            (0x43, 0) => ExtendedCommunity::OriginValidation(ExtCommOriginValidation{inner: slice}),
+            (0x43, 1) => ExtendedCommunity::Cost(ExtCommCost{inner: slice}),
+            (0x80..=0x8f, _) => ExtendedCommunity::Experimental(ExtCommExperimental{inner: slice}),
             (_, _) => ExtendedCommunity::Other(ExtCommOther{inner: slice}),
             
         };
@@ -750,11 +1567,272 @@ impl<'a> Iterator for ExtendedCommunityIter<'a> {
     }
 }
 
+define_path_attr!(Ipv6AddrSpecificExtCommunity, doc="IPv6 Address Specific Extended Community Attribute");
+
+impl<'a> Ipv6AddrSpecificExtCommunity<'a> {
+    pub fn communities(&self) -> Result<Ipv6ExtCommunityIter<'a>> {
+        let slice = self.value();
+        if slice.len() % 20 > 0 {
+            Err(BgpError::BadLength)
+        } else {
+            Ok(Ipv6ExtCommunityIter {
+                inner: slice,
+            })
+        }
+    }
+}
+
+impl<'a> fmt::Debug for Ipv6AddrSpecificExtCommunity<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.communities().fmt(fmt)
+    }
+}
+
+/// An IPv6 Address Specific Extended Community: a 16-octet IPv6 Global
+/// Administrator and a 2-octet Local Administrator.
+pub struct Ipv6ExtCommunity<'a> {
+    inner: &'a [u8],
+}
+
+impl<'a> Ipv6ExtCommunity<'a> {
+    pub fn type_high(&self) -> u8 {
+        self.inner[0]
+    }
+
+    pub fn type_low(&self) -> u8 {
+        self.inner[1]
+    }
+
+    pub fn is_transitive(&self) -> bool {
+        self.type_high() & EXT_COMM_FLAG_NON_TRANSITIVE == 0
+    }
+
+    pub fn global_admin(&self) -> [u16; 8] {
+        let mut segments = [0u16; 8];
+        for (i, segment) in segments.iter_mut().enumerate() {
+            *segment = (self.inner[2 + i * 2] as u16) << 8 | self.inner[3 + i * 2] as u16;
+        }
+        segments
+    }
+
+    pub fn local_admin(&self) -> u16 {
+        (self.inner[18] as u16) << 8 | self.inner[19] as u16
+    }
+}
+
+impl<'a> fmt::Debug for Ipv6ExtCommunity<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let global = self.global_admin();
+        fmt.debug_struct("Ipv6ExtCommunity")
+            .field("type", &self.type_high())
+            .field("subtype", &self.type_low())
+            .field("global_admin", &format_args!("{:x}:{:x}:{:x}:{:x}:{:x}:{:x}:{:x}:{:x}",
+                                                  global[0], global[1], global[2], global[3],
+                                                  global[4], global[5], global[6], global[7]))
+            .field("local_admin", &self.local_admin())
+            .finish()
+    }
+}
+
+#[derive(Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct Ipv6ExtCommunityIter<'a> {
+    inner: &'a [u8],
+}
+
+impl<'a> Iterator for Ipv6ExtCommunityIter<'a> {
+    type Item = Ipv6ExtCommunity<'a>;
+
+    fn next(&mut self) -> Option<Ipv6ExtCommunity<'a>> {
+        let community = Ipv6ExtCommunity{inner: self.inner.take(20).ok()?};
+        self.inner = &self.inner[20..];
+        Some(community)
+    }
+}
+
+impl<'a> fmt::Debug for Ipv6ExtCommunityIter<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_list().entries(self.clone()).finish()
+    }
+}
+
+define_path_attr!(LargeCommunities, doc="BGP Large Communities Attribute");
+
+impl<'a> LargeCommunities<'a> {
+    pub fn communities(&self) -> Result<LargeCommunityIter<'a>> {
+        let slice = self.value();
+        if slice.len() % 12 > 0 {
+            Err(BgpError::BadLength)
+        } else {
+            Ok(LargeCommunityIter {
+                inner: slice,
+            })
+        }
+    }
+}
+
+impl<'a> fmt::Debug for LargeCommunities<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.communities().fmt(fmt)
+    }
+}
+
+/// A Large Community: Global Administrator, Local Data Part 1 and Local
+/// Data Part 2, four octets each.
+pub struct LargeCommunity<'a> {
+    inner: &'a [u8],
+}
+
+impl<'a> LargeCommunity<'a> {
+    fn u32_at(&self, offset: usize) -> u32 {
+        (self.inner[offset] as u32) << 24
+            | (self.inner[offset + 1] as u32) << 16
+            | (self.inner[offset + 2] as u32) << 8
+            | (self.inner[offset + 3] as u32)
+    }
+
+    pub fn global_admin(&self) -> u32 {
+        self.u32_at(0)
+    }
+
+    pub fn local_data_1(&self) -> u32 {
+        self.u32_at(4)
+    }
+
+    pub fn local_data_2(&self) -> u32 {
+        self.u32_at(8)
+    }
+}
+
+impl<'a> fmt::Debug for LargeCommunity<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_fmt(format_args!("{}:{}:{}", self.global_admin(),
+                                   self.local_data_1(), self.local_data_2()))
+    }
+}
+
+#[derive(Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct LargeCommunityIter<'a> {
+    inner: &'a [u8],
+}
+
+impl<'a> Iterator for LargeCommunityIter<'a> {
+    type Item = LargeCommunity<'a>;
+
+    fn next(&mut self) -> Option<LargeCommunity<'a>> {
+        let community = LargeCommunity{inner: self.inner.take(12).ok()?};
+        self.inner = &self.inner[12..];
+        Some(community)
+    }
+}
+
+impl<'a> fmt::Debug for LargeCommunityIter<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// Any kind of community carried in an UPDATE.
+#[derive(Debug)]
+pub enum AnyCommunity<'a> {
+    /// From COMMUNITIES
+    Standard(Community),
+    /// From EXTENDED_COMMUNITIES
+    Extended(ExtendedCommunity<'a>),
+    /// From IPv6 Address Specific Extended Community
+    Ipv6Extended(Ipv6ExtCommunity<'a>),
+    /// From LARGE_COMMUNITY
+    Large(LargeCommunity<'a>),
+}
+
+#[derive(Clone)]
+enum CommunitySource<'a> {
+    Empty,
+    Standard(CommunityIter<'a>),
+    Extended(ExtendedCommunityIter<'a>),
+    Ipv6Extended(Ipv6ExtCommunityIter<'a>),
+    Large(LargeCommunityIter<'a>),
+}
+
+/// Iterates over the communities of every community-carrying attribute in
+/// a path attribute list, in attribute order.
+#[derive(Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct AllCommunities<'a> {
+    attrs: PathAttrIter<'a>,
+    current: CommunitySource<'a>,
+    error: bool,
+}
+
+impl<'a> AllCommunities<'a> {
+    pub fn new(attrs: PathAttrIter<'a>) -> AllCommunities<'a> {
+        AllCommunities {
+            attrs,
+            current: CommunitySource::Empty,
+            error: false,
+        }
+    }
+
+    fn next_source(&mut self) -> Option<Result<CommunitySource<'a>>> {
+        loop {
+            let source = match self.attrs.next()? {
+                Ok(PathAttr::Communities(attr)) =>
+                    attr.communities().map(CommunitySource::Standard),
+                Ok(PathAttr::ExtendedCommunities(attr)) =>
+                    attr.communities().map(CommunitySource::Extended),
+                Ok(PathAttr::Ipv6AddrSpecificExtCommunity(attr)) =>
+                    attr.communities().map(CommunitySource::Ipv6Extended),
+                Ok(PathAttr::LargeCommunities(attr)) =>
+                    attr.communities().map(CommunitySource::Large),
+                Ok(_) => continue,
+                Err(err) => Err(err),
+            };
+            return Some(source);
+        }
+    }
+}
+
+impl<'a> Iterator for AllCommunities<'a> {
+    type Item = Result<AnyCommunity<'a>>;
+
+    fn next(&mut self) -> Option<Result<AnyCommunity<'a>>> {
+        if self.error {
+            return None;
+        }
+        loop {
+            let community = match self.current {
+                CommunitySource::Empty => None,
+                CommunitySource::Standard(ref mut iter) => iter.next().map(AnyCommunity::Standard),
+                CommunitySource::Extended(ref mut iter) => iter.next().map(AnyCommunity::Extended),
+                CommunitySource::Ipv6Extended(ref mut iter) => iter.next().map(AnyCommunity::Ipv6Extended),
+                CommunitySource::Large(ref mut iter) => iter.next().map(AnyCommunity::Large),
+            };
+            if let Some(community) = community {
+                return Some(Ok(community));
+            }
+            match self.next_source()? {
+                Ok(source) => self.current = source,
+                Err(err) => {
+                    self.error = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Debug for AllCommunities<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_list().entries(self.clone()).finish()
+    }
+}
+
 define_path_attr!(As4Path, doc="AsPath with four-byte-asns");
 
 impl<'a> As4Path<'a> {
 
-    pub fn segments(&self) -> AsPathIter {
+    pub fn segments(&self) -> AsPathIter<'a> {
         AsPathIter{
             inner: self.value(),
             error: false,
@@ -763,9 +1841,17 @@ impl<'a> As4Path<'a> {
     }
 }
 
+impl<'a> As4Path<'a> {
+
+    /// See `AsPath::validate_width`.
+    pub fn validate_width(&self) -> AsPathWidth {
+        as_path_width(self.value())
+    }
+}
+
 impl<'a> fmt::Debug for As4Path<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        self.segments().fmt(fmt)
+        self.segments().fmt_verbose(fmt)
     }
 }
 
@@ -774,41 +1860,275 @@ define_path_attr!(As4Aggregator, doc="Four-byte ASN version of Aggregator");
 impl<'a> As4Aggregator<'a> {
 
     /// The last AS number that formed the aggregate route
-    pub fn aut_num(&self) -> u32 {
-        (self.value()[0] as u32) << 24
+    #[inline]
+    pub fn aut_num(&self) -> AsNumber {
+        AsNumber((self.value()[0] as u32) << 24
             | (self.value()[1] as u32) << 16
             | (self.value()[2] as u32) << 8
-            | self.value()[3] as u32
+            | self.value()[3] as u32)
     }
 
     /// The IP address of the BGP speaker that formed the aggregate route
     /// (encoded as 4 octets).  This SHOULD be the same address as
     /// the one used for the BGP Identifier of the speaker.
+    #[inline]
     pub fn ident(&self) -> u32 {
         (self.value()[4] as u32) << 24
             | (self.value()[5] as u32) << 16
             | (self.value()[6] as u32) << 8
             | self.value()[7] as u32
     }
+
+    /// The identifier as an IPv4 address.
+    #[inline]
+    pub fn ident_addr(&self) -> IpAddrBytes<'a> {
+        IpAddrBytes::v4(&self.value()[4..])
+    }
 }
 
 impl<'a> fmt::Debug for As4Aggregator<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        fmt.write_fmt(format_args!("AS{}, {}.{}.{}.{}", self.aut_num(),
-                                   self.value()[4], self.value()[5],
-                                   self.value()[6], self.value()[7],))
+        write!(fmt, "AS{}, {}", self.aut_num(), self.ident_addr())
+    }
+}
+
+define_path_attr!(PmsiTunnel, derive(Debug), doc="P-Multicast Service Interface Tunnel Attribute");
+
+impl<'a> PmsiTunnel<'a> {
+    /// The Leaf Information Required flag and reserved bits.
+    pub fn flags(&self) -> Result<u8> {
+        self.value().at(0)
+    }
+
+    /// The tunnel type, e.g. 6 for Ingress Replication (RFC 6514).
+    pub fn tunnel_type(&self) -> Result<u8> {
+        self.value().at(1)
+    }
+
+    /// The MPLS label, or for VXLAN tunnels the VNI in the 24 bits of the
+    /// field (RFC 8365).
+    pub fn label(&self) -> Result<Label> {
+        Label::from_bytes(self.value().skip(2)?)
+    }
+
+    /// The Tunnel Identifier, whose format depends on the tunnel type.
+    pub fn tunnel_identifier(&self) -> Result<&'a [u8]> {
+        self.value().skip(5)
     }
 }
 
-define_path_attr!(PmsiTunnel, derive(Debug), doc="");
-define_path_attr!(TunnelEncapAttr, derive(Debug), doc="");
-define_path_attr!(TrafficEngineering, derive(Debug), doc="");
-define_path_attr!(Ipv6AddrSpecificExtCommunity, derive(Debug), doc="");
+define_path_attr!(TunnelEncapAttr, derive(Debug), doc="Tunnel Encapsulation Attribute");
+
+impl<'a> TunnelEncapAttr<'a> {
+    /// The Tunnel TLVs, typed by tunnel type, with their sub-TLVs
+    /// undecoded (RFC 9012).
+    pub fn tunnels(&self) -> TlvIter<'a> {
+        TlvIter::new(self.value())
+    }
+}
+
+define_path_attr!(TrafficEngineering, doc="Traffic Engineering Attribute (RFC 5543)");
+
+impl<'a> TrafficEngineering<'a> {
+
+    /// Interface Switching Capability, e.g. 1 for PSC-1 (RFC 4203).
+    pub fn switching_capability(&self) -> u8 {
+        self.value()[0]
+    }
+
+    /// LSP Encoding Type, e.g. 1 for packet (RFC 3471).
+    pub fn encoding(&self) -> u8 {
+        self.value()[1]
+    }
+
+    /// Maximum LSP Bandwidth at `priority` 0 to 7, in bytes per second.
+    pub fn max_lsp_bandwidth(&self, priority: usize) -> Option<f32> {
+        if priority > 7 {
+            return None;
+        }
+        self.value().u32_at(4 + priority * 4).ok().map(f32::from_bits)
+    }
+
+    /// The Switching Capability specific information, undecoded.
+    pub fn switching_info(&self) -> &'a [u8] {
+        &self.value()[36..]
+    }
+}
+
+impl<'a> fmt::Debug for TrafficEngineering<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let mut bandwidths = [0.0; 8];
+        for (priority, bandwidth) in bandwidths.iter_mut().enumerate() {
+            *bandwidth = self.max_lsp_bandwidth(priority).unwrap_or(0.0);
+        }
+        fmt.debug_struct("TrafficEngineering")
+            .field("switching_capability", &self.switching_capability())
+            .field("encoding", &self.encoding())
+            .field("max_lsp_bandwidth", &bandwidths)
+            .field("switching_info", &self.switching_info())
+            .finish()
+    }
+}
 define_path_attr!(Aigp, derive(Debug), doc="The Accumulated IGP Metric Attribute");
-define_path_attr!(PeDistinguisherLabels, derive(Debug), doc="");
+
+impl<'a> Aigp<'a> {
+    /// The AIGP TLVs, with 1 octet types (RFC 7311).
+    pub fn tlvs(&self) -> TlvIter<'a> {
+        TlvIter::with_widths(self.value(), 1, 2)
+    }
+}
+
+define_path_attr!(PeDistinguisherLabels, derive(Debug), doc="PE Distinguisher Labels Attribute");
 define_path_attr!(BgpLs, derive(Debug), doc="North-Bound Distribution of Link-State and TE Information");
-define_path_attr!(AttrSet, derive(Debug), doc="");
-define_path_attr!(Other, derive(Debug), doc="");
+
+impl<'a> PeDistinguisherLabels<'a> {
+    /// The PE addresses with their labels. The addresses are IPv6 if
+    /// `ipv6`, which is given by the AFI of the routes the attribute is
+    /// carried with, and IPv4 otherwise (RFC 6514 section 8).
+    pub fn entries(&self, ipv6: bool) -> PeDistinguisherLabelsIter<'a> {
+        PeDistinguisherLabelsIter {
+            inner: self.value(),
+            address_len: if ipv6 { 16 } else { 4 },
+            error: false,
+        }
+    }
+}
+
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct PeDistinguisherLabelsIter<'a> {
+    inner: &'a [u8],
+    address_len: usize,
+    error: bool,
+}
+
+impl<'a> Iterator for PeDistinguisherLabelsIter<'a> {
+    type Item = Result<(IpAddrBytes<'a>, Label)>;
+
+    fn next(&mut self) -> Option<Result<(IpAddrBytes<'a>, Label)>> {
+        if self.error || self.inner.is_empty() {
+            return None;
+        }
+        let entry = match self.inner.take(self.address_len + 3) {
+            Ok(entry) => entry,
+            Err(err) => {
+                self.error = true;
+                return Some(Err(err));
+            }
+        };
+        self.inner = &self.inner[entry.len()..];
+        let (address, label) = entry.split_at(self.address_len);
+        Some(IpAddrBytes::from_bytes(address).and_then(|address| Ok((address, Label::from_bytes(label)?))))
+    }
+}
+
+impl<'a> BgpLs<'a> {
+    /// The node, link and prefix attribute TLVs (RFC 9552).
+    pub fn tlvs(&self) -> TlvIter<'a> {
+        TlvIter::new(self.value())
+    }
+}
+
+define_path_attr!(AttrSet, derive(Debug), doc="ATTR_SET: carries the path attributes of a customer network across a provider network");
+define_path_attr!(Other, derive(Debug), doc="Path attribute of a type not recognized by this crate");
+
+impl<'a> Other<'a> {
+    /// Whether the code is deprecated or simply unknown.
+    pub fn kind(&self) -> AttrKind {
+        AttrKind::from(self.code())
+    }
+}
+
+/// Iterates over `(Result<PathAttr>, bytes)` pairs. An attribute that
+/// fails to decode is skipped using its declared length, and the
+/// iteration continues with the next one. When the header or declared
+/// length itself overruns the attribute list, the error is paired with
+/// all the remaining bytes and the iteration ends.
+/// Created by `PathAttrIter::lossy`.
+#[derive(Debug, Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct LossyPathAttrIter<'a> {
+    attrs: PathAttrIter<'a>,
+}
+
+impl<'a> Iterator for LossyPathAttrIter<'a> {
+    type Item = (Result<PathAttr<'a>>, &'a [u8]);
+
+    fn next(&mut self) -> Option<(Result<PathAttr<'a>>, &'a [u8])> {
+        let rest = self.attrs.inner;
+        match self.attrs.next_raw()? {
+            Ok(raw) => Some((self.attrs.decode(raw), raw.bytes)),
+            Err(err) => Some((Err(err), rest)),
+        }
+    }
+}
+
+/// An optional transitive attribute of a type not recognized by this
+/// crate, with the exact bytes it was received as.
+#[derive(Debug, Clone, Copy)]
+pub struct UnknownTransitive<'a> {
+    /// The whole attribute, header included.
+    pub bytes: &'a [u8],
+    flags: u8,
+    code: u8,
+    value: &'a [u8],
+}
+
+impl<'a> Attr<'a> for UnknownTransitive<'a> {
+    fn flags(&self) -> u8 {
+        self.flags
+    }
+
+    fn code(&self) -> u8 {
+        self.code
+    }
+
+    fn value(&self) -> &'a [u8] {
+        self.value
+    }
+}
+
+impl<'a> UnknownTransitive<'a> {
+    /// Writes the attribute as it is to be passed on to other peers: the
+    /// received bytes with the Partial bit set (RFC 4271, section 5).
+    pub fn write_forward<'b>(&self, buf: &'b mut [u8]) -> Result<&'b [u8]> {
+        let out = buf.get_mut(..self.bytes.len()).ok_or(BgpError::BadLength)?;
+        out.copy_from_slice(self.bytes);
+        out[0] |= FLAG_PARTIAL;
+        Ok(out)
+    }
+}
+
+/// Iterates over the optional transitive attributes of types not
+/// recognized by this crate, skipping all others without decoding them.
+/// Created by `PathAttrIter::unknown_transitive`.
+#[derive(Debug, Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct UnknownTransitiveIter<'a> {
+    attrs: PathAttrIter<'a>,
+}
+
+impl<'a> Iterator for UnknownTransitiveIter<'a> {
+    type Item = Result<UnknownTransitive<'a>>;
+
+    fn next(&mut self) -> Option<Result<UnknownTransitive<'a>>> {
+        loop {
+            let raw = match self.attrs.next_raw()? {
+                Ok(raw) => raw,
+                Err(err) => return Some(Err(err)),
+            };
+            let optional_transitive = FLAG_OPTIONAL | FLAG_TRANSITIVE;
+            let known = matches!(AttrKind::from(raw.code), AttrKind::Known(_));
+            if raw.flags & optional_transitive == optional_transitive && !known {
+                return Some(Ok(UnknownTransitive {
+                    bytes: raw.bytes,
+                    flags: raw.flags,
+                    code: raw.code,
+                    value: raw.value,
+                }));
+            }
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -817,12 +2137,12 @@ mod tests {
     #[test]
     fn parse_as_set() {
         let bytes = &[0x40, 0x02, 0x0a, 0x02, 0x01, 0x00, 0x1e, 0x01, 0x02, 0x00, 0x0a, 0x00, 0x14];
-        let as_path = AsPath{inner: bytes};
+        let as_path = AsPath::from_attr_bytes(bytes).unwrap();
         let mut segments = as_path.segments();
         match segments.next() {
             Some(Ok(AsPathSegment::AsSequence(seq))) => {
                 let mut asns = seq.aut_nums().unwrap();
-                assert_eq!(asns.next().unwrap(), 30);
+                assert_eq!(asns.next().unwrap(), AsNumber(30));
                 let next = asns.next();
                 assert!(next.is_none(), "expected None, got {:?}", next);
             },
@@ -831,8 +2151,8 @@ mod tests {
         match segments.next() {
             Some(Ok(AsPathSegment::AsSet(set))) => {
                 let mut asns = set.aut_nums().unwrap();
-                assert_eq!(asns.next().unwrap(), 10);
-                assert_eq!(asns.next().unwrap(), 20);
+                assert_eq!(asns.next().unwrap(), AsNumber(10));
+                assert_eq!(asns.next().unwrap(), AsNumber(20));
                 assert!(asns.next().is_none());
             }
             _ => panic!("expected AS_SET")
@@ -840,4 +2160,395 @@ mod tests {
         assert!(segments.next().is_none());
     }
 
+    #[test]
+    fn empty_and_truncated_as_path() {
+        // iBGP-originated routes carry an empty AS_PATH
+        let as_path = AsPath::from_attr_bytes(&[0x40, 0x02, 0x00]).unwrap();
+        assert!(as_path.segments().next().is_none());
+
+        // segment header cut short
+        let as_path = AsPath::from_value(&[0x02]).unwrap();
+        let mut segments = as_path.segments();
+        assert!(matches!(segments.next(), Some(Err(BgpError::BadLength))));
+        assert!(segments.next().is_none());
+
+        // segment claims three AS numbers but holds one
+        let as_path = AsPath::from_value(&[0x02, 0x03, 0xfd, 0xe8]).unwrap();
+        let mut segments = as_path.segments();
+        assert!(matches!(segments.next(), Some(Err(BgpError::BadLength))));
+        assert!(segments.next().is_none());
+
+        let mut attrs = PathAttrIter::new(&[0x40, 0x01, 0x00], false);
+        assert!(matches!(attrs.next(), Some(Err(BgpError::Invalid))));
+    }
+
+    #[test]
+    fn as_path_debug() {
+        use core::fmt::Write;
+
+        let as_path = AsPath::from_value(&[0x02, 0x02, 0xfd, 0xe8, 0x0d, 0x1c,
+                                           0x01, 0x02, 0x00, 0x0a, 0x00, 0x14]).unwrap();
+        let mut buf = FmtBuf::new();
+        write!(buf, "{:?}", as_path).unwrap();
+        assert_eq!(buf.as_bytes(), b"[[65000, 3356], {10, 20}]");
+
+        let as_path = AsPath::from_value(&[0x02, 0x01, 0xfd, 0xe8, 0x02, 0x03, 0x00]).unwrap();
+        let mut buf = FmtBuf::new();
+        write!(buf, "{:?}", as_path).unwrap();
+        assert_eq!(buf.as_bytes(), b"[[65000], Err(BadLength)]");
+    }
+
+    #[test]
+    fn detect_as_path_width() {
+        // AS_PATH 65000 2914 with four-octet AS numbers
+        let bytes = &[0x40, 0x02, 0x0a, 0x02, 0x02, 0x00, 0x00, 0xfd, 0xe8, 0x00, 0x00, 0x0b, 0x62];
+        assert_eq!(AsPath::from_attr_bytes(bytes).unwrap().validate_width(), AsPathWidth::FourByte);
+        let mut attrs = PathAttrIter::new(bytes, false).detect_as_width();
+        match attrs.next() {
+            Some(Ok(PathAttr::As4Path(path))) => assert_eq!(path.validate_width(), AsPathWidth::FourByte),
+            x => panic!("expected As4Path, got {:?}", x)
+        }
+
+        // AS_PATH 65000 2914 with two-octet AS numbers
+        let bytes = &[0x40, 0x02, 0x06, 0x02, 0x02, 0xfd, 0xe8, 0x0b, 0x62];
+        assert_eq!(As4Path::from_attr_bytes(bytes).unwrap().validate_width(), AsPathWidth::TwoByte);
+        let mut attrs = PathAttrIter::new(bytes, true).detect_as_width();
+        assert!(matches!(attrs.next(), Some(Ok(PathAttr::AsPath(_)))));
+
+        assert_eq!(AsPath::from_value(&[]).unwrap().validate_width(), AsPathWidth::Either);
+        assert_eq!(AsPath::from_value(&[0x05, 0x01, 0x00]).unwrap().validate_width(), AsPathWidth::Neither);
+        // an AS_CONFED_SEQUENCE of two-octet AS numbers, which AsPathIter
+        // would reject whatever the width
+        let confed = &[0x03, 0x02, 0x00, 0x00, 0xfd, 0xe8];
+        assert_eq!(AsPath::from_value(confed).unwrap().validate_width(), AsPathWidth::Neither);
+        let mut attrs = PathAttrIter::new(&[0x40, 0x02, 0x00], true).detect_as_width();
+        assert!(matches!(attrs.next(), Some(Ok(PathAttr::As4Path(_)))));
+    }
+
+    #[test]
+    fn parse_route_targets() {
+        let bytes = &[0xc0, 0x10, 0x18,
+                      0x00, 0x02, 0xfd, 0xe8, 0x00, 0x00, 0x00, 0x64, // RT 65000:100
+                      0x01, 0x02, 0xc0, 0x00, 0x02, 0x01, 0x00, 0x07, // RT 192.0.2.1:7
+                      0x02, 0x03, 0x00, 0x03, 0x0d, 0x40, 0x00, 0x2a, // RO 200000:42
+        ];
+        let ext_comms = ExtendedCommunities::from_attr_bytes(bytes).unwrap();
+        let mut iter = ext_comms.communities().unwrap();
+        match iter.next() {
+            Some(ExtendedCommunity::RouteTarget(rt)) => {
+                assert_eq!(rt.admin(), ExtCommAdmin::As2(65000));
+                assert_eq!(rt.assigned(), 100);
+            }
+            x => panic!("expected RouteTarget, got {:?}", x)
+        }
+        match iter.next() {
+            Some(ExtendedCommunity::RouteTarget(rt)) => {
+                assert_eq!(rt.admin(), ExtCommAdmin::Ipv4(0xc0000201));
+                assert_eq!(rt.assigned(), 7);
+            }
+            x => panic!("expected RouteTarget, got {:?}", x)
+        }
+        match iter.next() {
+            Some(ExtendedCommunity::RouteOrigin(ro)) => {
+                assert_eq!(ro.admin(), ExtCommAdmin::As4(200000));
+                assert_eq!(ro.assigned(), 42);
+            }
+            x => panic!("expected RouteOrigin, got {:?}", x)
+        }
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn parse_extended_communities_capture() {
+        // EXTENDED_COMMUNITIES from a VPNv4 route with an OSPF route type
+        // community and an RPKI origin validation state (non-transitive).
+        let bytes = &[0xc0, 0x10, 0x18,
+                      0x00, 0x02, 0x0b, 0x62, 0x00, 0x00, 0x00, 0x01, // RT 2914:1
+                      0x03, 0x06, 0x00, 0x00, 0x00, 0x00, 0x05, 0x00, // OSPF route type
+                      0x43, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // origin validation: valid
+        ];
+        let ext_comms = ExtendedCommunities::from_attr_bytes(bytes).unwrap();
+        let mut iter = ext_comms.communities().unwrap();
+        match iter.next() {
+            Some(ExtendedCommunity::RouteTarget(rt)) => {
+                assert!(rt.is_transitive());
+                assert_eq!(rt.value(), &[0x0b, 0x62, 0x00, 0x00, 0x00, 0x01]);
+                assert_eq!(rt.admin(), ExtCommAdmin::As2(2914));
+            }
+            x => panic!("expected RouteTarget, got {:?}", x)
+        }
+        match iter.next() {
+            Some(ExtendedCommunity::Opaque(opaque)) => {
+                assert!(opaque.is_transitive());
+                assert_eq!(opaque.type_low(), 0x06);
+                assert_eq!(opaque.value(), &[0x00, 0x00, 0x00, 0x00, 0x05, 0x00]);
+            }
+            x => panic!("expected Opaque, got {:?}", x)
+        }
+        match iter.next() {
+            Some(ExtendedCommunity::OriginValidation(ov)) => {
+                assert!(!ov.is_transitive());
+                assert_eq!(ov.state(), ValidationState::Valid);
+            }
+            x => panic!("expected OriginValidation, got {:?}", x)
+        }
+        assert!(iter.next().is_none());
+
+        let bytes = &[0x43, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02, // invalid
+                      0x43, 0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x02];
+        let mut iter = ExtendedCommunityIter::new(bytes).unwrap();
+        match iter.next() {
+            Some(ExtendedCommunity::OriginValidation(ov)) => assert_eq!(ov.state(), ValidationState::Invalid),
+            x => panic!("expected OriginValidation, got {:?}", x)
+        }
+        assert!(matches!(iter.next(), Some(ExtendedCommunity::Cost(_))));
+    }
+
+    #[test]
+    fn parse_traffic_engineering() {
+        let mut bytes = [0; 3 + 38];
+        bytes[..7].copy_from_slice(&[0x80, 0x18, 38, 1, 1, 0, 0]);
+        // 1.25e8 bytes per second (1 Gbit/s) at priority 0 and 7
+        bytes[7..11].copy_from_slice(&[0x4c, 0xee, 0x6b, 0x28]);
+        bytes[35..39].copy_from_slice(&[0x4c, 0xee, 0x6b, 0x28]);
+        bytes[39..].copy_from_slice(&[0x05, 0xdc]);
+        let te = TrafficEngineering::from_attr_bytes(&bytes).unwrap();
+        assert_eq!(te.switching_capability(), 1);
+        assert_eq!(te.encoding(), 1);
+        assert_eq!(te.max_lsp_bandwidth(0), Some(1.25e8));
+        assert_eq!(te.max_lsp_bandwidth(1), Some(0.0));
+        assert_eq!(te.max_lsp_bandwidth(7), Some(1.25e8));
+        assert_eq!(te.max_lsp_bandwidth(8), None);
+        assert_eq!(te.switching_info(), &[0x05, 0xdc]);
+
+        assert!(TrafficEngineering::from_value(&[1, 1, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn classify_attr_codes() {
+        // BGP Entropy Label Capability, deprecated by RFC 7447
+        let mut attrs = PathAttrIter::new(&[0xc0, 0x1c, 0x00, 0xc0, 0xfe, 0x01, 0x00], true);
+        match attrs.next() {
+            Some(Ok(PathAttr::Other(other))) => {
+                assert_eq!(other.kind(), AttrKind::Deprecated(28));
+                assert_eq!(other.kind().deprecated_name(), Some("BGP Entropy Label Capability Attribute"));
+                #[cfg(feature = "names")]
+                assert_eq!(other.kind().name(), "BGP Entropy Label Capability Attribute");
+            }
+            x => panic!("expected Other, got {:?}", x)
+        }
+        match attrs.next() {
+            Some(Ok(PathAttr::Other(other))) => {
+                assert_eq!(other.kind(), AttrKind::Unknown(254));
+                assert_eq!(other.kind().deprecated_name(), None);
+                #[cfg(feature = "names")]
+                assert_eq!(other.kind().name(), "Unassigned");
+            }
+            x => panic!("expected Other, got {:?}", x)
+        }
+        assert_eq!(AttrKind::from(8), AttrKind::Known(8));
+        assert_eq!(AttrKind::from(30).code(), 30);
+    }
+
+    #[test]
+    fn lossy_attrs() {
+        let bytes = &[0x40, 0x01, 0x02, 0x00, 0x00, // ORIGIN of 2 octets
+                      0x40, 0x05, 0x04, 0x00, 0x00, 0x00, 0x64, // LOCAL_PREF 100
+                      0x40, 0x03, 0x05, 0xc0, 0x00, // NEXT_HOP, truncated
+        ];
+        let mut attrs = PathAttrIter::new(bytes, true).lossy();
+        let (attr, raw) = attrs.next().unwrap();
+        assert!(matches!(attr, Err(BgpError::Invalid)));
+        assert_eq!(raw, &bytes[..5]);
+        let (attr, raw) = attrs.next().unwrap();
+        assert!(matches!(attr, Ok(PathAttr::LocalPreference(_))));
+        assert_eq!(raw, &bytes[5..12]);
+        let (attr, raw) = attrs.next().unwrap();
+        assert!(matches!(attr, Err(BgpError::BadLength)));
+        assert_eq!(raw, &bytes[12..]);
+        assert!(attrs.next().is_none());
+    }
+
+    #[test]
+    fn forward_unknown_transitive() {
+        let bytes = &[0xd0, 0xfe, 0x00, 0x02, 0xab, 0xcd, // unknown, extended length
+                      0x80, 0xfd, 0x01, 0x01, // unknown, non-transitive
+                      0x40, 0x05, 0x04, 0x00, 0x00, 0x00, 0x64, // LOCAL_PREF 100
+                      0xc0, 0x1c, 0x00, // deprecated
+                      0xc0, 0x20, 0x0c, 0x00, // LARGE_COMMUNITY, truncated
+        ];
+        let mut attrs = PathAttrIter::new(bytes, true).unknown_transitive();
+        let attr = attrs.next().unwrap().unwrap();
+        assert_eq!((attr.code(), attr.value()), (0xfe, &[0xab, 0xcd][..]));
+        assert_eq!(attr.bytes, &bytes[..6]);
+        let mut buf = [0; 6];
+        assert_eq!(attr.write_forward(&mut buf).unwrap(), &[0xf0, 0xfe, 0x00, 0x02, 0xab, 0xcd]);
+        assert!(matches!(attr.write_forward(&mut [0; 5]), Err(BgpError::BadLength)));
+        let attr = attrs.next().unwrap().unwrap();
+        assert_eq!((attr.code(), attr.is_empty()), (0x1c, true));
+        assert!(matches!(attrs.next(), Some(Err(BgpError::BadLength))));
+        assert!(attrs.next().is_none());
+    }
+
+    #[test]
+    fn path_attr_header() {
+        let bytes = &[0xd0, 0xfe, 0x00, 0x02, 0xab, 0xcd, // unknown, extended length
+                      0x40, 0x05, 0x04, 0x00, 0x00, 0x00, 0x64, // LOCAL_PREF 100
+                      0x90, 0x0f, 0x00, 0x03, 0x00, 0x02, 0x01, // MP_UNREACH_NLRI IPv6
+        ];
+        let mut attrs = PathAttrIter::new(bytes, true);
+        let attr = attrs.next().unwrap().unwrap();
+        assert!(matches!(attr, PathAttr::Other(_)));
+        assert_eq!((attr.flags(), attr.code(), attr.len()), (0xd0, 0xfe, 2));
+        assert!(attr.is_ext_len() && attr.is_optional());
+        assert_eq!(attr.value(), &[0xab, 0xcd]);
+        let attr = attrs.next().unwrap().unwrap();
+        assert_eq!((attr.flags(), attr.code(), attr.value()), (0x40, 5, &[0, 0, 0, 0x64][..]));
+        let attr = attrs.next().unwrap().unwrap();
+        assert_eq!((attr.flags(), attr.code(), attr.value()), (0x90, 15, &[0x00, 0x02, 0x01][..]));
+        assert!(attrs.next().is_none());
+    }
+
+    #[test]
+    fn parse_opaque_subtypes() {
+        let bytes = &[0x43, 0x01, 0x80, 0x07, 0x00, 0x00, 0x01, 0x00, // cost: absolute, id 7, 256
+                      0x03, 0x0b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x64, // color 100
+                      0x03, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x08, // encapsulation: VXLAN
+                      0x03, 0x0d, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // default gateway
+        ];
+        let mut iter = ExtendedCommunityIter::new(bytes).unwrap();
+        match iter.next() {
+            Some(ExtendedCommunity::Cost(cost)) => {
+                assert!(!cost.is_transitive());
+                assert_eq!(cost.point_of_insertion(), 128);
+                assert_eq!(cost.community_id(), 7);
+                assert_eq!(cost.cost(), 256);
+            }
+            x => panic!("expected Cost, got {:?}", x)
+        }
+        match iter.next() {
+            Some(ExtendedCommunity::Color(color)) => {
+                assert_eq!(color.flags(), 0);
+                assert_eq!(color.color(), 100);
+            }
+            x => panic!("expected Color, got {:?}", x)
+        }
+        match iter.next() {
+            Some(ExtendedCommunity::Encapsulation(encap)) => assert_eq!(encap.tunnel_type(), 8),
+            x => panic!("expected Encapsulation, got {:?}", x)
+        }
+        assert!(matches!(iter.next(), Some(ExtendedCommunity::DefaultGateway(_))));
+        assert!(iter.next().is_none());
+    }
+
+    #[test]
+    fn extended_communities_bad_length() {
+        assert!(ExtendedCommunityIter::new(&[0x00, 0x02, 0xfd, 0xe8, 0x00, 0x00, 0x00]).is_err());
+        let bytes = &[0xc0, 0x10, 0x07, 0x00, 0x02, 0xfd, 0xe8, 0x00, 0x00, 0x00];
+        assert!(ExtendedCommunities::from_attr_bytes(bytes).unwrap().communities().is_err());
+    }
+
+    #[test]
+    fn standalone_attribute_constructors() {
+        // AS_PATH 65000 65001 from an MRT TABLE_DUMP_V2 attribute section
+        let as_path = AsPath::from_attr_bytes(&[0x40, 0x02, 0x06, 0x02, 0x02, 0xfd, 0xe8, 0xfd, 0xe9]).unwrap();
+        assert_eq!(as_path.code(), 2);
+        assert_eq!(as_path.flags(), FLAG_TRANSITIVE);
+        assert!(AsPath::from_attr_bytes(&[0x40, 0x01, 0x01, 0x00]).is_err());
+        assert!(AsPath::from_attr_bytes(&[0x40, 0x02, 0x06, 0x02, 0x02, 0xfd, 0xe8]).is_err());
+
+        let communities = Communities::from_value(&[0xfd, 0xe8, 0x00, 0x64]).unwrap();
+        assert_eq!(communities.code(), 8);
+        assert!(communities.is_optional() && communities.is_transitive());
+        assert!(communities.contains(Community::new(65000, 100)));
+
+        assert!(Origin::from_value(&[]).is_err());
+        assert_eq!(Origin::from_value(&[2]).unwrap().origin(), OriginType::Incomplete);
+        assert_eq!(Origin::from_value(&[2]).unwrap().try_origin().unwrap(), OriginType::Incomplete);
+        assert_eq!(Origin::from_value(&[7]).unwrap().origin(), OriginType::Unknown(7));
+        assert!(matches!(Origin::from_value(&[7]).unwrap().try_origin(), Err(BgpError::Invalid)));
+
+        match MpUnreachNlri::from_value(&[0x00, 0x01, 0x01, 0x18, 0xc6, 0x33, 0x64]) {
+            Ok(MpUnreachNlri::Ipv4Unicast(unreach)) => assert_eq!(unreach.nlris().count(), 1),
+            x => panic!("expected Ipv4Unicast, got {:?}", x)
+        }
+    }
+
+    #[test]
+    fn parse_rib_entry_attributes() {
+        let bytes = &[0x40, 0x01, 0x01, 0x00, // ORIGIN IGP
+                      0x50, 0x02, 0x00, 0x0a, 0x02, 0x02, 0x00, 0x00, 0x0d, 0x1c,
+                      0x00, 0x00, 0x0b, 0x62, // AS_PATH 3356 2914
+                      0x80, 0x0e, 0x11, 0x10, 0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00, 0x00,
+                      0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, // MP_REACH next hop 2001:db8::1
+        ];
+        let mut attrs = PathAttrIter::from_rib_entry(bytes, true);
+        match attrs.next() {
+            Some(Ok(PathAttr::Origin(origin))) => assert_eq!(origin.origin(), OriginType::Igp),
+            x => panic!("expected Origin, got {:?}", x)
+        }
+        match attrs.next() {
+            Some(Ok(PathAttr::As4Path(path))) => {
+                match path.segments().next() {
+                    Some(Ok(AsPathSegment::AsSequence(seq))) => {
+                        let mut asns = seq.aut_nums().unwrap();
+                        assert_eq!(asns.next(), Some(AsNumber(3356)));
+                        assert_eq!(asns.next(), Some(AsNumber(2914)));
+                        assert!(asns.next().is_none());
+                    }
+                    x => panic!("expected AS_SEQUENCE, got {:?}", x)
+                }
+            }
+            x => panic!("expected As4Path, got {:?}", x)
+        }
+        match attrs.next() {
+            Some(Ok(PathAttr::MpReachNlri(MpReachNlri::NextHopOnly(reach)))) => {
+                assert_eq!(reach.nexthop(), &bytes[22..]);
+            }
+            x => panic!("expected MpReachNlri::NextHopOnly, got {:?}", x)
+        }
+        assert!(attrs.next().is_none());
+    }
+
+    #[test]
+    fn attribute_tlvs() {
+        // AIGP TLV with a metric of 10
+        let aigp = Aigp::from_attr_bytes(&[0x80, 0x1a, 0x0b,
+                                           0x01, 0x00, 0x08, 0, 0, 0, 0, 0, 0, 0, 0x0a]).unwrap();
+        let mut tlvs = aigp.tlvs();
+        assert_eq!(tlvs.next().unwrap().unwrap(), Tlv { tlv_type: 1, value: &[0, 0, 0, 0, 0, 0, 0, 0x0a] });
+        assert!(tlvs.next().is_none());
+
+        // BGP-LS IGP metric TLV, then one cut short
+        let bgp_ls = BgpLs::from_attr_bytes(&[0x80, 0x1d, 0x0a,
+                                              0x04, 0x47, 0x00, 0x01, 0x0a,
+                                              0x04, 0x47, 0x00, 0x02, 0x00]).unwrap();
+        let mut tlvs = bgp_ls.tlvs();
+        assert_eq!(tlvs.next().unwrap().unwrap(), Tlv { tlv_type: 1095, value: &[0x0a] });
+        assert!(matches!(tlvs.next(), Some(Err(BgpError::BadLength))));
+        assert!(tlvs.next().is_none());
+    }
+
+    #[test]
+    fn parse_labels() {
+        // Ingress Replication to 192.0.2.1 with label 100
+        let pmsi = PmsiTunnel::from_attr_bytes(&[0xc0, 0x16, 0x09, 0x00, 0x06, 0x00, 0x06, 0x41,
+                                                 192, 0, 2, 1]).unwrap();
+        assert_eq!(pmsi.tunnel_type().unwrap(), 6);
+        assert_eq!(pmsi.label().unwrap(), Label { value: 100, traffic_class: 0, bottom_of_stack: true });
+        assert_eq!(pmsi.tunnel_identifier().unwrap(), &[192, 0, 2, 1]);
+        let pmsi = PmsiTunnel::from_attr_bytes(&[0xc0, 0x16, 0x03, 0x00, 0x06, 0x00]).unwrap();
+        assert!(matches!(pmsi.label(), Err(BgpError::BadLength)));
+
+        // 192.0.2.1 with label 16, then an entry cut short
+        let labels = PeDistinguisherLabels::from_attr_bytes(&[0xc0, 0x1b, 0x0a,
+                                                              192, 0, 2, 1, 0x00, 0x01, 0x01,
+                                                              192, 0, 2]).unwrap();
+        let mut entries = labels.entries(false);
+        let (address, label) = entries.next().unwrap().unwrap();
+        assert_eq!(address, IpAddrBytes::V4(&[192, 0, 2, 1]));
+        assert_eq!(label.value, 16);
+        assert!(matches!(entries.next(), Some(Err(BgpError::BadLength))));
+        assert!(entries.next().is_none());
+    }
 }
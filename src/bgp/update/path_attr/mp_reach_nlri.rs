@@ -1,5 +1,5 @@
 use super::*;
-use types::*;
+use bgp::update::nlri::NlriIter;
 use core::fmt;
 
 
@@ -10,99 +10,354 @@ pub enum MpReachNlri<'a> {
     Ipv4Multicast(Ipv4ReachNlri<'a>),
     Ipv6Unicast(Ipv6ReachNlri<'a>),
     Ipv6Multicast(Ipv6ReachNlri<'a>),
+    /// IPv4 prefixes reachable across an IPv6 softwire (RFC 5747).
+    Ipv4Over6(Ipv4ReachNlri<'a>),
+    /// IPv6 prefixes reachable across an IPv4 softwire (RFC 5512).
+    Ipv6Over4(Ipv6ReachNlri<'a>),
+    /// IPv4 prefixes with an MPLS label stack (RFC 8277).
+    Ipv4Labeled(Ipv4ReachNlri<'a>),
+    /// IPv6 prefixes with an MPLS label stack (RFC 8277).
+    Ipv6Labeled(Ipv6ReachNlri<'a>),
+    /// VPN-IPv4 prefixes: labels, a route distinguisher and an IPv4
+    /// prefix (RFC 4364).
+    Ipv4Vpn(Ipv4ReachNlri<'a>),
+    /// VPN-IPv6 prefixes (RFC 4659).
+    Ipv6Vpn(Ipv6ReachNlri<'a>),
+    /// MT-IPv4 or MT-IPv6, whose prefixes carry a topology identifier.
+    MultiTopology(MtReachNlri<'a>),
+    /// The abbreviated form stored in MRT TABLE_DUMP_V2 RIB entries,
+    /// which only carries the next hop.
+    NextHopOnly(NextHopOnlyReachNlri<'a>),
     Other(OtherReachNlri<'a>),
 }
 
 impl<'a> MpReachNlri<'a> {
 
     pub fn from_bytes(bytes: &'a [u8]) -> Result<MpReachNlri<'a>> {
-        if bytes.len() < 4 {
-            return Err(BgpError::BadLength);
-        }
+        MpReachNlri::try_from(bytes)
+    }
+
+    /// Parses a complete MP_REACH_NLRI attribute (flags, type code,
+    /// length and value).
+    pub fn from_attr_bytes(bytes: &'a [u8]) -> Result<MpReachNlri<'a>> {
+        MpReachNlri::try_from(bytes)
+    }
+
+    /// Parses a bare MP_REACH_NLRI attribute value, giving it the flags
+    /// the attribute is normally sent with.
+    pub fn from_value(value: &'a [u8]) -> Result<MpReachNlri<'a>> {
+        MpReachNlri::from_parts(value, default_flags(value), false, 1)
+    }
 
-        let flags = bytes[0];
-        let value = if flags & FLAG_EXT_LEN > 0 { &bytes[4..] } else { &bytes[3..]};
+    /// Decodes an attribute whose header has already been read, keeping
+    /// its flags.
+    pub(crate) fn from_raw(raw: RawAttr<'a>, add_paths: bool, max_labels: u8) -> Result<MpReachNlri<'a>> {
+        MpReachNlri::from_parts(raw.value, raw.flags, add_paths, max_labels)
+    }
 
-        let afi = Afi::from((value[0] as u16) << 8 | value[1] as u16);
-        let safi = Safi::from(value[2]);
+    fn from_parts(value: &'a [u8], flags: u8, add_paths: bool, max_labels: u8) -> Result<MpReachNlri<'a>> {
+        // afi, safi, next hop length, next hop, reserved
+        let afi = Afi::from(value.u16_at(0)?);
+        let safi = Safi::from(value.at(2)?);
+        nlri_offset(value)?;
         let reach = match (afi, safi) {
-            (AFI_IPV4, SAFI_UNICAST) => MpReachNlri::Ipv4Unicast(Ipv4ReachNlri{inner: value}),
-            (AFI_IPV4, SAFI_MULTICAST) => MpReachNlri::Ipv4Multicast(Ipv4ReachNlri{inner: value}),
-            (AFI_IPV6, SAFI_UNICAST) => MpReachNlri::Ipv6Unicast(Ipv6ReachNlri{inner: value}),
-            (AFI_IPV6, SAFI_MULTICAST) => MpReachNlri::Ipv6Multicast(Ipv6ReachNlri{inner: value}),
-            _ => MpReachNlri::Other(OtherReachNlri{inner: value}),
+            (AFI_IPV4, SAFI_UNICAST) => MpReachNlri::Ipv4Unicast(Ipv4ReachNlri{inner: value, flags, add_paths, max_labels}),
+            (AFI_IPV4, SAFI_MULTICAST) => MpReachNlri::Ipv4Multicast(Ipv4ReachNlri{inner: value, flags, add_paths, max_labels}),
+            (AFI_IPV6, SAFI_UNICAST) => MpReachNlri::Ipv6Unicast(Ipv6ReachNlri{inner: value, flags, add_paths, max_labels}),
+            (AFI_IPV6, SAFI_MULTICAST) => MpReachNlri::Ipv6Multicast(Ipv6ReachNlri{inner: value, flags, add_paths, max_labels}),
+            (AFI_IPV4, SAFI_4OVER6) => MpReachNlri::Ipv4Over6(Ipv4ReachNlri{inner: value, flags, add_paths, max_labels}),
+            (AFI_IPV6, SAFI_6OVER4) => MpReachNlri::Ipv6Over4(Ipv6ReachNlri{inner: value, flags, add_paths, max_labels}),
+            (AFI_IPV4, SAFI_MPLS_LABEL) => MpReachNlri::Ipv4Labeled(Ipv4ReachNlri{inner: value, flags, add_paths, max_labels}),
+            (AFI_IPV6, SAFI_MPLS_LABEL) => MpReachNlri::Ipv6Labeled(Ipv6ReachNlri{inner: value, flags, add_paths, max_labels}),
+            (AFI_IPV4, SAFI_MPLS_LABELED_VPN_ADDR) => MpReachNlri::Ipv4Vpn(Ipv4ReachNlri{inner: value, flags, add_paths, max_labels}),
+            (AFI_IPV6, SAFI_MPLS_LABELED_VPN_ADDR) => MpReachNlri::Ipv6Vpn(Ipv6ReachNlri{inner: value, flags, add_paths, max_labels}),
+            (AFI_MT_IPV4, _) | (AFI_MT_IPV6, _) => MpReachNlri::MultiTopology(MtReachNlri{inner: value, flags, add_paths, max_labels}),
+            _ => MpReachNlri::Other(OtherReachNlri{inner: value, flags}),
         };
         Ok(reach)
     }
 }
 
+impl<'a> MpReachNlri<'a> {
+
+    /// Parses the abbreviated MP_REACH_NLRI value of an MRT TABLE_DUMP_V2
+    /// RIB entry (RFC 6396, section 4.3.4): next hop length followed by
+    /// the next hop. AFI, SAFI and NLRI are given by the enclosing RIB
+    /// record.
+    ///
+    /// Fails with `BgpError::BadLength` unless the next hop length
+    /// accounts for the rest of the value and is one a next hop can have:
+    /// 4, 16 or 32 octets, or 12, 24 or 48 with route distinguishers. Use
+    /// `from_value` for dumps that store the full attribute instead.
+    pub fn from_rib_value(value: &'a [u8]) -> Result<MpReachNlri<'a>> {
+        MpReachNlri::from_rib_parts(value, default_flags(value))
+    }
+
+    /// Like `from_rib_value`, for an attribute whose header has already
+    /// been read.
+    pub(crate) fn from_rib_raw(raw: RawAttr<'a>) -> Result<MpReachNlri<'a>> {
+        MpReachNlri::from_rib_parts(raw.value, raw.flags)
+    }
+
+    fn from_rib_parts(value: &'a [u8], flags: u8) -> Result<MpReachNlri<'a>> {
+        let len = value.at(0)? as usize;
+        if value.len() != 1 + len || ![4, 12, 16, 24, 32, 48].contains(&len) {
+            return Err(BgpError::BadLength);
+        }
+        Ok(MpReachNlri::NextHopOnly(NextHopOnlyReachNlri{inner: value, flags}))
+    }
+
+    /// The attribute flags, as received.
+    pub fn flags(&self) -> u8 {
+        match *self {
+            MpReachNlri::Ipv4Unicast(ref reach) |
+            MpReachNlri::Ipv4Multicast(ref reach) |
+            MpReachNlri::Ipv4Over6(ref reach) |
+            MpReachNlri::Ipv4Labeled(ref reach) |
+            MpReachNlri::Ipv4Vpn(ref reach) => reach.flags,
+            MpReachNlri::Ipv6Unicast(ref reach) |
+            MpReachNlri::Ipv6Multicast(ref reach) |
+            MpReachNlri::Ipv6Over4(ref reach) |
+            MpReachNlri::Ipv6Labeled(ref reach) |
+            MpReachNlri::Ipv6Vpn(ref reach) => reach.flags,
+            MpReachNlri::MultiTopology(ref reach) => reach.flags,
+            MpReachNlri::NextHopOnly(ref reach) => reach.flags,
+            MpReachNlri::Other(ref reach) => reach.flags,
+        }
+    }
+
+    /// The attribute value.
+    pub fn value(&self) -> &'a [u8] {
+        match *self {
+            MpReachNlri::Ipv4Unicast(ref reach) |
+            MpReachNlri::Ipv4Multicast(ref reach) |
+            MpReachNlri::Ipv4Over6(ref reach) |
+            MpReachNlri::Ipv4Labeled(ref reach) |
+            MpReachNlri::Ipv4Vpn(ref reach) => reach.inner,
+            MpReachNlri::Ipv6Unicast(ref reach) |
+            MpReachNlri::Ipv6Multicast(ref reach) |
+            MpReachNlri::Ipv6Over4(ref reach) |
+            MpReachNlri::Ipv6Labeled(ref reach) |
+            MpReachNlri::Ipv6Vpn(ref reach) => reach.inner,
+            MpReachNlri::MultiTopology(ref reach) => reach.inner,
+            MpReachNlri::NextHopOnly(ref reach) => reach.inner,
+            MpReachNlri::Other(ref reach) => reach.inner,
+        }
+    }
+
+    /// The address family, which the abbreviated MRT form leaves out.
+    pub fn afi_safi(&self) -> Option<AfiSafi> {
+        match *self {
+            MpReachNlri::NextHopOnly(_) => None,
+            _ => Some(AfiSafi::from_bytes(self.value())),
+        }
+    }
+
+    /// The next hop, whatever the address family.
+    pub fn nexthop(&self) -> Nexthop<'a> {
+        match *self {
+            MpReachNlri::Ipv4Unicast(ref reach) |
+            MpReachNlri::Ipv4Multicast(ref reach) |
+            MpReachNlri::Ipv4Over6(ref reach) |
+            MpReachNlri::Ipv4Labeled(ref reach) |
+            MpReachNlri::Ipv4Vpn(ref reach) => reach.nexthop(),
+            MpReachNlri::Ipv6Unicast(ref reach) |
+            MpReachNlri::Ipv6Multicast(ref reach) |
+            MpReachNlri::Ipv6Over4(ref reach) |
+            MpReachNlri::Ipv6Labeled(ref reach) |
+            MpReachNlri::Ipv6Vpn(ref reach) => reach.nexthop(),
+            MpReachNlri::MultiTopology(ref reach) => reach.nexthop(),
+            MpReachNlri::NextHopOnly(ref reach) => Nexthop::from_bytes(reach.nexthop()),
+            MpReachNlri::Other(ref reach) => reach.nexthop(),
+        }
+    }
+
+    /// The prefixes, for the address families decoded here.
+    pub fn nlris(&self) -> Option<NlriIter<'a>> {
+        match *self {
+            MpReachNlri::Ipv4Unicast(ref reach) |
+            MpReachNlri::Ipv4Multicast(ref reach) |
+            MpReachNlri::Ipv4Over6(ref reach) |
+            MpReachNlri::Ipv4Labeled(ref reach) |
+            MpReachNlri::Ipv4Vpn(ref reach) => Some(reach.nlris()),
+            MpReachNlri::Ipv6Unicast(ref reach) |
+            MpReachNlri::Ipv6Multicast(ref reach) |
+            MpReachNlri::Ipv6Over4(ref reach) |
+            MpReachNlri::Ipv6Labeled(ref reach) |
+            MpReachNlri::Ipv6Vpn(ref reach) => Some(reach.nlris()),
+            MpReachNlri::MultiTopology(ref reach) => Some(reach.nlris()),
+            MpReachNlri::NextHopOnly(_) | MpReachNlri::Other(_) => None,
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a [u8]> for MpReachNlri<'a> {
+    type Error = BgpError;
+
+    fn try_from(bytes: &'a [u8]) -> Result<MpReachNlri<'a>> {
+        match RawAttr::split(bytes)? {
+            raw if raw.code == 14 => MpReachNlri::from_raw(raw, false, 1),
+            _ => Err(BgpError::Invalid),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum MpUnreachNlri<'a> {
     Ipv4Unicast(Ipv4UnreachNlri<'a>),
     Ipv4Multicast(Ipv4UnreachNlri<'a>),
     Ipv6Unicast(Ipv6UnreachNlri<'a>),
     Ipv6Multicast(Ipv6UnreachNlri<'a>),
+    /// IPv4 prefixes reachable across an IPv6 softwire (RFC 5747).
+    Ipv4Over6(Ipv4UnreachNlri<'a>),
+    /// IPv6 prefixes reachable across an IPv4 softwire (RFC 5512).
+    Ipv6Over4(Ipv6UnreachNlri<'a>),
+    /// IPv4 prefixes with an MPLS label stack (RFC 8277).
+    Ipv4Labeled(Ipv4UnreachNlri<'a>),
+    /// IPv6 prefixes with an MPLS label stack (RFC 8277).
+    Ipv6Labeled(Ipv6UnreachNlri<'a>),
+    /// VPN-IPv4 prefixes: labels, a route distinguisher and an IPv4
+    /// prefix (RFC 4364).
+    Ipv4Vpn(Ipv4UnreachNlri<'a>),
+    /// VPN-IPv6 prefixes (RFC 4659).
+    Ipv6Vpn(Ipv6UnreachNlri<'a>),
+    /// MT-IPv4 or MT-IPv6, whose prefixes carry a topology identifier.
+    MultiTopology(MtUnreachNlri<'a>),
     Other(OtherUnreachNlri<'a>),
 }
 
 impl<'a> MpUnreachNlri<'a> {
 
     pub fn from_bytes(bytes: &'a [u8]) -> Result<MpUnreachNlri<'a>> {
-        if bytes.len() < 4 {
-            return Err(BgpError::BadLength);
-        }
+        MpUnreachNlri::try_from(bytes)
+    }
 
-        let flags = bytes[0];
-        let value = if flags & FLAG_EXT_LEN > 0 { &bytes[4..] } else { &bytes[3..]};
+    /// Parses a complete MP_UNREACH_NLRI attribute (flags, type code,
+    /// length and value).
+    pub fn from_attr_bytes(bytes: &'a [u8]) -> Result<MpUnreachNlri<'a>> {
+        MpUnreachNlri::try_from(bytes)
+    }
+
+    /// Parses a bare MP_UNREACH_NLRI attribute value, giving it the flags
+    /// the attribute is normally sent with.
+    pub fn from_value(value: &'a [u8]) -> Result<MpUnreachNlri<'a>> {
+        MpUnreachNlri::from_parts(value, default_flags(value), false)
+    }
 
-        let afi = Afi::from((value[0] as u16) << 8 | value[1] as u16);
-        let safi = Safi::from(value[2]);
+    /// Decodes an attribute whose header has already been read, keeping
+    /// its flags.
+    pub(crate) fn from_raw(raw: RawAttr<'a>, add_paths: bool) -> Result<MpUnreachNlri<'a>> {
+        MpUnreachNlri::from_parts(raw.value, raw.flags, add_paths)
+    }
+
+    fn from_parts(value: &'a [u8], flags: u8, add_paths: bool) -> Result<MpUnreachNlri<'a>> {
+        // afi, safi
+        let afi = Afi::from(value.u16_at(0)?);
+        let safi = Safi::from(value.at(2)?);
         let reach = match (afi, safi) {
-            (AFI_IPV4, SAFI_UNICAST) => MpUnreachNlri::Ipv4Unicast(Ipv4UnreachNlri{inner: value}),
-            (AFI_IPV4, SAFI_MULTICAST) => MpUnreachNlri::Ipv4Multicast(Ipv4UnreachNlri{inner: value}),
-            (AFI_IPV6, SAFI_UNICAST) => MpUnreachNlri::Ipv6Unicast(Ipv6UnreachNlri{inner: value}),
-            (AFI_IPV6, SAFI_MULTICAST) => MpUnreachNlri::Ipv6Multicast(Ipv6UnreachNlri{inner: value}),
-            _ => MpUnreachNlri::Other(OtherUnreachNlri{inner: value}),
+            (AFI_IPV4, SAFI_UNICAST) => MpUnreachNlri::Ipv4Unicast(Ipv4UnreachNlri{inner: value, flags, add_paths}),
+            (AFI_IPV4, SAFI_MULTICAST) => MpUnreachNlri::Ipv4Multicast(Ipv4UnreachNlri{inner: value, flags, add_paths}),
+            (AFI_IPV6, SAFI_UNICAST) => MpUnreachNlri::Ipv6Unicast(Ipv6UnreachNlri{inner: value, flags, add_paths}),
+            (AFI_IPV6, SAFI_MULTICAST) => MpUnreachNlri::Ipv6Multicast(Ipv6UnreachNlri{inner: value, flags, add_paths}),
+            (AFI_IPV4, SAFI_4OVER6) => MpUnreachNlri::Ipv4Over6(Ipv4UnreachNlri{inner: value, flags, add_paths}),
+            (AFI_IPV6, SAFI_6OVER4) => MpUnreachNlri::Ipv6Over4(Ipv6UnreachNlri{inner: value, flags, add_paths}),
+            (AFI_IPV4, SAFI_MPLS_LABEL) => MpUnreachNlri::Ipv4Labeled(Ipv4UnreachNlri{inner: value, flags, add_paths}),
+            (AFI_IPV6, SAFI_MPLS_LABEL) => MpUnreachNlri::Ipv6Labeled(Ipv6UnreachNlri{inner: value, flags, add_paths}),
+            (AFI_IPV4, SAFI_MPLS_LABELED_VPN_ADDR) => MpUnreachNlri::Ipv4Vpn(Ipv4UnreachNlri{inner: value, flags, add_paths}),
+            (AFI_IPV6, SAFI_MPLS_LABELED_VPN_ADDR) => MpUnreachNlri::Ipv6Vpn(Ipv6UnreachNlri{inner: value, flags, add_paths}),
+            (AFI_MT_IPV4, _) | (AFI_MT_IPV6, _) => MpUnreachNlri::MultiTopology(MtUnreachNlri{inner: value, flags, add_paths}),
+            _ => MpUnreachNlri::Other(OtherUnreachNlri{inner: value, flags}),
         };
         Ok(reach)
     }
 }
 
-macro_rules! impl_reach_ip_nlri {
-    ($reach_nlri:ident, $unreach_nlri:ident, $nlri:ident, $nlri_iter:ident, $nexthop: ident, $prefix:ident) => {
+impl<'a> MpUnreachNlri<'a> {
 
-        pub struct $reach_nlri<'a> {
-            inner: &'a [u8],
+    /// The attribute value.
+    pub fn value(&self) -> &'a [u8] {
+        match *self {
+            MpUnreachNlri::Ipv4Unicast(ref unreach) |
+            MpUnreachNlri::Ipv4Multicast(ref unreach) |
+            MpUnreachNlri::Ipv4Over6(ref unreach) |
+            MpUnreachNlri::Ipv4Labeled(ref unreach) |
+            MpUnreachNlri::Ipv4Vpn(ref unreach) => unreach.inner,
+            MpUnreachNlri::Ipv6Unicast(ref unreach) |
+            MpUnreachNlri::Ipv6Multicast(ref unreach) |
+            MpUnreachNlri::Ipv6Over4(ref unreach) |
+            MpUnreachNlri::Ipv6Labeled(ref unreach) |
+            MpUnreachNlri::Ipv6Vpn(ref unreach) => unreach.inner,
+            MpUnreachNlri::MultiTopology(ref unreach) => unreach.inner,
+            MpUnreachNlri::Other(ref unreach) => unreach.inner,
         }
+    }
 
-        pub struct $unreach_nlri<'a> {
-            inner: &'a [u8],
+    /// The attribute flags, as received.
+    pub fn flags(&self) -> u8 {
+        match *self {
+            MpUnreachNlri::Ipv4Unicast(ref unreach) |
+            MpUnreachNlri::Ipv4Multicast(ref unreach) |
+            MpUnreachNlri::Ipv4Over6(ref unreach) |
+            MpUnreachNlri::Ipv4Labeled(ref unreach) |
+            MpUnreachNlri::Ipv4Vpn(ref unreach) => unreach.flags,
+            MpUnreachNlri::Ipv6Unicast(ref unreach) |
+            MpUnreachNlri::Ipv6Multicast(ref unreach) |
+            MpUnreachNlri::Ipv6Over4(ref unreach) |
+            MpUnreachNlri::Ipv6Labeled(ref unreach) |
+            MpUnreachNlri::Ipv6Vpn(ref unreach) => unreach.flags,
+            MpUnreachNlri::MultiTopology(ref unreach) => unreach.flags,
+            MpUnreachNlri::Other(ref unreach) => unreach.flags,
         }
+    }
 
-        pub struct $nlri<'a> {
-            inner: &'a [u8],
-        }
+    pub fn afi_safi(&self) -> AfiSafi {
+        AfiSafi::from_bytes(self.value())
+    }
 
-        #[derive(Clone)]
-        pub struct $nlri_iter<'a> {
-            inner: &'a [u8],
-            error: bool,
+    /// The prefixes, for the address families decoded here.
+    pub fn nlris(&self) -> Option<NlriIter<'a>> {
+        match *self {
+            MpUnreachNlri::Ipv4Unicast(ref unreach) |
+            MpUnreachNlri::Ipv4Multicast(ref unreach) |
+            MpUnreachNlri::Ipv4Over6(ref unreach) |
+            MpUnreachNlri::Ipv4Labeled(ref unreach) |
+            MpUnreachNlri::Ipv4Vpn(ref unreach) => Some(unreach.nlris()),
+            MpUnreachNlri::Ipv6Unicast(ref unreach) |
+            MpUnreachNlri::Ipv6Multicast(ref unreach) |
+            MpUnreachNlri::Ipv6Over4(ref unreach) |
+            MpUnreachNlri::Ipv6Labeled(ref unreach) |
+            MpUnreachNlri::Ipv6Vpn(ref unreach) => Some(unreach.nlris()),
+            MpUnreachNlri::MultiTopology(ref unreach) => Some(unreach.nlris()),
+            MpUnreachNlri::Other(_) => None,
         }
+    }
+}
 
-        pub struct $nexthop<'a> {
-            inner: &'a [u8],
+impl<'a> TryFrom<&'a [u8]> for MpUnreachNlri<'a> {
+    type Error = BgpError;
+
+    fn try_from(bytes: &'a [u8]) -> Result<MpUnreachNlri<'a>> {
+        match RawAttr::split(bytes)? {
+            raw if raw.code == 15 => MpUnreachNlri::from_raw(raw, false),
+            _ => Err(BgpError::Invalid),
         }
+    }
+}
 
-        impl<'a> $nlri<'a> {
-            pub fn prefix(&self) -> $prefix<'a> {
-                $prefix{inner: self.inner}
-            }
+macro_rules! impl_reach_ip_nlri {
+    ($reach_nlri:ident, $unreach_nlri:ident) => {
+
+        pub struct $reach_nlri<'a> {
+            inner: &'a [u8],
+            flags: u8,
+            add_paths: bool,
+            max_labels: u8,
         }
 
-        impl<'a> fmt::Debug for $nlri<'a> {
-            fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-                self.prefix().fmt(fmt)
-            }
+        pub struct $unreach_nlri<'a> {
+            inner: &'a [u8],
+            flags: u8,
+            add_paths: bool,
         }
 
         impl<'a> $reach_nlri<'a> {
@@ -111,22 +366,32 @@ macro_rules! impl_reach_ip_nlri {
                 self.inner[3] as usize
             }
 
-            pub fn nexthop(&self) -> $nexthop<'a> {
-                $nexthop {
-                    inner: &self.inner[4..self.nexthop_len() + 4],
-                }
+            pub fn nexthop(&self) -> Nexthop<'a> {
+                Nexthop::from_bytes(&self.inner[4..self.nexthop_len() + 4])
+            }
+
+            /// The number of legacy RFC 2858 SNPAs, skipped to find the
+            /// NLRI. Non-zero only in attributes from old speakers.
+            pub fn snpa_count(&self) -> u8 {
+                snpa_count(self.inner)
             }
 
-            pub fn nlris(&self) -> $nlri_iter<'a> {
-                let offset = 2 + 1 + 1 + self.nexthop_len() + 1;
-                $nlri_iter{inner: &self.inner[offset..], error: false}
+            pub fn nlris(&self) -> NlriIter<'a> {
+                let offset = nlri_offset(self.inner).unwrap_or(self.inner.len());
+                NlriIter::with_family(&self.inner[offset..], family_afi(self.inner),
+                                      Safi::from(self.inner[2]), self.add_paths)
+                    .with_max_labels(self.max_labels)
             }
         }
 
         impl<'a> $unreach_nlri<'a> {
-            pub fn nlris(&self) -> $nlri_iter<'a> {
+            /// The withdrawn prefixes. Each carries a single label field,
+            /// whether or not multiple labels were negotiated (RFC 8277
+            /// section 2.4).
+            pub fn nlris(&self) -> NlriIter<'a> {
                 let offset = 2 + 1;
-                $nlri_iter{inner: &self.inner[offset..], error: false}
+                NlriIter::with_family(&self.inner[offset..], family_afi(self.inner),
+                                      Safi::from(self.inner[2]), self.add_paths)
             }
         }
 
@@ -147,38 +412,155 @@ macro_rules! impl_reach_ip_nlri {
             }
         }
 
-        impl<'a> Iterator for $nlri_iter<'a> {
-            type Item = Result<$nlri<'a>>;
+    }
+}
 
-            fn next(&mut self) -> Option<Result<$nlri<'a>>> {
-                if self.error || self.inner.is_empty() {
-                    return None;
-                }
+/// The flags MP_REACH_NLRI and MP_UNREACH_NLRI are normally sent with:
+/// optional, with an extended length if the value needs it.
+fn default_flags(value: &[u8]) -> u8 {
+    if value.len() > 0xff { FLAG_OPTIONAL | FLAG_EXT_LEN } else { FLAG_OPTIONAL }
+}
 
-                let mask_len = self.inner[0] as usize;
-                assert!(mask_len <= 128);
-                let byte_len = (mask_len+15) / 8;
-                if self.inner.len() < byte_len {
-                    self.error = true;
-                    return Some(Err(BgpError::BadLength));
-                }
-                let slice = &self.inner[..byte_len];
-                let nlri = $nlri{inner: slice};
-                self.inner = &self.inner[byte_len..];
-                Some(Ok(nlri))
-            }
+/// The offset of the NLRI in an MP_REACH_NLRI value.
+///
+/// RFC 4760 reserves the octet following the next hop. RFC 2858 used it
+/// for the number of SNPAs, each an octet giving its length in
+/// semi-octets followed by the SNPA, and these are skipped. Fails with
+/// `BgpError::BadLength` if they do not fit in the value.
+fn nlri_offset(value: &[u8]) -> Result<usize> {
+    let reserved = 4 + value.at(3)? as usize;
+    let mut offset = reserved + 1;
+    for _ in 0..value.at(reserved)? {
+        let semi_octets = value.at(offset)? as usize;
+        offset += 1 + (semi_octets + 1) / 2;
+    }
+    if offset > value.len() {
+        return Err(BgpError::BadLength);
+    }
+    Ok(offset)
+}
+
+/// The number of SNPAs, from the octet following the next hop.
+fn snpa_count(value: &[u8]) -> u8 {
+    value.at(4 + value.at(3).unwrap_or(0) as usize).unwrap_or(0)
+}
+
+fn family_afi(value: &[u8]) -> Afi {
+    Afi::from((value[0] as u16) << 8 | value[1] as u16)
+}
+
+/// The next hop of an MP_REACH_NLRI, typed by its length rather than by
+/// the address family of the NLRI: RFC 8950 allows IPv6 next hops for
+/// IPv4 NLRI.
+#[derive(Debug)]
+pub enum Nexthop<'a> {
+    /// A 4 octet IPv4 address.
+    Ipv4(Ipv4Nexthop<'a>),
+    /// A 16 octet IPv6 global address, optionally followed by a 16 octet
+    /// link-local address.
+    Ipv6(Ipv6Nexthop<'a>),
+    /// A VPN next hop (SAFI 128, RFC 4364/4659): a route distinguisher
+    /// of zeros followed by an IPv4 or IPv6 address, 12, 24 or 48 octets.
+    Vpn(VpnNexthop<'a>),
+    /// Any other length.
+    Other(&'a [u8]),
+}
+
+impl<'a> Nexthop<'a> {
+    pub fn from_bytes(bytes: &'a [u8]) -> Nexthop<'a> {
+        match bytes.len() {
+            4 => Nexthop::Ipv4(Ipv4Nexthop{inner: bytes}),
+            16 | 32 => Nexthop::Ipv6(Ipv6Nexthop{inner: bytes}),
+            12 | 24 | 48 => Nexthop::Vpn(VpnNexthop{inner: bytes}),
+            _ => Nexthop::Other(bytes),
         }
+    }
 
-        impl<'a> fmt::Debug for $nlri_iter<'a> {
-            fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-                fmt.debug_list().entries(self.clone()).finish()
-            }
+    /// The next hop as it appears in the attribute.
+    pub fn bytes(&self) -> &'a [u8] {
+        match *self {
+            Nexthop::Ipv4(ref nexthop) => nexthop.inner,
+            Nexthop::Ipv6(ref nexthop) => nexthop.inner,
+            Nexthop::Vpn(ref nexthop) => nexthop.inner,
+            Nexthop::Other(bytes) => bytes,
         }
+    }
 
+    /// The next hop address with any route distinguisher stripped: the
+    /// IPv4 address, or the global IPv6 address.
+    pub fn addr(&self) -> Option<IpAddrBytes<'a>> {
+        match *self {
+            Nexthop::Ipv4(ref nexthop) => Some(nexthop.addr()),
+            Nexthop::Ipv6(ref nexthop) => Some(nexthop.global_addr()),
+            Nexthop::Vpn(ref nexthop) => nexthop.address().addr(),
+            Nexthop::Other(_) => None,
+        }
+    }
+
+    /// The IPv4 next hop, with any route distinguisher stripped.
+    pub fn ipv4(&self) -> Option<Ipv4Nexthop<'a>> {
+        match *self {
+            Nexthop::Ipv4(ref nexthop) => Some(Ipv4Nexthop{inner: nexthop.inner}),
+            Nexthop::Vpn(ref nexthop) => nexthop.address().ipv4(),
+            _ => None,
+        }
+    }
+
+    /// The IPv6 next hop, with any route distinguisher stripped.
+    pub fn ipv6(&self) -> Option<Ipv6Nexthop<'a>> {
+        match *self {
+            Nexthop::Ipv6(ref nexthop) => Some(Ipv6Nexthop{inner: nexthop.inner}),
+            Nexthop::Vpn(ref nexthop) => nexthop.address().ipv6(),
+            _ => None,
+        }
     }
 }
 
-impl_reach_ip_nlri!(Ipv4ReachNlri, Ipv4UnreachNlri, Ipv4Nlri, Ipv4NlriIter, Ipv4Nexthop, Ipv4Prefix);
+pub struct VpnNexthop<'a> {
+    inner: &'a [u8],
+}
+
+impl<'a> VpnNexthop<'a> {
+    /// The route distinguisher preceding the address, which RFC 4364 and
+    /// RFC 4659 require to be zero.
+    pub fn route_distinguisher(&self) -> RouteDistinguisher<'a> {
+        // `Nexthop::from_bytes` only makes VPN next hops of 12, 24 or 48
+        // octets, so the fallback is never taken
+        self.inner.slice(0, 8).and_then(RouteDistinguisher::from_bytes).unwrap_or(RouteDistinguisher(&[0; 8]))
+    }
+
+    /// The next hop address with the route distinguisher stripped. For
+    /// the 48 octet form this is the global IPv6 address only.
+    pub fn address(&self) -> Nexthop<'a> {
+        match self.inner.len() {
+            48 => Nexthop::Ipv6(Ipv6Nexthop{inner: &self.inner[8..24]}),
+            _ => Nexthop::from_bytes(&self.inner[8..]),
+        }
+    }
+
+    /// The link-local IPv6 address of the 48 octet form.
+    pub fn link_local(&self) -> Option<Ipv6Nexthop<'a>> {
+        match self.inner.len() {
+            48 => Some(Ipv6Nexthop{inner: &self.inner[32..]}),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> fmt::Debug for VpnNexthop<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("VpnNexthop")
+            .field("route_distinguisher", &self.route_distinguisher())
+            .field("address", &self.address())
+            .finish()
+    }
+}
+
+impl_reach_ip_nlri!(Ipv4ReachNlri, Ipv4UnreachNlri);
+
+pub struct Ipv4Nexthop<'a> {
+    inner: &'a [u8],
+}
 
 impl<'a> Ipv4Nexthop<'a> {
     pub fn to_u32(&self) -> u32 {
@@ -187,63 +569,173 @@ impl<'a> Ipv4Nexthop<'a> {
             | (self.inner[2] as u32) << 8
             | (self.inner[3] as u32)
     }
+
+    pub fn addr(&self) -> IpAddrBytes<'a> {
+        IpAddrBytes::v4(self.inner)
+    }
 }
 
 impl<'a> fmt::Debug for Ipv4Nexthop<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        fmt.write_fmt(format_args!("{}.{}.{}.{}",
-                                   self.inner[0], self.inner[1], self.inner[2], self.inner[3]))
+        self.addr().fmt(fmt)
     }
 }
 
-impl_reach_ip_nlri!(Ipv6ReachNlri, Ipv6UnreachNlri, Ipv6Nlri, Ipv6NlriIter, Ipv6Nexthop, Ipv6Prefix);
+impl_reach_ip_nlri!(Ipv6ReachNlri, Ipv6UnreachNlri);
+impl_reach_ip_nlri!(MtReachNlri, MtUnreachNlri);
+
+pub struct Ipv6Nexthop<'a> {
+    inner: &'a [u8],
+}
 
 impl<'a> Ipv6Nexthop<'a> {
-    pub fn global(&self) -> [u16; 8] {
+    fn segments(bytes: &[u8]) -> [u16; 8] {
         let mut segments = [0u16; 8];
-        let global = &self.inner[..16];
-        for (i, mut segment) in segments.iter_mut().enumerate() {
-            *segment = (global[i * 2] as u16) << 8 | global[i * 2 + 1] as u16;
+        for (i, segment) in segments.iter_mut().enumerate() {
+            *segment = (bytes[i * 2] as u16) << 8 | bytes[i * 2 + 1] as u16;
         }
         segments
     }
 
-    pub fn link_local(&self) -> [u16; 8] {
-        let mut segments = [0u16; 8];
-        let link_local = &self.inner[16..];
-        for (i, mut segment) in segments.iter_mut().enumerate() {
-            *segment = (link_local[i * 2] as u16) << 8 | link_local[i * 2 + 1] as u16;
+    /// The global address.
+    pub fn global(&self) -> [u16; 8] {
+        Ipv6Nexthop::segments(&self.inner[..16])
+    }
+
+    /// The link-local address, present when the next hop is 32 octets.
+    pub fn link_local(&self) -> Option<[u16; 8]> {
+        match self.inner.len() {
+            32 => Some(Ipv6Nexthop::segments(&self.inner[16..])),
+            _ => None,
+        }
+    }
+
+    pub fn global_addr(&self) -> IpAddrBytes<'a> {
+        IpAddrBytes::v6(self.inner)
+    }
+
+    pub fn link_local_addr(&self) -> Option<IpAddrBytes<'a>> {
+        match self.inner.len() {
+            32 => Some(IpAddrBytes::v6(&self.inner[16..])),
+            _ => None,
         }
-        segments
     }
 }
 
+/// Formats the global address, followed by `/` and the link-local
+/// address if present.
 impl<'a> fmt::Debug for Ipv6Nexthop<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        assert_eq!(self.inner.len(), 32);
-        let (global, link_local) = self.inner.split_at(16);
-        fmt.write_fmt(format_args!("{:02x}{:02x}:{:02x}{:02x}:{:02x}{:02x}:{:02x}{:02x}:{:02x}{:02x}:{:02x}{:02x}:{:02x}{:02x}:{:02x}{:02x}/{:02x}{:02x}:{:02x}{:02x}:{:02x}{:02x}:{:02x}{:02x}:{:02x}{:02x}:{:02x}{:02x}:{:02x}{:02x}:{:02x}{:02x}",
-                                   global[0], global[1], global[2], global[3],
-                                   global[4], global[5], global[6], global[7],
-                                   global[8], global[9], global[10], global[11],
-                                   global[12], global[13], global[14], global[15],
+        write!(fmt, "{}", self.global_addr())?;
+        if let Some(link_local) = self.link_local_addr() {
+            write!(fmt, "/{}", link_local)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct NextHopOnlyReachNlri<'a> {
+    inner: &'a [u8],
+    flags: u8,
+}
+
+impl<'a> NextHopOnlyReachNlri<'a> {
+    /// The next hop address, 4 octets for IPv4 or 16 or 32 octets for IPv6.
+    pub fn nexthop(&self) -> &'a [u8] {
+        &self.inner[1..]
+    }
+
+    /// The next hop, if it is an IPv4 address.
+    pub fn ipv4(&self) -> Option<Ipv4Nexthop<'a>> {
+        match self.nexthop().len() {
+            4 => Some(Ipv4Nexthop{inner: self.nexthop()}),
+            _ => None,
+        }
+    }
 
-                                   link_local[0], link_local[1], link_local[2], link_local[3],
-                                   link_local[4], link_local[5], link_local[6], link_local[7],
-                                   link_local[8], link_local[9], link_local[10], link_local[11],
-                                   link_local[12], link_local[13], link_local[14], link_local[15],
-        ))
+    /// The next hop, if it is an IPv6 global address, optionally followed
+    /// by a link-local address.
+    pub fn ipv6(&self) -> Option<Ipv6Nexthop<'a>> {
+        match self.nexthop().len() {
+            16 | 32 => Some(Ipv6Nexthop{inner: self.nexthop()}),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> fmt::Debug for NextHopOnlyReachNlri<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("NextHopOnlyReachNlri")
+            .field("nexthop", &self.nexthop())
+            .finish()
     }
 }
 
 #[derive(Debug)]
 pub struct OtherReachNlri<'a> {
-    inner: &'a [u8]
+    inner: &'a [u8],
+    flags: u8,
+}
+
+impl<'a> OtherReachNlri<'a> {
+    pub fn afi(&self) -> Afi {
+        Afi::from((self.inner[0] as u16) << 8 | self.inner[1] as u16)
+    }
+
+    pub fn safi(&self) -> Safi {
+        Safi::from(self.inner[2])
+    }
+
+    pub fn afi_safi(&self) -> AfiSafi {
+        AfiSafi::new(self.afi(), self.safi())
+    }
+
+    /// The next hop as it appears in the attribute, whatever its format.
+    pub fn nexthop_bytes(&self) -> &'a [u8] {
+        let nexthop_len = self.inner.at(3).unwrap_or(0) as usize;
+        self.inner.slice(4, nexthop_len).unwrap_or_default()
+    }
+
+    pub fn nexthop(&self) -> Nexthop<'a> {
+        Nexthop::from_bytes(self.nexthop_bytes())
+    }
+
+    /// The number of legacy RFC 2858 SNPAs, skipped to find the NLRI.
+    pub fn snpa_count(&self) -> u8 {
+        snpa_count(self.inner)
+    }
+
+    /// The undecoded NLRI following the next hop, reserved octet and any
+    /// SNPAs.
+    pub fn nlri_bytes(&self) -> &'a [u8] {
+        let offset = nlri_offset(self.inner).unwrap_or(self.inner.len());
+        &self.inner[offset..]
+    }
 }
 
 #[derive(Debug)]
 pub struct OtherUnreachNlri<'a> {
-    inner: &'a [u8]
+    inner: &'a [u8],
+    flags: u8,
+}
+
+impl<'a> OtherUnreachNlri<'a> {
+    pub fn afi(&self) -> Afi {
+        Afi::from((self.inner[0] as u16) << 8 | self.inner[1] as u16)
+    }
+
+    pub fn safi(&self) -> Safi {
+        Safi::from(self.inner[2])
+    }
+
+    pub fn afi_safi(&self) -> AfiSafi {
+        AfiSafi::new(self.afi(), self.safi())
+    }
+
+    /// The undecoded NLRI following the AFI and SAFI.
+    pub fn nlri_bytes(&self) -> &'a [u8] {
+        &self.inner[3..]
+    }
 }
 
 
@@ -251,7 +743,7 @@ pub struct OtherUnreachNlri<'a> {
 mod test {
 
     use super::*;
-    use types::*;
+    use core::fmt::Write;
     #[test]
     fn parse_mp_nlri_multicast() {
 	      // path_attrs: [Origin(Igp),
@@ -278,9 +770,191 @@ mod test {
                       19,    // prefixlength 2
                       212, 77, 0 // prefix 2
         ];
-        let mut iter = Ipv4NlriIter{inner: bytes, error: false};
-        assert_eq!(iter.next().unwrap().unwrap().prefix(), Ipv4Prefix{inner: &[22, 193, 43, 128]});
-        assert_eq!(iter.next().unwrap().unwrap().prefix(), Ipv4Prefix{inner: &[19, 212, 77, 0]});
+        let mut iter = NlriIter::with_family(bytes, AFI_IPV4, SAFI_MULTICAST, false);
+        let nlri = iter.next().unwrap().unwrap();
+        assert!(nlri.afi == AFI_IPV4 && nlri.safi == SAFI_MULTICAST);
+        assert_eq!(nlri.prefix, Prefix::Ipv4(Ipv4Prefix::new(22, &[193, 43, 128])));
+        assert_eq!(iter.next().unwrap().unwrap().prefix, Prefix::Ipv4(Ipv4Prefix::new(19, &[212, 77, 0])));
         assert!(iter.next().is_none());
     }
+
+    #[test]
+    fn parse_rib_mp_reach() {
+        // abbreviated: next hop 192.0.2.1
+        match MpReachNlri::from_rib_value(&[4, 192, 0, 2, 1]) {
+            Ok(MpReachNlri::NextHopOnly(reach)) => {
+                assert_eq!(reach.ipv4().unwrap().to_u32(), 0xc0000201);
+                assert!(reach.ipv6().is_none());
+            }
+            x => panic!("expected NextHopOnly, got {:?}", x)
+        }
+        // full encoding, as written by some dumpers, is read with from_value
+        let full = [0, 1, 1, 4, 192, 0, 2, 1, 0, 24, 198, 51, 100];
+        assert!(matches!(MpReachNlri::from_rib_value(&full), Err(BgpError::BadLength)));
+        match MpReachNlri::from_value(&full) {
+            Ok(MpReachNlri::Ipv4Unicast(reach)) => {
+                assert_eq!(reach.nexthop().ipv4().unwrap().to_u32(), 0xc0000201);
+                assert_eq!(reach.nlris().count(), 1);
+            }
+            x => panic!("expected Ipv4Unicast, got {:?}", x)
+        }
+        // a full BGP-LS value of 65 octets starts with 0x40 (AFI 16388),
+        // which as a next hop length would account for the rest
+        let mut bgp_ls = [0; 65];
+        bgp_ls[..8].copy_from_slice(&[0x40, 0x04, 71, 4, 192, 0, 2, 1]);
+        assert!(matches!(MpReachNlri::from_rib_value(&bgp_ls), Err(BgpError::BadLength)));
+        assert!(matches!(MpReachNlri::from_value(&bgp_ls), Ok(MpReachNlri::Other(_))));
+        // IPv4 NLRI with an IPv6 next hop (RFC 8950)
+        match MpReachNlri::from_value(&[0, 1, 1, 16, 0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0,
+                                        0, 0, 0, 0, 0, 0, 0, 1, 0, 24, 198, 51, 100]) {
+            Ok(MpReachNlri::Ipv4Unicast(reach)) => {
+                let nexthop = reach.nexthop();
+                assert!(nexthop.ipv4().is_none());
+                assert_eq!(nexthop.ipv6().unwrap().global(), [0x2001, 0xdb8, 0, 0, 0, 0, 0, 1]);
+                assert!(nexthop.ipv6().unwrap().link_local().is_none());
+                assert_eq!(reach.nlris().count(), 1);
+            }
+            x => panic!("expected Ipv4Unicast, got {:?}", x)
+        }
+        // VPN-IPv4 with an RD-prefixed next hop
+        match MpReachNlri::from_value(&[0, 1, 128, 12, 0, 0, 0, 0, 0, 0, 0, 0, 192, 0, 2, 1, 0]) {
+            Ok(MpReachNlri::Ipv4Vpn(reach)) => {
+                match reach.nexthop() {
+                    Nexthop::Vpn(vpn) => {
+                        assert_eq!(vpn.route_distinguisher(), RouteDistinguisher(&[0; 8]));
+                        assert!(vpn.link_local().is_none());
+                    }
+                    x => panic!("expected Vpn, got {:?}", x)
+                }
+                assert_eq!(reach.nexthop().ipv4().unwrap().to_u32(), 0xc0000201);
+                assert!(reach.nlris().next().is_none());
+            }
+            x => panic!("expected Ipv4Vpn, got {:?}", x)
+        }
+        // IPv6 next hop with a link-local address
+        match MpReachNlri::from_value(&[0, 2, 1, 32, 0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0,
+                                        0, 0, 0, 0, 0, 0, 0, 1, 0xfe, 0x80, 0, 0, 0, 0, 0,
+                                        0, 0, 0, 0, 0, 0, 0, 0, 2, 0]) {
+            Ok(MpReachNlri::Ipv6Unicast(reach)) => {
+                let nexthop = reach.nexthop().ipv6().unwrap();
+                assert_eq!(nexthop.global(), [0x2001, 0xdb8, 0, 0, 0, 0, 0, 1]);
+                assert_eq!(nexthop.link_local(), Some([0xfe80, 0, 0, 0, 0, 0, 0, 2]));
+                let mut buf = FmtBuf::new();
+                write!(buf, "{:?}", nexthop).unwrap();
+                assert_eq!(buf.as_bytes(), b"2001:db8::1/fe80::2");
+                assert_eq!(reach.nexthop().addr(), IpAddrBytes::from_bytes(&reach.nexthop().bytes()[..16]).ok());
+            }
+            x => panic!("expected Ipv6Unicast, got {:?}", x)
+        }
+        assert!(MpReachNlri::from_rib_value(&[]).is_err());
+        assert!(MpReachNlri::from_rib_value(&[16, 0x20, 0x01]).is_err());
+
+        assert_eq!(MpReachNlri::from_rib_value(&[4, 192, 0, 2, 1]).unwrap().afi_safi(), None);
+        assert_eq!(MpReachNlri::from_value(&[0, 2, 2, 0, 0]).unwrap().afi_safi(), Some(IPV6_MULTICAST));
+        assert_eq!(MpUnreachNlri::from_value(&[0, 1, 1]).unwrap().afi_safi(), IPV4_UNICAST);
+    }
+
+    #[test]
+    fn skip_legacy_snpas() {
+        // next hop 192.0.2.1, one SNPA of 3 semi-octets, then 198.51.100.0/24
+        let value = [0, 1, 1, 4, 192, 0, 2, 1, 1, 3, 0xab, 0xc0, 24, 198, 51, 100];
+        match MpReachNlri::from_value(&value) {
+            Ok(MpReachNlri::Ipv4Unicast(reach)) => {
+                assert_eq!(reach.snpa_count(), 1);
+                let mut nlris = reach.nlris();
+                let nlri = nlris.next().unwrap().unwrap();
+                assert_eq!(nlri.prefix, Prefix::Ipv4(Ipv4Prefix::new(24, &[198, 51, 100])));
+                assert!(nlris.next().is_none());
+            }
+            x => panic!("expected Ipv4Unicast, got {:?}", x)
+        }
+        match MpReachNlri::from_value(&[0, 1, 133, 4, 192, 0, 2, 1, 1, 2, 0xab]) {
+            Ok(MpReachNlri::Other(reach)) => {
+                assert_eq!(reach.snpa_count(), 1);
+                assert!(reach.nlri_bytes().is_empty());
+            }
+            x => panic!("expected Other, got {:?}", x)
+        }
+
+        // the SNPA claims more octets than there are
+        assert!(matches!(MpReachNlri::from_value(&value[..11]), Err(BgpError::BadLength)));
+        assert!(matches!(MpReachNlri::from_value(&[0, 1, 1, 4, 192, 0, 2, 1, 2, 1, 0xa0]),
+                         Err(BgpError::BadLength)));
+    }
+
+    #[test]
+    fn parse_multi_topology() {
+        // MT-IPv4 unicast via 192.0.2.1: 198.51.100.0/24 in topology 2
+        let value = [0, 29, 1, 4, 192, 0, 2, 1, 0, 0x00, 0x02, 24, 198, 51, 100];
+        match MpReachNlri::from_value(&value) {
+            Ok(MpReachNlri::MultiTopology(reach)) => {
+                let mut nlris = reach.nlris();
+                let nlri = nlris.next().unwrap().unwrap();
+                assert_eq!(nlri.afi, AFI_MT_IPV4);
+                assert_eq!(nlri.mt_id, Some(2));
+                assert_eq!(nlri.prefix, Prefix::Ipv4(Ipv4Prefix::new(24, &[198, 51, 100])));
+                assert!(nlris.next().is_none());
+            }
+            x => panic!("expected MultiTopology, got {:?}", x)
+        }
+
+        // MT-IPv6 unicast withdrawal of 2001:db8::/32 in topology 3, then
+        // a topology identifier cut short
+        match MpUnreachNlri::from_value(&[0, 30, 1, 0x00, 0x03, 32, 0x20, 0x01, 0x0d, 0xb8, 0x00]) {
+            Ok(MpUnreachNlri::MultiTopology(unreach)) => {
+                let mut nlris = unreach.nlris();
+                let nlri = nlris.next().unwrap().unwrap();
+                assert_eq!(nlri.mt_id, Some(3));
+                assert_eq!(nlri.prefix, Prefix::Ipv6(Ipv6Prefix::new(32, &[0x20, 0x01, 0x0d, 0xb8])));
+                assert!(matches!(nlris.next(), Some(Err(BgpError::BadLength))));
+                assert!(nlris.next().is_none());
+            }
+            x => panic!("expected MultiTopology, got {:?}", x)
+        }
+    }
+
+    #[test]
+    fn parse_softwire() {
+        // 4over6: 198.51.100.0/24 via 2001:db8::1
+        let value = [0, 1, 67, 16, 0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1,
+                     0, 24, 198, 51, 100];
+        match MpReachNlri::from_value(&value) {
+            Ok(MpReachNlri::Ipv4Over6(reach)) => {
+                assert!(reach.nexthop().ipv6().is_some());
+                let nlri = reach.nlris().next().unwrap().unwrap();
+                assert_eq!(nlri.safi, SAFI_4OVER6);
+                assert_eq!(nlri.prefix, Prefix::Ipv4(Ipv4Prefix::new(24, &[198, 51, 100])));
+            }
+            x => panic!("expected Ipv4Over6, got {:?}", x)
+        }
+        match MpUnreachNlri::from_value(&[0, 2, 68, 32, 0x20, 0x01, 0x0d, 0xb8]) {
+            Ok(MpUnreachNlri::Ipv6Over4(unreach)) => {
+                let nlri = unreach.nlris().next().unwrap().unwrap();
+                assert_eq!(nlri.prefix, Prefix::Ipv6(Ipv6Prefix::new(32, &[0x20, 0x01, 0x0d, 0xb8])));
+            }
+            x => panic!("expected Ipv6Over4, got {:?}", x)
+        }
+    }
+
+    #[test]
+    fn other_family_parts() {
+        // BGP-LS via 192.0.2.1, with a made up NLRI
+        let value = [0x40, 0x04, 71, 4, 192, 0, 2, 1, 0, 0x00, 0x01, 0x00, 0x00];
+        match MpReachNlri::from_value(&value) {
+            Ok(MpReachNlri::Other(reach)) => {
+                assert_eq!(reach.afi_safi(), AfiSafi::new(AFI_BGP_LS, SAFI_LS));
+                assert_eq!(reach.nexthop_bytes(), &[192, 0, 2, 1]);
+                assert_eq!(reach.nlri_bytes(), &[0x00, 0x01, 0x00, 0x00]);
+            }
+            x => panic!("expected Other, got {:?}", x)
+        }
+        match MpUnreachNlri::from_value(&[0x40, 0x04, 71, 0x00, 0x01, 0x00, 0x00]) {
+            Ok(MpUnreachNlri::Other(unreach)) => {
+                assert_eq!(unreach.afi(), AFI_BGP_LS);
+                assert_eq!(unreach.safi(), SAFI_LS);
+                assert_eq!(unreach.nlri_bytes(), &[0x00, 0x01, 0x00, 0x00]);
+            }
+            x => panic!("expected Other, got {:?}", x)
+        }
+    }
 }
@@ -0,0 +1,254 @@
+use super::*;
+use core::fmt;
+
+/// The BGP Community Container attribute code registered for Wide BGP
+/// Communities (draft-ietf-idr-wide-bgp-communities).
+pub const ATTR_COMMUNITY_CONTAINER: u8 = 34;
+
+/// Container type of a Wide BGP Community.
+pub const CONTAINER_WIDE_COMMUNITY: u16 = 1;
+
+define_path_attr!(WideCommunities, doc="BGP Community Container attribute, carrying Wide BGP Communities.
+
+The draft is not final, so the attribute is decoded as `PathAttr::Other`.
+Use `Other::wide_communities` to opt in.");
+
+impl_try_from_attr!(WideCommunities, 34, _);
+impl_from_value!(WideCommunities, 34, FLAGS_OPTIONAL_TRANSITIVE, _);
+
+impl<'a> WideCommunities<'a> {
+    pub fn containers(&self) -> CommunityContainerIter<'a> {
+        CommunityContainerIter {
+            inner: self.value(),
+            error: false,
+        }
+    }
+}
+
+impl<'a> fmt::Debug for WideCommunities<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_list().entries(self.containers()).finish()
+    }
+}
+
+impl<'a> Other<'a> {
+    /// Decodes a BGP Community Container attribute. Fails with
+    /// `BgpError::Invalid` for any other attribute code.
+    pub fn wide_communities(&self) -> Result<WideCommunities<'a>> {
+        if self.code() != ATTR_COMMUNITY_CONTAINER {
+            return Err(BgpError::Invalid);
+        }
+        Ok(WideCommunities { flags: self.flags(), code: self.code(), value: self.value() })
+    }
+}
+
+/// One container: type, flags, a type specific octet and a value.
+pub struct CommunityContainer<'a> {
+    inner: &'a [u8],
+}
+
+impl<'a> CommunityContainer<'a> {
+    pub fn container_type(&self) -> u16 {
+        (self.inner[0] as u16) << 8 | self.inner[1] as u16
+    }
+
+    pub fn flags(&self) -> u8 {
+        self.inner[2]
+    }
+
+    /// The octet following the flags, the hop count for Wide Communities.
+    pub fn hop_count(&self) -> u8 {
+        self.inner[3]
+    }
+
+    pub fn value(&self) -> &'a [u8] {
+        &self.inner[6..]
+    }
+
+    /// Decodes the container as a Wide BGP Community. Fails with
+    /// `BgpError::Invalid` for other container types.
+    pub fn wide_community(&self) -> Result<WideCommunity<'a>> {
+        if self.container_type() != CONTAINER_WIDE_COMMUNITY {
+            return Err(BgpError::Invalid);
+        }
+        if self.value().len() < 12 {
+            return Err(BgpError::BadLength);
+        }
+        Ok(WideCommunity { inner: self.value() })
+    }
+}
+
+impl<'a> fmt::Debug for CommunityContainer<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self.wide_community() {
+            Ok(community) => community.fmt(fmt),
+            Err(_) => fmt.debug_struct("CommunityContainer")
+                .field("type", &self.container_type())
+                .field("flags", &self.flags())
+                .field("value", &self.value())
+                .finish(),
+        }
+    }
+}
+
+#[derive(Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct CommunityContainerIter<'a> {
+    inner: &'a [u8],
+    error: bool,
+}
+
+impl<'a> Iterator for CommunityContainerIter<'a> {
+    type Item = Result<CommunityContainer<'a>>;
+
+    fn next(&mut self) -> Option<Result<CommunityContainer<'a>>> {
+        if self.error || self.inner.is_empty() {
+            return None;
+        }
+        if self.inner.len() < 6 {
+            self.error = true;
+            return Some(Err(BgpError::BadLength));
+        }
+        let end = 6 + ((self.inner[4] as usize) << 8 | self.inner[5] as usize);
+        if self.inner.len() < end {
+            self.error = true;
+            return Some(Err(BgpError::BadLength));
+        }
+        let container = CommunityContainer { inner: &self.inner[..end] };
+        self.inner = &self.inner[end..];
+        Some(Ok(container))
+    }
+}
+
+/// A Wide BGP Community: a community value, the AS that added it, the AS
+/// it is to be interpreted in, and a list of atoms.
+pub struct WideCommunity<'a> {
+    inner: &'a [u8],
+}
+
+impl<'a> WideCommunity<'a> {
+    fn u32_at(&self, offset: usize) -> u32 {
+        (self.inner[offset] as u32) << 24
+            | (self.inner[offset + 1] as u32) << 16
+            | (self.inner[offset + 2] as u32) << 8
+            | (self.inner[offset + 3] as u32)
+    }
+
+    pub fn community(&self) -> u32 {
+        self.u32_at(0)
+    }
+
+    pub fn source_as(&self) -> u32 {
+        self.u32_at(4)
+    }
+
+    pub fn context_as(&self) -> u32 {
+        self.u32_at(8)
+    }
+
+    pub fn atoms(&self) -> WideCommunityAtomIter<'a> {
+        WideCommunityAtomIter {
+            tlvs: TlvIter::with_widths(&self.inner[12..], 1, 2),
+        }
+    }
+}
+
+impl<'a> fmt::Debug for WideCommunity<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_struct("WideCommunity")
+            .field("community", &self.community())
+            .field("source_as", &self.source_as())
+            .field("context_as", &self.context_as())
+            .field("atoms", &self.atoms())
+            .finish()
+    }
+}
+
+/// The role of a Wide Community TLV.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum WideCommunityTlv {
+    Target,
+    ExcludeTarget,
+    Parameter,
+    Unknown(u8),
+}
+
+impl From<u8> for WideCommunityTlv {
+    fn from(value: u8) -> WideCommunityTlv {
+        match value {
+            1 => WideCommunityTlv::Target,
+            2 => WideCommunityTlv::ExcludeTarget,
+            3 => WideCommunityTlv::Parameter,
+            n => WideCommunityTlv::Unknown(n),
+        }
+    }
+}
+
+/// A Target, Exclude Target or Parameter TLV with its undecoded atoms.
+#[derive(Debug)]
+pub struct WideCommunityAtom<'a> {
+    pub kind: WideCommunityTlv,
+    pub value: &'a [u8],
+}
+
+#[derive(Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct WideCommunityAtomIter<'a> {
+    tlvs: TlvIter<'a>,
+}
+
+impl<'a> fmt::Debug for WideCommunityAtomIter<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_list().entries(self.clone()).finish()
+    }
+}
+
+impl<'a> Iterator for WideCommunityAtomIter<'a> {
+    type Item = Result<WideCommunityAtom<'a>>;
+
+    fn next(&mut self) -> Option<Result<WideCommunityAtom<'a>>> {
+        Some(self.tlvs.next()?.map(|tlv| WideCommunityAtom {
+            kind: WideCommunityTlv::from(tlv.tlv_type as u8),
+            value: tlv.value,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_wide_communities() {
+        let bytes = &[0xc0, 0x22, 0x1f,
+                      0x00, 0x01, 0x80, 0x00, 0x00, 0x13, // wide community, 19 octets
+                      0xff, 0xff, 0x00, 0x01,             // community
+                      0x00, 0x00, 0xfd, 0xe8,             // source AS 65000
+                      0x00, 0x00, 0xfd, 0xe9,             // context AS 65001
+                      0x01, 0x00, 0x04,                   // target TLV
+                      0x01, 0x00, 0x01, 0x00,             // one atom
+                      0x00, 0x02, 0x00, 0x00, 0x00, 0x00, // container type 2, empty
+        ];
+        let mut attrs = PathAttrIter::new(bytes, true);
+        let other = match attrs.next() {
+            Some(Ok(PathAttr::Other(other))) => other,
+            x => panic!("expected Other, got {:?}", x)
+        };
+        let wide = other.wide_communities().unwrap();
+        let mut containers = wide.containers();
+        let community = containers.next().unwrap().unwrap().wide_community().unwrap();
+        assert_eq!(community.community(), 0xffff0001);
+        assert_eq!(community.source_as(), 65000);
+        assert_eq!(community.context_as(), 65001);
+        let mut atoms = community.atoms();
+        let atom = atoms.next().unwrap().unwrap();
+        assert_eq!(atom.kind, WideCommunityTlv::Target);
+        assert_eq!(atom.value, &[0x01, 0x00, 0x01, 0x00]);
+        assert!(atoms.next().is_none());
+
+        let container = containers.next().unwrap().unwrap();
+        assert_eq!(container.container_type(), 2);
+        assert!(matches!(container.wide_community(), Err(BgpError::Invalid)));
+        assert!(containers.next().is_none());
+    }
+}
@@ -0,0 +1,165 @@
+use types::*;
+use super::path_attr::*;
+use super::withdrawn_routes::*;
+use super::nlri::*;
+
+/// A single route change carried by an UPDATE.
+#[derive(Debug)]
+pub enum RouteEvent<'a> {
+    /// The prefix is reachable with the UPDATE's path attributes.
+    Announce {
+        nlri: Nlri<'a>,
+        attrs: PathAttrIter<'a>,
+    },
+    /// The prefix is no longer reachable.
+    Withdraw {
+        nlri: Nlri<'a>,
+    },
+    /// An MP_REACH_NLRI of an address family whose prefixes are not
+    /// decoded here, such as EVPN or flow specification.
+    OtherAnnounce {
+        reach: OtherReachNlri<'a>,
+        attrs: PathAttrIter<'a>,
+    },
+    /// An MP_UNREACH_NLRI of an address family whose prefixes are not
+    /// decoded here.
+    OtherWithdraw {
+        unreach: OtherUnreachNlri<'a>,
+    },
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Stage {
+    Withdrawn,
+    MpUnreach,
+    Nlri,
+    MpReach,
+    Done,
+}
+
+/// Iterator over the route changes in an UPDATE: withdrawn routes, then
+/// MP_UNREACH_NLRI prefixes, then NLRI, then MP_REACH_NLRI prefixes.
+/// MP_REACH_NLRI and MP_UNREACH_NLRI are read with the ADD-PATH and label
+/// settings of the path attribute iterator. Address families whose
+/// prefixes are not decoded give one `OtherAnnounce` or `OtherWithdraw`
+/// per attribute.
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct Routes<'a> {
+    withdrawn: WithdrawnRoutes<'a>,
+    attrs: PathAttrIter<'a>,
+    nlris: NlriIter<'a>,
+    scan: PathAttrIter<'a>,
+    mp: Option<NlriIter<'a>>,
+    stage: Stage,
+}
+
+impl<'a> Routes<'a> {
+    pub fn new(withdrawn: WithdrawnRoutes<'a>, attrs: PathAttrIter<'a>, nlris: NlriIter<'a>) -> Routes<'a> {
+        Routes {
+            withdrawn,
+            scan: attrs.clone(),
+            attrs,
+            nlris,
+            mp: None,
+            stage: Stage::Withdrawn,
+        }
+    }
+
+    /// Whether AS numbers are read as four octets, see
+    /// `PathAttrIter::four_byte_asn`.
+    pub fn four_byte_asn(&self) -> bool {
+        self.attrs.four_byte_asn()
+    }
+
+    /// Whether routes are read with Path Identifiers. The session's
+    /// ADD-PATH setting applies alike to the withdrawn routes, the NLRI
+    /// field and the MP_REACH_NLRI and MP_UNREACH_NLRI attributes.
+    pub fn add_paths(&self) -> bool {
+        self.nlris.add_paths()
+    }
+
+    fn fail(&mut self, err: BgpError) -> Option<Result<RouteEvent<'a>>> {
+        self.stage = Stage::Done;
+        Some(Err(err))
+    }
+
+    /// Finds the next MP_REACH_NLRI (code 14) or MP_UNREACH_NLRI (code 15)
+    /// attribute and starts iterating over its prefixes, or moves on to
+    /// the next stage when there are no more. Returns an event only for an
+    /// address family whose prefixes are not decoded, or on error.
+    fn next_mp(&mut self, code: u8) -> Option<Result<RouteEvent<'a>>> {
+        let (add_paths, max_labels) = (self.attrs.add_paths(), self.attrs.max_labels());
+        while let Some(raw) = self.scan.next_raw() {
+            let raw = match raw {
+                Ok(raw) => raw,
+                Err(err) => return self.fail(err),
+            };
+            if raw.code != code {
+                continue;
+            }
+            self.mp = match code {
+                14 => match MpReachNlri::from_raw(raw, add_paths, max_labels) {
+                    Ok(MpReachNlri::Other(reach)) =>
+                        return Some(Ok(RouteEvent::OtherAnnounce { reach, attrs: self.attrs.clone() })),
+                    Ok(reach) => reach.nlris(),
+                    Err(err) => return self.fail(err),
+                },
+                _ => match MpUnreachNlri::from_raw(raw, add_paths) {
+                    Ok(MpUnreachNlri::Other(unreach)) => return Some(Ok(RouteEvent::OtherWithdraw { unreach })),
+                    Ok(unreach) => unreach.nlris(),
+                    Err(err) => return self.fail(err),
+                },
+            };
+            if self.mp.is_some() {
+                return None;
+            }
+        }
+        self.mp = None;
+        self.stage = if code == 15 { Stage::Nlri } else { Stage::Done };
+        None
+    }
+}
+
+impl<'a> Iterator for Routes<'a> {
+    type Item = Result<RouteEvent<'a>>;
+
+    fn next(&mut self) -> Option<Result<RouteEvent<'a>>> {
+        loop {
+            match self.stage {
+                Stage::Withdrawn => match self.withdrawn.next() {
+                    Some(Ok(nlri)) => return Some(Ok(RouteEvent::Withdraw { nlri })),
+                    Some(Err(err)) => return self.fail(err),
+                    None => self.stage = Stage::MpUnreach,
+                },
+                Stage::Nlri => match self.nlris.next() {
+                    Some(Ok(nlri)) => return Some(Ok(RouteEvent::Announce {
+                        nlri,
+                        attrs: self.attrs.clone(),
+                    })),
+                    Some(Err(err)) => return self.fail(err),
+                    None => {
+                        self.scan = self.attrs.clone();
+                        self.stage = Stage::MpReach;
+                    }
+                },
+                Stage::MpUnreach | Stage::MpReach => {
+                    let stage = self.stage;
+                    match self.mp.as_mut().and_then(|mp| mp.next()) {
+                        Some(Ok(nlri)) => return Some(Ok(if stage == Stage::MpUnreach {
+                            RouteEvent::Withdraw { nlri }
+                        } else {
+                            RouteEvent::Announce { nlri, attrs: self.attrs.clone() }
+                        })),
+                        Some(Err(err)) => return self.fail(err),
+                        None => (),
+                    }
+                    let code = if stage == Stage::MpUnreach { 15 } else { 14 };
+                    if let Some(event) = self.next_mp(code) {
+                        return Some(event);
+                    }
+                }
+                Stage::Done => return None,
+            }
+        }
+    }
+}
@@ -1,50 +1,80 @@
 use types::*;
 use core::fmt;
+use super::nlri::Nlri;
 
 /// This is a variable-length field that contains a list of IP
 /// address prefixes for the routes that are being withdrawn from
 /// service.
 #[derive(Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
 pub struct WithdrawnRoutes<'a> {
     pub inner: &'a [u8],
+    add_paths: bool,
     error: Option<BgpError>,
 }
 
 impl<'a> WithdrawnRoutes<'a> {
     pub fn new(inner: &'a [u8]) -> WithdrawnRoutes<'a> {
         WithdrawnRoutes {
-            inner: inner,
+            inner,
+            add_paths: false,
             error: None,
         }
     }
+
+    /// Expects each route to carry a 4-octet Path Identifier, for
+    /// sessions that negotiated ADD-PATH receive for IPv4 unicast.
+    pub fn with_add_paths(mut self, add_paths: bool) -> WithdrawnRoutes<'a> {
+        self.add_paths = add_paths;
+        self
+    }
+
+    /// Whether each route is read with a Path Identifier.
+    pub fn add_paths(&self) -> bool {
+        self.add_paths
+    }
+
+    fn fail(&mut self, err: BgpError) -> Option<Result<Nlri<'a>>> {
+        self.error = Some(err);
+        Some(Err(err))
+    }
 }
 
 impl<'a> Iterator for WithdrawnRoutes<'a> {
-    type Item = Result<Ipv4Prefix<'a>>;
+    type Item = Result<Nlri<'a>>;
 
-    fn next(&mut self) -> Option<Result<Ipv4Prefix<'a>>> {
+    fn next(&mut self) -> Option<Result<Nlri<'a>>> {
         if self.error.is_some() {
             return None;
         }
-        if self.inner.len() == 0 {
+        if self.inner.is_empty() {
             return None;
         }
-        if self.inner.len() < 1 {
-            let err = BgpError::BadLength;
-            self.error = Some(err);
-            return Some(Err(err));
-        }
-        let mask_len = self.inner[0] as usize;
-        let prefix_len = (mask_len+15) / 8; // length in bytes to represent masklen and ip prefix
-
-        if self.inner.len() < prefix_len {
-            let err = BgpError::BadLength;
-            self.error = Some(err);
-            return Some(Err(err));
-        }
-        let prefix = &self.inner[..prefix_len];
-        self.inner = &self.inner[prefix_len..];
-        Some(Ok(Ipv4Prefix{inner: prefix}))
+        let path_id = if self.add_paths {
+            match self.inner.u32_at(0) {
+                Ok(path_id) => {
+                    self.inner = &self.inner[4..];
+                    Some(path_id)
+                }
+                Err(err) => return self.fail(err),
+            }
+        } else {
+            None
+        };
+        let (prefix, rest) = match Prefix::split(self.inner, false) {
+            Ok(split) => split,
+            Err(err) => return self.fail(err),
+        };
+        self.inner = rest;
+        Some(Ok(Nlri {
+            afi: AFI_IPV4,
+            safi: SAFI_UNICAST,
+            path_id,
+            labels: None,
+            rd: None,
+            mt_id: None,
+            prefix,
+        }))
     }
 }
 
@@ -84,4 +114,55 @@ mod tests {
         let routes = WithdrawnRoutes::new(bytes);
         assert_eq!(routes.count(), 22);
     }
+
+    #[test]
+    fn withdrawn_mask_lengths() {
+        let cases: [(&[u8], &[u8]); 6] = [
+            (&[0], &[0]),
+            (&[1, 0x80], &[1, 0x80]),
+            (&[7, 0xfe], &[7, 0xfe]),
+            (&[8, 10], &[8, 10]),
+            (&[25, 192, 0, 2, 128], &[25, 192, 0, 2, 128]),
+            (&[32, 192, 0, 2, 1], &[32, 192, 0, 2, 1]),
+        ];
+        for &(bytes, expected) in &cases {
+            let mut routes = WithdrawnRoutes::new(bytes);
+            let nlri = routes.next().unwrap().unwrap();
+            assert_eq!(nlri.prefix, Prefix::Ipv4(Ipv4Prefix::new(expected[0], &expected[1..])));
+            assert!(routes.next().is_none());
+        }
+
+        // one octet short of each mask length
+        for &(bytes, _) in &cases[1..] {
+            let mut routes = WithdrawnRoutes::new(&bytes[..bytes.len() - 1]);
+            assert!(matches!(routes.next(), Some(Err(BgpError::BadLength))));
+            assert!(routes.next().is_none());
+        }
+
+        let mut routes = WithdrawnRoutes::new(&[33, 192, 0, 2, 1, 0]);
+        assert!(matches!(routes.next(), Some(Err(BgpError::Invalid))));
+        assert!(routes.next().is_none());
+    }
+
+    #[test]
+    fn withdrawn_add_paths() {
+        let bytes = &[0, 0, 0, 7, 24, 192, 0, 2, // path id 7, 192.0.2.0/24
+                      0, 0, 1, 0, 0]; // path id 256, 0.0.0.0/0
+        let mut routes = WithdrawnRoutes::new(bytes).with_add_paths(true);
+        assert!(routes.add_paths() && !WithdrawnRoutes::new(bytes).add_paths());
+        let nlri = routes.next().unwrap().unwrap();
+        assert_eq!((nlri.path_id, nlri.prefix), (Some(7), Prefix::Ipv4(Ipv4Prefix::new(24, &[192, 0, 2]))));
+        let nlri = routes.next().unwrap().unwrap();
+        assert_eq!((nlri.path_id, nlri.prefix), (Some(256), Prefix::Ipv4(Ipv4Prefix::new(0, &[]))));
+        assert!(routes.next().is_none());
+
+        // without ADD-PATH the path id is read as a /0 and then 0.0.0.7/24
+        let mut routes = WithdrawnRoutes::new(bytes);
+        assert_eq!(routes.next().unwrap().unwrap().prefix, Prefix::Ipv4(Ipv4Prefix::new(0, &[])));
+
+        // truncated path id
+        let mut routes = WithdrawnRoutes::new(&[0, 0, 7]).with_add_paths(true);
+        assert!(matches!(routes.next(), Some(Err(BgpError::BadLength))));
+        assert!(routes.next().is_none());
+    }
 }
@@ -1,15 +1,70 @@
 //! BGP Monitoring Protocol
 
 use bgp;
+use bgp::SessionConfig;
+use bgp::open::Open;
+use bgp::open::OptionalParam;
+use bgp::open::capability::Capability;
+use bgp::update::Update;
+use bgp::update::routes::{Routes, RouteEvent};
 use types::*;
+use core::convert::TryFrom;
+use core::fmt;
 use core::str;
+#[cfg(feature = "stats")]
+use stats::ParserStats;
 
-#[derive(Debug)]
+mod peer_table;
+pub use self::peer_table::*;
+
+#[derive(Debug, Clone, Copy)]
 pub struct PerPeer<'a> {
+    /// Always `BMP_PER_PEER_HEADER_LEN` octets.
     inner: &'a [u8],
 }
 
+/// The length of the common header that starts every BMP message.
+pub const BMP_COMMON_HEADER_LEN: usize = 6;
+/// The length of the per-peer header following the common header of
+/// most BMP messages (RFC 7854 section 4.2).
+pub const BMP_PER_PEER_HEADER_LEN: usize = 42;
+/// The Local Address, Local Port and Remote Port fields preceding the
+/// OPEN messages of a Peer Up Notification.
+const PEER_UP_ADDRESSES_LEN: usize = 20;
+
 impl<'a> PerPeer<'a> {
+    /// The per-peer header of a message that has one. The message must
+    /// be long enough, as checked by `Bmp::from_bytes`.
+    fn from_message(message: &'a [u8]) -> PerPeer<'a> {
+        PerPeer {
+            inner: &message[BMP_COMMON_HEADER_LEN..BMP_COMMON_HEADER_LEN + BMP_PER_PEER_HEADER_LEN],
+        }
+    }
+
+    /// A per-peer header of exactly `BMP_PER_PEER_HEADER_LEN` octets, such
+    /// as one to write with `StatisticsReportBuilder`.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<PerPeer<'a>> {
+        if bytes.len() != BMP_PER_PEER_HEADER_LEN {
+            return Err(BgpError::BadLength);
+        }
+        Ok(PerPeer { inner: bytes })
+    }
+
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.inner
+    }
+
+    /// The length of the per-peer header.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        self.inner.len()
+    }
+
+    /// The part of a message following its per-peer header.
+    fn body(message: &'a [u8]) -> &'a [u8] {
+        &message[BMP_COMMON_HEADER_LEN + PerPeer::from_message(message).len()..]
+    }
+
     pub fn peer_type(&self) -> u8 {
         self.inner[0]
     }
@@ -18,6 +73,14 @@ impl<'a> PerPeer<'a> {
         self.inner[1]
     }
 
+    /// The peer flags, read according to the peer type.
+    pub fn flags(&self) -> PeerFlags {
+        PeerFlags {
+            bits: self.peer_flags(),
+            loc_rib: self.peer_type() == BMP_PEER_LOC_RIB,
+        }
+    }
+
     pub fn flag_ipv6(&self) -> bool {
         self.peer_flags() & BMP_FLAG_IPV6 > 0
     }
@@ -30,19 +93,60 @@ impl<'a> PerPeer<'a> {
         self.peer_flags() & BMP_FLAG_LEGACY_AS > 0
     }
 
-    pub fn peer_distinguisher(&self) -> &'a[u8] {
-        &self.inner[2..10]
+    pub fn flag_adj_rib_out(&self) -> bool {
+        self.peer_flags() & BMP_FLAG_ADJ_RIB_OUT > 0
+    }
+
+    /// The width in octets of the AS numbers in the AS_PATH of monitored
+    /// UPDATEs: 2 if the A flag is set, otherwise 4. The Peer AS field is
+    /// 4 octets either way.
+    pub fn asn_width(&self) -> usize {
+        if self.flag_legacy_asn() { 2 } else { 4 }
+    }
+
+    /// `config` with the AS number width of the monitored UPDATEs.
+    pub fn session_config(&self, config: SessionConfig) -> SessionConfig {
+        SessionConfig {
+            four_byte_asn: self.asn_width() == 4,
+            ..config
+        }
+    }
+
+    /// Which RIB the routes in the message were taken from, derived from
+    /// the peer type and the L and O flags.
+    pub fn rib_kind(&self) -> RibKind {
+        if self.peer_type() == BMP_PEER_LOC_RIB {
+            return RibKind::LocRib;
+        }
+        match (self.flag_adj_rib_out(), self.flag_l()) {
+            (false, false) => RibKind::AdjRibInPre,
+            (false, true) => RibKind::AdjRibInPost,
+            (true, false) => RibKind::AdjRibOutPre,
+            (true, true) => RibKind::AdjRibOutPost,
+        }
+    }
+
+    pub fn peer_distinguisher(&self) -> RouteDistinguisher<'a> {
+        // the constructors make every header BMP_PER_PEER_HEADER_LEN
+        // octets long, so the fallback is never taken
+        self.inner.slice(2, 8).and_then(RouteDistinguisher::from_bytes).unwrap_or(RouteDistinguisher(&[0; 8]))
     }
 
     pub fn peer_address(&self) -> &'a [u8] {
         &self.inner[10..26]
     }
 
-    pub fn peer_as(&self) -> u32 {
-        (self.inner[26] as u32) << 24
+    /// The peer address: IPv6 if the V flag is set, otherwise IPv4 in the
+    /// last 4 octets of the field.
+    pub fn peer_ip(&self) -> IpAddrBytes<'a> {
+        peer_ip(self.peer_address(), self.flag_ipv6())
+    }
+
+    pub fn peer_as(&self) -> AsNumber {
+        AsNumber((self.inner[26] as u32) << 24
         | (self.inner[27] as u32) << 16
         | (self.inner[28] as u32) << 8
-        | (self.inner[29] as u32)
+        | (self.inner[29] as u32))
     }
 
     pub fn peer_id(&self) -> u32 {
@@ -68,14 +172,132 @@ impl<'a> PerPeer<'a> {
 
 }
 
+/// An address in a 16 octet BMP address field.
+fn peer_ip(field: &[u8], ipv6: bool) -> IpAddrBytes<'_> {
+    if ipv6 {
+        IpAddrBytes::v6(field)
+    } else {
+        IpAddrBytes::v4(&field[12..])
+    }
+}
+
+/// The flags of a per-peer header. Loc-RIB peers have only the F flag
+/// (RFC 9069), in the position other peer types use for the V flag.
+///
+/// The `Debug` output lists the flags set, e.g. `PeerFlags(V | L)`, with
+/// any bits not allocated in hex.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct PeerFlags {
+    bits: u8,
+    loc_rib: bool,
+}
+
+impl PeerFlags {
+    /// The flags octet as it appears in the header.
+    pub fn bits(&self) -> u8 {
+        self.bits
+    }
+
+    /// The V flag: the peer address is an IPv6 address.
+    pub fn ipv6(&self) -> bool {
+        !self.loc_rib && self.bits & BMP_FLAG_IPV6 > 0
+    }
+
+    /// The L flag: the routes are post-policy.
+    pub fn post_policy(&self) -> bool {
+        !self.loc_rib && self.bits & BMP_FLAG_L > 0
+    }
+
+    /// The A flag: the AS_PATHs are in the legacy 2-octet format.
+    pub fn legacy_as(&self) -> bool {
+        !self.loc_rib && self.bits & BMP_FLAG_LEGACY_AS > 0
+    }
+
+    /// The O flag: the routes are from the Adj-RIB-Out (RFC 8671).
+    pub fn adj_rib_out(&self) -> bool {
+        !self.loc_rib && self.bits & BMP_FLAG_ADJ_RIB_OUT > 0
+    }
+
+    /// The F flag of Loc-RIB peers: the Loc-RIB is filtered (RFC 9069).
+    pub fn filtered(&self) -> bool {
+        self.loc_rib && self.bits & BMP_FLAG_FILTERED > 0
+    }
+
+    /// The bits set that the peer type allocates no flag for.
+    pub fn unknown(&self) -> u8 {
+        if self.loc_rib {
+            self.bits & !BMP_FLAG_FILTERED
+        } else {
+            self.bits & !(BMP_FLAG_IPV6 | BMP_FLAG_L | BMP_FLAG_LEGACY_AS | BMP_FLAG_ADJ_RIB_OUT)
+        }
+    }
+}
+
+impl fmt::Debug for PeerFlags {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let flags = [
+            (self.ipv6(), "V"),
+            (self.post_policy(), "L"),
+            (self.legacy_as(), "A"),
+            (self.adj_rib_out(), "O"),
+            (self.filtered(), "F"),
+        ];
+        fmt.write_str("PeerFlags(")?;
+        let mut first = true;
+        for &(_, name) in flags.iter().filter(|&&(set, _)| set) {
+            write!(fmt, "{}{}", if first { "" } else { " | " }, name)?;
+            first = false;
+        }
+        if self.unknown() > 0 {
+            write!(fmt, "{}{:#04x}", if first { "" } else { " | " }, self.unknown())?;
+        }
+        fmt.write_str(")")
+    }
+}
+
+/// The RIB a BMP message reports on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RibKind {
+    /// Adj-RIB-In before inbound policy.
+    AdjRibInPre,
+    /// Adj-RIB-In after inbound policy.
+    AdjRibInPost,
+    /// Adj-RIB-Out before outbound policy (RFC 8671).
+    AdjRibOutPre,
+    /// Adj-RIB-Out after outbound policy (RFC 8671).
+    AdjRibOutPost,
+    /// The Loc-RIB (RFC 9069).
+    LocRib,
+}
+
 #[derive(Debug)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
 pub struct MessageIter<'a> {
     inner: &'a [u8],
-    four_byte_asn: bool,
-    add_path: bool,
+    config: SessionConfig,
+    lenient: bool,
     error: bool,
 }
 
+impl<'a> MessageIter<'a> {
+    pub fn new(inner: &'a [u8], config: SessionConfig) -> MessageIter<'a> {
+        MessageIter {
+            inner,
+            config,
+            lenient: false,
+            error: false,
+        }
+    }
+
+    /// Keep going after a message fails to parse, as long as its length
+    /// field can be trusted to find the next one. The error is still
+    /// returned for the failed message.
+    pub fn lenient(mut self) -> MessageIter<'a> {
+        self.lenient = true;
+        self
+    }
+}
+
 impl<'a> Iterator for MessageIter<'a> {
     type Item = Result<bgp::Message<'a>>;
 
@@ -90,7 +312,7 @@ impl<'a> Iterator for MessageIter<'a> {
         }
 
         let message_len  = (self.inner[16] as usize) << 8 | (self.inner[17] as usize);
-        if self.inner.len() < message_len {
+        if message_len < 19 || self.inner.len() < message_len {
             self.error = true;
             return Some(Err(BgpError::BadLength));
         }
@@ -98,45 +320,50 @@ impl<'a> Iterator for MessageIter<'a> {
         let slice = &self.inner[..message_len];
         self.inner = &self.inner[message_len..];
 
-        Some(bgp::Message::from_bytes(slice,
-                                      self.four_byte_asn,
-                                      self.add_path))
+        let message = bgp::Message::from_bytes_with_config(slice, &self.config);
+        if message.is_err() && !self.lenient {
+            self.error = true;
+        }
+        Some(message)
     }
 }
 
 pub trait PeerInfo {
-    fn peer_info(&self) -> PerPeer;
+    fn peer_info(&self) -> PerPeer<'_>;
 }
 
 pub trait Messages {
-    fn messages(&self, four_byte_asn: bool, add_path: bool) -> MessageIter;
+    fn messages_with_config(&self, config: &SessionConfig) -> MessageIter<'_>;
+
+    fn messages(&self, four_byte_asn: bool, add_path: bool) -> MessageIter<'_> {
+        let config = SessionConfig {
+            four_byte_asn,
+            add_paths: add_path,
+            ..SessionConfig::default()
+        };
+        self.messages_with_config(&config)
+    }
 }
 
 macro_rules! def_bmptype {
     ($bmptype:ident) => {
         #[derive(PartialEq,Debug)]
+        #[cfg_attr(feature = "defmt", derive(defmt::Format))]
         pub struct $bmptype<'a> {
             pub inner: &'a [u8],
         }
     };
     ($bmptype:ident PeerInfo) => {
         impl<'a> PeerInfo for $bmptype<'a> {
-            fn peer_info(&self) -> PerPeer {
-                PerPeer {
-                    inner: &self.inner[6..6+42],
-                }
+            fn peer_info(&self) -> PerPeer<'_> {
+                PerPeer::from_message(self.inner)
             }
         }
     };
-    ($bmptype:ident (Messages $offset:expr) ) => {
+    ($bmptype:ident (Messages $skip:expr) ) => {
         impl<'a> Messages for $bmptype<'a> {
-            fn messages(&self, four_byte_asn: bool, add_path: bool) -> MessageIter {
-                MessageIter {
-                    inner: &self.inner[$offset..],
-                    four_byte_asn: four_byte_asn,
-                    add_path: add_path,
-                    error: false,
-                }
+            fn messages_with_config(&self, config: &SessionConfig) -> MessageIter<'_> {
+                MessageIter::new(&PerPeer::body(self.inner)[$skip..], *config)
             }
         }
     };
@@ -146,12 +373,119 @@ macro_rules! def_bmptype {
     };
 }
 
-def_bmptype!(RouteMonitoring, PeerInfo, (Messages 48));
+def_bmptype!(RouteMonitoring, PeerInfo, (Messages 0));
 def_bmptype!(StatisticsReport, PeerInfo);
-def_bmptype!(PeerDownNotification);
-def_bmptype!(PeerUpNotification, PeerInfo, (Messages 48+20));
+def_bmptype!(PeerDownNotification, PeerInfo);
+def_bmptype!(PeerUpNotification, PeerInfo, (Messages PEER_UP_ADDRESSES_LEN));
 def_bmptype!(Initiation);
 
+impl<'a> RouteMonitoring<'a> {
+    /// The UPDATE carried by the message. Fails with `BgpError::Invalid`
+    /// unless the message carries exactly one BGP message and it is an
+    /// UPDATE; use `Messages::messages_with_config` for the others.
+    pub fn update(&self, config: &SessionConfig) -> Result<Update<'a>> {
+        let mut messages = MessageIter::new(PerPeer::body(self.inner), *config);
+        match (messages.next(), messages.next()) {
+            (Some(Ok(bgp::Message::Update(update))), None) => Ok(update),
+            (Some(Err(err)), _) => Err(err),
+            _ => Err(BgpError::Invalid),
+        }
+    }
+
+    /// Every route change in the monitored UPDATEs, paired with the peer
+    /// it was received from.
+    pub fn events(&self, config: &SessionConfig) -> RouteMonitoringEvents<'a> {
+        RouteMonitoringEvents {
+            peer: PerPeer::from_message(self.inner),
+            messages: MessageIter::new(PerPeer::body(self.inner), *config),
+            routes: None,
+        }
+    }
+}
+
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct RouteMonitoringEvents<'a> {
+    peer: PerPeer<'a>,
+    messages: MessageIter<'a>,
+    routes: Option<Routes<'a>>,
+}
+
+impl<'a> Iterator for RouteMonitoringEvents<'a> {
+    type Item = Result<(PerPeer<'a>, RouteEvent<'a>)>;
+
+    fn next(&mut self) -> Option<Result<(PerPeer<'a>, RouteEvent<'a>)>> {
+        loop {
+            if let Some(event) = self.routes.as_mut().and_then(|routes| routes.next()) {
+                return Some(event.map(|event| (self.peer, event)));
+            }
+            self.routes = None;
+            match self.messages.next()? {
+                Ok(bgp::Message::Update(update)) => self.routes = Some(update.routes()),
+                Ok(_) => (),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+impl<'a> PeerDownNotification<'a> {
+    /// The Reason code: why the session was closed (RFC 7854 section
+    /// 4.9), or `None` if the message ends after the per-peer header.
+    pub fn reason(&self) -> Option<u8> {
+        PerPeer::body(self.inner).first().copied()
+    }
+}
+
+impl<'a> PeerUpNotification<'a> {
+    /// The OPEN messages sent and received by the monitored router.
+    pub fn opens(&self) -> Result<(Open<'a>, Open<'a>)> {
+        let body = &PerPeer::body(self.inner)[PEER_UP_ADDRESSES_LEN..];
+        let mut messages = MessageIter::new(body, SessionConfig::default());
+        match (messages.next(), messages.next()) {
+            (Some(Ok(bgp::Message::Open(sent))), Some(Ok(bgp::Message::Open(received)))) =>
+                Ok((sent, received)),
+            (Some(Err(err)), _) | (_, Some(Err(err))) => Err(err),
+            _ => Err(BgpError::Invalid),
+        }
+    }
+
+    /// The local address of the TCP session, of the same family as the
+    /// peer address.
+    pub fn local_address(&self) -> IpAddrBytes<'a> {
+        let body = PerPeer::body(self.inner);
+        peer_ip(&body[..16], self.peer_info().flag_ipv6())
+    }
+
+    /// The Information TLVs following the OPEN messages, such as a string
+    /// describing the peer. Fails if the OPEN messages are truncated.
+    pub fn information(&self) -> Result<RouterInfoIter<'a>> {
+        let opens = &PerPeer::body(self.inner)[PEER_UP_ADDRESSES_LEN..];
+        let sent_len = opens.u16_at(16)? as usize;
+        let received_len = opens.skip(sent_len)?.u16_at(16)? as usize;
+        Ok(RouterInfoIter::new(opens.skip(sent_len + received_len)?))
+    }
+
+    /// The parsing parameters implied by the capabilities both OPENs
+    /// advertise: four-octet AS numbers and extended messages. ADD-PATH
+    /// is negotiated per address family and left off.
+    pub fn session_config(&self) -> Result<SessionConfig> {
+        let (sent, received) = self.opens()?;
+        let both = |f: fn(&Capability) -> bool| has_capability(&sent, f) && has_capability(&received, f);
+        Ok(SessionConfig {
+            four_byte_asn: both(|cap| matches!(*cap, Capability::FourByteASN(_))),
+            extended_message: both(|cap| matches!(*cap, Capability::ExtendedMessage(_))),
+            ..SessionConfig::default()
+        })
+    }
+}
+
+fn has_capability(open: &Open, f: fn(&Capability) -> bool) -> bool {
+    open.params().any(|param| match param {
+        Ok(OptionalParam::Capability(ref cap)) => f(cap),
+        _ => false,
+    })
+}
+
 impl<'a> Initiation<'a> {
     pub fn from_bytes(bytes: &'a [u8]) -> Initiation<'a> {
         Initiation {
@@ -160,43 +494,39 @@ impl<'a> Initiation<'a> {
     }
 
     pub fn router_info(&self) -> RouterInfoIter<'a> {
-        RouterInfoIter {
-            inner: &self.inner[6..],
-            error: false,
-        }
+        RouterInfoIter::new(self.inner.skip(BMP_COMMON_HEADER_LEN).unwrap_or_default())
     }
 }
 
+#[must_use = "iterators are lazy and do nothing unless consumed"]
 pub struct RouterInfoIter<'a> {
-    inner: &'a [u8],
+    tlvs: TlvIter<'a>,
     error: bool,
 }
 
+impl<'a> RouterInfoIter<'a> {
+    fn new(inner: &'a [u8]) -> RouterInfoIter<'a> {
+        RouterInfoIter {
+            tlvs: TlvIter::new(inner),
+            error: false,
+        }
+    }
+}
+
 impl<'a> Iterator for RouterInfoIter<'a> {
     type Item = Result<RouterInfo<'a>>;
 
     fn next(&mut self) -> Option<Result<RouterInfo<'a>>> {
-        if self.inner.is_empty() || self.error {
+        if self.error {
             return None;
         }
 
-        if self.inner.len() < 4 {
-            self.error = true;
-            return Some(Err(BgpError::BadLength));
-        }
-
-        let msg_type = (self.inner[0] as u16) << 8 | self.inner[1] as u16;
-        let msg_len = (self.inner[2] as usize) << 8 | self.inner[3] as usize;
-
-        if self.inner.len() < msg_len {
-            self.error = true;
-            return Some(Err(BgpError::BadLength));
-        }
-
-        let slice = &self.inner[4..msg_len + 4];
-        self.inner = &self.inner[msg_len + 4..];
+        let tlv = match self.tlvs.next()? {
+            Ok(tlv) => tlv,
+            Err(err) => return Some(Err(err)),
+        };
 
-        let str_slice = match str::from_utf8(slice) {
+        let str_slice = match str::from_utf8(tlv.value) {
             Ok(string) => string,
             Err(_) => {
                 self.error = true;
@@ -204,11 +534,11 @@ impl<'a> Iterator for RouterInfoIter<'a> {
             }
         };
 
-        let ret = match msg_type {
+        let ret = match tlv.tlv_type {
             0 => RouterInfo::String(str_slice),
             1 => RouterInfo::SysDescr(str_slice),
             2 => RouterInfo::SysName(str_slice),
-            _ => RouterInfo::Other(slice),
+            _ => RouterInfo::Other(tlv.value),
         };
         Some(Ok(ret))
     }
@@ -222,12 +552,149 @@ pub enum RouterInfo<'a> {
     Other(&'a [u8]),
 }
 
+/// Information TLV carrying a free-form string.
+pub const BMP_INFO_STRING: u16 = 0;
+/// Initiation information TLV carrying the sysDescr MIB-II object.
+pub const BMP_INFO_SYS_DESCR: u16 = 1;
+/// Initiation information TLV carrying the sysName MIB-II object.
+pub const BMP_INFO_SYS_NAME: u16 = 2;
+/// Termination information TLV carrying the reason for the termination.
+pub const BMP_INFO_REASON: u16 = 1;
+
+/// The session was closed administratively.
+pub const BMP_TERM_ADMIN_CLOSE: u16 = 0;
+/// The session was closed for an unspecified reason.
+pub const BMP_TERM_UNSPECIFIED: u16 = 1;
+/// The monitored router ran out of resources for the session.
+pub const BMP_TERM_OUT_OF_RESOURCES: u16 = 2;
+/// The session was a redundant connection.
+pub const BMP_TERM_REDUNDANT_CONNECTION: u16 = 3;
+/// The session was closed administratively and is not to be reopened.
+pub const BMP_TERM_PERMANENTLY_ADMIN_CLOSE: u16 = 4;
+
+/// Encodes an Initiation message into a caller supplied buffer.
+pub struct InitiationBuilder<'b> {
+    buf: &'b mut [u8],
+    pos: usize,
+}
+
+impl<'b> InitiationBuilder<'b> {
+    /// Fails with `BgpError::BadLength` if `buf` can not hold the common
+    /// header.
+    pub fn new(buf: &'b mut [u8]) -> Result<InitiationBuilder<'b>> {
+        if buf.len() < BMP_COMMON_HEADER_LEN {
+            return Err(BgpError::BadLength);
+        }
+        buf[..BMP_COMMON_HEADER_LEN].copy_from_slice(&[3, 0, 0, 0, 0, BMP_MSG_INIT]);
+        Ok(InitiationBuilder { buf, pos: BMP_COMMON_HEADER_LEN })
+    }
+
+    /// Appends the information TLV `info`, e.g. one of `BMP_INFO_STRING`,
+    /// `BMP_INFO_SYS_DESCR` or `BMP_INFO_SYS_NAME`. Nothing is written on
+    /// error.
+    pub fn push(&mut self, info: &Tlv) -> Result<()> {
+        self.pos += info.write(&mut self.buf[self.pos..])?.len();
+        Ok(())
+    }
+
+    /// Completes the encoding and returns the message, with its length
+    /// field filled in.
+    pub fn finish(self) -> Result<&'b [u8]> {
+        let len = u32::try_from(self.pos).map_err(|_| BgpError::BadLength)?;
+        self.buf[1..5].copy_from_slice(&len.to_be_bytes());
+        Ok(&self.buf[..self.pos])
+    }
+}
+
 
 
 def_bmptype!(Termination);
+
+impl<'a> Termination<'a> {
+    /// The information TLVs: the reason as a `BMP_INFO_REASON` TLV and
+    /// any `BMP_INFO_STRING` TLVs.
+    pub fn information(&self) -> TlvIter<'a> {
+        TlvIter::new(self.inner.skip(BMP_COMMON_HEADER_LEN).unwrap_or_default())
+    }
+
+    /// The reason code, one of the `BMP_TERM_*` constants, or `None` if
+    /// the message gives none.
+    pub fn reason(&self) -> Result<Option<u16>> {
+        for tlv in self.information() {
+            let tlv = tlv?;
+            if tlv.tlv_type == BMP_INFO_REASON {
+                if tlv.value.len() != 2 {
+                    return Err(BgpError::BadLength);
+                }
+                return Ok(Some(tlv.value.u16_at(0)?));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Writes a Termination message with the reason code `reason` and,
+    /// unless empty, the string `message` to the start of `buf` and
+    /// returns it. Fails with `BgpError::BadLength` if `buf` is too short.
+    pub fn write<'b>(buf: &'b mut [u8], reason: u16, message: &str) -> Result<&'b [u8]> {
+        let mut pos = BMP_COMMON_HEADER_LEN;
+        if buf.len() < pos {
+            return Err(BgpError::BadLength);
+        }
+        if !message.is_empty() {
+            let string = Tlv { tlv_type: BMP_INFO_STRING, value: message.as_bytes() };
+            pos += string.write(&mut buf[pos..])?.len();
+        }
+        let reason = Tlv { tlv_type: BMP_INFO_REASON, value: &reason.to_be_bytes() };
+        pos += reason.write(&mut buf[pos..])?.len();
+        let len = u32::try_from(pos).map_err(|_| BgpError::BadLength)?;
+        buf[..BMP_COMMON_HEADER_LEN].copy_from_slice(&[3, 0, 0, 0, 0, BMP_MSG_TERM]);
+        buf[1..5].copy_from_slice(&len.to_be_bytes());
+        Ok(&buf[..pos])
+    }
+}
 def_bmptype!(RouteMirroring, PeerInfo);
 
+/// Route Mirroring TLV carrying a verbatim BGP message.
+pub const BMP_MIRROR_BGP_MESSAGE: u16 = 0;
+/// Route Mirroring TLV carrying a code about the mirrored messages.
+pub const BMP_MIRROR_INFORMATION: u16 = 1;
+
+impl<'a> RouteMirroring<'a> {
+    /// The mirrored BGP messages, parsed with the parameters of the
+    /// session they were received on. Information TLVs are skipped.
+    pub fn mirrored_messages(&self, config: &SessionConfig) -> MirroredMessageIter<'a> {
+        MirroredMessageIter {
+            tlvs: TlvIter::new(PerPeer::body(self.inner)),
+            config: *config,
+        }
+    }
+}
+
+/// Iterates over the mirrored BGP messages. As each message has its own
+/// TLV, one that fails to parse does not end the iteration.
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct MirroredMessageIter<'a> {
+    tlvs: TlvIter<'a>,
+    config: SessionConfig,
+}
+
+impl<'a> Iterator for MirroredMessageIter<'a> {
+    type Item = Result<bgp::Message<'a>>;
+
+    fn next(&mut self) -> Option<Result<bgp::Message<'a>>> {
+        loop {
+            match self.tlvs.next()? {
+                Ok(Tlv { tlv_type: BMP_MIRROR_BGP_MESSAGE, value }) =>
+                    return Some(bgp::decode(value, &self.config)),
+                Ok(_) => continue,
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Bmp<'a> {
     /// Route Monitoring (RM): Used to provide an initial dump of all
     /// routes received from a peer as well as an ongoing mechanism that
@@ -271,6 +738,7 @@ pub const BMP_MSG_ROUTEMIRROR: u8 = 6;
 pub const BMP_PEER_GLOBAL:     u8 = 0;
 pub const BMP_PEER_RD:         u8 = 1;
 pub const BMP_PEER_LOCAL:      u8 = 2;
+pub const BMP_PEER_LOC_RIB:    u8 = 3;
 
 /// The V flag indicates the the Peer address is an IPv6 address.
 /// For IPv4 peers this is set to 0.
@@ -294,10 +762,150 @@ pub const BMP_FLAG_L:          u8 = 0b01000000;
 /// sent in the BMP UPDATE message.  This flag has no significance
 /// when used with route mirroring messages (Section 4.7).
 pub const BMP_FLAG_LEGACY_AS:  u8 = 0b00100000;
+/// The O flag indicates Adj-RIB-In (if set to 0) or Adj-RIB-Out (if set
+/// to 1) [RFC8671].
+pub const BMP_FLAG_ADJ_RIB_OUT: u8 = 0b00010000;
+/// The F flag of Loc-RIB peers indicates the Loc-RIB is filtered
+/// [RFC9069].
+pub const BMP_FLAG_FILTERED:   u8 = 0b10000000;
 
 impl<'a> Bmp<'a> {
 
     pub fn from_bytes(bytes: &'a [u8]) -> Result<Bmp<'a>> {
+        Bmp::try_from(bytes)
+    }
+
+    /// The name IANA lists for the message type.
+    #[cfg(feature = "names")]
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Bmp::RouteMonitoring(_) => "Route Monitoring",
+            Bmp::StatisticsReport(_) => "Statistics Report",
+            Bmp::PeerDownNotification(_) => "Peer Down Notification",
+            Bmp::PeerUpNotification(_) => "Peer Up Notification",
+            Bmp::Initiation(_) => "Initiation Message",
+            Bmp::Termination(_) => "Termination Message",
+            Bmp::RouteMirroring(_) => "Route Mirroring Message",
+        }
+    }
+
+    /// Decodes every part of the message, including the BGP messages it
+    /// carries, returning the first error. See `bgp::Message::deep_validate`.
+    ///
+    /// BGP messages are parsed with `SessionConfig::default()`, except
+    /// that the A flag decides the AS number width. Use
+    /// `Decoded::deep_validate` to apply what a `PeerTable` knows.
+    pub fn deep_validate(&self) -> Result<()> {
+        self.deep_validate_with_config(self.message_config(SessionConfig::default()))
+    }
+
+    fn deep_validate_with_config(&self, config: SessionConfig) -> Result<()> {
+        match *self {
+            Bmp::StatisticsReport(ref report) => validate_all(report.stats())?,
+            Bmp::PeerUpNotification(ref peer_up) => {
+                peer_up.opens()?;
+            }
+            Bmp::Initiation(ref initiation) => validate_all(initiation.router_info())?,
+            Bmp::Termination(ref termination) => {
+                validate_all(termination.information())?;
+                termination.reason()?;
+            }
+            Bmp::RouteMirroring(ref mirroring) => for message in mirroring.mirrored_messages(&config) {
+                message?.deep_validate()?;
+            },
+            _ => (),
+        }
+        for message in self.bgp_messages(config) {
+            message?.deep_validate()?;
+        }
+        Ok(())
+    }
+
+    /// `config` adjusted to the message: the A flag of a Route Monitoring
+    /// or Route Mirroring message decides the AS number width.
+    fn message_config(&self, config: SessionConfig) -> SessionConfig {
+        match *self {
+            Bmp::RouteMonitoring(ref rm) => rm.peer_info().session_config(config),
+            Bmp::RouteMirroring(ref mirroring) => mirroring.peer_info().session_config(config),
+            _ => config,
+        }
+    }
+
+    /// The BGP messages carried by a Route Monitoring or Peer Up
+    /// Notification.
+    fn bgp_messages(&self, config: SessionConfig) -> MessageIter<'a> {
+        let inner = match *self {
+            Bmp::RouteMonitoring(ref rm) => PerPeer::body(rm.inner),
+            Bmp::PeerUpNotification(ref peer_up) => &PerPeer::body(peer_up.inner)[PEER_UP_ADDRESSES_LEN..],
+            _ => &[],
+        };
+        MessageIter::new(inner, config)
+    }
+
+}
+
+/// A BMP message and the session parameters its BGP messages are parsed
+/// with.
+#[derive(Debug)]
+pub struct Decoded<'a> {
+    pub bmp: Bmp<'a>,
+    pub config: SessionConfig,
+}
+
+impl<'a> Decoded<'a> {
+    /// The BGP messages carried by a Route Monitoring or Peer Up
+    /// Notification. Other message types carry none.
+    pub fn messages(&self) -> MessageIter<'a> {
+        self.bmp.bgp_messages(self.config)
+    }
+
+    /// Like `Bmp::deep_validate`, using the session parameters chosen by
+    /// `decode`.
+    pub fn deep_validate(&self) -> Result<()> {
+        self.bmp.deep_validate_with_config(self.config)
+    }
+}
+
+/// Parses a BMP message, choosing the session parameters for the BGP
+/// messages it carries.
+///
+/// Route Monitoring and Route Mirroring messages use the parameters
+/// `peers` holds for the monitored peer, or `SessionConfig::default()` for
+/// unknown peers. Either way, the A flag of the per-peer header decides
+/// the AS number width.
+/// `PeerTable::decode` also keeps the table up to date.
+///
+/// This is the entry point most users want; `Bmp::from_bytes` together
+/// with `Messages::messages_with_config` remains for full control.
+pub fn decode<'a, const N: usize>(bytes: &'a [u8], peers: &PeerTable<N>) -> Result<Decoded<'a>> {
+    let bmp = Bmp::from_bytes(bytes)?;
+    let config = match bmp {
+        Bmp::RouteMonitoring(ref rm) => peers.config_for(&rm.peer_info()),
+        Bmp::RouteMirroring(ref mirroring) => peers.config_for(&mirroring.peer_info()),
+        _ => SessionConfig::default(),
+    };
+    Ok(Decoded { bmp, config })
+}
+
+/// Like `decode`, recording in `stats` the BGP messages carried by the
+/// BMP message, or the error if it does not parse.
+#[cfg(feature = "stats")]
+pub fn decode_with_stats<'a, const N: usize, const M: usize>(bytes: &'a [u8], peers: &PeerTable<N>,
+                                                             stats: &mut ParserStats<M>) -> Result<Decoded<'a>> {
+    let decoded = decode(bytes, peers);
+    match decoded {
+        Ok(ref decoded) => for message in decoded.messages() {
+            stats.record(&message);
+        },
+        Err(err) => stats.record_error(err),
+    }
+    decoded
+}
+
+impl<'a> TryFrom<&'a [u8]> for Bmp<'a> {
+    type Error = BgpError;
+
+    fn try_from(bytes: &'a [u8]) -> Result<Bmp<'a>> {
         if bytes.len() < 6 {
             return Err(BgpError::BadLength)
         }
@@ -317,6 +925,17 @@ impl<'a> Bmp<'a> {
         }
 
         let bmp_type = bytes[5];
+        let min_length = match bmp_type {
+            BMP_MSG_ROUTEMON | BMP_MSG_ROUTEMIRROR => BMP_COMMON_HEADER_LEN + BMP_PER_PEER_HEADER_LEN,
+            BMP_MSG_STATREPORT => BMP_COMMON_HEADER_LEN + BMP_PER_PEER_HEADER_LEN + 4,
+            BMP_MSG_PEERDOWN => BMP_COMMON_HEADER_LEN + BMP_PER_PEER_HEADER_LEN + 1,
+            BMP_MSG_PEERUP => BMP_COMMON_HEADER_LEN + BMP_PER_PEER_HEADER_LEN + PEER_UP_ADDRESSES_LEN,
+            _ => BMP_COMMON_HEADER_LEN,
+        };
+        if bytes.len() < min_length {
+            return Err(BgpError::BadLength);
+        }
+
         match bmp_type{
             BMP_MSG_ROUTEMON    => Ok(Bmp::RouteMonitoring(RouteMonitoring{inner: bytes})),
             BMP_MSG_STATREPORT  => Ok(Bmp::StatisticsReport(StatisticsReport{inner: bytes})),
@@ -328,11 +947,13 @@ impl<'a> Bmp<'a> {
             _ => Err(BgpError::Invalid)
         }
     }
-
 }
 
+/// A statistic of a type not known to the crate, or of a known type with
+/// an unexpected length.
 #[derive(Debug)]
 pub struct UnknownStatistic<'a> {
+    pub stat_type: u16,
     pub inner: &'a [u8],
 }
 
@@ -366,11 +987,11 @@ pub enum Statistic<'a> {
     /// Stat Type = 9: Number of routes in per-AFI/SAFI Adj-RIB-In.  The
     /// value is structured as: AFI (2 bytes), SAFI (1 byte), followed by
     /// a 64-bit Gauge.
-    PerAfiSafiAdjRibInSize(Afi, Safi, u64),
+    PerAfiSafiAdjRibInSize(AfiSafi, u64),
     /// Stat Type = 10: Number of routes in per-AFI/SAFI Loc-RIB.  The
     /// value is structured as: AFI (2 bytes), SAFI (1 byte), followed by
     /// a 64-bit Gauge.
-    PerAfiSafiLocRibSize(Afi, Safi, u64),
+    PerAfiSafiLocRibSize(AfiSafi, u64),
     /// Stat Type = 11: (32-bit Counter) Number of updates subjected to
     /// treat-as-withdraw treatment [RFC7606].
     UpdatesTreatedAsWithdraws(u32),
@@ -383,115 +1004,193 @@ pub enum Statistic<'a> {
     Unknown(UnknownStatistic<'a>),
 }
 
+impl<'a> Statistic<'a> {
+    pub fn stat_type(&self) -> u16 {
+        match *self {
+            Statistic::RejectedPrefixCount(_) => 0,
+            Statistic::DuplicatePrefixAdvertisementCount(_) => 1,
+            Statistic::DuplicatePrefixWithdrawCount(_) => 2,
+            Statistic::ClusterListLoopInvalidationCount(_) => 3,
+            Statistic::AsPathLoopInvalidationCount(_) => 4,
+            Statistic::OriginatorIdInvalidationCount(_) => 5,
+            Statistic::AsConfedInvalidationCount(_) => 6,
+            Statistic::AdjRibsInSize(_) => 7,
+            Statistic::LocRibSize(_) => 8,
+            Statistic::PerAfiSafiAdjRibInSize(..) => 9,
+            Statistic::PerAfiSafiLocRibSize(..) => 10,
+            Statistic::UpdatesTreatedAsWithdraws(_) => 11,
+            Statistic::PrefixesTreatedAsWithdraws(_) => 12,
+            Statistic::DuplicateUpdateCount(_) => 13,
+            Statistic::Unknown(ref unknown) => unknown.stat_type,
+        }
+    }
+
+    /// The length of the statistic TLV, its type and length fields
+    /// included.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> usize {
+        4 + match *self {
+            Statistic::AdjRibsInSize(_) | Statistic::LocRibSize(_) => 8,
+            Statistic::PerAfiSafiAdjRibInSize(..) | Statistic::PerAfiSafiLocRibSize(..) => 11,
+            Statistic::Unknown(ref unknown) => unknown.inner.len(),
+            _ => 4,
+        }
+    }
+
+    /// Writes the statistic TLV to the start of `buf` and returns it.
+    /// Fails with `BgpError::BadLength` if `buf` is too short or an
+    /// unknown statistic does not fit the 2 octet length field.
+    pub fn write<'b>(&self, buf: &'b mut [u8]) -> Result<&'b [u8]> {
+        let len = self.len();
+        if buf.len() < len || len - 4 > 0xffff {
+            return Err(BgpError::BadLength);
+        }
+        let stat_type = self.stat_type();
+        buf[..4].copy_from_slice(&[(stat_type >> 8) as u8, stat_type as u8,
+                                   ((len - 4) >> 8) as u8, (len - 4) as u8]);
+        let value = &mut buf[4..len];
+        match *self {
+            Statistic::RejectedPrefixCount(n)
+            | Statistic::DuplicatePrefixAdvertisementCount(n)
+            | Statistic::DuplicatePrefixWithdrawCount(n)
+            | Statistic::ClusterListLoopInvalidationCount(n)
+            | Statistic::AsPathLoopInvalidationCount(n)
+            | Statistic::OriginatorIdInvalidationCount(n)
+            | Statistic::AsConfedInvalidationCount(n)
+            | Statistic::UpdatesTreatedAsWithdraws(n)
+            | Statistic::PrefixesTreatedAsWithdraws(n)
+            | Statistic::DuplicateUpdateCount(n) =>
+                value.copy_from_slice(&n.to_be_bytes()),
+            Statistic::AdjRibsInSize(n) | Statistic::LocRibSize(n) =>
+                value.copy_from_slice(&n.to_be_bytes()),
+            Statistic::PerAfiSafiAdjRibInSize(family, n) | Statistic::PerAfiSafiLocRibSize(family, n) => {
+                let afi = u16::from(family.afi);
+                value[..3].copy_from_slice(&[(afi >> 8) as u8, afi as u8, u8::from(family.safi)]);
+                value[3..].copy_from_slice(&n.to_be_bytes());
+            }
+            Statistic::Unknown(ref unknown) => value.copy_from_slice(unknown.inner),
+        }
+        Ok(&buf[..len])
+    }
+}
+
 #[derive(Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
 pub struct StatisticsIter<'a> {
-    inner: &'a [u8],
+    tlvs: TlvIter<'a>,
+    remaining: u32,
     error: bool,
 }
 
+impl<'a> StatisticsIter<'a> {
+    /// The bytes following the statistics not yet iterated over. Once the
+    /// iterator is exhausted, this is whatever follows the number of
+    /// statistics given by the Stats Count field, such as padding or a
+    /// vendor trailer.
+    pub fn trailing_bytes(&self) -> &'a [u8] {
+        self.tlvs.remaining()
+    }
+}
+
+impl<'a> Statistic<'a> {
+    /// Decodes a statistic TLV.
+    fn from_tlv(tlv: Tlv<'a>) -> Result<Statistic<'a>> {
+        let slice = tlv.value;
+        let stat = match (tlv.tlv_type, slice.len()) {
+            (0, 4) => Statistic::RejectedPrefixCount(slice.u32_at(0)?),
+            (1, 4) => Statistic::DuplicatePrefixAdvertisementCount(slice.u32_at(0)?),
+            (2, 4) => Statistic::DuplicatePrefixWithdrawCount(slice.u32_at(0)?),
+            (3, 4) => Statistic::ClusterListLoopInvalidationCount(slice.u32_at(0)?),
+            (4, 4) => Statistic::AsPathLoopInvalidationCount(slice.u32_at(0)?),
+            (5, 4) => Statistic::OriginatorIdInvalidationCount(slice.u32_at(0)?),
+            (6, 4) => Statistic::AsConfedInvalidationCount(slice.u32_at(0)?),
+            (7, 8) => Statistic::AdjRibsInSize(slice.u64_at(0)?),
+            (8, 8) => Statistic::LocRibSize(slice.u64_at(0)?),
+            (9, 11) => Statistic::PerAfiSafiAdjRibInSize(AfiSafi::from_bytes(slice), slice.u64_at(3)?),
+            (10, 11) => Statistic::PerAfiSafiLocRibSize(AfiSafi::from_bytes(slice), slice.u64_at(3)?),
+            (11, 4) => Statistic::UpdatesTreatedAsWithdraws(slice.u32_at(0)?),
+            (12, 4) => Statistic::PrefixesTreatedAsWithdraws(slice.u32_at(0)?),
+            (13, 4) => Statistic::DuplicateUpdateCount(slice.u32_at(0)?),
+            (stat_type, _) => Statistic::Unknown(UnknownStatistic{stat_type, inner: slice}),
+        };
+        Ok(stat)
+    }
+}
+
 impl<'a> Iterator for StatisticsIter<'a> {
     type Item = Result<Statistic<'a>>;
     fn next(&mut self) -> Option<Result<Statistic<'a>>> {
-        if self.inner.is_empty() || self.error {
+        if self.remaining == 0 || self.error {
             return None;
         }
-
-        if self.inner.len() < 2 {
-            self.error = true;
-            return Some(Err(BgpError::BadLength))
-        }
-
-        let stat_type
-            = (self.inner[0] as usize) << 8
-            | (self.inner[1] as usize);
-
-        let stat_len
-            = (self.inner[2] as usize) << 8
-            | (self.inner[3] as usize);
-
-        if self.inner.len() < stat_len + 4 {
-            self.error = true;
-            return Some(Err(BgpError::BadLength));
-        }
-
-        let slice = &self.inner[4..stat_len + 4];
-
-        self.inner = &self.inner[stat_len + 4..];
-        let stat = match (stat_type, stat_len) {
-            (0, 4) => Statistic::RejectedPrefixCount((slice[0] as u32) << 24 | (slice[1] as u32) << 16
-                                                     | (slice[2] as u32) << 8 | (slice[3] as u32)),
-            (1, 4) => Statistic::DuplicatePrefixAdvertisementCount((slice[0] as u32) << 24 | (slice[1] as u32) << 16
-                                                     | (slice[2] as u32) << 8 | (slice[3] as u32)),
-            (2, 4) => Statistic::DuplicatePrefixWithdrawCount((slice[0] as u32) << 24 | (slice[1] as u32) << 16
-                                                     | (slice[2] as u32) << 8 | (slice[3] as u32)),
-            (3, 4) => Statistic::ClusterListLoopInvalidationCount((slice[0] as u32) << 24 | (slice[1] as u32) << 16
-                                                     | (slice[2] as u32) << 8 | (slice[3] as u32)),
-            (4, 4) => Statistic::AsPathLoopInvalidationCount((slice[0] as u32) << 24 | (slice[1] as u32) << 16
-                                                     | (slice[2] as u32) << 8 | (slice[3] as u32)),
-            (5, 4) => Statistic::OriginatorIdInvalidationCount((slice[0] as u32) << 24 | (slice[1] as u32) << 16
-                                                     | (slice[2] as u32) << 8 | (slice[3] as u32)),
-            (6, 4) => Statistic::AsConfedInvalidationCount((slice[0] as u32) << 24 | (slice[1] as u32) << 16
-                                                     | (slice[2] as u32) << 8 | (slice[3] as u32)),
-            (7, 8) => Statistic::AdjRibsInSize(
-                (slice[0] as u64) << 56 | (slice[1] as u64) << 48
-                    | (slice[2] as u64) << 40 | (slice[3] as u64) << 32
-                    | (slice[4] as u64) << 24 | (slice[5] as u64) << 16
-                    | (slice[6] as u64) << 8 | (slice[7] as u64)),
-            (8, 8) => Statistic::LocRibSize(
-                (slice[0] as u64) << 56 | (slice[1] as u64) << 48
-                    | (slice[2] as u64) << 40 | (slice[3] as u64) << 32
-                    | (slice[4] as u64) << 24 | (slice[5] as u64) << 16
-                    | (slice[6] as u64) << 8 | (slice[7] as u64)),
-            (9, 11) => Statistic::PerAfiSafiAdjRibInSize(
-                Afi::from((slice[0] as u16) << 8 | slice[1] as u16),
-                Safi::from(slice[2]),
-                (slice[3] as u64) << 56 | (slice[4] as u64) << 48
-                    | (slice[5] as u64) << 40 | (slice[6] as u64) << 32
-                    | (slice[7] as u64) << 24 | (slice[8] as u64) << 16
-                    | (slice[9] as u64) << 8 | (slice[10] as u64)),
-            (10, 11) => Statistic::PerAfiSafiLocRibSize(
-                Afi::from((slice[0] as u16) << 8 | slice[1] as u16),
-                Safi::from(slice[2]),
-                (slice[3] as u64) << 56 | (slice[4] as u64) << 48
-                    | (slice[5] as u64) << 40 | (slice[6] as u64) << 32
-                    | (slice[7] as u64) << 24 | (slice[8] as u64) << 16
-                    | (slice[9] as u64) << 8 | (slice[10] as u64)),
-            (11, 4) => Statistic::UpdatesTreatedAsWithdraws(
-                (slice[0] as u32) << 24 | (slice[1] as u32) << 16
-                    | (slice[2] as u32) << 8 | (slice[3] as u32)),
-            (12, 4) => Statistic::PrefixesTreatedAsWithdraws(
-                (slice[0] as u32) << 24 | (slice[1] as u32) << 16
-                    | (slice[2] as u32) << 8 | (slice[3] as u32)),
-            (13, 4) => Statistic::DuplicateUpdateCount(
-                (slice[0] as u32) << 24 | (slice[1] as u32) << 16
-                    | (slice[2] as u32) << 8 | (slice[3] as u32)),
-            _ => Statistic::Unknown(UnknownStatistic{inner: slice}),
-        };
-        Some(Ok(stat))
+        self.remaining -= 1;
+
+        // running out of TLVs before the Stats Count is reached is an error
+        let stat = self.tlvs.next()
+            .unwrap_or(Err(BgpError::BadLength))
+            .and_then(Statistic::from_tlv);
+        self.error = stat.is_err();
+        Some(stat)
     }
 }
 
 
 impl<'a> StatisticsReport<'a> {
+    /// The Stats Count field, or 0 if the report is truncated.
     pub fn stats_count(&self) -> u32 {
-        let offset = 48;
-        let slice = &self.inner[offset..];
-        (slice[0] as u32) << 24
-            | (slice[1] as u32) << 16
-            | (slice[2] as u32) << 8
-            | (slice[3] as u32) 
+        PerPeer::body(self.inner).u32_at(0).unwrap_or(0)
     }
 
+    /// The statistics, as many as the Stats Count field gives. Fails with
+    /// `BgpError::BadLength` if the report holds fewer.
     pub fn stats(&self) -> StatisticsIter<'a> {
-        let offset = 48 + 4;
-        let slice = &self.inner[offset..];
         StatisticsIter {
-            inner: slice,
+            tlvs: TlvIter::new(PerPeer::body(self.inner).skip(4).unwrap_or_default()),
+            remaining: self.stats_count(),
             error: false,
         }
     }
 }
 
+/// Encodes a Statistics Report message into a caller supplied buffer.
+pub struct StatisticsReportBuilder<'b> {
+    buf: &'b mut [u8],
+    pos: usize,
+    count: u32,
+}
+
+impl<'b> StatisticsReportBuilder<'b> {
+    /// Starts a report about `peer`. Fails with `BgpError::BadLength` if
+    /// `buf` can not hold the headers.
+    pub fn new(buf: &'b mut [u8], peer: &PerPeer) -> Result<StatisticsReportBuilder<'b>> {
+        let pos = BMP_COMMON_HEADER_LEN + peer.len() + 4;
+        if buf.len() < pos {
+            return Err(BgpError::BadLength);
+        }
+        buf[..BMP_COMMON_HEADER_LEN].copy_from_slice(&[3, 0, 0, 0, 0, BMP_MSG_STATREPORT]);
+        buf[BMP_COMMON_HEADER_LEN..pos - 4].copy_from_slice(peer.as_bytes());
+        Ok(StatisticsReportBuilder { buf, pos, count: 0 })
+    }
+
+    /// Appends `stat`. A `Statistic::Unknown` can carry any type and
+    /// value. Nothing is written on error.
+    pub fn push(&mut self, stat: &Statistic) -> Result<()> {
+        self.pos += stat.write(&mut self.buf[self.pos..])?.len();
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Completes the encoding and returns the message, with its length and
+    /// Stats Count fields filled in.
+    pub fn finish(self) -> Result<&'b [u8]> {
+        let len = u32::try_from(self.pos).map_err(|_| BgpError::BadLength)?;
+        self.buf[1..5].copy_from_slice(&len.to_be_bytes());
+        let count = BMP_COMMON_HEADER_LEN + BMP_PER_PEER_HEADER_LEN;
+        self.buf[count..count + 4].copy_from_slice(&self.count.to_be_bytes());
+        Ok(&self.buf[..self.pos])
+    }
+}
+
 #[cfg(test)]
 mod test {
 
@@ -539,14 +1238,14 @@ mod test {
         match bmp {
             Bmp::PeerUpNotification(peerup) => {
                 let peer_info = peerup.peer_info();
-                assert_eq!(peer_info.flag_ipv6(), false);
-                assert_eq!(peer_info.flag_l(), false);
-                assert_eq!(peer_info.flag_legacy_asn(), false);
+                assert!(!peer_info.flag_ipv6());
+                assert!(!peer_info.flag_l());
+                assert!(!peer_info.flag_legacy_asn());
 
-                assert_eq!(peer_info.peer_distinguisher(), &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, ]);
+                assert_eq!(peer_info.peer_distinguisher(), RouteDistinguisher(&[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, ]));
                 assert_eq!(peer_info.peer_address(), &[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
                                                        0x00, 0x00, 0x00, 0x00, 0x0a, 0xff, 0x00, 0x65,]);
-                assert_eq!(peer_info.peer_as(), 32934);
+                assert_eq!(peer_info.peer_as(), AsNumber(32934));
                 assert_eq!(peer_info.peer_id(), 0x0a0a0a01);
 
                 assert_eq!(peer_info.timestamp(), (0x54a20e0b, 0x000e0c20));
@@ -554,13 +1253,13 @@ mod test {
                 let mut messages = peerup.messages(false, false);
                 match messages.next().unwrap() {
                     Ok(bgp::Message::Open(open)) => {
-                        assert_eq!(open.aut_num(), 100);
+                        assert_eq!(open.aut_num(), AsNumber(100));
                     }
                     x => panic!("Expected Message::Open, got {:?}", x)
                 }
                 match messages.next().unwrap() {
                     Ok(bgp::Message::Open(open)) => {
-                        assert_eq!(open.aut_num(),32934);
+                        assert_eq!(open.aut_num(), AsNumber(32934));
                     }
                     x => panic!("Expected Message::Open, got {:?}", x)
                 }
@@ -597,4 +1296,434 @@ mod test {
             assert!(messages.next().is_none());
         }
     }
+
+    fn per_peer_header(peer_type: u8, flags: u8) -> [u8; 42] {
+        let mut bytes = [0; 42];
+        bytes[0] = peer_type;
+        bytes[1] = flags;
+        bytes
+    }
+
+    #[test]
+    fn rib_kind() {
+        let cases = [
+            (BMP_PEER_GLOBAL, 0, RibKind::AdjRibInPre),
+            (BMP_PEER_GLOBAL, BMP_FLAG_L, RibKind::AdjRibInPost),
+            (BMP_PEER_RD, BMP_FLAG_ADJ_RIB_OUT, RibKind::AdjRibOutPre),
+            (BMP_PEER_GLOBAL, BMP_FLAG_ADJ_RIB_OUT | BMP_FLAG_L | BMP_FLAG_IPV6, RibKind::AdjRibOutPost),
+            (BMP_PEER_LOC_RIB, 0b10000000, RibKind::LocRib),
+        ];
+        for &(peer_type, flags, kind) in &cases {
+            let bytes = per_peer_header(peer_type, flags);
+            assert_eq!(PerPeer::from_bytes(&bytes).unwrap().rib_kind(), kind);
+        }
+    }
+
+    #[test]
+    fn peer_flags() {
+        use core::fmt::Write;
+        use types::FmtBuf;
+
+        let cases: [(u8, u8, &str); 4] = [
+            (BMP_PEER_GLOBAL, 0, "PeerFlags()"),
+            (BMP_PEER_GLOBAL, BMP_FLAG_IPV6 | BMP_FLAG_L, "PeerFlags(V | L)"),
+            (BMP_PEER_RD, BMP_FLAG_LEGACY_AS | BMP_FLAG_ADJ_RIB_OUT | 0x03, "PeerFlags(A | O | 0x03)"),
+            (BMP_PEER_LOC_RIB, BMP_FLAG_FILTERED | BMP_FLAG_L, "PeerFlags(F | 0x40)"),
+        ];
+        for &(peer_type, flags, expected) in &cases {
+            let bytes = per_peer_header(peer_type, flags);
+            let peer_flags = PerPeer::from_bytes(&bytes).unwrap().flags();
+            assert_eq!(peer_flags.bits(), flags);
+            let mut buf = FmtBuf::new();
+            write!(buf, "{:?}", peer_flags).unwrap();
+            assert_eq!(buf.as_bytes(), expected.as_bytes());
+        }
+
+        let bytes = per_peer_header(BMP_PEER_LOC_RIB, BMP_FLAG_FILTERED);
+        let peer_flags = PerPeer::from_bytes(&bytes).unwrap().flags();
+        assert!(peer_flags.filtered());
+        assert!(!peer_flags.ipv6());
+        assert_eq!(peer_flags.unknown(), 0);
+    }
+
+    #[test]
+    fn route_monitoring_events() {
+        let update = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x5d, 0x02, 0x00, 0x04, 0x18, 0x0a, 0x00,
+                      0x01, 0x00, 0x3e, 0x40, 0x01, 0x01, 0x00, 0x40, 0x02, 0x06, 0x02, 0x01,
+                      0x00, 0x00, 0xfd, 0xe8, 0x40, 0x03, 0x04, 0x0a, 0x00, 0x00, 0x01, 0x80,
+                      0x0f, 0x08, 0x00, 0x02, 0x01, 0x20, 0x20, 0x01, 0x0d, 0xb8, 0x80, 0x0e,
+                      0x1c, 0x00, 0x02, 0x01, 0x10, 0x20, 0x01, 0x0d, 0xb8, 0x00, 0x00, 0x00,
+                      0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x30, 0x20,
+                      0x01, 0x0d, 0xb8, 0x00, 0x01, 0x18, 0xc6, 0x33, 0x64];
+        let mut bytes = [0; 48 + 93];
+        bytes[..6].copy_from_slice(&[3, 0, 0, 0, 48 + 93, BMP_MSG_ROUTEMON]);
+        bytes[6] = BMP_PEER_GLOBAL;
+        bytes[6 + 26..6 + 30].copy_from_slice(&[0x00, 0x00, 0xfd, 0xe9]);
+        bytes[48..].copy_from_slice(&update);
+
+        let rm = match Bmp::from_bytes(&bytes) {
+            Ok(Bmp::RouteMonitoring(rm)) => rm,
+            x => panic!("expected Route Monitoring, got {:?}", x)
+        };
+        assert_eq!(rm.update(&SessionConfig::default()).unwrap().routes().count(), 4);
+        let mut events = rm.events(&SessionConfig::default());
+        let expected = [
+            (false, Prefix::Ipv4(Ipv4Prefix::new(0x18, &[0x0a, 0x00, 0x01]))),
+            (false, Prefix::Ipv6(Ipv6Prefix::new(0x20, &[0x20, 0x01, 0x0d, 0xb8]))),
+            (true, Prefix::Ipv4(Ipv4Prefix::new(0x18, &[0xc6, 0x33, 0x64]))),
+            (true, Prefix::Ipv6(Ipv6Prefix::new(0x30, &[0x20, 0x01, 0x0d, 0xb8, 0x00, 0x01]))),
+        ];
+        for &(announce, ref expected) in &expected {
+            match events.next() {
+                Some(Ok((peer, RouteEvent::Announce { ref nlri, ref attrs }))) if announce => {
+                    assert_eq!(peer.peer_as(), AsNumber(65001));
+                    assert_eq!(&nlri.prefix, expected);
+                    assert_eq!(attrs.clone().count(), 5);
+                }
+                Some(Ok((_, RouteEvent::Withdraw { ref nlri }))) if !announce => {
+                    assert_eq!(&nlri.prefix, expected);
+                }
+                x => panic!("expected {:?}, got {:?}", expected, x)
+            }
+        }
+        assert!(events.next().is_none());
+    }
+
+    #[test]
+    fn decode_with_peer_table() {
+        let keepalive = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                         0xff, 0xff, 0xff, 0xff, 0x00, 0x13, 0x04];
+        let mut bytes = [0; 48 + 19];
+        bytes[..6].copy_from_slice(&[3, 0, 0, 0, 48 + 19, BMP_MSG_ROUTEMON]);
+        bytes[6 + 1] = BMP_FLAG_LEGACY_AS;
+        bytes[6 + 30..6 + 34].copy_from_slice(&[0x0a, 0x00, 0x00, 0x01]);
+        bytes[48..].copy_from_slice(&keepalive);
+
+        let mut peers: PeerTable<4> = PeerTable::new();
+        let decoded = decode(&bytes, &peers).unwrap();
+        assert!(!decoded.config.four_byte_asn);
+        assert!(!decoded.config.add_paths);
+
+        let rm = match decoded.bmp {
+            Bmp::RouteMonitoring(ref rm) => rm,
+            ref x => panic!("expected RouteMonitoring, got {:?}", x)
+        };
+        assert!(matches!(rm.update(&decoded.config), Err(BgpError::Invalid)));
+        let peer = rm.peer_info();
+        assert_eq!(peer.len(), BMP_PER_PEER_HEADER_LEN);
+        assert_eq!(peer.asn_width(), 2);
+        let config = SessionConfig { add_paths: true, ..SessionConfig::default() };
+        peers.insert(&peer, config).unwrap();
+        let decoded = decode(&bytes, &peers).unwrap();
+        assert!(!decoded.config.four_byte_asn);
+        assert!(decoded.config.add_paths);
+        let mut messages = decoded.messages();
+        assert!(matches!(messages.next(), Some(Ok(bgp::Message::KeepAlive(_)))));
+        assert!(messages.next().is_none());
+
+        let initiation = [3, 0, 0, 0, 6, BMP_MSG_INIT];
+        let decoded = decode(&initiation, &peers).unwrap();
+        assert!(decoded.messages().next().is_none());
+    }
+
+    #[test]
+    fn deep_validate() {
+        // an UPDATE with a COMMUNITIES attribute of 3 octets
+        let update = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x1d, 0x02, 0x00, 0x00, 0x00, 0x06, 0xc0,
+                      0x08, 0x03, 0xfd, 0xe8, 0x00];
+        let mut bytes = [0; 48 + 29];
+        bytes[..6].copy_from_slice(&[3, 0, 0, 0, 48 + 29, BMP_MSG_ROUTEMON]);
+        bytes[48..].copy_from_slice(&update);
+        let bmp = Bmp::from_bytes(&bytes).unwrap();
+        assert!(matches!(bmp.deep_validate(), Err(BgpError::BadLength)));
+        assert!(matches!(decode(&bytes, &PeerTable::<1>::new()).unwrap().deep_validate(),
+                         Err(BgpError::BadLength)));
+
+        let bytes = [3, 0, 0, 0, 12, BMP_MSG_INIT, 0x00, 0x02, 0x00, 0x02, b'r', b'1'];
+        assert!(Bmp::from_bytes(&bytes).unwrap().deep_validate().is_ok());
+
+        // the same UPDATE mirrored in a BGP Message TLV
+        let mut bytes = [0; 48 + 4 + 29];
+        bytes[..6].copy_from_slice(&[3, 0, 0, 0, 48 + 4 + 29, BMP_MSG_ROUTEMIRROR]);
+        bytes[48..52].copy_from_slice(&[0x00, 0x00, 0x00, 29]);
+        bytes[52..].copy_from_slice(&update);
+        let bmp = Bmp::from_bytes(&bytes).unwrap();
+        assert!(matches!(bmp.deep_validate(), Err(BgpError::BadLength)));
+
+        // a reason TLV of 3 octets, and a truncated string TLV
+        let bytes = [3, 0, 0, 0, 13, BMP_MSG_TERM, 0x00, 0x01, 0x00, 0x03, 0x00, 0x00, 0x01];
+        assert!(matches!(Bmp::from_bytes(&bytes).unwrap().deep_validate(), Err(BgpError::BadLength)));
+        let bytes = [3, 0, 0, 0, 12, BMP_MSG_TERM, 0x00, 0x00, 0x00, 0x04, b'b', b'y'];
+        assert!(matches!(Bmp::from_bytes(&bytes).unwrap().deep_validate(), Err(BgpError::BadLength)));
+        let bytes = [3, 0, 0, 0, 12, BMP_MSG_TERM, 0x00, 0x01, 0x00, 0x02, 0x00, 0x01];
+        assert!(Bmp::from_bytes(&bytes).unwrap().deep_validate().is_ok());
+    }
+
+    #[test]
+    fn write_initiation_and_termination() {
+        let mut buf = [0; 64];
+        let mut builder = InitiationBuilder::new(&mut buf).unwrap();
+        builder.push(&Tlv { tlv_type: BMP_INFO_SYS_NAME, value: b"proxy1" }).unwrap();
+        builder.push(&Tlv { tlv_type: BMP_INFO_SYS_DESCR, value: b"bmp proxy" }).unwrap();
+        assert!(matches!(builder.push(&Tlv { tlv_type: BMP_INFO_STRING, value: &[b'x'; 64] }),
+                         Err(BgpError::BadLength)));
+        builder.push(&Tlv { tlv_type: BMP_INFO_STRING, value: b"hello" }).unwrap();
+        let bytes = builder.finish().unwrap();
+        assert_eq!(&bytes[..6], &[3, 0, 0, 0, 38, BMP_MSG_INIT]);
+        match Bmp::from_bytes(bytes) {
+            Ok(Bmp::Initiation(initiation)) => {
+                let mut info = initiation.router_info();
+                assert!(matches!(info.next(), Some(Ok(RouterInfo::SysName("proxy1")))));
+                assert!(matches!(info.next(), Some(Ok(RouterInfo::SysDescr("bmp proxy")))));
+                assert!(matches!(info.next(), Some(Ok(RouterInfo::String("hello")))));
+                assert!(info.next().is_none());
+            }
+            x => panic!("expected Initiation, got {:?}", x)
+        }
+
+        let mut buf = [0; 32];
+        let bytes = Termination::write(&mut buf, BMP_TERM_OUT_OF_RESOURCES, "bye").unwrap();
+        assert_eq!(bytes, &[3, 0, 0, 0, 19, BMP_MSG_TERM,
+                            0x00, 0x00, 0x00, 0x03, b'b', b'y', b'e',
+                            0x00, 0x01, 0x00, 0x02, 0x00, 0x02]);
+        match Bmp::from_bytes(bytes) {
+            Ok(Bmp::Termination(termination)) =>
+                assert_eq!(termination.reason().unwrap(), Some(BMP_TERM_OUT_OF_RESOURCES)),
+            x => panic!("expected Termination, got {:?}", x)
+        }
+        let bytes = Termination::write(&mut buf, BMP_TERM_ADMIN_CLOSE, "").unwrap();
+        assert_eq!(bytes.len(), 12);
+        assert!(matches!(Termination::write(&mut buf[..11], BMP_TERM_ADMIN_CLOSE, ""), Err(BgpError::BadLength)));
+    }
+
+    #[test]
+    fn truncated_tlvs() {
+        // the information TLV claims 4 octets, only 2 are present
+        let bytes = [3, 0, 0, 0, 12, BMP_MSG_INIT, 0x00, 0x02, 0x00, 0x04, b'r', b'1'];
+        let initiation = Initiation::from_bytes(&bytes);
+        let mut info = initiation.router_info();
+        assert!(matches!(info.next(), Some(Err(BgpError::BadLength))));
+        assert!(info.next().is_none());
+        assert!(Initiation::from_bytes(&bytes[..3]).router_info().next().is_none());
+
+        // a statistic TLV cut off in its header
+        let mut bytes = [0; 48 + 4 + 3];
+        bytes[..6].copy_from_slice(&[3, 0, 0, 0, 55, BMP_MSG_STATREPORT]);
+        bytes[48..52].copy_from_slice(&[0, 0, 0, 1]);
+        match Bmp::from_bytes(&bytes) {
+            Ok(Bmp::StatisticsReport(report)) => {
+                assert_eq!(report.stats_count(), 1);
+                let mut stats = report.stats();
+                assert!(matches!(stats.next(), Some(Err(BgpError::BadLength))));
+                assert!(stats.next().is_none());
+            }
+            x => panic!("expected Statistics Report, got {:?}", x)
+        }
+    }
+
+    #[test]
+    fn write_stats() {
+        let stats = [
+            Statistic::RejectedPrefixCount(7),
+            Statistic::LocRibSize(1 << 40),
+            Statistic::PerAfiSafiAdjRibInSize(IPV6_UNICAST, 3),
+            Statistic::Unknown(UnknownStatistic { stat_type: 0xfffe, inner: &[1, 2] }),
+        ];
+        let mut bytes = [0; 48 + 4 + 8 + 12 + 15 + 6];
+        bytes[..6].copy_from_slice(&[3, 0, 0, 0, 93, BMP_MSG_STATREPORT]);
+        bytes[48..52].copy_from_slice(&[0, 0, 0, 4]);
+        let mut pos = 52;
+        for stat in &stats {
+            pos += stat.write(&mut bytes[pos..]).unwrap().len();
+        }
+        assert_eq!(pos, bytes.len());
+        assert_eq!(&bytes[52..60], &[0x00, 0x00, 0x00, 0x04, 0, 0, 0, 7]);
+        assert_eq!(&bytes[72..79], &[0x00, 0x09, 0x00, 0x0b, 0x00, 0x02, 0x01]);
+
+        let report = StatisticsReport { inner: &bytes };
+        let mut decoded = report.stats();
+        assert!(matches!(decoded.next(), Some(Ok(Statistic::RejectedPrefixCount(7)))));
+        assert!(matches!(decoded.next(), Some(Ok(Statistic::LocRibSize(0x100_0000_0000)))));
+        assert!(matches!(decoded.next(), Some(Ok(Statistic::PerAfiSafiAdjRibInSize(IPV6_UNICAST, 3)))));
+        match decoded.next() {
+            Some(Ok(Statistic::Unknown(unknown))) => {
+                assert_eq!(unknown.stat_type, 0xfffe);
+                assert_eq!(unknown.inner, &[1, 2]);
+            }
+            x => panic!("expected unknown statistic, got {:?}", x)
+        }
+        assert!(decoded.next().is_none());
+
+        assert!(matches!(stats[1].write(&mut [0; 11]), Err(BgpError::BadLength)));
+    }
+
+    #[test]
+    fn build_stats_report() {
+        let mut header = per_peer_header(BMP_PEER_GLOBAL, BMP_FLAG_IPV6);
+        header[24..26].copy_from_slice(&[0, 1]);
+        let peer = PerPeer::from_bytes(&header).unwrap();
+        assert!(matches!(PerPeer::from_bytes(&header[1..]), Err(BgpError::BadLength)));
+
+        let mut buf = [0; 128];
+        let mut builder = StatisticsReportBuilder::new(&mut buf, &peer).unwrap();
+        builder.push(&Statistic::AdjRibsInSize(900_000)).unwrap();
+        builder.push(&Statistic::Unknown(UnknownStatistic { stat_type: 0, inner: &[1, 2] })).unwrap();
+        let bytes = builder.finish().unwrap();
+        assert_eq!(bytes.len(), 48 + 4 + 12 + 6);
+
+        let report = match Bmp::from_bytes(bytes) {
+            Ok(Bmp::StatisticsReport(report)) => report,
+            x => panic!("expected Statistics Report, got {:?}", x)
+        };
+        assert_eq!(report.peer_info().peer_ip(), IpAddrBytes::from_bytes(&header[10..26]).unwrap());
+        assert_eq!(report.stats_count(), 2);
+        let mut stats = report.stats();
+        assert!(matches!(stats.next(), Some(Ok(Statistic::AdjRibsInSize(900_000)))));
+        assert!(matches!(stats.next(), Some(Ok(Statistic::Unknown(_)))));
+        assert!(stats.next().is_none());
+
+        let mut buf = [0; 52 + 4];
+        let mut builder = StatisticsReportBuilder::new(&mut buf, &peer).unwrap();
+        assert!(matches!(builder.push(&Statistic::LocRibSize(1)), Err(BgpError::BadLength)));
+        assert_eq!(builder.finish().unwrap().len(), 52);
+        assert!(matches!(StatisticsReportBuilder::new(&mut [0; 51], &peer), Err(BgpError::BadLength)));
+    }
+
+    #[test]
+    fn stats_count_bounds_stats() {
+        // one statistic followed by two octets of padding
+        let mut bytes = [0; 48 + 4 + 8 + 2];
+        bytes[..6].copy_from_slice(&[3, 0, 0, 0, 62, BMP_MSG_STATREPORT]);
+        bytes[48..60].copy_from_slice(&[0, 0, 0, 1, 0x00, 0x00, 0x00, 0x04, 0, 0, 0, 7]);
+        let report = match Bmp::from_bytes(&bytes) {
+            Ok(Bmp::StatisticsReport(report)) => report,
+            x => panic!("expected Statistics Report, got {:?}", x)
+        };
+        let mut stats = report.stats();
+        assert!(matches!(stats.next(), Some(Ok(Statistic::RejectedPrefixCount(7)))));
+        assert!(stats.next().is_none());
+        assert_eq!(stats.trailing_bytes(), &[0, 0]);
+
+        // the count claims a second statistic that is not there
+        bytes[51] = 2;
+        let report = StatisticsReport { inner: &bytes[..60] };
+        let mut stats = report.stats();
+        assert!(stats.next().unwrap().is_ok());
+        assert!(matches!(stats.next(), Some(Err(BgpError::BadLength))));
+        assert!(stats.next().is_none());
+    }
+
+    #[test]
+    fn peer_up_information() {
+        let open = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                    0xff, 0xff, 0xff, 0xff, 0x00, 0x1d, 0x01, 0x04, 0xfd, 0xe8, 0x00, 0xb4,
+                    0x0a, 0x00, 0x00, 0x01, 0x00];
+        let mut bytes = [0; 68 + 29 * 2 + 6];
+        bytes[..6].copy_from_slice(&[3, 0, 0, 0, 132, BMP_MSG_PEERUP]);
+        bytes[6 + 22..6 + 26].copy_from_slice(&[192, 0, 2, 2]);
+        bytes[48 + 12..48 + 16].copy_from_slice(&[192, 0, 2, 1]);
+        bytes[68..97].copy_from_slice(&open);
+        bytes[97..126].copy_from_slice(&open);
+        bytes[126..].copy_from_slice(&[0x00, 0x00, 0x00, 0x02, b'r', b'1']);
+        let peer_up = match Bmp::from_bytes(&bytes) {
+            Ok(Bmp::PeerUpNotification(peer_up)) => peer_up,
+            x => panic!("expected Peer Up Notification, got {:?}", x)
+        };
+        assert_eq!(peer_up.peer_info().peer_ip(), IpAddrBytes::V4(&[192, 0, 2, 2]));
+        assert_eq!(peer_up.local_address(), IpAddrBytes::V4(&[192, 0, 2, 1]));
+        let mut info = peer_up.information().unwrap();
+        assert!(matches!(info.next(), Some(Ok(RouterInfo::String("r1")))));
+        assert!(info.next().is_none());
+
+        let peer_up = PeerUpNotification { inner: &bytes[..110] };
+        assert!(matches!(peer_up.information(), Err(BgpError::BadLength)));
+    }
+
+    #[test]
+    fn route_mirroring() {
+        let keepalive = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                         0xff, 0xff, 0xff, 0xff, 0x00, 0x13, 0x04];
+        let mut bytes = [0; 48 + 6 + 4 + 19];
+        bytes[..6].copy_from_slice(&[3, 0, 0, 0, 77, BMP_MSG_ROUTEMIRROR]);
+        bytes[48..58].copy_from_slice(&[0x00, 0x01, 0x00, 0x02, 0x00, 0x01, // messages lost
+                                        0x00, 0x00, 0x00, 0x13]);
+        bytes[58..].copy_from_slice(&keepalive);
+        let mirroring = match Bmp::from_bytes(&bytes) {
+            Ok(Bmp::RouteMirroring(mirroring)) => mirroring,
+            x => panic!("expected Route Mirroring, got {:?}", x)
+        };
+        let mut messages = mirroring.mirrored_messages(&SessionConfig::default());
+        assert!(matches!(messages.next(), Some(Ok(bgp::Message::KeepAlive(_)))));
+        assert!(messages.next().is_none());
+
+        // the BGP Message TLV is cut short
+        let mirroring = RouteMirroring { inner: &bytes[..70] };
+        let mut messages = mirroring.mirrored_messages(&SessionConfig::default());
+        assert!(matches!(messages.next(), Some(Err(BgpError::BadLength))));
+        assert!(messages.next().is_none());
+    }
+
+    #[test]
+    fn lenient_message_iter() {
+        // a message with an unknown type followed by a KEEPALIVE
+        let bytes = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x13, 0x09,
+                      0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x13, 0x04];
+
+        let mut messages = MessageIter::new(bytes, SessionConfig::default());
+        assert!(messages.next().unwrap().is_err());
+        assert!(messages.next().is_none());
+
+        let mut messages = MessageIter::new(bytes, SessionConfig::default()).lenient();
+        assert!(messages.next().unwrap().is_err());
+        match messages.next() {
+            Some(Ok(bgp::Message::KeepAlive(_))) => {}
+            x => panic!("expected KEEPALIVE, got {:?}", x)
+        }
+        assert!(messages.next().is_none());
+    }
+
+    #[test]
+    fn peer_up_session_config() {
+        let open = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                    0xff, 0xff, 0xff, 0xff, 0x00, 0x29, 0x01, 0x04, 0xfd, 0xe8, 0x00, 0xb4,
+                    0x0a, 0x00, 0x00, 0x01, 0x0c, 0x02, 0x02, 0x06, 0x00, 0x02, 0x06, 0x41,
+                    0x04, 0x00, 0x00, 0xfd, 0xe8];
+        let mut bytes = [0; 68 + 41 * 2];
+        bytes[..6].copy_from_slice(&[3, 0, 0, 0, 150, BMP_MSG_PEERUP]);
+        bytes[68..68 + 41].copy_from_slice(&open);
+        bytes[68 + 41..].copy_from_slice(&open);
+
+        match Bmp::from_bytes(&bytes) {
+            Ok(Bmp::PeerUpNotification(peer_up)) => {
+                let config = peer_up.session_config().unwrap();
+                assert!(config.four_byte_asn);
+                assert!(config.extended_message);
+                assert_eq!(config.max_message_len(), 65535);
+            }
+            x => panic!("expected Peer Up, got {:?}", x)
+        }
+
+        // the received OPEN lacks the Extended Message capability
+        let received = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                        0xff, 0xff, 0xff, 0xff, 0x00, 0x25, 0x01, 0x04, 0xfd, 0xe9, 0x00, 0xb4,
+                        0x0a, 0x00, 0x00, 0x02, 0x08, 0x02, 0x06, 0x41, 0x04, 0x00, 0x00, 0xfd,
+                        0xe9];
+        let mut bytes = [0; 68 + 41 + 37];
+        bytes[..6].copy_from_slice(&[3, 0, 0, 0, 146, BMP_MSG_PEERUP]);
+        bytes[68..68 + 41].copy_from_slice(&open);
+        bytes[68 + 41..].copy_from_slice(&received);
+
+        match Bmp::from_bytes(&bytes) {
+            Ok(Bmp::PeerUpNotification(peer_up)) => {
+                let config = peer_up.session_config().unwrap();
+                assert!(config.four_byte_asn);
+                assert!(!config.extended_message);
+            }
+            x => panic!("expected Peer Up, got {:?}", x)
+        }
+    }
 }
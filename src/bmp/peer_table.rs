@@ -0,0 +1,171 @@
+use bgp::SessionConfig;
+use types::*;
+use super::{decode, Bmp, Decoded, PeerInfo, PerPeer};
+
+/// Identifies a monitored peer: its peer distinguisher, address and BGP
+/// identifier, as given in the per-peer header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerKey {
+    pub distinguisher: [u8; 8],
+    pub address: [u8; 16],
+    pub bgp_id: u32,
+}
+
+impl<'a, 'b> From<&'b PerPeer<'a>> for PeerKey {
+    fn from(peer: &'b PerPeer<'a>) -> PeerKey {
+        let mut key = PeerKey {
+            distinguisher: [0; 8],
+            address: [0; 16],
+            bgp_id: peer.peer_id(),
+        };
+        key.distinguisher.copy_from_slice(peer.peer_distinguisher().octets());
+        key.address.copy_from_slice(peer.peer_address());
+        key
+    }
+}
+
+/// Session parameters of up to `N` monitored peers, without allocating.
+#[derive(Debug, Clone)]
+pub struct PeerTable<const N: usize = 64> {
+    entries: [Option<(PeerKey, SessionConfig)>; N],
+}
+
+impl<const N: usize> PeerTable<N> {
+    pub fn new() -> PeerTable<N> {
+        PeerTable { entries: [None; N] }
+    }
+
+    /// Records `config` for `peer`, replacing any previous entry. Fails
+    /// with `BgpError::BadLength` when the table is full.
+    pub fn insert(&mut self, peer: &PerPeer, config: SessionConfig) -> Result<()> {
+        let key = PeerKey::from(peer);
+        let slot = match self.entries.iter().position(|entry| matches!(*entry, Some((k, _)) if k == key)) {
+            Some(index) => index,
+            None => self.entries.iter().position(Option::is_none).ok_or(BgpError::BadLength)?,
+        };
+        self.entries[slot] = Some((key, config));
+        Ok(())
+    }
+
+    pub fn get(&self, peer: &PerPeer) -> Option<SessionConfig> {
+        let key = PeerKey::from(peer);
+        self.entries.iter()
+            .filter_map(|entry| *entry)
+            .find(|&(k, _)| k == key)
+            .map(|(_, config)| config)
+    }
+
+    /// The parameters to parse the monitored UPDATEs of `peer` with: those
+    /// recorded for it, or `SessionConfig::default()`, with the AS number
+    /// width given by the A flag. See `PerPeer::asn_width`.
+    pub fn config_for(&self, peer: &PerPeer) -> SessionConfig {
+        peer.session_config(self.get(peer).unwrap_or_default())
+    }
+
+    pub fn remove(&mut self, peer: &PerPeer) -> Option<SessionConfig> {
+        let key = PeerKey::from(peer);
+        let slot = self.entries.iter().position(|entry| matches!(*entry, Some((k, _)) if k == key))?;
+        self.entries[slot].take().map(|(_, config)| config)
+    }
+
+    /// Updates the table from a BMP message: a Peer Up Notification
+    /// records the session parameters its OPEN messages negotiated, and a
+    /// Peer Down Notification forgets them. Other messages are ignored.
+    pub fn learn(&mut self, bmp: &Bmp) -> Result<()> {
+        match *bmp {
+            Bmp::PeerUpNotification(ref peer_up) =>
+                self.insert(&peer_up.peer_info(), peer_up.session_config()?),
+            Bmp::PeerDownNotification(ref peer_down) => {
+                self.remove(&peer_down.peer_info());
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Parses a BMP message with `bmp::decode` and learns from it, so
+    /// that Route Monitoring messages of a peer are parsed with the
+    /// parameters of its last Peer Up Notification.
+    pub fn decode<'a>(&mut self, bytes: &'a [u8]) -> Result<Decoded<'a>> {
+        let decoded = decode(bytes, self)?;
+        self.learn(&decoded.bmp)?;
+        Ok(decoded)
+    }
+
+    /// The number of peers in the table.
+    pub fn len(&self) -> usize {
+        self.entries.iter().filter(|entry| entry.is_some()).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<const N: usize> Default for PeerTable<N> {
+    fn default() -> PeerTable<N> {
+        PeerTable::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bmp::*;
+
+    #[test]
+    fn insert_and_remove() {
+        let mut a = [0; 42];
+        a[30..34].copy_from_slice(&[0x0a, 0x00, 0x00, 0x01]);
+        let mut b = a;
+        b[2..10].copy_from_slice(&[0, 0, 0xfd, 0xe8, 0, 0, 0, 1]);
+        let (a, b) = (PerPeer::from_bytes(&a).unwrap(), PerPeer::from_bytes(&b).unwrap());
+
+        let mut peers: PeerTable<1> = PeerTable::new();
+        assert!(peers.is_empty());
+        let config = SessionConfig { four_byte_asn: false, ..SessionConfig::default() };
+        peers.insert(&a, SessionConfig::default()).unwrap();
+        peers.insert(&a, config).unwrap();
+        assert_eq!(peers.len(), 1);
+        assert_eq!(peers.get(&a), Some(config));
+        assert_eq!(peers.get(&b), None);
+        assert!(peers.config_for(&a).four_byte_asn);
+        assert!(matches!(peers.insert(&b, config), Err(BgpError::BadLength)));
+
+        assert_eq!(peers.remove(&a), Some(config));
+        assert!(peers.is_empty());
+        peers.insert(&b, config).unwrap();
+        assert_eq!(peers.get(&b), Some(config));
+    }
+
+    #[test]
+    fn learn_from_peer_up() {
+        let open = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                    0xff, 0xff, 0xff, 0xff, 0x00, 0x29, 0x01, 0x04, 0xfd, 0xe8, 0x00, 0xb4,
+                    0x0a, 0x00, 0x00, 0x01, 0x0c, 0x02, 0x02, 0x06, 0x00, 0x02, 0x06, 0x41,
+                    0x04, 0x00, 0x00, 0xfd, 0xe8];
+        let mut peer_up = [0; 68 + 41 * 2];
+        peer_up[..6].copy_from_slice(&[3, 0, 0, 0, 150, BMP_MSG_PEERUP]);
+        peer_up[6 + 30..6 + 34].copy_from_slice(&[0x0a, 0x00, 0x00, 0x02]);
+        peer_up[68..68 + 41].copy_from_slice(&open);
+        peer_up[68 + 41..].copy_from_slice(&open);
+
+        let mut route_monitoring = [0; 48];
+        route_monitoring.copy_from_slice(&peer_up[..48]);
+        route_monitoring[4..6].copy_from_slice(&[48, BMP_MSG_ROUTEMON]);
+
+        let mut peer_down = [0; 49];
+        peer_down[..48].copy_from_slice(&peer_up[..48]);
+        peer_down[4..6].copy_from_slice(&[49, BMP_MSG_PEERDOWN]);
+        peer_down[48] = 4;
+
+        let mut peers: PeerTable = PeerTable::new();
+        assert!(!peers.decode(&route_monitoring).unwrap().config.extended_message);
+        peers.decode(&peer_up).unwrap();
+        assert_eq!(peers.len(), 1);
+        assert!(peers.decode(&route_monitoring).unwrap().config.extended_message);
+        peers.decode(&peer_down).unwrap();
+        assert!(peers.is_empty());
+        assert!(!peers.decode(&route_monitoring).unwrap().config.extended_message);
+    }
+}
@@ -0,0 +1,492 @@
+//! C interface for collectors not written in Rust.
+//!
+//! Build a shared or static library with
+//! `cargo rustc --release --features ffi --crate-type cdylib` (or
+//! `staticlib`).
+//!
+//! Parsed messages and iterators are returned as opaque handles. The
+//! caller must keep the input buffer alive and unmodified for as long as
+//! any handle derived from it exists, and release every handle with the
+//! matching `*_free` function.
+//!
+//! Functions returning `c_int` return `BGPARSE_OK` (or, for `*_next`,
+//! `BGPARSE_DONE` when the iterator is exhausted) on success and one of
+//! the negative `BGPARSE_ERR_*` codes on failure.
+
+use std::boxed::Box;
+use std::os::raw::c_int;
+use std::ptr;
+use std::slice;
+
+use types::*;
+use bgp::Message;
+use bgp::update::path_attr::PathAttrIter;
+use bgp::update::withdrawn_routes::WithdrawnRoutes;
+use bgp::update::nlri::{Nlri, NlriIter};
+use bmp::*;
+
+pub const BGPARSE_OK: c_int = 0;
+pub const BGPARSE_DONE: c_int = 1;
+pub const BGPARSE_ERR_BAD_LENGTH: c_int = -1;
+pub const BGPARSE_ERR_INVALID: c_int = -2;
+pub const BGPARSE_ERR_NULL: c_int = -3;
+
+fn error_code(err: BgpError) -> c_int {
+    match err {
+        BgpError::BadLength => BGPARSE_ERR_BAD_LENGTH,
+        BgpError::Invalid => BGPARSE_ERR_INVALID,
+    }
+}
+
+/// Opaque handle to a parsed BGP message.
+pub struct BgparseMessage {
+    message: Message<'static>,
+}
+
+/// Opaque handle to a parsed BMP message.
+pub struct BgparseBmp {
+    bmp: Bmp<'static>,
+}
+
+/// Opaque handle to an iterator over the BGP messages carried in a BMP
+/// Route Monitoring or Peer Up message.
+pub struct BgparseMessageIter {
+    inner: MessageIter<'static>,
+}
+
+enum PrefixSource {
+    Withdrawn(WithdrawnRoutes<'static>),
+    Nlri(NlriIter<'static>),
+}
+
+/// Opaque handle to an iterator over the withdrawn routes or NLRI of an
+/// UPDATE.
+pub struct BgparsePrefixIter {
+    inner: PrefixSource,
+}
+
+/// Opaque handle to an iterator over the path attributes of an UPDATE.
+pub struct BgparseAttrIter {
+    inner: PathAttrIter<'static>,
+}
+
+/// An IP prefix. `addr` holds the significant octets of the prefix,
+/// zero-padded.
+#[repr(C)]
+pub struct BgparsePrefix {
+    pub afi: u16,
+    pub masklen: u8,
+    pub has_path_id: u8,
+    pub path_id: u32,
+    pub addr: [u8; 16],
+}
+
+/// A path attribute. `value` points into the caller's buffer.
+#[repr(C)]
+pub struct BgparseAttr {
+    pub flags: u8,
+    pub code: u8,
+    pub value: *const u8,
+    pub value_len: usize,
+}
+
+unsafe fn input<'a>(buf: *const u8, len: usize) -> Option<&'a [u8]> {
+    if buf.is_null() {
+        None
+    } else {
+        Some(slice::from_raw_parts(buf, len))
+    }
+}
+
+fn write_prefix(out: &mut BgparsePrefix, nlri: &Nlri) -> c_int {
+    let (afi, max_len) = match nlri.prefix {
+        Prefix::Ipv4(_) => (1, 4),
+        Prefix::Ipv6(_) => (2, 16),
+    };
+    let octets = nlri.prefix.octets();
+    if octets.len() > max_len {
+        return BGPARSE_ERR_INVALID;
+    }
+    out.afi = afi;
+    out.masklen = nlri.prefix.mask_len();
+    out.has_path_id = nlri.path_id.is_some() as u8;
+    out.path_id = nlri.path_id.unwrap_or(0);
+    out.addr = [0; 16];
+    out.addr[..octets.len()].copy_from_slice(octets);
+    BGPARSE_OK
+}
+
+/// Parses the BGP message in `buf[..len]`.
+///
+/// # Safety
+///
+/// `buf` must point to `len` readable bytes that outlive the returned
+/// handle, and `out` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn bgparse_message_parse(buf: *const u8, len: usize,
+                                               four_byte_asn: bool, add_paths: bool,
+                                               out: *mut *mut BgparseMessage) -> c_int {
+    if out.is_null() {
+        return BGPARSE_ERR_NULL;
+    }
+    *out = ptr::null_mut();
+    let raw = match input(buf, len) {
+        Some(raw) => raw,
+        None => return BGPARSE_ERR_NULL,
+    };
+    match Message::from_bytes(raw, four_byte_asn, add_paths) {
+        Ok(message) => {
+            *out = Box::into_raw(Box::new(BgparseMessage { message }));
+            BGPARSE_OK
+        }
+        Err(err) => error_code(err),
+    }
+}
+
+/// Returns the BGP message type code (1 = OPEN, 2 = UPDATE, ...), or 0
+/// if `message` is null.
+///
+/// # Safety
+///
+/// `message` must be null or a handle returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn bgparse_message_type(message: *const BgparseMessage) -> u8 {
+    match message.as_ref() {
+        Some(message) => message.message.msg_type(),
+        None => 0,
+    }
+}
+
+/// # Safety
+///
+/// `message` must be null or a handle returned by this library that has
+/// not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn bgparse_message_free(message: *mut BgparseMessage) {
+    if !message.is_null() {
+        drop(Box::from_raw(message));
+    }
+}
+
+/// Starts iterating over the withdrawn routes of an UPDATE.
+///
+/// # Safety
+///
+/// `message` must be a handle returned by this library and `out` a valid
+/// pointer. The iterator must not outlive the message's input buffer.
+#[no_mangle]
+pub unsafe extern "C" fn bgparse_update_withdrawn(message: *const BgparseMessage,
+                                                  out: *mut *mut BgparsePrefixIter) -> c_int {
+    if out.is_null() {
+        return BGPARSE_ERR_NULL;
+    }
+    *out = ptr::null_mut();
+    match message.as_ref().map(|m| &m.message) {
+        Some(Message::Update(update)) => {
+            let inner = PrefixSource::Withdrawn(update.withdrawn_routes());
+            *out = Box::into_raw(Box::new(BgparsePrefixIter { inner }));
+            BGPARSE_OK
+        }
+        Some(_) => BGPARSE_ERR_INVALID,
+        None => BGPARSE_ERR_NULL,
+    }
+}
+
+/// Starts iterating over the NLRI of an UPDATE.
+///
+/// # Safety
+///
+/// `message` must be a handle returned by this library and `out` a valid
+/// pointer. The iterator must not outlive the message's input buffer.
+#[no_mangle]
+pub unsafe extern "C" fn bgparse_update_nlris(message: *const BgparseMessage,
+                                              out: *mut *mut BgparsePrefixIter) -> c_int {
+    if out.is_null() {
+        return BGPARSE_ERR_NULL;
+    }
+    *out = ptr::null_mut();
+    match message.as_ref().map(|m| &m.message) {
+        Some(Message::Update(update)) => {
+            let inner = PrefixSource::Nlri(update.nlris());
+            *out = Box::into_raw(Box::new(BgparsePrefixIter { inner }));
+            BGPARSE_OK
+        }
+        Some(_) => BGPARSE_ERR_INVALID,
+        None => BGPARSE_ERR_NULL,
+    }
+}
+
+/// Writes the next prefix to `out`. Returns `BGPARSE_DONE` at the end.
+///
+/// # Safety
+///
+/// `iter` must be a handle returned by this library and `out` a valid
+/// pointer.
+#[no_mangle]
+pub unsafe extern "C" fn bgparse_prefix_iter_next(iter: *mut BgparsePrefixIter,
+                                                  out: *mut BgparsePrefix) -> c_int {
+    let (iter, out) = match (iter.as_mut(), out.as_mut()) {
+        (Some(iter), Some(out)) => (iter, out),
+        _ => return BGPARSE_ERR_NULL,
+    };
+    match iter.inner {
+        PrefixSource::Withdrawn(ref mut routes) => match routes.next() {
+            Some(Ok(nlri)) => write_prefix(out, &nlri),
+            Some(Err(err)) => error_code(err),
+            None => BGPARSE_DONE,
+        },
+        PrefixSource::Nlri(ref mut nlris) => match nlris.next() {
+            Some(Ok(nlri)) => write_prefix(out, &nlri),
+            Some(Err(err)) => error_code(err),
+            None => BGPARSE_DONE,
+        },
+    }
+}
+
+/// # Safety
+///
+/// `iter` must be null or a handle returned by this library that has not
+/// been freed.
+#[no_mangle]
+pub unsafe extern "C" fn bgparse_prefix_iter_free(iter: *mut BgparsePrefixIter) {
+    if !iter.is_null() {
+        drop(Box::from_raw(iter));
+    }
+}
+
+/// Starts iterating over the path attributes of an UPDATE.
+///
+/// # Safety
+///
+/// `message` must be a handle returned by this library and `out` a valid
+/// pointer. The iterator must not outlive the message's input buffer.
+#[no_mangle]
+pub unsafe extern "C" fn bgparse_update_attrs(message: *const BgparseMessage,
+                                              out: *mut *mut BgparseAttrIter) -> c_int {
+    if out.is_null() {
+        return BGPARSE_ERR_NULL;
+    }
+    *out = ptr::null_mut();
+    match message.as_ref() {
+        Some(&BgparseMessage { message: Message::Update(ref update) }) => {
+            *out = Box::into_raw(Box::new(BgparseAttrIter { inner: update.path_attrs() }));
+            BGPARSE_OK
+        }
+        Some(_) => BGPARSE_ERR_INVALID,
+        None => BGPARSE_ERR_NULL,
+    }
+}
+
+/// Writes the next path attribute to `out`. Returns `BGPARSE_DONE` at the
+/// end. The attribute value is not decoded.
+///
+/// # Safety
+///
+/// `iter` must be a handle returned by this library and `out` a valid
+/// pointer.
+#[no_mangle]
+pub unsafe extern "C" fn bgparse_attr_iter_next(iter: *mut BgparseAttrIter,
+                                                out: *mut BgparseAttr) -> c_int {
+    let (iter, out) = match (iter.as_mut(), out.as_mut()) {
+        (Some(iter), Some(out)) => (iter, out),
+        _ => return BGPARSE_ERR_NULL,
+    };
+    let raw = match iter.inner.next_raw() {
+        Some(Ok(raw)) => raw,
+        Some(Err(err)) => return error_code(err),
+        None => return BGPARSE_DONE,
+    };
+    out.flags = raw.flags;
+    out.code = raw.code;
+    out.value = raw.value.as_ptr();
+    out.value_len = raw.value.len();
+    BGPARSE_OK
+}
+
+/// # Safety
+///
+/// `iter` must be null or a handle returned by this library that has not
+/// been freed.
+#[no_mangle]
+pub unsafe extern "C" fn bgparse_attr_iter_free(iter: *mut BgparseAttrIter) {
+    if !iter.is_null() {
+        drop(Box::from_raw(iter));
+    }
+}
+
+/// Parses the BMP message in `buf[..len]`.
+///
+/// # Safety
+///
+/// `buf` must point to `len` readable bytes that outlive the returned
+/// handle, and `out` must be a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn bgparse_bmp_parse(buf: *const u8, len: usize,
+                                           out: *mut *mut BgparseBmp) -> c_int {
+    if out.is_null() {
+        return BGPARSE_ERR_NULL;
+    }
+    *out = ptr::null_mut();
+    let raw = match input(buf, len) {
+        Some(raw) => raw,
+        None => return BGPARSE_ERR_NULL,
+    };
+    match Bmp::from_bytes(raw) {
+        Ok(bmp) => {
+            *out = Box::into_raw(Box::new(BgparseBmp { bmp }));
+            BGPARSE_OK
+        }
+        Err(err) => error_code(err),
+    }
+}
+
+/// Returns the BMP message type (one of the `BMP_MSG_*` constants), or
+/// 255 if `bmp` is null.
+///
+/// # Safety
+///
+/// `bmp` must be null or a handle returned by this library.
+#[no_mangle]
+pub unsafe extern "C" fn bgparse_bmp_type(bmp: *const BgparseBmp) -> u8 {
+    match bmp.as_ref().map(|b| &b.bmp) {
+        Some(&Bmp::RouteMonitoring(_)) => BMP_MSG_ROUTEMON,
+        Some(&Bmp::StatisticsReport(_)) => BMP_MSG_STATREPORT,
+        Some(&Bmp::PeerDownNotification(_)) => BMP_MSG_PEERDOWN,
+        Some(&Bmp::PeerUpNotification(_)) => BMP_MSG_PEERUP,
+        Some(&Bmp::Initiation(_)) => BMP_MSG_INIT,
+        Some(&Bmp::Termination(_)) => BMP_MSG_TERM,
+        Some(&Bmp::RouteMirroring(_)) => BMP_MSG_ROUTEMIRROR,
+        None => 255,
+    }
+}
+
+/// Starts iterating over the BGP messages carried in a Route Monitoring or
+/// Peer Up message.
+///
+/// # Safety
+///
+/// `bmp` must be a handle returned by this library and `out` a valid
+/// pointer. The iterator must not outlive the BMP message's input buffer.
+#[no_mangle]
+pub unsafe extern "C" fn bgparse_bmp_messages(bmp: *const BgparseBmp,
+                                              four_byte_asn: bool, add_paths: bool,
+                                              out: *mut *mut BgparseMessageIter) -> c_int {
+    if out.is_null() {
+        return BGPARSE_ERR_NULL;
+    }
+    *out = ptr::null_mut();
+    let inner = match bmp.as_ref().map(|b| &b.bmp) {
+        Some(Bmp::RouteMonitoring(rm)) => rm.messages(four_byte_asn, add_paths),
+        Some(Bmp::PeerUpNotification(pu)) => pu.messages(four_byte_asn, add_paths),
+        Some(_) => return BGPARSE_ERR_INVALID,
+        None => return BGPARSE_ERR_NULL,
+    };
+    *out = Box::into_raw(Box::new(BgparseMessageIter { inner }));
+    BGPARSE_OK
+}
+
+/// Parses the next BGP message into a new handle stored in `out`, which
+/// must be released with `bgparse_message_free`. Returns `BGPARSE_DONE`
+/// at the end.
+///
+/// # Safety
+///
+/// `iter` must be a handle returned by this library and `out` a valid
+/// pointer.
+#[no_mangle]
+pub unsafe extern "C" fn bgparse_message_iter_next(iter: *mut BgparseMessageIter,
+                                                   out: *mut *mut BgparseMessage) -> c_int {
+    let iter = match iter.as_mut() {
+        Some(iter) if !out.is_null() => iter,
+        _ => return BGPARSE_ERR_NULL,
+    };
+    *out = ptr::null_mut();
+    match iter.inner.next() {
+        Some(Ok(message)) => {
+            *out = Box::into_raw(Box::new(BgparseMessage { message }));
+            BGPARSE_OK
+        }
+        Some(Err(err)) => error_code(err),
+        None => BGPARSE_DONE,
+    }
+}
+
+/// # Safety
+///
+/// `iter` must be null or a handle returned by this library that has not
+/// been freed.
+#[no_mangle]
+pub unsafe extern "C" fn bgparse_message_iter_free(iter: *mut BgparseMessageIter) {
+    if !iter.is_null() {
+        drop(Box::from_raw(iter));
+    }
+}
+
+/// # Safety
+///
+/// `bmp` must be null or a handle returned by this library that has not
+/// been freed.
+#[no_mangle]
+pub unsafe extern "C" fn bgparse_bmp_free(bmp: *mut BgparseBmp) {
+    if !bmp.is_null() {
+        drop(Box::from_raw(bmp));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iterate_update() {
+        let bytes = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x2f, 0x02, 0x00, 0x00, 0x00, 0x14, 0x40,
+                      0x01, 0x01, 0x00, 0x40, 0x02, 0x06, 0x02, 0x01, 0x00, 0x00, 0xfb, 0xff,
+                      0x40, 0x03, 0x04, 0x0a, 0x00, 0x0e, 0x01, 0x18, 0xc6, 0x33, 0x64];
+        unsafe {
+            let mut message = ptr::null_mut();
+            assert_eq!(bgparse_message_parse(bytes.as_ptr(), bytes.len(), true, false,
+                                             &mut message), BGPARSE_OK);
+            assert_eq!(bgparse_message_type(message), 2);
+
+            let mut attrs = ptr::null_mut();
+            assert_eq!(bgparse_update_attrs(message, &mut attrs), BGPARSE_OK);
+            let mut attr = BgparseAttr { flags: 0, code: 0, value: ptr::null(), value_len: 0 };
+            let mut codes = [0; 3];
+            for code in &mut codes {
+                assert_eq!(bgparse_attr_iter_next(attrs, &mut attr), BGPARSE_OK);
+                *code = attr.code;
+            }
+            assert_eq!(codes, [1, 2, 3]);
+            assert_eq!(attr.value_len, 4);
+            assert_eq!(bgparse_attr_iter_next(attrs, &mut attr), BGPARSE_DONE);
+            bgparse_attr_iter_free(attrs);
+
+            let mut nlris = ptr::null_mut();
+            assert_eq!(bgparse_update_nlris(message, &mut nlris), BGPARSE_OK);
+            let mut prefix = BgparsePrefix { afi: 0, masklen: 0, has_path_id: 0, path_id: 0,
+                                             addr: [0; 16] };
+            assert_eq!(bgparse_prefix_iter_next(nlris, &mut prefix), BGPARSE_OK);
+            assert_eq!(prefix.masklen, 24);
+            assert_eq!(&prefix.addr[..4], &[0xc6, 0x33, 0x64, 0x00]);
+            assert_eq!(bgparse_prefix_iter_next(nlris, &mut prefix), BGPARSE_DONE);
+            bgparse_prefix_iter_free(nlris);
+
+            bgparse_message_free(message);
+        }
+    }
+
+    #[test]
+    fn parse_errors() {
+        let bytes = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x14, 0x04];
+        unsafe {
+            let mut message = ptr::null_mut();
+            assert_eq!(bgparse_message_parse(bytes.as_ptr(), bytes.len(), true, false,
+                                             &mut message), BGPARSE_ERR_BAD_LENGTH);
+            assert!(message.is_null());
+            assert_eq!(bgparse_message_parse(ptr::null(), 0, true, false, &mut message),
+                       BGPARSE_ERR_NULL);
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use core::convert::TryFrom;
+use core::fmt;
+use types::*;
+
+/// An IPv4 or IPv6 address, borrowed from the message it appears in.
+///
+/// Displayed as `192.0.2.1` and, following RFC 5952, `2001:db8::1`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IpAddrBytes<'a> {
+    V4(&'a [u8; 4]),
+    V6(&'a [u8; 16]),
+}
+
+impl<'a> IpAddrBytes<'a> {
+    /// An IPv4 address from 4 octets or an IPv6 address from 16. Fails
+    /// with `BgpError::BadLength` for any other length.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<IpAddrBytes<'a>> {
+        match bytes.len() {
+            4 => Ok(IpAddrBytes::v4(bytes)),
+            16 => Ok(IpAddrBytes::v6(bytes)),
+            _ => Err(BgpError::BadLength),
+        }
+    }
+
+    /// The IPv4 address in the first 4 octets of `bytes`.
+    pub(crate) fn v4(bytes: &'a [u8]) -> IpAddrBytes<'a> {
+        IpAddrBytes::V4(<&[u8; 4]>::try_from(&bytes[..4]).unwrap())
+    }
+
+    /// The IPv6 address in the first 16 octets of `bytes`.
+    pub(crate) fn v6(bytes: &'a [u8]) -> IpAddrBytes<'a> {
+        IpAddrBytes::V6(<&[u8; 16]>::try_from(&bytes[..16]).unwrap())
+    }
+
+    pub fn octets(&self) -> &'a [u8] {
+        match *self {
+            IpAddrBytes::V4(octets) => octets,
+            IpAddrBytes::V6(octets) => octets,
+        }
+    }
+
+    pub fn is_ipv4(&self) -> bool {
+        matches!(*self, IpAddrBytes::V4(_))
+    }
+
+    pub fn is_ipv6(&self) -> bool {
+        matches!(*self, IpAddrBytes::V6(_))
+    }
+
+    #[cfg(feature = "core-net")]
+    pub fn to_core_net(&self) -> core::net::IpAddr {
+        match *self {
+            IpAddrBytes::V4(&octets) => core::net::IpAddr::from(octets),
+            IpAddrBytes::V6(&octets) => core::net::IpAddr::from(octets),
+        }
+    }
+}
+
+impl<'a> fmt::Display for IpAddrBytes<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let octets = match *self {
+            IpAddrBytes::V4(octets) =>
+                return write!(fmt, "{}.{}.{}.{}", octets[0], octets[1], octets[2], octets[3]),
+            IpAddrBytes::V6(octets) => octets,
+        };
+        let mut segments = [0u16; 8];
+        for (i, segment) in segments.iter_mut().enumerate() {
+            *segment = (octets[i * 2] as u16) << 8 | octets[i * 2 + 1] as u16;
+        }
+        // the first of the longest runs of two or more zero segments is
+        // shortened to "::"
+        let (mut zeros, mut zeros_len, mut run) = (0, 0, 0);
+        for (i, &segment) in segments.iter().enumerate() {
+            run = if segment == 0 { run + 1 } else { 0 };
+            if run > zeros_len {
+                zeros = i + 1 - run;
+                zeros_len = run;
+            }
+        }
+        if zeros_len < 2 {
+            zeros_len = 0;
+        }
+        let mut i = 0;
+        while i < 8 {
+            if zeros_len > 0 && i == zeros {
+                fmt.write_str("::")?;
+                i += zeros_len;
+                continue;
+            }
+            if i > 0 && !(zeros_len > 0 && i == zeros + zeros_len) {
+                fmt.write_str(":")?;
+            }
+            write!(fmt, "{:x}", segments[i])?;
+            i += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Debug for IpAddrBytes<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, fmt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+    use types::FmtBuf;
+
+    #[test]
+    fn display_addresses() {
+        let cases: [(&[u8], &[u8]); 6] = [
+            (&[192, 0, 2, 1], b"192.0.2.1"),
+            (&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1], b"2001:db8::1"),
+            (&[0; 16], b"::"),
+            (&[0xfe, 0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0], b"fe80::"),
+            (&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1], b"2001:db8:0:1::1"),
+            (&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 1, 0, 1, 0, 1, 0, 1, 0, 1], b"2001:db8:0:1:1:1:1:1"),
+        ];
+        for &(bytes, expected) in &cases {
+            let mut buf = FmtBuf::new();
+            write!(buf, "{}", IpAddrBytes::from_bytes(bytes).unwrap()).unwrap();
+            assert_eq!(buf.as_bytes(), expected);
+        }
+        assert!(matches!(IpAddrBytes::from_bytes(&[0; 5]), Err(BgpError::BadLength)));
+        assert_eq!(IpAddrBytes::from_bytes(&[192, 0, 2, 1]).unwrap(), IpAddrBytes::V4(&[192, 0, 2, 1]));
+    }
+
+    #[cfg(feature = "core-net")]
+    #[test]
+    fn to_core_net() {
+        let addr = IpAddrBytes::from_bytes(&[192, 0, 2, 1]).unwrap();
+        assert_eq!(addr.to_core_net(), core::net::IpAddr::from([192, 0, 2, 1]));
+    }
+}
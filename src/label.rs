@@ -0,0 +1,94 @@
+use types::*;
+
+/// An MPLS label stack entry as carried in BGP (RFC 8277): a 20 bit
+/// label, 3 bits that are the Traffic Class in a stack and the bottom of
+/// stack bit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Label {
+    pub value: u32,
+    pub traffic_class: u8,
+    pub bottom_of_stack: bool,
+}
+
+impl Label {
+    /// Decodes the first 3 octets of `bytes`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Label> {
+        let bytes = bytes.take(3)?;
+        let raw = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        Ok(Label {
+            value: raw >> 4,
+            traffic_class: (raw >> 1) as u8 & 0x07,
+            bottom_of_stack: raw & 1 > 0,
+        })
+    }
+
+    /// Whether the label is the IPv4 (0) or IPv6 (2) Explicit NULL label.
+    pub fn is_explicit_null(&self) -> bool {
+        self.value == 0 || self.value == 2
+    }
+
+    /// Whether the label is the Implicit NULL label (3).
+    pub fn is_implicit_null(&self) -> bool {
+        self.value == 3
+    }
+}
+
+/// Iterates over back to back 3 octet label stack entries, as in labeled
+/// NLRI, up to and including the first with the bottom of stack bit set.
+/// Fewer than 3 octets left before then yield `BgpError::BadLength`.
+#[derive(Debug, Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct LabelStack<'a> {
+    inner: &'a [u8],
+    error: bool,
+}
+
+impl<'a> LabelStack<'a> {
+    pub fn new(inner: &'a [u8]) -> LabelStack<'a> {
+        LabelStack {
+            inner,
+            error: false,
+        }
+    }
+}
+
+impl<'a> Iterator for LabelStack<'a> {
+    type Item = Result<Label>;
+
+    fn next(&mut self) -> Option<Result<Label>> {
+        if self.error || self.inner.is_empty() {
+            return None;
+        }
+        let label = Label::from_bytes(self.inner);
+        self.error = label.is_err();
+        self.inner = match label {
+            Ok(ref label) if !label.bottom_of_stack => &self.inner[3..],
+            _ => &[],
+        };
+        Some(label)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn label_stack() {
+        let bytes = [0x00, 0x00, 0x30, 0x01, 0x86, 0xa7, 0x00];
+        let mut labels = LabelStack::new(&bytes);
+        let label = labels.next().unwrap().unwrap();
+        assert_eq!(label, Label { value: 3, traffic_class: 0, bottom_of_stack: false });
+        assert!(label.is_implicit_null());
+        let label = labels.next().unwrap().unwrap();
+        assert_eq!(label, Label { value: 6250, traffic_class: 3, bottom_of_stack: true });
+        assert!(!label.is_explicit_null());
+        // whatever follows the bottom of the stack is not a label
+        assert!(labels.next().is_none());
+
+        let mut labels = LabelStack::new(&bytes[..5]);
+        assert!(!labels.next().unwrap().unwrap().bottom_of_stack);
+        assert!(matches!(labels.next(), Some(Err(BgpError::BadLength))));
+        assert!(labels.next().is_none());
+    }
+}
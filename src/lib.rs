@@ -6,14 +6,50 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 //! Parse BGP messages.
+//!
+//! `bgp::decode` parses a BGP message using the `SessionConfig` of the
+//! session it was received on. `bmp::decode` parses a BMP message and
+//! picks the `SessionConfig` for the BGP messages inside it from a
+//! `bmp::PeerTable`.
+//!
+//! ```
+//! use bgparse::bgp::{self, Message, SessionConfig};
+//!
+//! let mut keepalive = [0xff; 19];
+//! keepalive[16..].copy_from_slice(&[0x00, 0x13, 0x04]);
+//! match bgp::decode(&keepalive, &SessionConfig::default()) {
+//!     Ok(Message::KeepAlive(_)) => {}
+//!     x => panic!("expected KEEPALIVE, got {:?}", x),
+//! }
+//! ```
+//!
+//! The parsers behind them, such as `bgp::Message::from_bytes_with_config`
+//! and `bmp::Bmp::from_bytes`, remain available for finer control.
 
 
 #![cfg_attr(feature="clippy", feature(plugin))]
 #![cfg_attr(feature="clippy", plugin(clippy))]
 #![no_std]
+#[cfg(feature = "ffi")]
+extern crate std;
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
 pub mod types;
 pub mod bgp;
 pub mod bmp;
+pub mod render;
 mod afi;
 mod safi;
+mod afi_safi;
+mod as_number;
+mod ip_addr;
+mod label;
+mod mac_addr;
+mod route_distinguisher;
+mod tlv;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "stats")]
+pub mod stats;
 
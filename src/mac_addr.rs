@@ -0,0 +1,104 @@
+use core::convert::TryFrom;
+use core::fmt;
+use types::*;
+
+/// An Ethernet MAC address, borrowed from the message it appears in.
+///
+/// Displayed as `00:11:22:33:44:55`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MacAddr<'a>(pub &'a [u8; 6]);
+
+impl<'a> MacAddr<'a> {
+    /// Fails with `BgpError::BadLength` unless `bytes` is 6 octets.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<MacAddr<'a>> {
+        <&[u8; 6]>::try_from(bytes).map(MacAddr).map_err(|_| BgpError::BadLength)
+    }
+
+    pub fn octets(&self) -> &'a [u8] {
+        self.0
+    }
+}
+
+impl<'a> fmt::Display for MacAddr<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write_octets(fmt, self.0)
+    }
+}
+
+impl<'a> fmt::Debug for MacAddr<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, fmt)
+    }
+}
+
+/// An EVPN Ethernet Segment Identifier (RFC 7432 section 5), borrowed
+/// from the message it appears in.
+///
+/// Displayed as its 10 octets in hex, separated by colons.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Esi<'a>(pub &'a [u8; 10]);
+
+impl<'a> Esi<'a> {
+    /// Fails with `BgpError::BadLength` unless `bytes` is 10 octets.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Esi<'a>> {
+        <&[u8; 10]>::try_from(bytes).map(Esi).map_err(|_| BgpError::BadLength)
+    }
+
+    pub fn octets(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// How the identifier was derived, e.g. 0 for an arbitrary value or 1
+    /// for one auto-generated from LACP.
+    pub fn esi_type(&self) -> u8 {
+        self.0[0]
+    }
+
+    /// Whether this is the reserved ESI 0, which denotes a single-homed
+    /// site.
+    pub fn is_zero(&self) -> bool {
+        self.0.iter().all(|&octet| octet == 0)
+    }
+}
+
+impl<'a> fmt::Display for Esi<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write_octets(fmt, self.0)
+    }
+}
+
+impl<'a> fmt::Debug for Esi<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, fmt)
+    }
+}
+
+fn write_octets(fmt: &mut fmt::Formatter, octets: &[u8]) -> fmt::Result {
+    for (i, octet) in octets.iter().enumerate() {
+        write!(fmt, "{}{:02x}", if i > 0 { ":" } else { "" }, octet)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+    use types::FmtBuf;
+
+    #[test]
+    fn display_mac_and_esi() {
+        let mut buf = FmtBuf::new();
+        write!(buf, "{}", MacAddr::from_bytes(&[0x00, 0x11, 0x22, 0xaa, 0xbb, 0x0c]).unwrap()).unwrap();
+        assert_eq!(buf.as_bytes(), b"00:11:22:aa:bb:0c");
+        assert!(matches!(MacAddr::from_bytes(&[0; 5]), Err(BgpError::BadLength)));
+
+        let esi = Esi::from_bytes(&[0x01, 0x11, 0x22, 0x33, 0x44, 0x55, 0x66, 0x77, 0x88, 0x00]).unwrap();
+        let mut buf = FmtBuf::new();
+        write!(buf, "{}", esi).unwrap();
+        assert_eq!(buf.as_bytes(), b"01:11:22:33:44:55:66:77:88:00");
+        assert_eq!(esi.esi_type(), 1);
+        assert!(!esi.is_zero());
+        assert!(Esi::from_bytes(&[0; 10]).unwrap().is_zero());
+    }
+}
@@ -0,0 +1,772 @@
+//! JSON in the format of the ExaBGP 4 API, so that consumers of ExaBGP
+//! output can ingest messages parsed by this crate unchanged.
+//!
+//! On sessions without four-octet AS numbers, AS4_PATH and AS4_AGGREGATOR
+//! are merged into AS_PATH and AGGREGATOR as in RFC 6793 section 4.2.3,
+//! so the paths show the four-octet AS numbers rather than AS_TRANS.
+
+use alloc::string::String;
+use core::fmt::Write;
+use core::str;
+use types::*;
+use bgp::Message;
+use bgp::open::{Open, OptionalParam};
+use bgp::open::capability::*;
+use bgp::route_refresh::RouteRefresh;
+use bgp::update::{Update, UpdateAttributes};
+use bgp::update::normalize::{merge_as4_aggregator, merge_as4_path};
+use bgp::update::nlri::Nlri;
+use bgp::update::path_attr::*;
+use bgp::update::routes::RouteEvent;
+use bmp::{Bmp, Decoded, PerPeer, PeerInfo};
+
+/// The ExaBGP version the output claims to come from.
+pub const EXABGP_VERSION: &str = "4.0.1";
+
+// Writing to a String cannot fail.
+macro_rules! put {
+    ($out:expr, $($arg:tt)*) => {{
+        let _ = write!($out, $($arg)*);
+    }}
+}
+
+/// Whether a message was received from the neighbor or sent to it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Receive,
+    Send,
+}
+
+/// The session a message belongs to.
+#[derive(Debug, Clone, Copy)]
+pub struct Neighbor<'a> {
+    /// Seconds and microseconds since the epoch.
+    pub time: (u32, u32),
+    /// Rendered as `null` when not known.
+    pub local_address: Option<IpAddrBytes<'a>>,
+    pub peer_address: IpAddrBytes<'a>,
+    /// Rendered as `null` when not known.
+    pub local_as: Option<AsNumber>,
+    pub peer_as: AsNumber,
+    pub direction: Direction,
+}
+
+impl<'a> Neighbor<'a> {
+    /// The peer a BMP message reports on. Messages about the Adj-RIB-Out
+    /// are sent to the peer, all others received from it.
+    pub fn from_per_peer(peer: &PerPeer<'a>) -> Neighbor<'a> {
+        Neighbor {
+            time: peer.timestamp(),
+            local_address: None,
+            peer_address: peer.peer_ip(),
+            local_as: None,
+            peer_as: peer.peer_as(),
+            direction: if peer.flag_adj_rib_out() { Direction::Send } else { Direction::Receive },
+        }
+    }
+}
+
+/// Renders messages as ExaBGP does, one JSON object per message. The
+/// `counter` field counts the messages rendered, starting at 1.
+#[derive(Debug, Clone)]
+pub struct Renderer<'a> {
+    host: &'a str,
+    pid: u32,
+    ppid: u32,
+    counter: u64,
+}
+
+impl<'a> Renderer<'a> {
+    /// A renderer reporting `host`, `pid` and `ppid` as the process the
+    /// messages come from.
+    pub fn new(host: &'a str, pid: u32, ppid: u32) -> Renderer<'a> {
+        Renderer {
+            host,
+            pid,
+            ppid,
+            counter: 0,
+        }
+    }
+
+    /// Renders a BGP message exchanged with `neighbor`. Fails with the
+    /// first error decoding the message.
+    pub fn message(&mut self, neighbor: &Neighbor, message: &Message) -> Result<String> {
+        let mut body = String::new();
+        let kind = match *message {
+            Message::Open(ref open) => {
+                render_open(&mut body, open)?;
+                "open"
+            }
+            Message::Update(ref update) => {
+                render_update(&mut body, update)?;
+                "update"
+            }
+            Message::Notification(_, raw) => {
+                put!(body, ",\"notification\":{{\"code\":{},\"subcode\":{},\"data\":\"", raw[19], raw[20]);
+                hex(&mut body, &raw[21..]);
+                body.push_str("\"}");
+                "notification"
+            }
+            Message::KeepAlive(_) => "keepalive",
+            Message::Refresh(ref refresh) => {
+                render_refresh(&mut body, refresh);
+                "refresh"
+            }
+        };
+        Ok(self.finish(neighbor, kind, &body))
+    }
+
+    /// Renders a BMP message: a Route Monitoring message as the UPDATE it
+    /// carries, and Peer Up and Peer Down Notifications as neighbor state
+    /// changes. Other BMP messages have no ExaBGP equivalent and fail with
+    /// `BgpError::Invalid`.
+    pub fn bmp(&mut self, decoded: &Decoded) -> Result<String> {
+        match decoded.bmp {
+            Bmp::RouteMonitoring(ref rm) => {
+                let update = rm.update(&decoded.config)?;
+                self.message(&Neighbor::from_per_peer(&rm.peer_info()), &Message::Update(update))
+            }
+            Bmp::PeerUpNotification(ref up) => {
+                let (sent, _) = up.opens()?;
+                let neighbor = Neighbor {
+                    local_address: Some(up.local_address()),
+                    local_as: Some(sent.as_number()),
+                    ..Neighbor::from_per_peer(&up.peer_info())
+                };
+                Ok(self.finish(&neighbor, "state", ",\"state\":\"up\""))
+            }
+            Bmp::PeerDownNotification(ref down) => {
+                let mut body = String::from(",\"state\":\"down\",\"reason\":");
+                string(&mut body, match down.reason() {
+                    Some(1) => "local system closed the session, notification sent",
+                    Some(2) => "local system closed the session, no notification sent",
+                    Some(3) => "remote system closed the session with a notification",
+                    Some(4) => "remote system closed the session without a notification",
+                    Some(5) => "peer de-configured",
+                    Some(6) => "local system closed the session",
+                    _ => "unknown",
+                });
+                Ok(self.finish(&Neighbor::from_per_peer(&down.peer_info()), "state", &body))
+            }
+            _ => Err(BgpError::Invalid),
+        }
+    }
+
+    fn finish(&mut self, neighbor: &Neighbor, kind: &str, body: &str) -> String {
+        self.counter += 1;
+        let mut out = String::new();
+        put!(out, "{{\"exabgp\":\"{}\",\"time\":{}.{:06},\"host\":", EXABGP_VERSION, neighbor.time.0, neighbor.time.1);
+        string(&mut out, self.host);
+        put!(out, ",\"pid\":{},\"ppid\":{},\"counter\":{},\"type\":\"{}\",\"neighbor\":{{\"address\":{{\"local\":",
+             self.pid, self.ppid, self.counter, kind);
+        match neighbor.local_address {
+            Some(addr) => put!(out, "\"{}\"", addr),
+            None => out.push_str("null"),
+        }
+        put!(out, ",\"peer\":\"{}\"}},\"asn\":{{\"local\":", neighbor.peer_address);
+        match neighbor.local_as {
+            Some(asn) => put!(out, "{}", asn),
+            None => out.push_str("null"),
+        }
+        put!(out, ",\"peer\":{}}},\"direction\":\"{}\"{}}}}}", neighbor.peer_as,
+             match neighbor.direction {
+                 Direction::Receive => "receive",
+                 Direction::Send => "send",
+             },
+             body);
+        out
+    }
+}
+
+/// Writes `s` as a JSON string.
+fn string(out: &mut String, s: &str) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => put!(out, "\\u{:04x}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn hex(out: &mut String, bytes: &[u8]) {
+    for byte in bytes {
+        put!(out, "{:02X}", byte);
+    }
+}
+
+/// Writes `"name":`, preceded by a comma unless it is the first member
+/// of its object.
+fn key(out: &mut String, first: &mut bool, name: &str) {
+    if !*first {
+        out.push(',');
+    }
+    *first = false;
+    put!(out, "\"{}\":", name);
+}
+
+fn afi(out: &mut String, afi: Afi) {
+    match u16::from(afi) {
+        1 => out.push_str("ipv4"),
+        2 => out.push_str("ipv6"),
+        25 => out.push_str("l2vpn"),
+        16388 => out.push_str("bgp-ls"),
+        afi => put!(out, "{}", afi),
+    }
+}
+
+fn safi(out: &mut String, safi: Safi) {
+    match u8::from(safi) {
+        1 => out.push_str("unicast"),
+        2 => out.push_str("multicast"),
+        4 => out.push_str("nlri-mpls"),
+        5 => out.push_str("mcast-vpn"),
+        65 => out.push_str("vpls"),
+        70 => out.push_str("evpn"),
+        71 => out.push_str("bgp-ls"),
+        72 => out.push_str("bgp-ls-vpn"),
+        128 => out.push_str("mpls-vpn"),
+        132 => out.push_str("rtc"),
+        133 => out.push_str("flow"),
+        134 => out.push_str("flow-vpn"),
+        safi => put!(out, "{}", safi),
+    }
+}
+
+/// Writes an address family as ExaBGP names it, e.g. `ipv4 unicast`.
+fn family(out: &mut String, afi: Afi, safi: Safi, separator: char) {
+    self::afi(out, afi);
+    out.push(separator);
+    self::safi(out, safi);
+}
+
+fn render_open(out: &mut String, open: &Open) -> Result<()> {
+    put!(out, ",\"open\":{{\"version\":{},\"asn\":{},\"hold_time\":{},\"router_id\":\"{}\",\"capabilities\":{{",
+         open.version(), open.as_number(), open.hold_time(), open.router_id());
+    let capabilities = || open.params().filter_map(|param| match param {
+        Ok(OptionalParam::Capability(cap)) => Some(Ok(cap)),
+        Ok(_) => None,
+        Err(err) => Some(Err(err)),
+    });
+    let mut first = true;
+    let mut seen = [false; 256];
+
+    // every Multiprotocol and ADD-PATH capability goes in one member
+    for cap in capabilities() {
+        if let Capability::MultiProtocol(mp) = cap? {
+            if !seen[1] {
+                key(out, &mut first, "1");
+                out.push_str("{\"name\":\"multiprotocol\",\"families\":[");
+            } else {
+                out.push(',');
+            }
+            seen[1] = true;
+            out.push('"');
+            family(out, mp.afi(), mp.safi(), ' ');
+            out.push('"');
+        }
+    }
+    if seen[1] {
+        out.push_str("]}");
+    }
+    for cap in capabilities() {
+        if let Capability::AddPath(add_path) = cap? {
+            if !seen[69] {
+                key(out, &mut first, "69");
+                out.push_str("{\"name\":\"addpath\"");
+            }
+            seen[69] = true;
+            out.push_str(",\"");
+            family(out, add_path.afi(), add_path.safi(), '/');
+            out.push_str("\":");
+            match add_path.direction() {
+                AddPathDirection::Receive => out.push_str("\"receive\""),
+                AddPathDirection::Send => out.push_str("\"send\""),
+                AddPathDirection::Both => out.push_str("\"send/receive\""),
+                AddPathDirection::Unknown(n) => put!(out, "\"{}\"", n),
+            }
+        }
+    }
+    if seen[69] {
+        out.push('}');
+    }
+
+    for cap in capabilities() {
+        let cap = cap?;
+        if let Capability::MultiProtocol(_) | Capability::AddPath(_) = cap {
+            continue;
+        }
+        let code = cap_code(&cap);
+        if seen[code as usize] {
+            continue;
+        }
+        seen[code as usize] = true;
+        put!(out, "{}\"{}\":{{\"name\":\"{}\"", if first { "" } else { "," }, code, match cap {
+            Capability::RouteRefresh(_) => "route-refresh",
+            Capability::Orf(_) => "outbound-route-filter",
+            Capability::MultipleRoutes(_) => "multiple-routes",
+            Capability::ExtendedNextHopEncoding(_) => "nexthop",
+            Capability::ExtendedMessage(_) => "extended-message",
+            Capability::BgpSec(_) => "bgpsec",
+            Capability::MultipleLabels(_) => "multiple-labels",
+            Capability::Role(_) => "role",
+            Capability::GracefulRestart(_) => "graceful-restart",
+            Capability::FourByteASN(_) => "asn4",
+            Capability::DeprecatedDynamicCapability(_) | Capability::DynamicCapability(_) => "dynamic",
+            Capability::MultiSession(_) => "multi-session",
+            Capability::EnhancedRouteRefresh(_) => "enhanced-route-refresh",
+            Capability::LongLivedGracefulRestart(_) => "llgr",
+            Capability::Fqdn(_) => "hostname",
+            _ => "unknown",
+        });
+        first = false;
+        match cap {
+            Capability::FourByteASN(ref asn4) => put!(out, ",\"asn4\":{}", asn4.aut_num()),
+            Capability::Fqdn(ref fqdn) => {
+                if let Some(hostname) = fqdn.hostname().and_then(|name| str::from_utf8(name).ok()) {
+                    out.push_str(",\"host-name\":");
+                    string(out, hostname);
+                }
+                if let Some(domain_name) = fqdn.domain_name().and_then(|name| str::from_utf8(name).ok()) {
+                    out.push_str(",\"domain-name\":");
+                    string(out, domain_name);
+                }
+            }
+            _ => (),
+        }
+        out.push('}');
+    }
+    out.push_str("}}");
+    Ok(())
+}
+
+fn cap_code(cap: &Capability) -> u8 {
+    match *cap {
+        Capability::MultiProtocol(ref cap) => cap.code(),
+        Capability::RouteRefresh(ref cap) => cap.code(),
+        Capability::Orf(ref cap) => cap.code(),
+        Capability::MultipleRoutes(ref cap) => cap.code(),
+        Capability::ExtendedNextHopEncoding(ref cap) => cap.code(),
+        Capability::ExtendedMessage(ref cap) => cap.code(),
+        Capability::BgpSec(ref cap) => cap.code(),
+        Capability::MultipleLabels(ref cap) => cap.code(),
+        Capability::Role(ref cap) => cap.code(),
+        Capability::GracefulRestart(ref cap) => cap.code(),
+        Capability::FourByteASN(ref cap) => cap.code(),
+        Capability::DeprecatedDynamicCapability(ref cap) => cap.code(),
+        Capability::DynamicCapability(ref cap) => cap.code(),
+        Capability::MultiSession(ref cap) => cap.code(),
+        Capability::AddPath(ref cap) => cap.code(),
+        Capability::EnhancedRouteRefresh(ref cap) => cap.code(),
+        Capability::LongLivedGracefulRestart(ref cap) => cap.code(),
+        Capability::Fqdn(ref cap) => cap.code(),
+        Capability::Private(ref cap) => cap.code(),
+        Capability::Other(ref cap) => cap.code(),
+    }
+}
+
+fn render_refresh(out: &mut String, refresh: &RouteRefresh) {
+    out.push_str(",\"route-refresh\":{\"message\":\"");
+    match refresh.subtype() {
+        0 => out.push_str("request"),
+        1 => out.push_str("begin"),
+        2 => out.push_str("end"),
+        n => put!(out, "{}", n),
+    }
+    out.push_str("\",\"afi\":\"");
+    afi(out, refresh.afi());
+    out.push_str("\",\"safi\":\"");
+    safi(out, refresh.safi());
+    out.push_str("\"}");
+}
+
+fn render_update(out: &mut String, update: &Update) -> Result<()> {
+    out.push_str(",\"message\":{\"update\":{");
+    if let Some(family) = update.end_of_rib()? {
+        out.push_str("\"eor\":{\"afi\":\"");
+        afi(out, family.afi);
+        out.push_str("\",\"safi\":\"");
+        safi(out, family.safi);
+        out.push_str("\"}}}");
+        return Ok(());
+    }
+    let mut first = true;
+    let mut attribute = String::new();
+    render_attributes(&mut attribute, update)?;
+    if !attribute.is_empty() {
+        key(out, &mut first, "attribute");
+        put!(out, "{{{}}}", attribute);
+    }
+    let attrs = update.attributes()?;
+    render_routes(out, &mut first, update, &attrs, true)?;
+    render_routes(out, &mut first, update, &attrs, false)?;
+    out.push_str("}}");
+    Ok(())
+}
+
+fn render_attributes(out: &mut String, update: &Update) -> Result<()> {
+    let mut first = true;
+    // on two-octet AS sessions, AS4_PATH and AS4_AGGREGATOR are merged in
+    let (as4_path, as4_aggregator) = if update.four_byte_asn() { (None, None) } else { update.as4_attrs()? };
+    for attr in update.try_path_attrs()? {
+        match attr? {
+            PathAttr::Origin(origin) => {
+                key(out, &mut first, "origin");
+                match origin.try_origin()? {
+                    OriginType::Igp => out.push_str("\"igp\""),
+                    OriginType::Egp => out.push_str("\"egp\""),
+                    OriginType::Incomplete => out.push_str("\"incomplete\""),
+                    OriginType::Unknown(n) => put!(out, "\"{}\"", n),
+                }
+            }
+            PathAttr::AsPath(path) => {
+                key(out, &mut first, "as-path");
+                // room for the builder's header and the bound merge_as4_path gives
+                let as4_len = as4_path.as_ref().map_or(0, |path| path.value().len());
+                let mut buf = alloc::vec![0; 4 + 2 * path.value().len() + as4_len];
+                let mut builder = AsPathBuilder::new(&mut buf, true);
+                merge_as4_path(&mut builder, path.segments(), as4_path.as_ref().map(|path| path.segments()))?;
+                as_path(out, builder.segments())?;
+            }
+            PathAttr::As4Path(ref path) if path.code() == 2 => {
+                key(out, &mut first, "as-path");
+                as_path(out, path.segments())?;
+            }
+            PathAttr::MultiExitDisc(med) => {
+                key(out, &mut first, "med");
+                put!(out, "{}", med.med());
+            }
+            PathAttr::LocalPreference(pref) => {
+                key(out, &mut first, "local-preference");
+                put!(out, "{}", pref.preference());
+            }
+            PathAttr::AtomicAggregate(_) => {
+                key(out, &mut first, "atomic-aggregate");
+                out.push_str("true");
+            }
+            PathAttr::Aggregator(aggregator) => {
+                key(out, &mut first, "aggregator");
+                let value = merge_as4_aggregator(&aggregator, as4_aggregator.as_ref());
+                let aggregator = As4Aggregator::from_value(&value)?;
+                put!(out, "\"{}:{}\"", aggregator.aut_num(), aggregator.ident_addr());
+            }
+            PathAttr::As4Aggregator(ref aggregator) if aggregator.code() == 7 => {
+                key(out, &mut first, "aggregator");
+                put!(out, "\"{}:{}\"", aggregator.aut_num(), aggregator.ident_addr());
+            }
+            PathAttr::Communities(communities) => {
+                key(out, &mut first, "community");
+                out.push('[');
+                for (i, community) in communities.communities()?.enumerate() {
+                    put!(out, "{}[{},{}]", if i > 0 { "," } else { "" }, community.asn(), community.value());
+                }
+                out.push(']');
+            }
+            PathAttr::OriginatorId(id) => {
+                key(out, &mut first, "originator-id");
+                put!(out, "\"{}\"", IpAddrBytes::V4(&id.ident().to_be_bytes()));
+            }
+            PathAttr::ClusterList(list) => {
+                key(out, &mut first, "cluster-list");
+                out.push('[');
+                for (i, id) in list.ids().enumerate() {
+                    put!(out, "{}\"{}\"", if i > 0 { "," } else { "" }, IpAddrBytes::V4(&id?.to_be_bytes()));
+                }
+                out.push(']');
+            }
+            PathAttr::ExtendedCommunities(communities) => {
+                communities.communities().map(|_| ())?;
+                key(out, &mut first, "extended-community");
+                out.push('[');
+                for (i, community) in communities.value().chunks(8).enumerate() {
+                    let value = community.iter().fold(0u64, |value, &byte| value << 8 | byte as u64);
+                    put!(out, "{}{{\"value\":{},\"string\":\"", if i > 0 { "," } else { "" }, value);
+                    extended_community(out, community);
+                    out.push_str("\"}");
+                }
+                out.push(']');
+            }
+            PathAttr::LargeCommunities(communities) => {
+                key(out, &mut first, "large-community");
+                out.push('[');
+                for (i, community) in communities.communities()?.enumerate() {
+                    put!(out, "{}[{},{},{}]", if i > 0 { "," } else { "" }, community.global_admin(),
+                         community.local_data_1(), community.local_data_2());
+                }
+                out.push(']');
+            }
+            // next hops are rendered with the routes, and AS4_PATH and
+            // AS4_AGGREGATOR are merged into AS_PATH and AGGREGATOR
+            PathAttr::NextHop(_) | PathAttr::MpReachNlri(_) | PathAttr::MpUnreachNlri(_) |
+            PathAttr::As4Path(_) | PathAttr::As4Aggregator(_) => (),
+            attr => {
+                put!(out, "{}\"attribute-0x{:02X}-0x{:02X}\":\"0x", if first { "" } else { "," },
+                     attr.code(), attr.flags());
+                first = false;
+                hex(out, attr.value());
+                out.push('"');
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Writes an AS path as a list of AS numbers, with each AS_SET as a
+/// nested list.
+fn as_path(out: &mut String, segments: AsPathIter) -> Result<()> {
+    out.push('[');
+    let mut first = true;
+    for segment in segments {
+        match segment? {
+            AsPathSegment::AsSequence(seq) => for asn in seq.aut_nums()? {
+                put!(out, "{}{}", if first { "" } else { "," }, asn);
+                first = false;
+            },
+            AsPathSegment::AsSet(set) => {
+                out.push_str(if first { "[" } else { ",[" });
+                first = false;
+                for (i, asn) in set.aut_nums()?.enumerate() {
+                    put!(out, "{}{}", if i > 0 { "," } else { "" }, asn);
+                }
+                out.push(']');
+            }
+        }
+    }
+    out.push(']');
+    Ok(())
+}
+
+/// Writes route targets and route origins as ExaBGP names them, and
+/// other extended communities in hexadecimal.
+fn extended_community(out: &mut String, community: &[u8]) {
+    let name = match community[1] {
+        0x02 => "target",
+        0x03 => "origin",
+        _ => "",
+    };
+    match community[0] & 0x3f {
+        0x00 if !name.is_empty() => put!(out, "{}:{}:{}", name, u16::from_be_bytes([community[2], community[3]]),
+                                         u32::from_be_bytes([community[4], community[5], community[6], community[7]])),
+        0x01 if !name.is_empty() => put!(out, "{}:{}:{}", name, IpAddrBytes::v4(&community[2..6]),
+                                         u16::from_be_bytes([community[6], community[7]])),
+        0x02 if !name.is_empty() => put!(out, "{}:{}:{}", name,
+                                         u32::from_be_bytes([community[2], community[3], community[4], community[5]]),
+                                         u16::from_be_bytes([community[6], community[7]])),
+        _ => {
+            out.push_str("0x");
+            hex(out, community);
+        }
+    }
+}
+
+/// Writes the announced or withdrawn routes of `update` as a member per
+/// address family. Announced routes are further grouped by next hop.
+fn render_routes(out: &mut String, first: &mut bool, update: &Update, attrs: &UpdateAttributes,
+                 announce: bool) -> Result<()> {
+    let mut current: Option<AfiSafi> = None;
+    for event in update.routes() {
+        let nlri = match (event?, announce) {
+            (RouteEvent::Announce { nlri, .. }, true) | (RouteEvent::Withdraw { nlri }, false) => nlri,
+            _ => continue,
+        };
+        let this = AfiSafi::new(nlri.afi, nlri.safi);
+        if current == Some(this) {
+            out.push(',');
+        } else {
+            match current {
+                Some(_) if announce => out.push_str("]},"),
+                Some(_) => out.push_str("],"),
+                None => {
+                    key(out, first, if announce { "announce" } else { "withdraw" });
+                    out.push('{');
+                }
+            }
+            out.push('"');
+            family(out, nlri.afi, nlri.safi, ' ');
+            out.push_str("\":");
+            if announce {
+                out.push_str("{\"");
+                match (attrs.next_hop, &attrs.mp_next_hop) {
+                    (Some(next_hop), _) if this == IPV4_UNICAST =>
+                        put!(out, "{}", IpAddrBytes::V4(&next_hop.to_be_bytes())),
+                    (_, Some(next_hop)) if next_hop.addr().is_some() =>
+                        put!(out, "{}", next_hop.addr().unwrap()),
+                    _ => out.push_str("no-nexthop"),
+                }
+                out.push_str("\":");
+            }
+            out.push('[');
+            current = Some(this);
+        }
+        render_nlri(out, &nlri)?;
+    }
+    match current {
+        Some(_) if announce => out.push_str("]}}"),
+        Some(_) => out.push_str("]}"),
+        None => (),
+    }
+    Ok(())
+}
+
+fn render_nlri(out: &mut String, nlri: &Nlri) -> Result<()> {
+    put!(out, "{{\"nlri\":\"{}\"", nlri.prefix);
+    if let Some(path_id) = nlri.path_id {
+        put!(out, ",\"path-information\":\"{}\"", IpAddrBytes::V4(&path_id.to_be_bytes()));
+    }
+    if let Some(rd) = nlri.rd {
+        out.push_str(",\"rd\":\"");
+        match rd.admin() {
+            Some(_) => put!(out, "{}", rd),
+            None => hex(out, rd.octets()),
+        }
+        out.push('"');
+    }
+    if let Some(labels) = nlri.label_stack() {
+        out.push_str(",\"label\":[");
+        for (i, label) in labels.enumerate() {
+            let label = label?;
+            let raw = label.value << 4 | (label.traffic_class as u32) << 1 | label.bottom_of_stack as u32;
+            put!(out, "{}[{},{}]", if i > 0 { "," } else { "" }, label.value, raw);
+        }
+        out.push(']');
+    }
+    out.push('}');
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::format;
+    use bgp::SessionConfig;
+    use core::convert::TryFrom;
+
+    fn neighbor() -> Neighbor<'static> {
+        Neighbor {
+            time: (1561651496, 873861),
+            local_address: Some(IpAddrBytes::V4(&[192, 0, 2, 1])),
+            peer_address: IpAddrBytes::V4(&[192, 0, 2, 2]),
+            local_as: Some(AsNumber(65001)),
+            peer_as: AsNumber(65000),
+            direction: Direction::Receive,
+        }
+    }
+
+    const HEADER: &str = "{\"exabgp\":\"4.0.1\",\"time\":1561651496.873861,\"host\":\"collector\",\"pid\":7,\"ppid\":1,";
+    const NEIGHBOR: &str = "\"neighbor\":{\"address\":{\"local\":\"192.0.2.1\",\"peer\":\"192.0.2.2\"},\
+                            \"asn\":{\"local\":65001,\"peer\":65000},\"direction\":\"receive\"";
+
+    #[test]
+    fn render_messages() {
+        let mut renderer = Renderer::new("collector", 7, 1);
+
+        // withdraws 10.0.1.0/24, announces 198.51.100.0/24 and 10.1.0.0/16
+        let bytes = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x36, 0x02, 0x00, 0x04, 0x18, 0x0a, 0x00,
+                      0x01, 0x00, 0x14, 0x40, 0x01, 0x01, 0x00, 0x40, 0x02, 0x06, 0x02, 0x01,
+                      0x00, 0x00, 0xfd, 0xe8, 0x40, 0x03, 0x04, 0x0a, 0x00, 0x00, 0x01, 0x18,
+                      0xc6, 0x33, 0x64, 0x10, 0x0a, 0x01];
+        let message = Message::try_from(&bytes[..]).unwrap();
+        let json = renderer.message(&neighbor(), &message).unwrap();
+        assert_eq!(json, format!("{}\"counter\":1,\"type\":\"update\",{},\"message\":{{\"update\":{{\
+                                  \"attribute\":{{\"origin\":\"igp\",\"as-path\":[65000]}},\
+                                  \"announce\":{{\"ipv4 unicast\":{{\"10.0.0.1\":[{{\"nlri\":\"198.51.100.0/24\"}},\
+                                  {{\"nlri\":\"10.1.0.0/16\"}}]}}}},\
+                                  \"withdraw\":{{\"ipv4 unicast\":[{{\"nlri\":\"10.0.1.0/24\"}}]}}}}}}}}}}",
+                                 HEADER, NEIGHBOR));
+
+        let mut eor = [0xff; 23];
+        eor[16..].copy_from_slice(&[0x00, 0x17, 0x02, 0x00, 0x00, 0x00, 0x00]);
+        let json = renderer.message(&neighbor(), &Message::try_from(&eor[..]).unwrap()).unwrap();
+        assert!(json.ends_with("\"message\":{\"update\":{\"eor\":{\"afi\":\"ipv4\",\"safi\":\"unicast\"}}}}}"));
+
+        let mut notification = [0xff; 22];
+        notification[16..].copy_from_slice(&[0x00, 0x16, 0x03, 0x06, 0x02, 0xab]);
+        let json = renderer.message(&neighbor(), &Message::try_from(&notification[..]).unwrap()).unwrap();
+        assert_eq!(json, format!("{}\"counter\":3,\"type\":\"notification\",{},\
+                                  \"notification\":{{\"code\":6,\"subcode\":2,\"data\":\"AB\"}}}}}}",
+                                 HEADER, NEIGHBOR));
+
+        let open = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                     0xff, 0xff, 0xff, 0xff, 0x00, 0x31, 0x01, 0x04, 0x5b, 0xa0, 0x00, 0xb4,
+                     0xc0, 0x00, 0x02, 0x02, 0x14, 0x02, 0x06, 0x01, 0x04, 0x00, 0x01, 0x00,
+                     0x01, 0x02, 0x06, 0x41, 0x04, 0xfa, 0x56, 0xea, 0x00, 0x02, 0x02, 0x02,
+                     0x00];
+        let json = renderer.message(&neighbor(), &Message::try_from(&open[..]).unwrap()).unwrap();
+        assert!(json.ends_with("\"open\":{\"version\":4,\"asn\":4200000000,\"hold_time\":180,\
+                                \"router_id\":\"192.0.2.2\",\"capabilities\":{\"1\":{\"name\":\"multiprotocol\",\
+                                \"families\":[\"ipv4 unicast\"]},\"65\":{\"name\":\"asn4\",\"asn4\":4200000000},\
+                                \"2\":{\"name\":\"route-refresh\"}}}}}"), "{}", json);
+    }
+    #[test]
+    fn render_two_octet_as_path() {
+        let mut renderer = Renderer::new("collector", 7, 1);
+
+        let bytes = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x4c, 0x02, 0x00, 0x00, 0x00, 0x31,
+                      // AS4_AGGREGATOR 4200000000, 192.0.2.1
+                      0xc0, 0x12, 0x08, 0xfa, 0x56, 0xea, 0x00, 0xc0, 0x00, 0x02, 0x01,
+                      // NEXT_HOP 10.0.0.1
+                      0x40, 0x03, 0x04, 0x0a, 0x00, 0x00, 0x01,
+                      // AS4_PATH [4200000000]
+                      0xc0, 0x11, 0x06, 0x02, 0x01, 0xfa, 0x56, 0xea, 0x00,
+                      // ORIGIN IGP
+                      0x40, 0x01, 0x01, 0x00,
+                      // AS_PATH [65001, AS_TRANS]
+                      0x40, 0x02, 0x06, 0x02, 0x02, 0xfd, 0xe9, 0x5b, 0xa0,
+                      // AGGREGATOR AS_TRANS, 192.0.2.1
+                      0xc0, 0x07, 0x06, 0x5b, 0xa0, 0xc0, 0x00, 0x02, 0x01,
+                      0x18, 0xc6, 0x33, 0x64];
+        let config = SessionConfig { four_byte_asn: false, ..SessionConfig::default() };
+        let message = Message::from_bytes_with_config(bytes, &config).unwrap();
+        let json = renderer.message(&neighbor(), &message).unwrap();
+        assert!(json.contains("\"attribute\":{\"origin\":\"igp\",\"as-path\":[65001,4200000000],\
+                               \"aggregator\":\"4200000000:192.0.2.1\"}"), "{}", json);
+    }
+
+    #[test]
+    fn render_labels() {
+        let mut renderer = Renderer::new("collector", 7, 1);
+
+        // announces 10.0.0.0/8 with label 16 in an IPv4 labeled unicast MP_REACH_NLRI
+        let mut bytes = [0xff; 44];
+        bytes[16..].copy_from_slice(&[0x00, 0x2c, 0x02, 0x00, 0x00, 0x00, 0x15,
+                                      0x40, 0x01, 0x01, 0x00,
+                                      0x80, 0x0e, 0x0e, 0x00, 0x01, 0x04, 0x04, 0xc0, 0x00, 0x02, 0x01, 0x00,
+                                      0x20, 0x00, 0x01, 0x01, 0x0a]);
+        let json = renderer.message(&neighbor(), &Message::try_from(&bytes[..]).unwrap()).unwrap();
+        assert!(json.ends_with("\"announce\":{\"ipv4 nlri-mpls\":{\"192.0.2.1\":\
+                                [{\"nlri\":\"10.0.0.0/8\",\"label\":[[16,257]]}]}}}}}}"), "{}", json);
+
+        // octets left over after a label without the bottom of stack bit
+        let nlri = Nlri {
+            afi: AFI_IPV4,
+            safi: SAFI_MPLS_LABEL,
+            path_id: None,
+            labels: Some(&[0x00, 0x01, 0x00, 0xab]),
+            rd: None,
+            mt_id: None,
+            prefix: Prefix::Ipv4(Ipv4Prefix::new(8, &[10])),
+        };
+        assert!(matches!(render_nlri(&mut String::new(), &nlri), Err(BgpError::BadLength)));
+    }
+
+    #[test]
+    fn render_route_distinguisher() {
+        let mut renderer = Renderer::new("collector", 7, 1);
+
+        // announces 10.0.0.0/8 with label 16 and route distinguisher 65000:1
+        let mut bytes = [0xff; 60];
+        bytes[16..].copy_from_slice(&[0x00, 0x3c, 0x02, 0x00, 0x00, 0x00, 0x25,
+                                      0x40, 0x01, 0x01, 0x00,
+                                      0x80, 0x0e, 0x1e, 0x00, 0x01, 0x80, 0x0c, 0, 0, 0, 0, 0, 0, 0, 0,
+                                      0xc0, 0x00, 0x02, 0x01, 0x00,
+                                      0x60, 0x00, 0x01, 0x01, 0, 0, 0xfd, 0xe8, 0, 0, 0, 1, 0x0a]);
+        let json = renderer.message(&neighbor(), &Message::try_from(&bytes[..]).unwrap()).unwrap();
+        assert!(json.ends_with("\"announce\":{\"ipv4 mpls-vpn\":{\"192.0.2.1\":[{\"nlri\":\"10.0.0.0/8\",\
+                                \"rd\":\"65000:1\",\"label\":[[16,257]]}]}}}}}}"), "{}", json);
+    }
+}
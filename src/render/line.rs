@@ -0,0 +1,204 @@
+//! One line per route, in the style of looking glasses and router CLIs,
+//! for debugging tools:
+//!
+//! ```text
+//! 10.0.0.0/8 via 192.0.2.1 AS_PATH 3356 1299 i communities [3356:20]
+//! ```
+
+use core::fmt;
+use types::*;
+use bgp::update::nlri::Nlri;
+use bgp::update::path_attr::*;
+use bgp::update::routes::RouteEvent;
+
+/// Displays a route change on one line: an announced prefix followed by
+/// its next hop, AS path, origin, LOCAL_PREF, MED, communities and large
+/// communities, or a withdrawn prefix followed by `withdrawn`. The NLRI
+/// of address families that are not decoded are shown by their length.
+///
+/// AS_SETs are shown in braces. If the path attributes do not parse, the
+/// prefix is followed by the error in angle brackets.
+pub struct RouteLine<'e, 'a: 'e>(pub &'e RouteEvent<'a>);
+
+impl<'e, 'a> fmt::Display for RouteLine<'e, 'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let (nlri, attrs) = match *self.0 {
+            RouteEvent::Announce { ref nlri, ref attrs } => (nlri, attrs),
+            RouteEvent::Withdraw { ref nlri } => {
+                write_nlri(fmt, nlri)?;
+                return fmt.write_str(" withdrawn");
+            }
+            RouteEvent::OtherAnnounce { ref reach, .. } =>
+                return write!(fmt, "{} NLRI of {} octets announced", reach.afi_safi(), reach.nlri_bytes().len()),
+            RouteEvent::OtherWithdraw { ref unreach } =>
+                return write!(fmt, "{} NLRI of {} octets withdrawn", unreach.afi_safi(), unreach.nlri_bytes().len()),
+        };
+        write_nlri(fmt, nlri)?;
+        let summary = match Summary::new(attrs.clone()) {
+            Ok(summary) => summary,
+            Err(err) => return write!(fmt, " <{}>", err),
+        };
+
+        let next_hop = match summary.next_hop {
+            Some(next_hop) if AfiSafi::new(nlri.afi, nlri.safi) == IPV4_UNICAST => Some(next_hop),
+            _ => summary.mp_next_hop,
+        };
+        if let Some(next_hop) = next_hop {
+            write!(fmt, " via {}", next_hop)?;
+        }
+        if let Some(path) = summary.as_path {
+            if path.clone().next().is_some() {
+                fmt.write_str(" AS_PATH")?;
+            }
+            for segment in path.flatten() {
+                match segment {
+                    AsPathSegment::AsSequence(seq) => for asn in seq.aut_nums().into_iter().flatten() {
+                        write!(fmt, " {}", asn)?;
+                    },
+                    AsPathSegment::AsSet(set) => {
+                        fmt.write_str(" {")?;
+                        for (i, asn) in set.aut_nums().into_iter().flatten().enumerate() {
+                            write!(fmt, "{}{}", if i > 0 { " " } else { "" }, asn)?;
+                        }
+                        fmt.write_str("}")?;
+                    }
+                }
+            }
+        }
+        match summary.origin {
+            Some(OriginType::Igp) => fmt.write_str(" i")?,
+            Some(OriginType::Egp) => fmt.write_str(" e")?,
+            Some(OriginType::Incomplete) => fmt.write_str(" ?")?,
+            Some(OriginType::Unknown(n)) => write!(fmt, " {}", n)?,
+            None => (),
+        }
+        if let Some(local_pref) = summary.local_pref {
+            write!(fmt, " local-pref {}", local_pref)?;
+        }
+        if let Some(med) = summary.med {
+            write!(fmt, " med {}", med)?;
+        }
+        if let Some(communities) = summary.communities {
+            fmt.write_str(" communities [")?;
+            for (i, community) in communities.enumerate() {
+                write!(fmt, "{}{}:{}", if i > 0 { " " } else { "" }, community.asn(), community.value())?;
+            }
+            fmt.write_str("]")?;
+        }
+        if let Some(communities) = summary.large_communities {
+            fmt.write_str(" large-communities [")?;
+            for (i, community) in communities.enumerate() {
+                write!(fmt, "{}{:?}", if i > 0 { " " } else { "" }, community)?;
+            }
+            fmt.write_str("]")?;
+        }
+        Ok(())
+    }
+}
+
+fn write_nlri(fmt: &mut fmt::Formatter, nlri: &Nlri) -> fmt::Result {
+    write!(fmt, "{}", nlri.prefix)?;
+    match nlri.path_id {
+        Some(path_id) => write!(fmt, " path-id {}", path_id),
+        None => Ok(()),
+    }
+}
+
+/// The attributes shown, each from its first occurrence, checked so that
+/// they can be displayed without errors.
+#[derive(Default)]
+struct Summary<'a> {
+    next_hop: Option<IpAddrBytes<'a>>,
+    mp_next_hop: Option<IpAddrBytes<'a>>,
+    as_path: Option<AsPathIter<'a>>,
+    origin: Option<OriginType>,
+    local_pref: Option<u32>,
+    med: Option<u32>,
+    communities: Option<CommunityIter<'a>>,
+    large_communities: Option<LargeCommunityIter<'a>>,
+}
+
+impl<'a> Summary<'a> {
+    fn new(attrs: PathAttrIter<'a>) -> Result<Summary<'a>> {
+        let mut summary = Summary::default();
+        for attr in attrs {
+            match attr? {
+                PathAttr::NextHop(next_hop) =>
+                    summary.next_hop = summary.next_hop.or(Some(next_hop.addr())),
+                PathAttr::MpReachNlri(reach) =>
+                    summary.mp_next_hop = summary.mp_next_hop.or(reach.nexthop().addr()),
+                PathAttr::AsPath(path) if summary.as_path.is_none() =>
+                    summary.as_path = Some(checked(path.segments())?),
+                PathAttr::As4Path(ref path) if path.code() == 2 && summary.as_path.is_none() =>
+                    summary.as_path = Some(checked(path.segments())?),
+                PathAttr::Origin(origin) =>
+                    summary.origin = summary.origin.or(Some(origin.try_origin()?)),
+                PathAttr::LocalPreference(pref) =>
+                    summary.local_pref = summary.local_pref.or(Some(pref.preference())),
+                PathAttr::MultiExitDisc(med) =>
+                    summary.med = summary.med.or(Some(med.med())),
+                PathAttr::Communities(communities) if summary.communities.is_none() =>
+                    summary.communities = Some(communities.communities()?),
+                PathAttr::LargeCommunities(communities) if summary.large_communities.is_none() =>
+                    summary.large_communities = Some(communities.communities()?),
+                _ => (),
+            }
+        }
+        Ok(summary)
+    }
+}
+
+/// Fails with the first error decoding the AS path.
+fn checked(segments: AsPathIter) -> Result<AsPathIter> {
+    for segment in segments.clone() {
+        match segment? {
+            AsPathSegment::AsSequence(seq) => seq.aut_nums().map(|_| ())?,
+            AsPathSegment::AsSet(set) => set.aut_nums().map(|_| ())?,
+        }
+    }
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bgp::Message;
+    use core::convert::TryFrom;
+    use core::fmt::Write;
+    use types::FmtBuf;
+
+    fn lines(bytes: &[u8], expected: &[&str]) {
+        let update = match Message::try_from(bytes) {
+            Ok(Message::Update(update)) => update,
+            x => panic!("expected Update, got {:?}", x),
+        };
+        assert_eq!(update.routes().count(), expected.len());
+        for (event, expected) in update.routes().zip(expected) {
+            let mut buf = FmtBuf::new();
+            write!(buf, "{}", RouteLine(&event.unwrap())).unwrap();
+            assert_eq!(buf.as_bytes(), expected.as_bytes());
+        }
+    }
+
+    #[test]
+    fn route_lines() {
+        lines(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                0xff, 0xff, 0xff, 0xff, 0x00, 0x38, 0x02, 0x00, 0x00, 0x00, 0x1f,
+                0x40, 0x01, 0x01, 0x00,
+                0x40, 0x02, 0x0a, 0x02, 0x02, 0x00, 0x00, 0x0d, 0x1c, 0x00, 0x00, 0x05, 0x13,
+                0x40, 0x03, 0x04, 0xc0, 0x00, 0x02, 0x01,
+                0xc0, 0x08, 0x04, 0x0d, 0x1c, 0x00, 0x14,
+                0x08, 0x0a],
+              &["10.0.0.0/8 via 192.0.2.1 AS_PATH 3356 1299 i communities [3356:20]"]);
+
+        // withdraws 10.0.1.0/24, announces 198.51.100.0/24 and 10.1.0.0/16
+        lines(&[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                0xff, 0xff, 0xff, 0xff, 0x00, 0x36, 0x02, 0x00, 0x04, 0x18, 0x0a, 0x00,
+                0x01, 0x00, 0x14, 0x40, 0x01, 0x01, 0x00, 0x40, 0x02, 0x06, 0x02, 0x01,
+                0x00, 0x00, 0xfd, 0xe8, 0x40, 0x03, 0x04, 0x0a, 0x00, 0x00, 0x01, 0x18,
+                0xc6, 0x33, 0x64, 0x10, 0x0a, 0x01],
+              &["10.0.1.0/24 withdrawn",
+                "198.51.100.0/24 via 10.0.0.1 AS_PATH 65000 i",
+                "10.1.0.0/16 via 10.0.0.1 AS_PATH 65000 i"]);
+    }
+}
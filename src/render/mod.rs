@@ -0,0 +1,6 @@
+//! Text renderings of parsed messages, in the formats other BGP tools
+//! produce, so that their consumers can take input from this crate.
+
+#[cfg(feature = "alloc")]
+pub mod json;
+pub mod line;
@@ -0,0 +1,147 @@
+use core::convert::TryFrom;
+use core::fmt;
+use types::*;
+
+/// Global Administrator field of a Route Target or Route Origin community,
+/// and the Administrator subfield of a route distinguisher.
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum ExtCommAdmin {
+    /// Two-octet AS number (type 0x00)
+    As2(u16),
+    /// IPv4 address (type 0x01)
+    Ipv4(u32),
+    /// Four-octet AS number (type 0x02)
+    As4(u32),
+}
+
+/// A route distinguisher (RFC 4364 section 4.2), as found in VPN NLRI,
+/// EVPN routes and the BMP peer distinguisher, borrowed from the message
+/// it appears in.
+///
+/// Displayed as `65000:1`, `10.0.0.1:1` or `4200000000:1` for types 0, 1
+/// and 2, and as the type followed by the value in hex for other types.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RouteDistinguisher<'a>(pub &'a [u8; 8]);
+
+impl<'a> RouteDistinguisher<'a> {
+    /// Fails with `BgpError::BadLength` unless `bytes` is 8 octets.
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<RouteDistinguisher<'a>> {
+        <&[u8; 8]>::try_from(bytes).map(RouteDistinguisher).map_err(|_| BgpError::BadLength)
+    }
+
+    /// Encodes a route distinguisher of type 0, 1 or 2 according to
+    /// `admin`, for `RouteDistinguisher(&bytes)` to borrow. Fails with
+    /// `BgpError::Invalid` if `assigned` does not fit the 2 octets types 1
+    /// and 2 leave for it.
+    pub fn encode(admin: ExtCommAdmin, assigned: u32) -> Result<[u8; 8]> {
+        let mut bytes = [0; 8];
+        match admin {
+            ExtCommAdmin::As2(asn) => {
+                bytes[2..4].copy_from_slice(&asn.to_be_bytes());
+                bytes[4..].copy_from_slice(&assigned.to_be_bytes());
+                return Ok(bytes);
+            }
+            ExtCommAdmin::Ipv4(addr) => {
+                bytes[1] = 1;
+                bytes[2..6].copy_from_slice(&addr.to_be_bytes());
+            }
+            ExtCommAdmin::As4(asn) => {
+                bytes[1] = 2;
+                bytes[2..6].copy_from_slice(&asn.to_be_bytes());
+            }
+        }
+        let assigned = u16::try_from(assigned).map_err(|_| BgpError::Invalid)?;
+        bytes[6..].copy_from_slice(&assigned.to_be_bytes());
+        Ok(bytes)
+    }
+
+    pub fn octets(&self) -> &'a [u8] {
+        self.0
+    }
+
+    pub fn rd_type(&self) -> u16 {
+        u16::from_be_bytes([self.0[0], self.0[1]])
+    }
+
+    /// The Administrator subfield, tagged with its encoding, or `None` if
+    /// the type is not 0, 1 or 2.
+    pub fn admin(&self) -> Option<ExtCommAdmin> {
+        let value = &self.0[2..];
+        match self.rd_type() {
+            0 => Some(ExtCommAdmin::As2(u16::from_be_bytes([value[0], value[1]]))),
+            1 => Some(ExtCommAdmin::Ipv4(u32::from_be_bytes([value[0], value[1], value[2], value[3]]))),
+            2 => Some(ExtCommAdmin::As4(u32::from_be_bytes([value[0], value[1], value[2], value[3]]))),
+            _ => None,
+        }
+    }
+
+    /// The Assigned Number subfield: four octets wide for type 0, two
+    /// otherwise. `None` if the type is not 0, 1 or 2.
+    pub fn assigned(&self) -> Option<u32> {
+        match self.rd_type() {
+            0 => Some(u32::from_be_bytes([self.0[4], self.0[5], self.0[6], self.0[7]])),
+            1 | 2 => Some(u16::from_be_bytes([self.0[6], self.0[7]]) as u32),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> fmt::Display for RouteDistinguisher<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match (self.admin(), self.assigned()) {
+            (Some(ExtCommAdmin::As2(asn)), Some(assigned)) => write!(fmt, "{}:{}", asn, assigned),
+            (Some(ExtCommAdmin::Ipv4(_)), Some(assigned)) =>
+                write!(fmt, "{}:{}", IpAddrBytes::v4(&self.0[2..6]), assigned),
+            (Some(ExtCommAdmin::As4(asn)), Some(assigned)) => write!(fmt, "{}:{}", asn, assigned),
+            _ => {
+                write!(fmt, "{}:", self.rd_type())?;
+                for octet in &self.0[2..] {
+                    write!(fmt, "{:02x}", octet)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl<'a> fmt::Debug for RouteDistinguisher<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, fmt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+    use types::FmtBuf;
+
+    #[test]
+    fn display_route_distinguishers() {
+        let cases: [(ExtCommAdmin, u32, &[u8]); 3] = [
+            (ExtCommAdmin::As2(65000), 1, b"65000:1"),
+            (ExtCommAdmin::Ipv4(0x0a000001), 1, b"10.0.0.1:1"),
+            (ExtCommAdmin::As4(4200000000), 1, b"4200000000:1"),
+        ];
+        for &(admin, assigned, expected) in &cases {
+            let bytes = RouteDistinguisher::encode(admin, assigned).unwrap();
+            let rd = RouteDistinguisher(&bytes);
+            assert_eq!(rd.admin(), Some(admin));
+            assert_eq!(rd.assigned(), Some(assigned));
+            let mut buf = FmtBuf::new();
+            write!(buf, "{}", rd).unwrap();
+            assert_eq!(buf.as_bytes(), expected);
+        }
+        assert_eq!(RouteDistinguisher::encode(ExtCommAdmin::As2(65000), 1).unwrap(),
+                   [0, 0, 0xfd, 0xe8, 0, 0, 0, 1]);
+        assert!(matches!(RouteDistinguisher::encode(ExtCommAdmin::As4(65000), 0x10000),
+                         Err(BgpError::Invalid)));
+
+        let rd = RouteDistinguisher::from_bytes(&[0, 3, 1, 2, 3, 4, 5, 6]).unwrap();
+        assert_eq!(rd.admin(), None);
+        let mut buf = FmtBuf::new();
+        write!(buf, "{}", rd).unwrap();
+        assert_eq!(buf.as_bytes(), b"3:010203040506");
+        assert!(matches!(RouteDistinguisher::from_bytes(&[0; 7]), Err(BgpError::BadLength)));
+    }
+}
@@ -55,7 +55,7 @@ pub const SAFI_VPNV_AUTODISC: Safi = Safi(134);
 // 255   Reserved                                                                 [RFC4760]
 
 
-#[derive(PartialEq, Eq, Clone, Copy)]
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
 pub struct Safi(u8);
 
 impl From<u8> for Safi {
@@ -64,6 +64,58 @@ impl From<u8> for Safi {
     }
 }
 
+impl From<Safi> for u8 {
+    fn from(safi: Safi) -> u8 {
+        safi.0
+    }
+}
+
+impl Safi {
+    /// The description IANA lists for the SAFI value.
+    #[cfg(feature = "names")]
+    pub fn name(&self) -> &'static str {
+        match self.0 {
+            1 => "Unicast",
+            2 => "Multicast",
+            4 => "NLRI with MPLS Labels",
+            5 => "MCAST-VPN",
+            6 => "Multi-Segment Pseudowires",
+            7 => "Encapsulation SAFI",
+            8 => "MCAST-VPLS",
+            9 => "BGP SFC",
+            64 => "Tunnel SAFI",
+            65 => "Virtual Private LAN Service (VPLS)",
+            66 => "BGP MDT SAFI",
+            67 => "BGP 4over6 SAFI",
+            68 => "BGP 6over4 SAFI",
+            69 => "Layer-1 VPN auto-discovery information",
+            70 => "BGP EVPNs",
+            71 => "BGP-LS",
+            72 => "BGP-LS-VPN",
+            73 => "SR TE Policy SAFI",
+            74 => "SD-WAN Capabilities",
+            75 => "Routing Policy SAFI",
+            76 => "Classful-Transport SAFI",
+            77 => "Tunneled Traffic Flowspec",
+            78 => "MCAST-TREE",
+            79 => "BGP-DPS (Dynamic Path Selection)",
+            80 => "BGP-LS-SPF",
+            83 => "BGP CAR",
+            84 => "BGP VPN CAR",
+            85 => "BGP-MUP SAFI",
+            128 => "MPLS-labeled VPN address",
+            129 => "Multicast for BGP/MPLS IP Virtual Private Networks (VPNs)",
+            132 => "Route Target constrains",
+            133 => "Dissemination of Flow Specification rules",
+            134 => "L3VPN Dissemination of Flow Specification rules",
+            140 => "VPN auto-discovery",
+            241..=254 => "Reserved for Private Use",
+            0 | 3 | 130 | 131 | 135..=139 | 141..=240 | 255 => "Reserved",
+            _ => "Unassigned",
+        }
+    }
+}
+
 impl fmt::Debug for Safi {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.0 {
@@ -73,3 +125,14 @@ impl fmt::Debug for Safi {
         }
     }
 }
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Safi {
+    fn format(&self, f: defmt::Formatter) {
+        match self.0 {
+            1 => defmt::write!(f, "unicast"),
+            2 => defmt::write!(f, "multicast"),
+            n => defmt::write!(f, "unknown({=u8})", n),
+        }
+    }
+}
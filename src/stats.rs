@@ -0,0 +1,193 @@
+//! Counts of what the parser has seen, for monitoring ingestion.
+//!
+//! `bgp::decode_with_stats` and `bmp::decode_with_stats` parse like their
+//! counterparts without the suffix and record each message in a
+//! `ParserStats`.
+
+use bgp::Message;
+use bgp::attr_flags::Attr;
+use bgp::update::routes::RouteEvent;
+use types::*;
+
+/// Message, attribute, prefix and error counts, without allocating.
+///
+/// Prefixes are counted for up to `N` address families. Prefixes of
+/// further families are only counted by `other_prefixes`.
+#[derive(Debug, Clone)]
+pub struct ParserStats<const N: usize = 16> {
+    messages: [u64; 6],
+    octets: u64,
+    attributes: [u64; 256],
+    families: [Option<(AfiSafi, u64, u64)>; N],
+    other_prefixes: u64,
+    bad_length: u64,
+    invalid: u64,
+}
+
+impl<const N: usize> ParserStats<N> {
+    pub fn new() -> ParserStats<N> {
+        ParserStats {
+            messages: [0; 6],
+            octets: 0,
+            attributes: [0; 256],
+            families: [None; N],
+            other_prefixes: 0,
+            bad_length: 0,
+            invalid: 0,
+        }
+    }
+
+    /// Records the outcome of parsing a BGP message. The path attributes
+    /// and prefixes of an UPDATE are counted too, and errors decoding them
+    /// are counted with the other errors.
+    pub fn record(&mut self, message: &Result<Message>) {
+        match *message {
+            Ok(ref message) => self.record_message(message),
+            Err(err) => self.record_error(err),
+        }
+    }
+
+    fn record_message(&mut self, message: &Message) {
+        self.messages[message.msg_type() as usize] += 1;
+        self.octets += message.len() as u64;
+        let update = match *message {
+            Message::Update(ref update) => update,
+            _ => return,
+        };
+        for attr in update.path_attrs() {
+            match attr {
+                Ok(attr) => self.attributes[attr.code() as usize] += 1,
+                Err(err) => self.record_error(err),
+            }
+        }
+        for event in update.routes() {
+            match event {
+                Ok(RouteEvent::Announce { ref nlri, .. }) =>
+                    self.record_prefix(AfiSafi::new(nlri.afi, nlri.safi), true),
+                Ok(RouteEvent::Withdraw { ref nlri }) =>
+                    self.record_prefix(AfiSafi::new(nlri.afi, nlri.safi), false),
+                Ok(RouteEvent::OtherAnnounce { .. }) | Ok(RouteEvent::OtherWithdraw { .. }) => (),
+                Err(err) => self.record_error(err),
+            }
+        }
+    }
+
+    fn record_prefix(&mut self, family: AfiSafi, announced: bool) {
+        let slot = self.families.iter().position(|entry| matches!(*entry, Some((f, _, _)) if f == family))
+            .or_else(|| self.families.iter().position(Option::is_none));
+        match slot.map(|slot| &mut self.families[slot]) {
+            Some(entry) => {
+                let (_, ref mut announcements, ref mut withdrawals) = *entry.get_or_insert((family, 0, 0));
+                if announced {
+                    *announcements += 1;
+                } else {
+                    *withdrawals += 1;
+                }
+            }
+            None => self.other_prefixes += 1,
+        }
+    }
+
+    /// Records a parse error.
+    pub fn record_error(&mut self, err: BgpError) {
+        match err {
+            BgpError::BadLength => self.bad_length += 1,
+            BgpError::Invalid => self.invalid += 1,
+        }
+    }
+
+    /// The number of messages parsed of type `msg_type`.
+    pub fn messages(&self, msg_type: u8) -> u64 {
+        self.messages.get(msg_type as usize).cloned().unwrap_or(0)
+    }
+
+    /// The number of messages parsed, of any type.
+    pub fn total_messages(&self) -> u64 {
+        self.messages.iter().sum()
+    }
+
+    /// The total length of the messages parsed, headers included.
+    pub fn octets(&self) -> u64 {
+        self.octets
+    }
+
+    /// The number of path attributes with type code `code`.
+    pub fn attributes(&self, code: u8) -> u64 {
+        self.attributes[code as usize]
+    }
+
+    /// The number of prefixes of `family` announced and withdrawn.
+    pub fn prefixes(&self, family: AfiSafi) -> (u64, u64) {
+        self.families.iter()
+            .filter_map(|entry| *entry)
+            .find(|&(f, _, _)| f == family)
+            .map_or((0, 0), |(_, announced, withdrawn)| (announced, withdrawn))
+    }
+
+    /// The address families prefixes were counted for, with the number
+    /// announced and withdrawn.
+    pub fn families(&self) -> impl Iterator<Item = (AfiSafi, u64, u64)> + '_ {
+        self.families.iter().filter_map(|entry| *entry)
+    }
+
+    /// The number of prefixes announced or withdrawn for families beyond
+    /// the first `N`.
+    pub fn other_prefixes(&self) -> u64 {
+        self.other_prefixes
+    }
+
+    /// The number of errors of kind `err`.
+    pub fn errors(&self, err: BgpError) -> u64 {
+        match err {
+            BgpError::BadLength => self.bad_length,
+            BgpError::Invalid => self.invalid,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = ParserStats::new();
+    }
+}
+
+impl<const N: usize> Default for ParserStats<N> {
+    fn default() -> ParserStats<N> {
+        ParserStats::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bgp::{self, SessionConfig};
+
+    #[test]
+    fn count_messages() {
+        let update = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+                      0xff, 0xff, 0xff, 0xff, 0x00, 0x36, 0x02, 0x00, 0x04, 0x18, 0x0a, 0x00,
+                      0x01, 0x00, 0x14, 0x40, 0x01, 0x01, 0x00, 0x40, 0x02, 0x06, 0x02, 0x01,
+                      0x00, 0x00, 0xfd, 0xe8, 0x40, 0x03, 0x04, 0x0a, 0x00, 0x00, 0x01, 0x18,
+                      0xc6, 0x33, 0x64, 0x10, 0x0a, 0x01];
+        let mut keepalive = [0xff; 19];
+        keepalive[16..].copy_from_slice(&[0x00, 0x13, 0x04]);
+        let config = SessionConfig::default();
+
+        let mut stats: ParserStats<1> = ParserStats::new();
+        bgp::decode_with_stats(&update, &config, &mut stats).unwrap();
+        bgp::decode_with_stats(&keepalive, &config, &mut stats).unwrap();
+        assert!(bgp::decode_with_stats(&keepalive[..18], &config, &mut stats).is_err());
+
+        assert_eq!(stats.total_messages(), 2);
+        assert_eq!(stats.messages(2), 1);
+        assert_eq!(stats.messages(4), 1);
+        assert_eq!(stats.octets(), 54 + 19);
+        assert_eq!(stats.attributes(2), 1);
+        assert_eq!(stats.attributes(4), 0);
+        assert_eq!(stats.prefixes(IPV4_UNICAST), (2, 1));
+        assert_eq!(stats.families().count(), 1);
+        assert_eq!(stats.errors(BgpError::BadLength), 1);
+        assert_eq!(stats.errors(BgpError::Invalid), 0);
+
+        stats.reset();
+        assert_eq!(stats.total_messages(), 0);
+    }
+}
@@ -0,0 +1,123 @@
+use types::*;
+use core::convert::TryFrom;
+
+/// A type-length-value element, with its type and length fields removed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tlv<'a> {
+    pub tlv_type: u16,
+    pub value: &'a [u8],
+}
+
+impl<'a> Tlv<'a> {
+    /// Writes the TLV with 2 octet type and length fields to the start of
+    /// `buf` and returns it. Fails with `BgpError::BadLength` if `buf` is
+    /// too short or the value does not fit the length field.
+    pub fn write<'b>(&self, buf: &'b mut [u8]) -> Result<&'b [u8]> {
+        let len = u16::try_from(self.value.len()).map_err(|_| BgpError::BadLength)?;
+        if buf.len() < 4 + self.value.len() {
+            return Err(BgpError::BadLength);
+        }
+        buf[..2].copy_from_slice(&self.tlv_type.to_be_bytes());
+        buf[2..4].copy_from_slice(&len.to_be_bytes());
+        buf[4..4 + self.value.len()].copy_from_slice(self.value);
+        Ok(&buf[..4 + self.value.len()])
+    }
+}
+
+/// Iterates over back to back TLVs, as found in BMP messages and many
+/// BGP attributes.
+///
+/// The type and length fields are 2 octets each unless set otherwise with
+/// `TlvIter::with_widths`. The length counts the value only. A TLV that
+/// does not fit yields `BgpError::BadLength` and ends the iteration.
+#[derive(Debug, Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct TlvIter<'a> {
+    inner: &'a [u8],
+    type_width: usize,
+    length_width: usize,
+    error: bool,
+}
+
+impl<'a> TlvIter<'a> {
+    pub fn new(inner: &'a [u8]) -> TlvIter<'a> {
+        TlvIter::with_widths(inner, 2, 2)
+    }
+
+    /// Iterates over TLVs with type and length fields of 1 or 2 octets.
+    pub fn with_widths(inner: &'a [u8], type_width: usize, length_width: usize) -> TlvIter<'a> {
+        debug_assert!((1..=2).contains(&type_width) && (1..=2).contains(&length_width));
+        TlvIter {
+            inner,
+            type_width,
+            length_width,
+            error: false,
+        }
+    }
+
+    /// The bytes not yet iterated over.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.inner
+    }
+
+    fn field_at(&self, offset: usize, width: usize) -> Result<u16> {
+        match width {
+            1 => self.inner.at(offset).map(u16::from),
+            _ => self.inner.u16_at(offset),
+        }
+    }
+
+    fn split_tlv(&mut self) -> Result<Tlv<'a>> {
+        let tlv_type = self.field_at(0, self.type_width)?;
+        let header_len = self.type_width + self.length_width;
+        let len = self.field_at(self.type_width, self.length_width)? as usize;
+        let value = self.inner.slice(header_len, len)?;
+        self.inner = self.inner.skip(header_len + len)?;
+        Ok(Tlv { tlv_type, value })
+    }
+}
+
+impl<'a> Iterator for TlvIter<'a> {
+    type Item = Result<Tlv<'a>>;
+
+    fn next(&mut self) -> Option<Result<Tlv<'a>>> {
+        if self.error || self.inner.is_empty() {
+            return None;
+        }
+        let tlv = self.split_tlv();
+        self.error = tlv.is_err();
+        Some(tlv)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn iterate_tlvs() {
+        let bytes = [0x00, 0x01, 0x00, 0x02, 0xaa, 0xbb,
+                     0x01, 0x02, 0x00, 0x00,
+                     0x00, 0x03, 0x00, 0x04, 0xcc];
+        let mut tlvs = TlvIter::new(&bytes);
+        assert_eq!(tlvs.next().unwrap().unwrap(), Tlv { tlv_type: 1, value: &[0xaa, 0xbb] });
+        assert_eq!(tlvs.next().unwrap().unwrap(), Tlv { tlv_type: 0x0102, value: &[] });
+        assert_eq!(tlvs.remaining(), &bytes[10..]);
+        assert!(matches!(tlvs.next(), Some(Err(BgpError::BadLength))));
+        assert!(tlvs.next().is_none());
+
+        let bytes = [0x01, 0x00, 0x01, 0xaa, 0x02];
+        let mut tlvs = TlvIter::with_widths(&bytes, 1, 2);
+        assert_eq!(tlvs.next().unwrap().unwrap(), Tlv { tlv_type: 1, value: &[0xaa] });
+        assert!(matches!(tlvs.next(), Some(Err(BgpError::BadLength))));
+        assert!(tlvs.next().is_none());
+    }
+
+    #[test]
+    fn write_tlv() {
+        let mut buf = [0; 6];
+        let tlv = Tlv { tlv_type: 0x0102, value: &[0xaa, 0xbb] };
+        assert_eq!(tlv.write(&mut buf).unwrap(), &[0x01, 0x02, 0x00, 0x02, 0xaa, 0xbb]);
+        assert!(matches!(tlv.write(&mut buf[..5]), Err(BgpError::BadLength)));
+    }
+}
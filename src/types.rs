@@ -3,67 +3,268 @@ use core::fmt;
 
 pub use afi::*;
 pub use safi::*;
+pub use afi_safi::*;
+pub use as_number::*;
+pub use ip_addr::*;
+pub use label::*;
+pub use mac_addr::*;
+pub use route_distinguisher::*;
+pub use tlv::*;
 
 pub const VALID_BGP_MARKER: [u8; 16] = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
                                         0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
 pub struct Ipv4Prefix<'a> {
-    pub inner: &'a [u8],
+    mask_len: u8,
+    octets: &'a [u8],
 }
 
-impl<'a> fmt::Debug for Ipv4Prefix<'a> {
+impl<'a> Ipv4Prefix<'a> {
+    /// A prefix of `mask_len` bits, given by the leading bits of `octets`.
+    pub fn new(mask_len: u8, octets: &'a [u8]) -> Ipv4Prefix<'a> {
+        Ipv4Prefix { mask_len, octets }
+    }
+
+    pub fn mask_len(&self) -> u8 {
+        self.mask_len
+    }
+
+    /// The octets covered by the mask length, as encoded.
+    pub fn octets(&self) -> &'a [u8] {
+        self.octets
+    }
+}
+
+impl<'a> fmt::Display for Ipv4Prefix<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        let masklen = self.inner[0];
-        if masklen == 0 {
-            return fmt.write_str("0/0");
-        }
+        write_prefix(fmt, self.mask_len, self.octets, false)
+    }
+}
 
-        let mut print_period = false;
-        for octet in &self.inner[1..] {
-            if print_period {
-                try!(fmt.write_str("."));
-            }
-            print_period = true;
-            try!(octet.fmt(fmt));
-        }
-        try!(fmt.write_str("/"));
-        masklen.fmt(fmt)
+impl<'a> fmt::Debug for Ipv4Prefix<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, fmt)
     }
 }
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
 pub struct Ipv6Prefix<'a> {
-    pub inner: &'a [u8],
+    mask_len: u8,
+    octets: &'a [u8],
+}
+
+impl<'a> Ipv6Prefix<'a> {
+    /// A prefix of `mask_len` bits, given by the leading bits of `octets`.
+    pub fn new(mask_len: u8, octets: &'a [u8]) -> Ipv6Prefix<'a> {
+        Ipv6Prefix { mask_len, octets }
+    }
+
+    pub fn mask_len(&self) -> u8 {
+        self.mask_len
+    }
+
+    /// The octets covered by the mask length, as encoded.
+    pub fn octets(&self) -> &'a [u8] {
+        self.octets
+    }
+}
+
+impl<'a> fmt::Display for Ipv6Prefix<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        write_prefix(fmt, self.mask_len, self.octets, true)
+    }
 }
 
 impl<'a> fmt::Debug for Ipv6Prefix<'a> {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
-        let masklen = self.inner[0];
-        if masklen == 0 {
-            return fmt.write_str("::/0");
-        }
-
-        let mut print_colon = false;
-        for chunk in self.inner[1..].chunks(2) {
-            let a = chunk[0] as u16;
-            let b: u8 = *chunk.get(1).unwrap_or(&0);
-            let segment: u16 = a << 8 | (b as u16);
-            if print_colon {
-                try!(fmt.write_str(":"));
+        fmt::Display::fmt(self, fmt)
+    }
+}
+
+/// Writes the address of an encoded prefix in full, the octets past the
+/// mask as zeros, followed by the mask length: `10.0.0.0/8`, `0.0.0.0/0`,
+/// `2001:db8::/32` or `::/0`.
+fn write_prefix(fmt: &mut fmt::Formatter, mask_len: u8, bytes: &[u8], ipv6: bool) -> fmt::Result {
+    let mut octets = [0; 16];
+    let len = if ipv6 { 16 } else { 4 };
+    let bytes = &bytes[..bytes.len().min(len)];
+    octets[..bytes.len()].copy_from_slice(bytes);
+    let addr = if ipv6 { IpAddrBytes::v6(&octets) } else { IpAddrBytes::v4(&octets) };
+    write!(fmt, "{}/{}", addr, mask_len)
+}
+
+/// An IPv4 or IPv6 prefix.
+///
+/// Ordered by address family, IPv4 first, then by mask length and then by
+/// the prefix octets.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Prefix<'a> {
+    Ipv4(Ipv4Prefix<'a>),
+    Ipv6(Ipv6Prefix<'a>),
+}
+
+impl<'a> fmt::Display for Prefix<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Prefix::Ipv4(ref prefix) => prefix.fmt(fmt),
+            Prefix::Ipv6(ref prefix) => prefix.fmt(fmt),
+        }
+    }
+}
+
+impl<'a> Prefix<'a> {
+    pub fn mask_len(&self) -> u8 {
+        match *self {
+            Prefix::Ipv4(ref prefix) => prefix.mask_len,
+            Prefix::Ipv6(ref prefix) => prefix.mask_len,
+        }
+    }
+
+    /// Whether this is the default route, `0.0.0.0/0` or `::/0`.
+    pub fn is_default(&self) -> bool {
+        self.mask_len() == 0
+    }
+
+    fn is_ipv6(&self) -> bool {
+        matches!(*self, Prefix::Ipv6(_))
+    }
+
+    /// The octets covered by the mask length, as encoded.
+    pub fn octets(&self) -> &'a [u8] {
+        match *self {
+            Prefix::Ipv4(ref prefix) => prefix.octets,
+            Prefix::Ipv6(ref prefix) => prefix.octets,
+        }
+    }
+
+    /// Bit `i` of the prefix, counting from the most significant bit of
+    /// the address, or `None` if `i` is not below the mask length.
+    pub fn bit(&self, i: u8) -> Option<bool> {
+        if i >= self.mask_len() {
+            return None;
+        }
+        Some(octet_bit(self.octets(), i))
+    }
+
+    /// The bits of the prefix, most significant first, as in a walk down
+    /// a radix trie.
+    pub fn iter_bits(&self) -> PrefixBits<'a> {
+        PrefixBits {
+            octets: self.octets(),
+            mask_len: self.mask_len(),
+            index: 0,
+        }
+    }
+
+    /// The number of leading bits this prefix and `other` have in
+    /// common, at most the shorter of the two mask lengths. 0 if they are
+    /// of different address families.
+    pub fn common_prefix_len(&self, other: &Prefix) -> u8 {
+        if self.is_ipv6() != other.is_ipv6() {
+            return 0;
+        }
+        let max = self.mask_len().min(other.mask_len());
+        let mut len = 0;
+        for (a, b) in self.octets().iter().zip(other.octets()) {
+            let diff = a ^ b;
+            len += diff.leading_zeros() as u8;
+            if diff != 0 || len >= max {
+                break;
             }
-            print_colon = true;
-            try!(fmt.write_fmt(format_args!("{:x}", segment)));
         }
-        if masklen < 112 {
-            try!(fmt.write_str("::"));
+        len.min(max)
+    }
+
+    /// The network address: the prefix with the bits past the mask length
+    /// cleared. Only the first 4 octets are used for IPv4.
+    pub fn network(&self) -> [u8; 16] {
+        self.address(false)
+    }
+
+    /// The broadcast address: the prefix with the bits past the mask
+    /// length set. Only the first 4 octets are used for IPv4.
+    pub fn broadcast(&self) -> [u8; 16] {
+        self.address(true)
+    }
+
+    /// The prefix as a radix trie key: the network address as an integer
+    /// aligned to the most significant bit, so that an IPv4 prefix takes
+    /// the top 32 bits, and the mask length.
+    pub fn to_key(&self) -> (u128, u8) {
+        (u128::from_be_bytes(self.network()), self.mask_len())
+    }
+
+    fn address(&self, host_bits: bool) -> [u8; 16] {
+        let mut address = [0; 16];
+        let len = if self.is_ipv6() { 16 } else { 4 };
+        let bytes = &self.octets()[..self.octets().len().min(len)];
+        address[..bytes.len()].copy_from_slice(bytes);
+        let mask_len = self.mask_len() as usize;
+        for (i, octet) in address[..len].iter_mut().enumerate() {
+            let covered = mask_len.saturating_sub(i * 8).min(8);
+            let mask = (0xff00u16 >> covered) as u8;
+            *octet = *octet & mask | if host_bits { !mask } else { 0 };
         }
-        try!(fmt.write_str("/"));
+        address
+    }
+
+    /// Splits the encoded prefix at the start of `bytes`, a mask length
+    /// followed by as many octets as it covers, from the rest. Fails with
+    /// `BgpError::Invalid` if the mask length exceeds 32, or 128 if
+    /// `ipv6`, and with `BgpError::BadLength` if the octets are missing.
+    pub fn split(bytes: &'a [u8], ipv6: bool) -> Result<(Prefix<'a>, &'a [u8])> {
+        let mask_len = bytes.at(0)?;
+        let (prefix, len) = Prefix::from_bits(mask_len, &bytes[1..], ipv6)?;
+        Ok((prefix, &bytes[1 + len..]))
+    }
+
+    /// The prefix of `mask_len` bits at the start of `bytes`, and the
+    /// number of octets it takes. Fails like `split`.
+    pub(crate) fn from_bits(mask_len: u8, bytes: &'a [u8], ipv6: bool) -> Result<(Prefix<'a>, usize)> {
+        if mask_len > if ipv6 { 128 } else { 32 } {
+            return Err(BgpError::Invalid);
+        }
+        let len = (mask_len as usize + 7) / 8;
+        let octets = bytes.take(len)?;
+        let prefix = if ipv6 {
+            Prefix::Ipv6(Ipv6Prefix::new(mask_len, octets))
+        } else {
+            Prefix::Ipv4(Ipv4Prefix::new(mask_len, octets))
+        };
+        Ok((prefix, len))
+    }
+}
+
+fn octet_bit(octets: &[u8], i: u8) -> bool {
+    let octet = octets.get(i as usize / 8).copied().unwrap_or(0);
+    octet & 0x80 >> (i % 8) > 0
+}
+
+/// Iterates over the bits of a prefix, see `Prefix::iter_bits`.
+#[derive(Debug, Clone)]
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+pub struct PrefixBits<'a> {
+    octets: &'a [u8],
+    mask_len: u8,
+    index: u8,
+}
+
+impl<'a> Iterator for PrefixBits<'a> {
+    type Item = bool;
 
-        masklen.fmt(fmt)
+    fn next(&mut self) -> Option<bool> {
+        if self.index >= self.mask_len {
+            return None;
+        }
+        let bit = octet_bit(self.octets, self.index);
+        self.index += 1;
+        Some(bit)
     }
 }
+
 #[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum BgpError {
     BadLength,
     Invalid,
@@ -77,3 +278,378 @@ impl fmt::Display for BgpError {
     }
 }
 
+/// A `BgpError` with where it was found: the offset into the message and
+/// a window of at most `WINDOW_LEN` octets from there, so the offending
+/// bytes can be logged. See `Update::validate_with_context`.
+#[derive(Debug, Clone, Copy)]
+pub struct ParseError<'a> {
+    pub error: BgpError,
+    pub offset: usize,
+    window: &'a [u8],
+}
+
+impl<'a> ParseError<'a> {
+    pub const WINDOW_LEN: usize = 16;
+
+    /// `error`, found at `offset` in `message`.
+    pub fn new(error: BgpError, message: &'a [u8], offset: usize) -> ParseError<'a> {
+        let offset = offset.min(message.len());
+        let window = &message[offset..];
+        ParseError {
+            error,
+            offset,
+            window: &window[..window.len().min(ParseError::WINDOW_LEN)],
+        }
+    }
+
+    /// The octets of the message from the offset on.
+    pub fn window(&self) -> &'a [u8] {
+        self.window
+    }
+
+    /// The window as a hex dump numbered with offsets into the message.
+    pub fn hex_dump(&self) -> HexDump<'a> {
+        HexDump::new(self.window).with_offset(self.offset)
+    }
+}
+
+impl<'a> fmt::Display for ParseError<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at offset {}", self.error, self.offset)?;
+        if !self.window.is_empty() {
+            write!(f, "\n{}", self.hex_dump())?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> From<ParseError<'a>> for BgpError {
+    fn from(err: ParseError<'a>) -> BgpError {
+        err.error
+    }
+}
+
+/// Bounds checked reads from untrusted input. Reads past the end fail with
+/// `BgpError::BadLength` instead of panicking.
+pub(crate) trait Checked {
+    /// The octet at `index`.
+    fn at(&self, index: usize) -> Result<u8>;
+
+    /// The first `len` octets.
+    fn take(&self, len: usize) -> Result<&[u8]>;
+
+    /// Everything after the first `len` octets.
+    fn skip(&self, len: usize) -> Result<&[u8]>;
+
+    /// The `len` octets starting at `offset`.
+    fn slice(&self, offset: usize, len: usize) -> Result<&[u8]>;
+
+    /// The big-endian 16-bit value at `offset`.
+    fn u16_at(&self, offset: usize) -> Result<u16> {
+        let bytes = self.slice(offset, 2)?;
+        Ok((bytes[0] as u16) << 8 | bytes[1] as u16)
+    }
+
+    /// The big-endian 32-bit value at `offset`.
+    fn u32_at(&self, offset: usize) -> Result<u32> {
+        let bytes = self.slice(offset, 4)?;
+        Ok(bytes.iter().fold(0, |acc, &byte| acc << 8 | byte as u32))
+    }
+
+    /// The big-endian 64-bit value at `offset`.
+    fn u64_at(&self, offset: usize) -> Result<u64> {
+        let bytes = self.slice(offset, 8)?;
+        Ok(bytes.iter().fold(0, |acc, &byte| acc << 8 | byte as u64))
+    }
+}
+
+impl Checked for [u8] {
+    fn at(&self, index: usize) -> Result<u8> {
+        self.get(index).cloned().ok_or(BgpError::BadLength)
+    }
+
+    fn take(&self, len: usize) -> Result<&[u8]> {
+        self.get(..len).ok_or(BgpError::BadLength)
+    }
+
+    fn skip(&self, len: usize) -> Result<&[u8]> {
+        self.get(len..).ok_or(BgpError::BadLength)
+    }
+
+    fn slice(&self, offset: usize, len: usize) -> Result<&[u8]> {
+        let end = offset.checked_add(len).ok_or(BgpError::BadLength)?;
+        self.get(offset..end).ok_or(BgpError::BadLength)
+    }
+}
+
+/// Runs `items` to the end, returning the first error.
+pub(crate) fn validate_all<T, I: Iterator<Item = Result<T>>>(items: I) -> Result<()> {
+    for item in items {
+        item?;
+    }
+    Ok(())
+}
+
+/// Full, decoded formatting for types whose `Debug` output is kept to a
+/// cheap summary because producing it means parsing nested structures.
+pub trait FmtVerbose {
+    fn fmt_verbose(&self, fmt: &mut fmt::Formatter) -> fmt::Result;
+
+    /// Wraps `self` so that `{:?}` uses `fmt_verbose`.
+    fn verbose(&self) -> Verbose<'_, Self> where Self: Sized {
+        Verbose(self)
+    }
+}
+
+/// `Debug` adapter returned by `FmtVerbose::verbose`.
+pub struct Verbose<'b, T: 'b>(pub &'b T);
+
+impl<'b, T: FmtVerbose> fmt::Debug for Verbose<'b, T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        self.0.fmt_verbose(fmt)
+    }
+}
+
+/// Displays bytes as a hex dump: one line per 16 octets, with the offset,
+/// the octets in hex and their printable ASCII characters.
+///
+/// ```text
+/// 0000  ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff  |................|
+/// 0010  00 13 04                                         |...|
+/// ```
+#[derive(Clone, Copy)]
+pub struct HexDump<'a> {
+    bytes: &'a [u8],
+    limit: usize,
+    offset: usize,
+}
+
+impl<'a> HexDump<'a> {
+    pub fn new(bytes: &'a [u8]) -> HexDump<'a> {
+        HexDump {
+            bytes,
+            limit: bytes.len(),
+            offset: 0,
+        }
+    }
+
+    /// Number the lines from `offset`, for bytes taken from further into
+    /// a message.
+    pub fn with_offset(mut self, offset: usize) -> HexDump<'a> {
+        self.offset = offset;
+        self
+    }
+
+    /// Dump at most `limit` octets, noting how many were left out.
+    pub fn limit(mut self, limit: usize) -> HexDump<'a> {
+        self.limit = limit;
+        self
+    }
+}
+
+impl<'a> fmt::Display for HexDump<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        let shown = &self.bytes[..self.limit.min(self.bytes.len())];
+        for (line, chunk) in shown.chunks(16).enumerate() {
+            if line > 0 {
+                fmt.write_str("\n")?;
+            }
+            write!(fmt, "{:04x} ", self.offset + line * 16)?;
+            for i in 0..16 {
+                match chunk.get(i) {
+                    Some(byte) => write!(fmt, " {:02x}", byte)?,
+                    None => fmt.write_str("   ")?,
+                }
+            }
+            fmt.write_str("  |")?;
+            for &byte in chunk {
+                let c = if (0x20..0x7f).contains(&byte) { byte as char } else { '.' };
+                fmt::Write::write_char(fmt, c)?;
+            }
+            fmt.write_str("|")?;
+        }
+        if shown.len() < self.bytes.len() {
+            if !shown.is_empty() {
+                fmt.write_str("\n")?;
+            }
+            write!(fmt, "... {} more octets", self.bytes.len() - shown.len())?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Debug for HexDump<'a> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self, fmt)
+    }
+}
+
+/// A fixed size `fmt::Write` sink for checking formatting output in
+/// tests.
+#[cfg(test)]
+pub(crate) struct FmtBuf {
+    bytes: [u8; 512],
+    len: usize,
+}
+
+#[cfg(test)]
+impl FmtBuf {
+    pub(crate) fn new() -> FmtBuf {
+        FmtBuf { bytes: [0; 512], len: 0 }
+    }
+
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+#[cfg(test)]
+impl fmt::Write for FmtBuf {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.bytes[self.len..self.len + s.len()].copy_from_slice(s.as_bytes());
+        self.len += s.len();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::fmt::Write;
+
+    #[test]
+    fn checked_reads() {
+        let bytes: &[u8] = &[0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07];
+        assert_eq!(bytes.at(7).unwrap(), 0x07);
+        assert!(matches!(bytes.at(8), Err(BgpError::BadLength)));
+        assert_eq!(bytes.take(2).unwrap(), &[0x00, 0x01]);
+        assert!(matches!(bytes.take(9), Err(BgpError::BadLength)));
+        assert_eq!(bytes.skip(8).unwrap(), &[]);
+        assert!(matches!(bytes.skip(9), Err(BgpError::BadLength)));
+        assert_eq!(bytes.slice(6, 2).unwrap(), &[0x06, 0x07]);
+        assert!(matches!(bytes.slice(usize::MAX, 2), Err(BgpError::BadLength)));
+        assert_eq!(bytes.u16_at(1).unwrap(), 0x0102);
+        assert_eq!(bytes.u32_at(4).unwrap(), 0x04050607);
+        assert!(matches!(bytes.u32_at(5), Err(BgpError::BadLength)));
+        assert_eq!(bytes.u64_at(0).unwrap(), 0x0001020304050607);
+    }
+
+    #[test]
+    fn default_routes() {
+        use bgp::update::nlri::NlriIter;
+        use bgp::update::withdrawn_routes::WithdrawnRoutes;
+
+        let cases: [(Prefix, &[u8], bool); 5] = [
+            (Prefix::Ipv4(Ipv4Prefix::new(0, &[])), b"0.0.0.0/0", true),
+            (Prefix::Ipv6(Ipv6Prefix::new(0, &[])), b"::/0", true),
+            (Prefix::Ipv4(Ipv4Prefix::new(8, &[10])), b"10.0.0.0/8", false),
+            (Prefix::Ipv4(Ipv4Prefix::new(32, &[192, 0, 2, 1])), b"192.0.2.1/32", false),
+            (Prefix::Ipv6(Ipv6Prefix::new(32, &[0x20, 0x01, 0x0d, 0xb8])), b"2001:db8::/32", false),
+        ];
+        for (prefix, expected, default) in &cases {
+            let mut buf = FmtBuf::new();
+            write!(buf, "{}", prefix).unwrap();
+            assert_eq!(buf.as_bytes(), *expected);
+            assert_eq!(prefix.is_default(), *default);
+        }
+
+        // 0/0 followed by 10/8, in every iterator
+        let bytes = [0, 8, 10];
+        let mut nlris = NlriIter::new(&bytes, false);
+        assert!(nlris.next().unwrap().unwrap().prefix.is_default());
+        assert_eq!(nlris.next().unwrap().unwrap().prefix.mask_len(), 8);
+        assert!(nlris.next().is_none());
+        let mut nlris = NlriIter::with_family(&bytes, AFI_IPV6, SAFI_UNICAST, false);
+        assert_eq!(nlris.next().unwrap().unwrap().prefix, Prefix::Ipv6(Ipv6Prefix::new(0, &[])));
+        assert_eq!(nlris.next().unwrap().unwrap().prefix.mask_len(), 8);
+        let mut withdrawn = WithdrawnRoutes::new(&bytes);
+        assert!(withdrawn.next().unwrap().unwrap().prefix.is_default());
+        assert!(!withdrawn.next().unwrap().unwrap().prefix.is_default());
+    }
+
+    #[test]
+    fn prefix_bits() {
+        let a = Prefix::Ipv4(Ipv4Prefix::new(20, &[10, 1, 0xf0]));
+        let b = Prefix::Ipv4(Ipv4Prefix::new(24, &[10, 1, 0xc0]));
+        assert_eq!(a.bit(4), Some(true));
+        assert_eq!(a.bit(0), Some(false));
+        assert_eq!(a.bit(19), Some(true));
+        assert_eq!(a.bit(20), None);
+        let mut bits = a.iter_bits();
+        assert_eq!(bits.by_ref().take(8).filter(|&bit| bit).count(), 2);
+        assert_eq!(bits.count(), 12);
+        assert_eq!(a.common_prefix_len(&b), 18);
+        assert_eq!(a.common_prefix_len(&a), 20);
+        let default = Prefix::Ipv4(Ipv4Prefix::new(0, &[]));
+        assert_eq!(a.common_prefix_len(&default), 0);
+        assert_eq!(default.iter_bits().count(), 0);
+        assert_eq!(a.common_prefix_len(&Prefix::Ipv6(Ipv6Prefix::new(8, &[10]))), 0);
+
+        assert_eq!(a.network()[..4], [10, 1, 0xf0, 0]);
+        assert_eq!(a.broadcast()[..4], [10, 1, 0xff, 0xff]);
+        assert_eq!(a.broadcast()[4..], [0; 12]);
+        assert_eq!(default.broadcast()[..4], [0xff; 4]);
+        let v6 = Prefix::Ipv6(Ipv6Prefix::new(33, &[0x20, 0x01, 0x0d, 0xb8, 0xff]));
+        assert_eq!(v6.network()[..5], [0x20, 0x01, 0x0d, 0xb8, 0x80]);
+        assert_eq!(v6.broadcast()[4..], [0xff; 12]);
+
+        assert_eq!(a.to_key(), (0x0a01f000 << 96, 20));
+        assert_eq!(default.to_key(), (0, 0));
+        assert_eq!(v6.to_key(), (0x2001_0db8_8000 << 80, 33));
+    }
+
+    #[test]
+    fn prefix_order() {
+        let mut prefixes = [
+            Prefix::Ipv6(Ipv6Prefix::new(0x20, &[0x20, 0x01, 0x0d, 0xb8])),
+            Prefix::Ipv4(Ipv4Prefix::new(0x18, &[0x0a, 0x00, 0x01])),
+            Prefix::Ipv4(Ipv4Prefix::new(0x10, &[0xc0, 0xa8])),
+            Prefix::Ipv4(Ipv4Prefix::new(0x18, &[0x0a, 0x00, 0x00])),
+            Prefix::Ipv6(Ipv6Prefix::new(0x00, &[])),
+        ];
+        prefixes.sort();
+        assert_eq!(prefixes, [
+            Prefix::Ipv4(Ipv4Prefix::new(0x10, &[0xc0, 0xa8])),
+            Prefix::Ipv4(Ipv4Prefix::new(0x18, &[0x0a, 0x00, 0x00])),
+            Prefix::Ipv4(Ipv4Prefix::new(0x18, &[0x0a, 0x00, 0x01])),
+            Prefix::Ipv6(Ipv6Prefix::new(0x00, &[])),
+            Prefix::Ipv6(Ipv6Prefix::new(0x20, &[0x20, 0x01, 0x0d, 0xb8])),
+        ]);
+        let key = Prefix::Ipv4(Ipv4Prefix::new(0x18, &[0x0a, 0x00, 0x01]));
+        assert_eq!(prefixes.binary_search(&key), Ok(2));
+    }
+
+    #[test]
+    fn hex_dump() {
+        let bytes = b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\x00\x15BGP";
+        let mut buf = FmtBuf::new();
+        write!(buf, "{}", HexDump::new(bytes)).unwrap();
+        assert_eq!(buf.as_bytes(), &b"\
+0000  ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff  |................|
+0010  00 15 42 47 50                                   |..BGP|"[..]);
+
+        let mut buf = FmtBuf::new();
+        write!(buf, "{}", HexDump::new(bytes).limit(4)).unwrap();
+        assert_eq!(buf.as_bytes(), &b"\
+0000  ff ff ff ff                                      |....|
+... 17 more octets"[..]);
+    }
+
+    #[test]
+    fn parse_error_window() {
+        let bytes = b"\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\xff\x00\x2dBGP-UPDATE-attributes";
+        let err = ParseError::new(BgpError::Invalid, bytes, 18);
+        assert_eq!((err.offset, err.window()), (18, &bytes[18..34]));
+        let mut buf = FmtBuf::new();
+        write!(buf, "{}", err).unwrap();
+        assert_eq!(buf.as_bytes(), &b"Invalid at offset 18
+0012  42 47 50 2d 55 50 44 41 54 45 2d 61 74 74 72 69  |BGP-UPDATE-attri|"[..]);
+        assert!(matches!(BgpError::from(err), BgpError::Invalid));
+
+        let err = ParseError::new(BgpError::BadLength, bytes, 100);
+        assert_eq!((err.offset, err.window()), (bytes.len(), &[][..]));
+        let mut buf = FmtBuf::new();
+        write!(buf, "{}", err).unwrap();
+        assert_eq!(buf.as_bytes(), &b"BadLength at offset 39"[..]);
+    }
+}